@@ -0,0 +1,56 @@
+//! Spells popped out into cards that float over every tab.
+
+use iced::Length;
+use iced::widget::{button, container, scrollable, text};
+
+use crate::{Element, Location, Row, SpellButtons, SpellId};
+use crate::hotmouse::Pt;
+use crate::preferences::Preferences;
+use crate::spells::spell::Spell;
+use crate::utils::TooltipExt;
+
+pub struct FloatingCard {
+    pub spell: Spell,
+    /// top left corner of the card, in window coordinates
+    pub position: Pt,
+}
+
+impl FloatingCard {
+    pub const MAX: usize = 3;
+
+    pub fn new(spell: Spell, existing: usize) -> Self {
+        // stagger new cards so they don't cover each other exactly
+        #[allow(clippy::cast_precision_loss)]
+        let offset = 30.0 * existing as f32;
+        Self { spell, position: Pt(60.0 + offset, 80.0 + offset) }
+    }
+
+    pub fn view<'s, 'c: 's>(&'s self, index: usize, preferences: &Preferences) -> Element<'c> {
+        let card = self.spell.view(FloatingCardButtons { index }, (), false, preferences);
+        container(scrollable(card))
+            .width(Length::Fixed(420.0))
+            .max_height(500.0)
+            .padding(8)
+            .style(Location::Tooltip)
+            .into()
+    }
+}
+
+struct FloatingCardButtons {
+    index: usize,
+}
+
+impl SpellButtons for FloatingCardButtons {
+    type Data = ();
+
+    fn view<'c>(self, id: SpellId, (): Self::Data) -> (Row<'c>, Element<'c>) {
+        let buttons = row![
+            Length::Fill,
+            button(text("Close").size(12))
+                .on_press(crate::Message::CloseCard(self.index))
+                .tooltip("Close this card"),
+        ];
+        let name = text(&*id.name).size(26).into();
+        (buttons, name)
+    }
+}
@@ -1,22 +1,32 @@
 use std::cmp::min;
+use std::collections::{BTreeSet, VecDeque};
+use std::fmt::{self, Display};
+use std::{fs, mem};
 use std::iter;
+use std::ops::Range;
+use std::slice;
 use std::sync::Arc;
 
 use iced::{Alignment, Length};
 use iced::alignment::Vertical;
-use iced::widget::{button, Column, container, horizontal_rule, scrollable, text};
+use iced::widget::{button, Column, container, horizontal_rule, pick_list, scrollable, text, text_input};
 use iced_core::Color;
 use iced_native::widget::tooltip::Position;
 use itertools::Itertools;
+use levenshtein::levenshtein;
 use serde::{Deserialize, Serialize};
 
-use crate::{Container, Element, ICON_FONT, Level, Location, Row, search, SpellButtons, SpellId, Tap};
+use crate::{Container, Element, export, ICON_FONT, Level, Location, Row, search, SpellButtons, SpellId, SPELLS, Tap};
 use crate::icon::Icon;
-use crate::search::SearchOptions;
-use crate::spells::spell::{CustomSpell, find_spell, Spell};
+use crate::preferences::Preferences;
+use crate::export::model::SpellText;
+use crate::search::{SearchOptions, SortOrder};
+use crate::spells::data::{CastingTime, Class, School};
+use crate::spells::spell::{CustomSpell, find_spell, fold, Spell, Upcast};
 use crate::spells::static_arc::StArc;
 use crate::utils::{SpacingExt, text_icon, TooltipExt};
 use crate::widgets::click_button::ClickButton;
+use crate::widgets::wrap::Wrap;
 
 #[derive(Debug, Copy, Clone)]
 pub enum MoveSpell {
@@ -24,11 +34,15 @@ pub enum MoveSpell {
     Down,
     Left,
     Right,
+    /// to the start of its level's list
+    Top,
+    /// to the end of its level's list
+    Bottom,
 }
 
 impl MoveSpell {
     pub fn is_negative(self) -> bool {
-        matches!(self, Self::Up | Self::Left)
+        matches!(self, Self::Up | Self::Left | Self::Top)
     }
 
     pub fn delta(self, num_cols: usize, all_tab: bool) -> usize {
@@ -44,7 +58,8 @@ pub enum Message {
     ToggleCollapse,
     ToggleCollapseAll,
     Prepare(SpellId),
-    PrepareAll(bool),
+    /// prepare or unprepare every spell, or only those of one level
+    PrepareAll(bool, Option<Level>),
     SpellTab(Option<Level>),
     AddSpell(SpellId),
     RemoveSpell(SpellId),
@@ -53,19 +68,254 @@ pub enum Message {
     Search(search::Message),
     // level, delta
     ChangeNumSlots(Level, i32),
+    /// start typing a level's slot total
+    BeginEditSlots(Level),
+    EditSlotsText(Level, String),
+    CommitEditSlots(Level),
     SlotsCast(Level, i32),
+    /// use a slot of this level, or a bonus slot if they're all used
+    SpendSlot(Level),
     SlotsReset,
+    /// regain pact slots
+    ShortRest,
+    TogglePact,
+    ChangePactSlots(i32),
+    SetPactLevel(Level),
+    PactCast(i32),
+    ToggleSorceryPoints,
+    ChangeSorceryPoints(i32),
+    SpendSorceryPoints(i32),
+    CreateSlot(Level),
+    BreakSlot(Level),
+    CastBonusSlot(Level),
     ViewSpell(SpellId),
+    /// show or hide the reaction trigger input for the viewed custom spell
+    ToggleEditTrigger,
+    ToggleRecentlyRemoved,
+    /// put back the spell at this index in the recently removed list
+    RestoreRemoved(usize),
+    /// relative scroll offset of the All tab's spell list
+    ScrollAll(f32),
+    /// open (or close, with `None`) the slot level chooser for a spell
+    ChooseCast(Option<SpellId>),
+    /// cast a spell with a slot of its own level, or choose a higher one if there aren't any left
+    Cast(SpellId),
+    CastAt(SpellId, Level),
+    /// start concentrating on a spell, or stop if it's already the one being concentrated on
+    Concentrate(SpellId),
+    DropConcentration,
+    SetLevel(String),
+    SetClass(Option<Class>),
+    /// set slot totals from the character's class and level
+    AutoSlots,
+    /// the most leveled spells this character can have prepared
+    SetPrepareLimit(String),
+    /// unprepare the most recently prepared spells until the character is at their limit
+    TrimPrepared,
+    ExportCsv,
+    ImportCsv,
+    /// save this character and their custom spells to a file, to import on another computer
+    ExportCharacter,
+    /// show all of a long description, or shorten it again
+    ToggleFullText(SpellId),
+    /// show or hide the question of whether to delete this character
+    ConfirmDelete(bool),
+    /// replace an unresolved spell with the spell its name is closest to
+    RelinkUnresolved(usize),
+    DiscardUnresolved(usize),
+    /// save a spell as a card image
+    ExportCard(SpellId),
+    /// save a card image for every prepared spell
+    ExportPreparedCards,
+    /// save every spell's full text as Markdown, to print
+    ExportSpellbook,
+    DismissStatus,
+    /// add a ritual spell to the spellbook, or take it out
+    ToggleRitualBook(SpellId),
+    /// only show spells in the ritual book
+    ToggleRitualBookFilter,
+    /// open or cancel setting every level's slots at once
+    ToggleSlotEntry,
+    SlotEntry(String),
+    SubmitSlots,
+    /// show only the prepared spells and slots, for combat
+    ToggleEncounter,
+    ToggleCastGroup(CastGroup),
+    /// switch between searching this character's spells and the spells they could add
+    ToggleSearchMode,
+    /// start typing a new name for this character, or stop
+    ToggleRename,
+    RenameText(String),
+    /// rename this character, handled by the app since the name can't be another character's
+    Rename(String),
+    /// sort a level's spells, or every level's, by name
+    SortLevel(Option<Level>),
+    /// pick the slot level to show a spell's upcast damage at
+    Upcast(SpellId, Level),
+    LoadoutName(String),
+    /// save the prepared spells as a loadout, replacing any with the same name
+    SaveLoadout,
+    /// prepare exactly the spells in a loadout
+    ApplyLoadout(usize),
+    DeleteLoadout(usize),
+}
+
+/// What a character page's search looks through
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum SearchMode {
+    /// the character's spells, in the level tabs
+    #[default]
+    Known,
+    /// every spell the character doesn't know yet, to add without going to the search page
+    Learnable,
+}
+
+/// How encounter mode groups prepared spells, by how long they take to cast
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum CastGroup {
+    BonusAction,
+    Action,
+    Reaction,
+    Longer,
+}
+
+impl CastGroup {
+    const ALL: [Self; 4] = [Self::BonusAction, Self::Action, Self::Reaction, Self::Longer];
+
+    fn of(casting_time: &CastingTime) -> Self {
+        match casting_time {
+            CastingTime::BonusAction => Self::BonusAction,
+            CastingTime::Action => Self::Action,
+            CastingTime::Reaction(_) => Self::Reaction,
+            CastingTime::Special | CastingTime::Minute(_) | CastingTime::Hour(_) => Self::Longer,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::BonusAction => "Bonus Actions",
+            Self::Action => "Actions",
+            Self::Reaction => "Reactions",
+            Self::Longer => "Longer",
+        }
+    }
 }
 
 #[derive(Default, Eq, PartialEq, Copy, Clone, Debug, Hash)]
 pub struct Slots {
-    total: u32,
-    used: u32,
+    pub total: u32,
+    pub used: u32,
 }
 
 impl Slots {
     const MAX_BY_LEVEL: [u32; 9] = [4, 3, 3, 3, 3, 2, 2, 1, 1];
+    /// sorcery points it costs to create a slot of levels 1 through 5
+    const CREATE_COST: [u32; 5] = [2, 3, 5, 6, 7];
+    const MAX_SORCERY_POINTS: u32 = 20;
+    /// slots by character level for wizards, clerics, and the other full casters
+    const FULL_CASTER: [[u32; 9]; 20] = [
+        [2, 0, 0, 0, 0, 0, 0, 0, 0],
+        [3, 0, 0, 0, 0, 0, 0, 0, 0],
+        [4, 2, 0, 0, 0, 0, 0, 0, 0],
+        [4, 3, 0, 0, 0, 0, 0, 0, 0],
+        [4, 3, 2, 0, 0, 0, 0, 0, 0],
+        [4, 3, 3, 0, 0, 0, 0, 0, 0],
+        [4, 3, 3, 1, 0, 0, 0, 0, 0],
+        [4, 3, 3, 2, 0, 0, 0, 0, 0],
+        [4, 3, 3, 3, 1, 0, 0, 0, 0],
+        [4, 3, 3, 3, 2, 0, 0, 0, 0],
+        [4, 3, 3, 3, 2, 1, 0, 0, 0],
+        [4, 3, 3, 3, 2, 1, 0, 0, 0],
+        [4, 3, 3, 3, 2, 1, 1, 0, 0],
+        [4, 3, 3, 3, 2, 1, 1, 0, 0],
+        [4, 3, 3, 3, 2, 1, 1, 1, 0],
+        [4, 3, 3, 3, 2, 1, 1, 1, 0],
+        [4, 3, 3, 3, 2, 1, 1, 1, 1],
+        [4, 3, 3, 3, 3, 1, 1, 1, 1],
+        [4, 3, 3, 3, 3, 2, 1, 1, 1],
+        [4, 3, 3, 3, 3, 2, 2, 1, 1],
+    ];
+    /// slots by character level for paladins and rangers
+    const HALF_CASTER: [[u32; 9]; 20] = [
+        [0, 0, 0, 0, 0, 0, 0, 0, 0],
+        [2, 0, 0, 0, 0, 0, 0, 0, 0],
+        [3, 0, 0, 0, 0, 0, 0, 0, 0],
+        [3, 0, 0, 0, 0, 0, 0, 0, 0],
+        [4, 2, 0, 0, 0, 0, 0, 0, 0],
+        [4, 2, 0, 0, 0, 0, 0, 0, 0],
+        [4, 3, 0, 0, 0, 0, 0, 0, 0],
+        [4, 3, 0, 0, 0, 0, 0, 0, 0],
+        [4, 3, 2, 0, 0, 0, 0, 0, 0],
+        [4, 3, 2, 0, 0, 0, 0, 0, 0],
+        [4, 3, 3, 0, 0, 0, 0, 0, 0],
+        [4, 3, 3, 0, 0, 0, 0, 0, 0],
+        [4, 3, 3, 1, 0, 0, 0, 0, 0],
+        [4, 3, 3, 1, 0, 0, 0, 0, 0],
+        [4, 3, 3, 2, 0, 0, 0, 0, 0],
+        [4, 3, 3, 2, 0, 0, 0, 0, 0],
+        [4, 3, 3, 3, 1, 0, 0, 0, 0],
+        [4, 3, 3, 3, 1, 0, 0, 0, 0],
+        [4, 3, 3, 3, 2, 0, 0, 0, 0],
+        [4, 3, 3, 3, 2, 0, 0, 0, 0],
+    ];
+
+    /// The slot totals a `class` character of `level` has, or `None` for warlocks, whose pact
+    /// magic works differently
+    pub fn for_class(class: Class, level: u8) -> Option<[u32; 9]> {
+        let idx = usize::from(level.clamp(1, 20)) - 1;
+        match class {
+            Class::Warlock => None,
+            Class::Paladin | Class::Ranger => Some(Self::HALF_CASTER[idx]),
+            // artificers round their half up, so they have slots at 1st level
+            Class::Artificer => Some(Self::HALF_CASTER[idx | 1]),
+            Class::Bard | Class::Cleric | Class::Druid | Class::Sorcerer | Class::Wizard => Some(Self::FULL_CASTER[idx]),
+        }
+    }
+
+    /// Sets the total number of slots, up to the most a character can have at `level`
+    fn set_total(&mut self, total: u32, level: Level) {
+        self.total = total.min(Self::MAX_BY_LEVEL[level as usize - 1]);
+        self.used = self.used.min(self.total);
+    }
+
+    /// Slot totals by level from something like `"4 3 3 1"`, `"4, 3, 3, 1"`, or `"4/3/3/1"`.
+    /// Missing levels have no slots, and each level is capped at its maximum.
+    pub fn parse_totals(str: &str) -> Result<[u32; 9], String> {
+        let mut totals = [0; 9];
+        let numbers = str.split(|c: char| c.is_whitespace() || c == ',' || c == '/')
+            .filter(|n| !n.is_empty())
+            .enumerate();
+        for (idx, n) in numbers {
+            let total = totals.get_mut(idx)
+                .ok_or("There are only 9 levels of slots")?;
+            let n = n.parse::<u32>()
+                .map_err(|_| format!("\"{n}\" isn't a number of slots"))?;
+            *total = n.min(Self::MAX_BY_LEVEL[idx]);
+        }
+        Ok(totals)
+    }
+}
+
+/// Warlock pact magic: a few slots that are all the same level, and come back on a short rest
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct PactSlots {
+    pub level: Level,
+    pub total: u32,
+    pub used: u32,
+}
+
+impl Default for PactSlots {
+    /// a 1st level warlock's
+    fn default() -> Self {
+        Self { level: Level::L1, total: 1, used: 0 }
+    }
+}
+
+impl PactSlots {
+    /// the most pact slots a warlock gets
+    const MAX: u32 = 4;
+    /// pact slots stop going up at 5th level
+    const LEVELS: [Level; 5] = [Level::L1, Level::L2, Level::L3, Level::L4, Level::L5];
 }
 
 pub struct Character {
@@ -74,37 +324,421 @@ pub struct Character {
     pub spells: [Vec<(Spell, bool)>; 10],
     /// slots (total, left) by level
     pub slots: [Slots; 9],
+    /// character level, if the user has set it
+    pub level: Option<u8>,
+    /// (total, used), if this character has sorcery points
+    pub sorcery_points: Option<Slots>,
+    /// if this character has pact magic
+    pub pact: Option<PactSlots>,
+    /// slots created from sorcery points, by level. Lost on a long rest
+    pub bonus_slots: [u32; 9],
+    /// names of the ritual spells in this (wizard's) spellbook, which can be ritual cast without
+    /// being prepared
+    pub ritual_book: BTreeSet<String>,
+    /// how many leveled spells this character can prepare, if the user has set it
+    pub prepare_limit: Option<u32>,
+    /// saved spells that don't match any spell, kept until the user fixes or discards them
+    pub unresolved: Vec<Unresolved>,
+    /// when this character was closed, in seconds since the unix epoch. `None` while it's open, or
+    /// if it was closed before this was saved
+    pub closed_at: Option<u64>,
+    /// whose spells are sorted first when searching with this as the default character
+    pub class: Option<Class>,
+    /// the concentration spell this character is keeping up, if any
+    pub concentrating_on: Option<SpellId>,
+    /// named sets of prepared spells, to switch between
+    pub loadouts: Vec<(String, Vec<SpellId>)>,
+}
+
+/// A class to pick for a character, or none
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ClassChoice(pub Option<Class>);
+
+impl ClassChoice {
+    fn all() -> Vec<Self> {
+        iter::once(Self(None))
+            .chain(Class::ALL.into_iter().map(Some).map(Self))
+            .collect()
+    }
+}
+
+impl Display for ClassChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(class) => Display::fmt(&class, f),
+            None => f.write_str("-"),
+        }
+    }
+}
+
+/// A saved spell name that doesn't match any official or custom spell, like a custom spell that
+/// was renamed or deleted
+pub struct Unresolved {
+    pub name: String,
+    pub prepared: bool,
+    /// how many of the character's other spells were saved before it, to keep it in the same place
+    pub index: usize,
+    /// the spell with the closest name, if any is close
+    pub likely: Option<Spell>,
+}
+
+impl Unresolved {
+    fn new(name: &str, prepared: bool, index: usize, custom: &[Arc<CustomSpell>]) -> Self {
+        let needle = fold(name);
+        let likely = SPELLS.iter()
+            .map(Spell::Static)
+            .chain(custom.iter().cloned().map(Spell::Custom))
+            .map(|spell| (levenshtein(&needle, spell.name_lower()), spell))
+            .filter(|(distance, _)| *distance <= (needle.chars().count() / 3).max(2))
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, spell)| spell);
+        Self { name: name.to_string(), prepared, index, likely }
+    }
 }
 
 impl Character {
-    pub fn from_serialized(serialized: &SerializeCharacter, custom: &[CustomSpell]) -> Self {
+    /// A character with no spells, slots, or anything else set yet
+    pub fn new(name: Arc<str>) -> Self {
+        Self {
+            name,
+            spells: Default::default(),
+            slots: Default::default(),
+            level: None,
+            sorcery_points: None,
+            pact: None,
+            bonus_slots: [0; 9],
+            ritual_book: BTreeSet::new(),
+            prepare_limit: None,
+            unresolved: Vec::new(),
+            closed_at: None,
+            class: None,
+            concentrating_on: None,
+            loadouts: Vec::new(),
+        }
+    }
+
+    pub fn from_serialized(serialized: &SerializeCharacter, custom: &[Arc<CustomSpell>]) -> Self {
         let mut spells: [Vec<(Spell, bool)>; 10] = Default::default();
-        serialized.spells.iter()
-            .filter_map(|(name, prepared)| {
-                find_spell(name, custom)
-                    .map(Spell::from)
-                    .map(|spell| (spell, *prepared))
-            })
-            .for_each(|spell_prepared| spells[spell_prepared.0.level()].push(spell_prepared));
+        let mut unresolved = Vec::new();
+        let mut resolved = 0;
+        for (name, prepared) in &serialized.spells {
+            match find_spell(name, custom) {
+                Some(spell) => {
+                    spells[spell.level()].push((spell, *prepared));
+                    resolved += 1;
+                }
+                None => {
+                    log::warn!("{} knows {name}, which doesn't match any spell", serialized.name);
+                    unresolved.push(Unresolved::new(name, *prepared, resolved, custom));
+                }
+            }
+        }
         let slots = serialized.slots.map_or_else(
             Default::default,
             |arr| arr.map(|(total, used)| Slots { total, used }),
         );
+        // only if they still know it
+        let concentrating_on = serialized.concentrating_on.as_ref()
+            .and_then(|name| spells.iter()
+                .flatten()
+                .find(|(spell, _)| *spell.name() == **name))
+            .map(|(spell, _)| spell.id());
+        let loadouts = serialized.loadouts.iter()
+            .map(|(name, spells)| (
+                name.clone(),
+                spells.iter()
+                    .filter_map(|name| find_spell(name, custom))
+                    .map(|spell| spell.id())
+                    .collect(),
+            ))
+            .collect();
         Self {
             name: Arc::clone(&serialized.name),
             spells,
             slots,
+            level: serialized.level,
+            sorcery_points: serialized.sorcery_points.map(|(total, used)| Slots { total, used }),
+            pact: serialized.pact,
+            bonus_slots: serialized.bonus_slots,
+            ritual_book: serialized.ritual_book.iter().cloned().collect(),
+            prepare_limit: serialized.prepare_limit,
+            unresolved,
+            closed_at: serialized.closed_at,
+            class: serialized.class,
+            concentrating_on,
+            loadouts,
+        }
+    }
+
+    /// Finds the spells for any unresolved names that match a spell now, like after a custom spell
+    /// with that name is made. Returns if any did.
+    pub fn resolve(&mut self, custom: &[Arc<CustomSpell>]) -> bool {
+        let before = self.unresolved.len();
+        let mut idx = 0;
+        while idx < self.unresolved.len() {
+            match find_spell(&self.unresolved[idx].name, custom) {
+                Some(spell) => {
+                    let entry = self.unresolved.remove(idx);
+                    self.insert_resolved(idx, entry.index, spell, entry.prepared);
+                }
+                None => idx += 1,
+            }
+        }
+        self.unresolved.len() != before
+    }
+
+    /// Puts `spell`, found for the unresolved entry that was at `entry` and saved at `index`, back
+    /// where it was saved, and moves the entries after it along one
+    fn insert_resolved(&mut self, entry: usize, index: usize, spell: Spell, prepared: bool) {
+        let level = spell.level();
+        let lower_levels = self.spells[..level as usize].iter().map(Vec::len).sum::<usize>();
+        let spells = &mut self.spells[level];
+        spells.insert(index.saturating_sub(lower_levels).min(spells.len()), (spell, prepared));
+        for later in &mut self.unresolved[entry..] {
+            later.index += 1;
+        }
+    }
+
+    /// Replaces each official spell with its current version, moving it to the list for its level
+    /// if that changed, after the spell overrides are reloaded
+    pub fn reload_spells(&mut self) {
+        let spells = mem::take(&mut self.spells);
+        for (spell, prepared) in spells.into_iter().flatten() {
+            let spell = spell.reloaded();
+            self.spells[spell.level()].push((spell, prepared));
+        }
+        for entry in &mut self.unresolved {
+            entry.likely = entry.likely.take().map(Spell::reloaded);
+        }
+    }
+
+    /// Replaces the unresolved spell at `index` with its likely match, keeping if it was prepared
+    /// or in the ritual book. Returns if it had a match.
+    pub fn relink(&mut self, index: usize) -> bool {
+        let Some(Unresolved { likely: Some(_), .. }) = self.unresolved.get(index) else {
+            return false;
+        };
+        let Unresolved { name, prepared, index: saved_at, likely } = self.unresolved.remove(index);
+        let spell = likely.expect("just checked that it has a likely match");
+        if self.ritual_book.remove(&name) {
+            self.ritual_book.insert(spell.name().to_string());
+        }
+        let known = self.spells[spell.level()].iter().any(|(s, _)| *s == spell);
+        if !known {
+            self.insert_resolved(index, saved_at, spell, prepared);
+        }
+        true
+    }
+
+    /// How many leveled spells are prepared. Cantrips are always prepared, so they don't count.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn num_prepared(&self) -> u32 {
+        self.spells[1..].iter()
+            .flatten()
+            .filter(|(_, prepared)| *prepared)
+            .count() as u32
+    }
+
+    /// How many more leveled spells are prepared than this character's limit, if any
+    pub fn over_prepare_limit(&self) -> Option<u32> {
+        self.prepare_limit
+            .map(|limit| self.num_prepared().saturating_sub(limit))
+            .filter(|&over| over != 0)
+    }
+
+    /// Unprepares spells until this character is at their prepare limit, starting from the end of
+    /// `order`, the names of spells in the order they were prepared. Spells that aren't in `order`
+    /// are treated as the oldest, in the order they're listed. Returns how many were unprepared.
+    pub fn trim_prepared(&mut self, order: &[String]) -> u32 {
+        let Some(mut over) = self.over_prepare_limit() else { return 0 };
+        let listed = self.spells[1..].iter()
+            .flatten()
+            .filter(|(spell, prepared)| *prepared && !order.iter().any(|name| **name == *spell.name()))
+            .map(|(spell, _)| spell.name().to_string())
+            .collect_vec();
+        let newest_first = listed.iter().chain(order).rev();
+        let mut trimmed = 0;
+        for name in newest_first {
+            if over == 0 {
+                break;
+            }
+            let spell = self.spells[1..].iter_mut()
+                .flatten()
+                .find(|(spell, prepared)| *prepared && *spell.name() == **name);
+            if let Some((_, prepared)) = spell {
+                *prepared = false;
+                over -= 1;
+                trimmed += 1;
+            }
+        }
+        trimmed
+    }
+
+    /// How many known spells are in each school, for the schools that have any
+    pub fn school_counts(&self) -> Vec<(School, usize)> {
+        self.spells.iter()
+            .flatten()
+            .map(|(spell, _)| spell.school())
+            .sorted()
+            .dedup_with_count()
+            .map(|(count, school)| (school, count))
+            .collect()
+    }
+
+    /// remaining slots by level, like `[4/3/2]`, or `None` if this character has no slots
+    pub fn slots_summary(&self) -> Option<String> {
+        const MAX_LEN: usize = 16;
+        let mut summary = String::from("[");
+        for Slots { total, used } in self.slots.iter().filter(|slots| slots.total != 0) {
+            let remaining = (total - used).to_string();
+            if summary.len() > 1 {
+                if summary.len() + 1 + remaining.len() > MAX_LEN {
+                    summary.push('…');
+                    break;
+                }
+                summary.push('/');
+            }
+            summary.push_str(&remaining);
+        }
+        (summary.len() > 1).then(|| summary + "]")
+    }
+
+    /// Each slot level a spell of `level` could be cast at, and if there's a slot left at that
+    /// level. Levels this character has no slots at are left out, and cantrips have no options.
+    pub fn cast_levels(&self, level: Level) -> Vec<(Level, bool)> {
+        Level::ALL[1..].iter()
+            .zip(iter::zip(&self.slots, self.bonus_slots))
+            .filter(|&(&slot_level, _)| slot_level >= level && level != Level::Cantrip)
+            .filter(|&(_, (slots, bonus))| slots.total + bonus != 0)
+            .map(|(&slot_level, (slots, bonus))| (slot_level, slots.used < slots.total || bonus != 0))
+            .collect()
+    }
+
+    /// Starts concentrating on the spell `id` if it's a concentration spell this character knows,
+    /// dropping whatever they were concentrating on before
+    fn concentrate_if_needed(&mut self, id: &SpellId) {
+        let concentration = self.spells[id.level].iter()
+            .any(|(spell, _)| spell.id() == *id && spell.concentration());
+        if concentration {
+            self.concentrating_on = Some(id.clone());
+        }
+    }
+
+    /// The spells this character can cast: prepared ones, and ritual book spells even if they
+    /// aren't prepared, since those can be ritual cast
+    pub fn castable(&self) -> impl Iterator<Item=&(Spell, bool)> {
+        self.spells.iter()
+            .flatten()
+            .filter(|(spell, prepared)| *prepared || (spell.ritual() && self.ritual_book.contains(&*spell.name())))
+    }
+
+    /// Uses up a slot of `level`, a created one only once the normal ones are gone. Returns if there
+    /// was one left.
+    pub fn spend_slot(&mut self, level: Level) -> bool {
+        let idx = level as usize - 1;
+        let slots = &mut self.slots[idx];
+        if slots.used < slots.total {
+            slots.used += 1;
+            true
+        } else if self.bonus_slots[idx] != 0 {
+            self.bonus_slots[idx] -= 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Replaces this character's copy of the custom spell named `old` with `renamed`
+    pub fn rename_custom_spell(&mut self, old: &str, renamed: &Arc<CustomSpell>) {
+        for (spell, _) in self.spells.iter_mut().flatten() {
+            if matches!(spell, Spell::Custom(custom) if &*custom.name == old) {
+                *spell = Spell::Custom(Arc::clone(renamed));
+            }
+        }
+        if self.ritual_book.remove(old) {
+            self.ritual_book.insert(renamed.name.to_string());
+        }
+    }
+
+    /// Replaces this character's copy of the custom spell `edited`, moving it to the list for its
+    /// level if that was changed. Returns whether this character knows it, and if it was moved.
+    pub fn update_custom_spell(&mut self, edited: &Arc<CustomSpell>) -> Option<bool> {
+        let found = self.spells.iter()
+            .enumerate()
+            .find_map(|(level, spells)| spells.iter()
+                .position(|(spell, _)| matches!(spell, Spell::Custom(custom) if custom.name == edited.name))
+                .map(|index| (level, index)));
+        let (level, index) = found?;
+        if level == edited.level as usize {
+            self.spells[level][index].0 = Spell::Custom(Arc::clone(edited));
+            Some(false)
+        } else {
+            let (_, prepared) = self.spells[level].remove(index);
+            self.spells[edited.level].push((Spell::Custom(Arc::clone(edited)), prepared));
+            Some(true)
         }
     }
 
+    /// Flexible Casting: spend sorcery points to create a slot of `level` (1-5)
+    pub fn create_slot(&mut self, level: Level) -> Result<(), &'static str> {
+        let points = self.sorcery_points.as_mut().ok_or("No sorcery points")?;
+        let cost = *Slots::CREATE_COST.get(level as usize - 1)
+            .ok_or("Can only create slots up to 5th level")?;
+        if points.total - points.used < cost {
+            return Err("Not enough sorcery points");
+        }
+        points.used += cost;
+        self.bonus_slots[level as usize - 1] += 1;
+        Ok(())
+    }
+
+    /// Flexible Casting: expend a slot of `level` to regain that many sorcery points. Created
+    /// slots are expended before normal ones.
+    pub fn break_slot(&mut self, level: Level) -> Result<(), &'static str> {
+        let points = self.sorcery_points.as_mut().ok_or("No sorcery points")?;
+        let gain = level as u32;
+        if points.used < gain {
+            return Err("Sorcery points are already (nearly) full");
+        }
+        let idx = level as usize - 1;
+        let slots = &mut self.slots[idx];
+        if self.bonus_slots[idx] != 0 {
+            self.bonus_slots[idx] -= 1;
+        } else if slots.used < slots.total {
+            slots.used += 1;
+        } else {
+            return Err("No slots of that level left");
+        }
+        points.used -= gain;
+        Ok(())
+    }
+
     pub fn serialize(&self) -> SerializeCharacter {
+        let mut spells = self.spells.iter()
+            .flatten()
+            .map(|(spell, prepared)| (spell.name(), *prepared))
+            .collect_vec();
+        // saved as they were, until the user decides what to do with them
+        for (inserted, entry) in self.unresolved.iter().enumerate() {
+            let idx = (entry.index + inserted).min(spells.len());
+            spells.insert(idx, (StArc::Arc(Arc::from(&*entry.name)), entry.prepared));
+        }
         SerializeCharacter {
             name: Arc::clone(&self.name),
-            spells: self.spells.iter()
-                .flatten()
-                .map(|(spell, prepared)| (spell.name(), *prepared))
-                .collect(),
+            spells,
             slots: Some(self.slots.each_ref().map(|&Slots { total, used, .. }| (total, used))),
+            level: self.level,
+            sorcery_points: self.sorcery_points.map(|Slots { total, used }| (total, used)),
+            pact: self.pact,
+            bonus_slots: self.bonus_slots,
+            ritual_book: self.ritual_book.iter().cloned().collect(),
+            prepare_limit: self.prepare_limit,
+            closed_at: self.closed_at,
+            class: self.class,
+            concentrating_on: self.concentrating_on.as_ref().map(|id| id.name.to_string()),
+            loadouts: self.loadouts.iter()
+                .map(|(name, spells)| (name.clone(), spells.iter().map(|id| id.name.to_string()).collect()))
+                .collect(),
         }
     }
 }
@@ -116,6 +750,42 @@ pub struct SerializeCharacter {
     name: Arc<str>,
     spells: Vec<(StArc<str>, bool)>,
     slots: Option<[(u32, u32); 9]>,
+    level: Option<u8>,
+    sorcery_points: Option<(u32, u32)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pact: Option<PactSlots>,
+    #[serde(default)]
+    bonus_slots: [u32; 9],
+    #[serde(default)]
+    ritual_book: Vec<String>,
+    #[serde(default)]
+    prepare_limit: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    closed_at: Option<u64>,
+    #[serde(default)]
+    class: Option<Class>,
+    /// the spell's name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    concentrating_on: Option<String>,
+    /// each loadout's name and the names of its spells
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    loadouts: Vec<(String, Vec<String>)>,
+}
+
+/// A spell removed from a character this session, and where it was
+pub struct RemovedSpell {
+    spell: Spell,
+    prepared: bool,
+    in_ritual_book: bool,
+    /// its index in its level's list
+    index: usize,
+    /// the spell that was right after it, to put it back in front of if the list has changed since
+    next: Option<String>,
+}
+
+impl RemovedSpell {
+    /// how many removed spells each character remembers
+    const MAX: usize = 10;
 }
 
 pub struct CharacterPage {
@@ -126,16 +796,61 @@ pub struct CharacterPage {
     pub tab: Option<Level>,
     pub search: SearchOptions,
     pub search_results: [Vec<usize>; 10],
+    /// something to tell the user, like the result of a csv import
+    status: Option<String>,
+    /// the level tab the status is about, to go to it
+    status_tab: Option<Level>,
+    /// spells whose long descriptions are shown in full
+    full_text: Vec<SpellId>,
+    /// names of the prepared spells at the last long rest this session
+    prepared_at_rest: Option<BTreeSet<String>>,
+    /// relative scroll offset of the All tab's spell list
+    all_scroll: f32,
+    /// the spell whose slot level chooser is open
+    casting: Option<SpellId>,
+    /// only showing spells in the ritual book
+    ritual_book_only: bool,
+    /// the level whose slot total is being typed, what's typed so far, and the input's id
+    editing_slots: Option<(Level, String, text_input::Id)>,
+    /// the slot totals being typed in, like `"4 3 3 1"`
+    slot_entry: Option<String>,
+    /// showing only the prepared spells and slots, instead of the tabs and search
+    encounter: bool,
+    /// encounter mode's groups that are collapsed
+    collapsed_groups: BTreeSet<CastGroup>,
+    /// names of the spells prepared this session, oldest first, for trimming down to the limit
+    prepare_order: Vec<String>,
+    /// editing the viewed custom spell's reaction trigger
+    editing_trigger: bool,
+    /// spells removed this session, most recent first
+    recently_removed: VecDeque<RemovedSpell>,
+    show_recently_removed: bool,
+    /// asking if this character should be deleted for good
+    confirm_delete: bool,
+    search_mode: SearchMode,
+    /// in [`SearchMode::Learnable`], the spells matching the search that the character doesn't know
+    learnable: Vec<Spell>,
+    /// the new name being typed, and the input's id
+    renaming: Option<(String, text_input::Id)>,
+    /// the name to save the prepared spells as a loadout under
+    loadout_name: String,
+    /// the slot level picked to show each spell's upcast damage at, if it isn't the spell's level
+    upcast: Vec<(SpellId, Level)>,
 }
 
 impl From<Arc<str>> for CharacterPage {
     fn from(name: Arc<str>) -> Self {
-        Self::from(Character { name, spells: Default::default(), slots: Default::default() })
+        let mut page = Self::from(Character::new(name));
+        // new characters have no slots yet
+        page.slot_entry = Some(String::new());
+        page
     }
 }
 
 impl From<Character> for CharacterPage {
-    fn from(character: Character) -> Self {
+    fn from(mut character: Character) -> Self {
+        // it's open now
+        character.closed_at = None;
         let search_results = character.spells.each_ref()
             .map(|spells| (0..spells.len()).collect_vec());
         let view_spell = character.spells.iter()
@@ -150,6 +865,27 @@ impl From<Character> for CharacterPage {
             tab: None,
             search: Default::default(),
             search_results,
+            status: None,
+            status_tab: None,
+            full_text: Vec::new(),
+            prepared_at_rest: None,
+            all_scroll: 0.0,
+            casting: None,
+            ritual_book_only: false,
+            editing_slots: None,
+            slot_entry: None,
+            encounter: false,
+            collapsed_groups: BTreeSet::new(),
+            prepare_order: Vec::new(),
+            editing_trigger: false,
+            recently_removed: VecDeque::new(),
+            show_recently_removed: false,
+            confirm_delete: false,
+            search_mode: SearchMode::Known,
+            learnable: Vec::new(),
+            renaming: None,
+            loadout_name: String::new(),
+            upcast: Vec::new(),
         }
     }
 }
@@ -170,15 +906,166 @@ impl CharacterPage {
         }
     }
 
-    pub fn add_spell(&mut self, spell: Spell) {
+    /// Views `id` in the All tab, the only one with room to show it. Returns if a reaction trigger
+    /// was being edited, which needs to be saved.
+    pub fn show_spell(&mut self, id: SpellId) -> bool {
+        self.tab = None;
+        self.view_spell = Some(id);
+        mem::take(&mut self.editing_trigger)
+    }
+
+    /// how many of this character's spells at `level` are hidden by the current search
+    pub fn hidden_by_search(&self, level: Level) -> usize {
+        self.character.spells[level].len() - self.search_results[level].len()
+    }
+
+    /// Adds `spell`, then makes sure it can be seen: on the All tab it's viewed, and on another
+    /// level's tab this either switches to the spell's level or says where it went
+    pub fn add_spell(&mut self, spell: Spell, switch_tab: bool) {
         let level = spell.level();
+        let id = spell.id();
         if !self.character.spells[level].iter().any(|(s, _)| *s == spell) {
             self.character.spells[level].push((spell, true));
         }
+        self.search();
+        match self.tab {
+            None => self.view_spell = Some(id),
+            Some(tab) if tab != level => if switch_tab {
+                self.tab = Some(level);
+            } else {
+                self.status = Some(format!("Added {} to the {level} tab", id.name));
+                self.status_tab = Some(level);
+            },
+            Some(_) => {}
+        }
+    }
+
+    /// Adds every spell in `spells` this character doesn't already know, prepared, searching only
+    /// once. Returns how many were added.
+    pub fn add_spells(&mut self, spells: impl IntoIterator<Item=Spell>) -> usize {
+        let mut added = 0;
+        for spell in spells {
+            let known = &mut self.character.spells[spell.level()];
+            if !known.iter().any(|(s, _)| *s == spell) {
+                known.push((spell, true));
+                added += 1;
+            }
+        }
+        if added != 0 {
+            self.search();
+            self.status = Some(format!("Added {added} spell{}", if added == 1 { "" } else { "s" }));
+        }
+        added
+    }
+
+    /// [`Character::update_custom_spell`], keeping its id up to date wherever it's selected
+    pub fn update_custom_spell(&mut self, edited: &Arc<CustomSpell>) {
+        let Some(moved) = self.character.update_custom_spell(edited) else { return };
+        if moved {
+            let id = edited.id();
+            for selected in [&mut self.view_spell, &mut self.casting].into_iter().flatten() {
+                if *selected.name == *id.name {
+                    *selected = id.clone();
+                }
+            }
+        }
+        // the indices into the spell lists might have changed, or the spell might not match the
+        // search anymore
+        self.search();
+    }
+
+    /// See [`Character::reload_spells`]
+    pub fn reload_spells(&mut self) {
+        self.character.reload_spells();
+        self.search();
+    }
+
+    /// Adds any unresolved spells that now match a spell, see [`Character::resolve`]
+    pub fn resolve(&mut self, custom: &[Arc<CustomSpell>]) {
+        if self.character.resolve(custom) {
+            self.search();
+        }
+    }
+
+    /// For a custom reaction spell, its trigger and a button to edit it in place, since otherwise
+    /// that means finding the spell in settings
+    fn trigger_editor<'c>(&self, index: usize, spell: &Spell) -> Option<Row<'c>> {
+        let Spell::Custom(custom) = spell else { return None };
+        let CastingTime::Reaction(when) = &custom.casting_time else { return None };
+        let when = when.as_deref().unwrap_or("");
+        let toggle = button(text(if self.editing_trigger { "Done" } else { "Edit trigger" }).size(12))
+            .on_press(crate::Message::Character(index, Message::ToggleEditTrigger));
+        let trigger: Element<'c> = if self.editing_trigger {
+            let id = spell.id();
+            text_input("which you take when…", when)
+                .size(14)
+                .on_input(move |when| crate::Message::EditReactionTrigger(id.clone(), when))
+                .on_submit(crate::Message::Character(index, Message::ToggleEditTrigger))
+                .into()
+        } else {
+            text(if when.is_empty() { "No trigger".to_string() } else { format!("Reaction: {when}") })
+                .size(14)
+                .into()
+        };
+        Some(row![trigger, toggle].spacing(6).align_items(Alignment::Center))
+    }
+
+    fn prepared_names(&self) -> BTreeSet<String> {
+        self.character.spells.iter()
+            .flatten()
+            .filter(|(_, prepared)| *prepared)
+            .map(|(spell, _)| spell.name().to_string())
+            .collect()
+    }
+
+    /// Finds the spells matching the search that this character doesn't know, for
+    /// [`SearchMode::Learnable`], sorted like the search page with this character as the default
+    fn search_learnable(&mut self, custom: &[Arc<CustomSpell>]) {
+        let needle = &self.search.search;
+        let spells = &self.character.spells;
+        self.learnable = SPELLS.iter()
+            .map(Spell::Static)
+            .chain(custom.iter()
+                .cloned()
+                .map(Spell::Custom))
+            .filter(|spell| !spells[spell.level()].iter().any(|(known, _)| known == *spell))
+            .filter(|spell| self.search.searchers()
+                .into_iter()
+                .filter(|searcher| !searcher.is_empty())
+                .all(|searcher| searcher.matches(spell)))
+            .filter(|spell| spell.name_lower().contains(needle))
+            .collect();
+        self.learnable.sort_by_cached_key(|spell| search::rank(spell, needle, SortOrder::default(), self.character.class));
+    }
+
+    /// The results of [`Self::search_learnable`], each with a button to add it
+    fn learnable_view<'c>(&self, index: usize) -> Element<'c> {
+        /// a character page isn't the place to browse every spell
+        const MAX_SHOWN: usize = 100;
+        let gray = Location::Muted;
+        let hidden = self.learnable.len().saturating_sub(MAX_SHOWN);
+        self.learnable.iter()
+            .take(MAX_SHOWN)
+            .fold(Column::new().spacing(4).padding([0, 20]), |col, spell| col.push(row![
+                text(&*spell.name()).size(18),
+                8,
+                text(SpellText::from(spell).level_line).size(14).style(gray),
+                Length::Fill,
+                button(text("Add").size(14))
+                    .on_press(crate::Message::Character(index, Message::AddSpell(spell.id()))),
+            ].align_items(Alignment::Center)))
+            .tap_if(self.learnable.is_empty(), |col| col.push(
+                text("Every spell matching the search is already on this character").size(14).style(gray)
+            ))
+            .tap_if(hidden != 0, |col| col.push(
+                text(format!("{hidden} more, narrow the search to see them")).size(14).style(gray)
+            ))
+            .tap(scrollable)
+            .into()
     }
 
     fn search(&mut self) {
-        let needle = self.search.search.to_lowercase();
+        let needle = fold(&self.search.search);
         self.search_results = self.character.spells.each_ref()
             .map(|spells| spells.iter()
                 .enumerate()
@@ -187,6 +1074,7 @@ impl CharacterPage {
                     .filter(|searcher| !searcher.is_empty())
                     .all(|searcher| searcher.matches(spell)))
                 .filter(|(_, (spell, _))| spell.name_lower().contains(&needle))
+                .filter(|(_, (spell, _))| !self.ritual_book_only || self.character.ritual_book.contains(&*spell.name()))
                 .map(|(index, _)| index)
                 .collect_vec());
         let n_results = self.search_results.iter()
@@ -202,8 +1090,84 @@ impl CharacterPage {
         }
     }
 
+    /// Sets the total of the slots being edited, if any. Returns if it changed anything.
+    fn commit_slot_edit(&mut self) -> bool {
+        let Some((level, text, _)) = self.editing_slots.take() else { return false };
+        let Ok(total) = text.parse() else { return false };
+        let slots = &mut self.character.slots[level as usize - 1];
+        let before = *slots;
+        slots.set_total(total, level);
+        *slots != before
+    }
+
+    pub fn cancel_slot_edit(&mut self) {
+        self.editing_slots = None;
+    }
+
+    pub fn cancel_rename(&mut self) {
+        self.renaming = None;
+    }
+
+    /// Renames the character to `name`, unless it's empty or `taken` by another character, which
+    /// the status then says. Returns if it was renamed.
+    pub fn rename(&mut self, name: &str, taken: bool) -> bool {
+        let name = name.trim();
+        if name.is_empty() {
+            self.status = Some("Characters need a name".to_string());
+            false
+        } else if taken {
+            self.status = Some(format!("{name} is already a character"));
+            false
+        } else {
+            self.character.name = Arc::from(name);
+            self.renaming = None;
+            true
+        }
+    }
+
+    /// The slot level picked to show `spell`'s upcast damage at
+    fn upcast_level(&self, spell: &Spell) -> Level {
+        self.upcast.iter()
+            .find(|(id, _)| *id == spell.id())
+            .map_or(spell.level(), |&(_, level)| level)
+    }
+
+    /// What should have keyboard focus after a message: the new name or slot total being typed, or
+    /// else the search bar
+    pub fn focus_id(&self) -> text_input::Id {
+        if let Some((_, id)) = &self.renaming {
+            return id.clone();
+        }
+        self.editing_slots.as_ref()
+            .map_or_else(|| self.search.id.clone(), |(_, _, id)| id.clone())
+    }
+
     /// returns true if the character should be saved now
-    pub fn update(&mut self, message: Message, custom: &[CustomSpell], num_cols: usize) -> bool {
+    /// `switch_tab_on_add` is the preference for what [`Self::add_spell`] does
+    pub fn update(&mut self, message: Message, custom: &[Arc<CustomSpell>], num_cols: usize, switch_tab_on_add: bool) -> bool {
+        // anything else happening on this page is like clicking away from the slot total
+        let committed = !matches!(
+            message,
+            Message::BeginEditSlots(_) | Message::EditSlotsText(..) | Message::CommitEditSlots(_),
+        ) && self.commit_slot_edit();
+        let add = matches!(message, Message::AddSpell(_));
+        let learnable_changed = matches!(
+            message,
+            Message::Search(_) | Message::ToggleSearchMode | Message::AddSpell(_) | Message::RemoveSpell(_) | Message::RestoreRemoved(_),
+        );
+        let status = self.status.clone();
+        let save = self.update_inner(message, custom, num_cols, switch_tab_on_add);
+        if learnable_changed && self.search_mode == SearchMode::Learnable {
+            self.search_learnable(custom);
+        }
+        // a new status isn't about the tab a spell was added to
+        if !add && self.status != status {
+            self.status_tab = None;
+        }
+        committed | save
+    }
+
+    fn update_inner(&mut self, message: Message, custom: &[Arc<CustomSpell>], num_cols: usize, switch_tab_on_add: bool) -> bool {
         match message {
             Message::ToggleCollapse => {
                 self.should_collapse_unprepared = !self.should_collapse_unprepared;
@@ -219,86 +1183,635 @@ impl CharacterPage {
                     .position(|(spell, _)| spell.name() == &*id.name);
                 idx.map_or(false, |idx| {
                     spells[idx].1 = !spells[idx].1;
+                    self.prepare_order.retain(|name| *name != *id.name);
+                    if spells[idx].1 {
+                        self.prepare_order.push(id.name.to_string());
+                    }
                     true
                 })
             }
-            Message::PrepareAll(prepare) => {
-                self.character.spells.iter_mut()
+            Message::PrepareAll(prepare, level) => {
+                let spells = self.character.spells.iter_mut()
+                    .enumerate()
+                    .filter(|&(spell_level, _)| level.map_or(true, |level| level as usize == spell_level))
+                    .flat_map(|(_, spells)| spells);
+                for (spell, prepared) in spells {
+                    if *prepared != prepare {
+                        let name = spell.name();
+                        if prepare {
+                            self.prepare_order.push(name.to_string());
+                        } else {
+                            self.prepare_order.retain(|prepared| *prepared != *name);
+                        }
+                    }
+                    *prepared = prepare;
+                }
+                true
+            }
+            Message::SpellTab(level) => {
+                self.tab = level;
+                self.status_tab = None;
+                false
+            }
+            Message::AddSpell(id) => {
+                let spell = find_spell(&id.name, custom).unwrap();
+                self.add_spell(spell, switch_tab_on_add);
+                true
+            }
+            Message::RemoveSpell(id) => {
+                let spells = &mut self.character.spells[id.level];
+                let idx = spells.iter()
+                    .position(|(spell, _)| spell.name() == &*id.name);
+                if let Some(idx) = idx {
+                    let (spell, prepared) = spells.remove(idx);
+                    let next = spells.get(idx).map(|(next, _)| next.name().to_string());
+                    let in_ritual_book = self.character.ritual_book.remove(&*id.name);
+                    if self.character.concentrating_on.as_ref() == Some(&id) {
+                        self.character.concentrating_on = None;
+                    }
+                    self.recently_removed.truncate(RemovedSpell::MAX - 1);
+                    self.recently_removed.push_front(RemovedSpell { spell, prepared, in_ritual_book, index: idx, next });
+                    self.search();
+                }
+                idx.is_some()
+            }
+            Message::ToggleRecentlyRemoved => {
+                self.show_recently_removed = !self.show_recently_removed;
+                false
+            }
+            Message::RestoreRemoved(idx) => {
+                let Some(removed) = self.recently_removed.remove(idx) else { return false };
+                let name = removed.spell.name();
+                let spells = &mut self.character.spells[removed.spell.level()];
+                // it might have been added back from search since
+                if spells.iter().any(|(spell, _)| spell.name() == name) {
+                    return false;
+                }
+                let index = removed.next.as_ref()
+                    .and_then(|next| spells.iter().position(|(spell, _)| *spell.name() == **next))
+                    .unwrap_or_else(|| removed.index.min(spells.len()));
+                spells.insert(index, (removed.spell, removed.prepared));
+                if removed.in_ritual_book {
+                    self.character.ritual_book.insert(name.to_string());
+                }
+                self.search();
+                true
+            }
+            Message::MoveSpell(id, move_spell) => {
+                let spells = &mut self.character.spells[id.level];
+                let idx = spells.iter()
+                    .position(|(spell, _)| spell.name() == &*id.name);
+                if let Some(idx) = idx {
+                    let all_tab = self.tab.is_none();
+                    match move_spell {
+                        MoveSpell::Top => {
+                            let spell = spells.remove(idx);
+                            spells.insert(0, spell);
+                        }
+                        MoveSpell::Bottom => {
+                            let spell = spells.remove(idx);
+                            spells.push(spell);
+                        }
+                        _ => {
+                            let new_idx = if move_spell.is_negative() {
+                                idx.saturating_sub(move_spell.delta(num_cols, all_tab))
+                            } else {
+                                min(idx + move_spell.delta(num_cols, all_tab), spells.len() - 1)
+                            };
+                            spells.swap(idx, new_idx);
+                        }
+                    }
+                    self.search();
+                }
+                idx.is_some()
+            }
+            Message::SortLevel(level) => {
+                let levels = match level {
+                    Some(level) => slice::from_mut(&mut self.character.spells[level]),
+                    None => &mut self.character.spells[..],
+                };
+                for spells in levels {
+                    // stable, so spells with the same name stay in the same order
+                    spells.sort_by(|(a, _), (b, _)| a.name_lower().cmp(b.name_lower()));
+                }
+                self.search();
+                true
+            }
+            Message::Search(search) => {
+                let search = self.search.update(search);
+                if search {
+                    self.search();
+                }
+                false
+            }
+            Message::ChangeNumSlots(level, delta) => {
+                let slots = &mut self.character.slots[level as usize - 1];
+                slots.set_total(slots.total.saturating_add_signed(delta), level);
+                true
+            }
+            Message::BeginEditSlots(level) => {
+                let total = self.character.slots[level as usize - 1].total;
+                self.editing_slots = Some((level, total.to_string(), text_input::Id::unique()));
+                false
+            }
+            Message::EditSlotsText(level, text) => {
+                if let Some((editing, old, _)) = &mut self.editing_slots {
+                    // at most two digits
+                    if *editing == level && text.len() <= 2 && text.chars().all(|c| c.is_ascii_digit()) {
+                        *old = text;
+                    }
+                }
+                false
+            }
+            Message::CommitEditSlots(level) => {
+                self.editing_slots.as_ref()
+                    .filter(|(editing, _, _)| *editing == level)
+                    .is_some() && self.commit_slot_edit()
+            }
+            Message::SlotsCast(level, delta) => {
+                let Slots { used, total, .. } = &mut self.character.slots[level as usize - 1];
+                *used = used.saturating_add_signed(delta)
+                    .clamp(0, *total);
+                true
+            }
+            Message::SpendSlot(level) => {
+                if self.character.spend_slot(level) {
+                    true
+                } else {
+                    self.status = Some(format!("No {level} level slots left"));
+                    false
+                }
+            }
+            Message::SlotsReset => {
+                self.prepared_at_rest = Some(self.prepared_names());
+                for slots in &mut self.character.slots {
+                    slots.used = 0;
+                }
+                if let Some(points) = &mut self.character.sorcery_points {
+                    points.used = 0;
+                }
+                if let Some(pact) = &mut self.character.pact {
+                    pact.used = 0;
+                }
+                self.character.bonus_slots = [0; 9];
+                true
+            }
+            Message::ShortRest => {
+                if let Some(pact) = &mut self.character.pact {
+                    pact.used = 0;
+                }
+                true
+            }
+            Message::TogglePact => {
+                self.character.pact = match self.character.pact {
+                    Some(_) => None,
+                    None => Some(PactSlots::default()),
+                };
+                true
+            }
+            Message::ChangePactSlots(delta) => {
+                if let Some(PactSlots { total, used, .. }) = &mut self.character.pact {
+                    *total = total.saturating_add_signed(delta)
+                        .clamp(0, PactSlots::MAX);
+                    *used = (*used).clamp(0, *total);
+                }
+                true
+            }
+            Message::SetPactLevel(level) => {
+                if let Some(pact) = &mut self.character.pact {
+                    pact.level = level;
+                }
+                true
+            }
+            Message::PactCast(delta) => {
+                if let Some(PactSlots { total, used, .. }) = &mut self.character.pact {
+                    *used = used.saturating_add_signed(delta)
+                        .clamp(0, *total);
+                }
+                true
+            }
+            Message::ToggleSorceryPoints => {
+                self.character.sorcery_points = match self.character.sorcery_points {
+                    Some(_) => None,
+                    None => Some(Slots::default()),
+                };
+                true
+            }
+            Message::ChangeSorceryPoints(delta) => {
+                if let Some(Slots { total, used }) = &mut self.character.sorcery_points {
+                    *total = total.saturating_add_signed(delta)
+                        .clamp(0, Slots::MAX_SORCERY_POINTS);
+                    *used = (*used).clamp(0, *total);
+                }
+                true
+            }
+            Message::SpendSorceryPoints(delta) => {
+                if let Some(Slots { total, used }) = &mut self.character.sorcery_points {
+                    *used = used.saturating_add_signed(delta)
+                        .clamp(0, *total);
+                }
+                true
+            }
+            Message::CreateSlot(level) => {
+                let result = self.character.create_slot(level);
+                self.status = result.err().map(String::from);
+                result.is_ok()
+            }
+            Message::BreakSlot(level) => {
+                let result = self.character.break_slot(level);
+                self.status = result.err().map(String::from);
+                result.is_ok()
+            }
+            Message::CastBonusSlot(level) => {
+                let bonus = &mut self.character.bonus_slots[level as usize - 1];
+                *bonus = bonus.saturating_sub(1);
+                true
+            }
+            Message::ViewSpell(id) => {
+                self.view_spell = Some(id);
+                // save the trigger that was being edited
+                mem::take(&mut self.editing_trigger)
+            }
+            Message::ToggleEditTrigger => {
+                self.editing_trigger = !self.editing_trigger;
+                // save when done editing
+                !self.editing_trigger
+            }
+            Message::ChooseCast(id) => {
+                self.casting = id;
+                false
+            }
+            Message::Cast(id) => {
+                if id.level == Level::Cantrip {
+                    self.status = Some(format!("Cast {}, cantrips don't use a slot", id.name));
+                    let before = self.character.concentrating_on.clone();
+                    self.character.concentrate_if_needed(&id);
+                    self.character.concentrating_on != before
+                } else if self.character.spend_slot(id.level) {
+                    self.casting = None;
+                    self.character.concentrate_if_needed(&id);
+                    true
+                } else {
+                    // upcast it instead
+                    self.casting = Some(id);
+                    false
+                }
+            }
+            Message::CastAt(id, level) => {
+                self.casting = None;
+                if id.level > level {
+                    false
+                } else if self.character.spend_slot(level) {
+                    self.character.concentrate_if_needed(&id);
+                    true
+                } else {
+                    self.status = Some(format!("No {level} level slots left"));
+                    false
+                }
+            }
+            Message::Concentrate(id) => {
+                if self.character.concentrating_on.as_ref() == Some(&id) {
+                    self.character.concentrating_on = None;
+                } else {
+                    self.character.concentrate_if_needed(&id);
+                }
+                true
+            }
+            Message::DropConcentration => {
+                self.character.concentrating_on = None;
+                true
+            }
+            Message::ScrollAll(offset) => {
+                self.all_scroll = offset;
+                false
+            }
+            Message::SetLevel(level) => {
+                if level.is_empty() {
+                    self.character.level = None;
+                    true
+                } else if let Ok(level) = level.parse::<u8>() {
+                    self.character.level = Some(level.clamp(1, 20));
+                    true
+                } else {
+                    false
+                }
+            }
+            Message::SetClass(class) => {
+                self.character.class = class;
+                true
+            }
+            Message::AutoSlots => {
+                let totals = self.character.class
+                    .zip(self.character.level)
+                    .and_then(|(class, level)| Slots::for_class(class, level));
+                if let Some(totals) = totals {
+                    for (slots, total) in self.character.slots.iter_mut().zip(totals) {
+                        slots.total = total;
+                        slots.used = slots.used.min(total);
+                    }
+                }
+                totals.is_some()
+            }
+            Message::SetPrepareLimit(limit) => {
+                if limit.is_empty() {
+                    self.character.prepare_limit = None;
+                    true
+                } else if let Ok(limit) = limit.parse() {
+                    self.character.prepare_limit = Some(limit);
+                    true
+                } else {
+                    false
+                }
+            }
+            Message::TrimPrepared => {
+                let trimmed = self.character.trim_prepared(&self.prepare_order);
+                self.prepare_order.retain(|name| self.character.spells.iter()
                     .flatten()
-                    .for_each(|(_, prepared)| *prepared = prepare);
+                    .any(|(spell, prepared)| *prepared && *spell.name() == **name));
+                trimmed != 0
+            }
+            Message::ExportCsv => {
+                let path = rfd::FileDialog::new()
+                    .add_filter("CSV", &["csv"])
+                    .set_file_name(&format!("{}.csv", export::file_name(&self.character.name)))
+                    .save_file();
+                if let Some(path) = path {
+                    self.status = fs::write(path, export::csv::export(&self.character))
+                        .err()
+                        .map(|e| format!("Export failed: {e}"));
+                }
+                false
+            }
+            Message::ExportCharacter => {
+                let path = rfd::FileDialog::new()
+                    .add_filter("Character", &["json"])
+                    .set_file_name(&format!("{}.{}", export::file_name(&self.character.name), export::character::EXTENSION))
+                    .save_file();
+                if let Some(path) = path {
+                    self.status = export::character::CharacterFile::new(&self.character)
+                        .save(&path)
+                        .err()
+                        .map(|e| format!("Export failed: {e}"));
+                }
+                false
+            }
+            Message::ExportSpellbook => {
+                let path = rfd::FileDialog::new()
+                    .add_filter("Markdown", &["md"])
+                    .set_file_name(&format!("{} Spellbook.md", export::file_name(&self.character.name)))
+                    .save_file();
+                if let Some(path) = path {
+                    self.status = fs::write(path, export::markdown::spellbook(&self.character))
+                        .err()
+                        .map(|e| format!("Export failed: {e}"));
+                }
+                false
+            }
+            Message::ExportCard(id) => {
+                let Some((spell, _)) = self.character.spells[id.level].iter().find(|(s, _)| s.name() == id.name) else {
+                    return false;
+                };
+                let path = rfd::FileDialog::new()
+                    .add_filter("PNG", &["png"])
+                    .set_file_name(&format!("{}.png", export::file_name(&spell.name())))
+                    .save_file();
+                if let Some(path) = path {
+                    self.status = export::cards::render(spell).save(path)
+                        .err()
+                        .map(|e| format!("Export failed: {e}"));
+                }
+                false
+            }
+            Message::ExportPreparedCards => {
+                let Some(dir) = rfd::FileDialog::new().pick_folder() else {
+                    return false;
+                };
+                let prepared = self.character.spells.iter()
+                    .flatten()
+                    .filter(|(_, prepared)| *prepared)
+                    .map(|(spell, _)| spell)
+                    .collect_vec();
+                let failed = prepared.iter()
+                    .filter_map(|spell| export::cards::save(spell, &dir)
+                        .err()
+                        .map(|e| format!("{} ({e})", spell.name())))
+                    .collect_vec();
+                self.status = Some(if failed.is_empty() {
+                    format!("Exported {} cards to {}", prepared.len(), dir.display())
+                } else {
+                    format!(
+                        "Exported {} of {} cards, couldn't export {}",
+                        prepared.len() - failed.len(),
+                        prepared.len(),
+                        failed.join(", "),
+                    )
+                });
+                false
+            }
+            Message::ImportCsv => {
+                let Some(path) = rfd::FileDialog::new()
+                    .add_filter("CSV", &["csv"])
+                    .pick_file() else {
+                    return false;
+                };
+                let csv = match fs::read_to_string(path) {
+                    Ok(csv) => csv,
+                    Err(e) => {
+                        self.status = Some(format!("Import failed: {e}"));
+                        return false;
+                    }
+                };
+                let import = export::csv::import(&csv, custom);
+                let imported = import.spells.len();
+                self.character.ritual_book.extend(import.ritual_book);
+                for (spell, prepared) in import.spells {
+                    let spells = &mut self.character.spells[spell.level()];
+                    match spells.iter_mut().find(|(s, _)| *s == spell) {
+                        Some((_, known_prepared)) => if let Some(prepared) = prepared {
+                            *known_prepared = prepared;
+                        },
+                        None => spells.push((spell, prepared.unwrap_or(true))),
+                    }
+                }
+                self.search();
+                self.status = Some(if import.unmatched.is_empty() {
+                    format!("Imported {imported} spells")
+                } else {
+                    format!(
+                        "Imported {imported} spells, couldn't find {}",
+                        import.unmatched.join(", "),
+                    )
+                });
+                imported != 0
+            }
+            Message::ConfirmDelete(confirm) => {
+                self.confirm_delete = confirm;
+                false
+            }
+            Message::RelinkUnresolved(index) => {
+                let relinked = self.character.relink(index);
+                if relinked {
+                    self.search();
+                }
+                relinked
+            }
+            Message::DiscardUnresolved(index) => {
+                if index < self.character.unresolved.len() {
+                    self.character.unresolved.remove(index);
+                    true
+                } else {
+                    false
+                }
+            }
+            Message::ToggleFullText(id) => {
+                if let Some(idx) = self.full_text.iter().position(|full| *full == id) {
+                    self.full_text.swap_remove(idx);
+                } else {
+                    self.full_text.push(id);
+                }
+                false
+            }
+            Message::DismissStatus => {
+                self.status = None;
+                self.status_tab = None;
+                false
+            }
+            Message::ToggleRitualBook(id) => {
+                let name = &*id.name;
+                if !self.character.ritual_book.remove(name) {
+                    self.character.ritual_book.insert(name.to_string());
+                }
+                if self.ritual_book_only {
+                    self.search();
+                }
                 true
             }
-            Message::SpellTab(level) => {
-                self.tab = level;
+            Message::ToggleRitualBookFilter => {
+                self.ritual_book_only = !self.ritual_book_only;
+                self.search();
+                false
+            }
+            Message::ToggleSlotEntry => {
+                self.slot_entry = match self.slot_entry {
+                    Some(_) => None,
+                    None => Some(String::new()),
+                };
                 false
             }
-            Message::AddSpell(id) => {
-                let spell = find_spell(&id.name, custom).unwrap();
-                self.add_spell(spell);
-                self.search();
-                true
+            Message::SlotEntry(entry) => {
+                self.slot_entry = Some(entry);
+                false
             }
-            Message::RemoveSpell(id) => {
-                let spells = &mut self.character.spells[id.level];
-                let idx = spells.iter()
-                    .position(|(spell, _)| spell.name() == &*id.name);
-                if let Some(idx) = idx {
-                    spells.remove(idx);
-                    self.search();
+            Message::SubmitSlots => {
+                let totals = self.slot_entry.as_deref()
+                    .and_then(|entry| Slots::parse_totals(entry).ok());
+                if let Some(totals) = totals {
+                    self.slot_entry = None;
+                    for (slots, total) in self.character.slots.iter_mut().zip(totals) {
+                        slots.total = total;
+                        slots.used = slots.used.min(total);
+                    }
                 }
-                idx.is_some()
+                totals.is_some()
             }
-            Message::MoveSpell(id, move_spell) => {
-                let spells = &mut self.character.spells[id.level];
-                let idx = spells.iter()
-                    .position(|(spell, _)| spell.name() == &*id.name);
-                if let Some(idx) = idx {
-                    let all_tab = self.tab.is_none();
-                    let new_idx = if move_spell.is_negative() {
-                        idx.saturating_sub(move_spell.delta(num_cols, all_tab))
-                    } else {
-                        min(idx + move_spell.delta(num_cols, all_tab), spells.len() - 1)
-                    };
-                    spells.swap(idx, new_idx);
-                    self.search();
-                }
-                idx.is_some()
+            Message::ToggleEncounter => {
+                self.encounter = !self.encounter;
+                self.casting = None;
+                false
             }
-            Message::Search(search) => {
-                let search = self.search.update(search);
-                if search {
-                    self.search();
+            Message::ToggleRename => {
+                self.renaming = match self.renaming {
+                    Some(_) => None,
+                    None => Some((self.character.name.to_string(), text_input::Id::unique())),
+                };
+                false
+            }
+            Message::RenameText(name) => {
+                if let Some((typed, _)) = &mut self.renaming {
+                    *typed = name;
                 }
                 false
             }
-            Message::ChangeNumSlots(level, delta) => {
-                let level = level as usize;
-                let Slots { total, used, .. } = &mut self.character.slots[level - 1];
-                *total = total.saturating_add_signed(delta)
-                    .clamp(0, Slots::MAX_BY_LEVEL[level - 1]);
-                *used = (*used).clamp(0, *total);
-                true
+            // see `Self::rename`
+            Message::Rename(_) => false,
+            Message::ToggleSearchMode => {
+                self.search_mode = match self.search_mode {
+                    SearchMode::Known => SearchMode::Learnable,
+                    SearchMode::Learnable => {
+                        self.learnable = Vec::new();
+                        SearchMode::Known
+                    }
+                };
+                false
             }
-            Message::SlotsCast(level, delta) => {
-                let Slots { used, total, .. } = &mut self.character.slots[level as usize - 1];
-                *used = used.saturating_add_signed(delta)
-                    .clamp(0, *total);
+            Message::Upcast(id, level) => {
+                self.upcast.retain(|(upcast, _)| *upcast != id);
+                self.upcast.push((id, level));
+                false
+            }
+            Message::LoadoutName(name) => {
+                self.loadout_name = name;
+                false
+            }
+            Message::SaveLoadout => {
+                let name = self.loadout_name.trim();
+                if name.is_empty() {
+                    return false;
+                }
+                let spells = self.character.spells.iter()
+                    .flatten()
+                    .filter(|(_, prepared)| *prepared)
+                    .map(|(spell, _)| spell.id())
+                    .collect();
+                match self.character.loadouts.iter_mut().find(|(n, _)| n == name) {
+                    Some((_, loadout)) => *loadout = spells,
+                    None => self.character.loadouts.push((name.to_string(), spells)),
+                }
+                self.loadout_name = String::new();
                 true
             }
-            Message::SlotsReset => {
-                for slots in &mut self.character.slots {
-                    slots.used = 0;
+            Message::ApplyLoadout(idx) => {
+                let Some((_, loadout)) = self.character.loadouts.get(idx) else { return false };
+                for (spell, prepared) in self.character.spells.iter_mut().flatten() {
+                    *prepared = loadout.iter().any(|id| id.level == spell.level() && *id.name == *spell.name());
                 }
+                // as if they were prepared in the loadout's order, so going over the prepare limit
+                // unprepares the last of them, not whatever was prepared last before this
+                let spells = &self.character.spells;
+                self.prepare_order = loadout.iter()
+                    .filter(|id| spells[id.level].iter().any(|(spell, prepared)| *prepared && *spell.name() == *id.name))
+                    .map(|id| id.name.to_string())
+                    .collect();
                 true
             }
-            Message::ViewSpell(id) => {
-                self.view_spell = Some(id);
+            Message::DeleteLoadout(idx) => {
+                if idx < self.character.loadouts.len() {
+                    self.character.loadouts.remove(idx);
+                    true
+                } else {
+                    false
+                }
+            }
+            Message::ToggleCastGroup(group) => {
+                if !self.collapsed_groups.remove(&group) {
+                    self.collapsed_groups.insert(group);
+                }
                 false
             }
         }
     }
 
-    pub fn view<'s, 'c: 's>(&'s self, index: usize, num_cols: usize, summary_tooltip: bool) -> Container<'c> {
+    /// `viewport_height` only needs to be approximate, it just has to be at least as tall as the
+    /// All tab's spell list
+    pub fn view<'s, 'c: 's>(
+        &'s self,
+        index: usize,
+        num_cols: usize,
+        summary_tooltip: bool,
+        viewport_height: f32,
+        preferences: &Preferences,
+        highlight: Location,
+    ) -> Container<'c> {
         let message = move |message: Message| crate::Message::Character(index, message);
 
         let Self {
@@ -306,6 +1819,17 @@ impl CharacterPage {
                 name,
                 spells,
                 slots,
+                level: character_level,
+                sorcery_points,
+                pact: _,
+                bonus_slots,
+                ritual_book: _,
+                prepare_limit: _,
+                unresolved: _,
+                closed_at: _,
+                class: _,
+                concentrating_on: _,
+                loadouts: _,
             },
             view_spell,
             should_collapse_all,
@@ -313,11 +1837,121 @@ impl CharacterPage {
             tab,
             search,
             search_results,
+            status,
+            prepared_at_rest: _,
+            all_scroll: _,
+            casting: _,
+            encounter: _,
+            collapsed_groups: _,
+            slot_entry: _,
+            ritual_book_only: _,
+            editing_slots: _,
+            prepare_order: _,
+            editing_trigger: _,
+            recently_removed: _,
+            show_recently_removed: _,
+            status_tab: _,
+            full_text: _,
+            confirm_delete: _,
+            search_mode: _,
+            learnable: _,
+            renaming: _,
+            loadout_name: _,
+            upcast: _,
         } = self;
         let selected_level = *tab;
 
+        let over_limit = self.character.over_prepare_limit();
+        #[allow(clippy::cast_possible_truncation)]
+        let num_leveled = spells[1..].iter().map(Vec::len).sum::<usize>() as u32;
+        // still allowed, the counter shows that it's too many
+        let prepare_all_limit = self.character.prepare_limit
+            .filter(|&limit| num_leveled > limit);
+        let at_limit = self.character.prepare_limit
+            .map_or(false, |limit| self.character.num_prepared() == limit);
+
+        // click a school to only show its spells
+        let school_chips = self.character.school_counts()
+            .into_iter()
+            .fold(row![].spacing(4).align_items(Alignment::Center), |row, (school, count)| {
+                let selected = self.search.school_search.schools.contains(&school);
+                row.push(button(text(format!("{school} {count}")).size(12))
+                    .style(Location::TriState(selected.then_some(true)))
+                    .padding([1, 6])
+                    .on_press(message(Message::Search(search::Message::PickSchool(school))))
+                    .tooltip(if selected { format!("Stop only showing {school} spells") } else { format!("Only show {school} spells") }))
+            });
+
+        let auto_slots = self.character.class
+            .zip(*character_level)
+            .filter(|&(class, level)| Slots::for_class(class, level).is_some());
+
         // row with details: delete, move tab, etc
-        let name_text = text(name.to_string()).size(30);
+        let name: Element<'c> = match &self.renaming {
+            Some((typed, id)) => text_input("Character Name", typed)
+                .id(id.clone())
+                .size(30)
+                .width(Length::Fixed(300.0))
+                .on_input(move |name| message(Message::RenameText(name)))
+                .on_submit(message(Message::Rename(typed.clone())))
+                .into(),
+            None => row![
+                text(name.to_string()).size(30),
+                4,
+                button(text("Rename").size(12))
+                    .style(Location::Transparent)
+                    .padding([0, 4])
+                    .on_press(message(Message::ToggleRename))
+                    .tooltip("Enter to rename, Esc to cancel"),
+            ].align_items(Alignment::Center)
+                .into(),
+        };
+        let name_text = row![
+            name,
+            12,
+            text("Level").size(16),
+            4,
+            text_input("-", &character_level.map_or_else(String::new, |level| level.to_string()))
+                .width(Length::Fixed(36.0))
+                .on_input(move |level| message(Message::SetLevel(level))),
+            12,
+            text("Class").size(16),
+            4,
+            pick_list(
+                ClassChoice::all(),
+                Some(ClassChoice(self.character.class)),
+                move |choice| message(Message::SetClass(choice.0)),
+            ).text_size(14)
+                .tooltip("Searching with this as the default character puts this class's spells first"),
+            12,
+            text("Prepared").size(16),
+            4,
+            text(self.character.num_prepared())
+                .size(16)
+                .tap_if(at_limit, |text| text.style(Location::Warning))
+                .tap_if(over_limit.is_some(), |text| text.style(Location::Error)),
+            2,
+            text("/").size(16),
+            2,
+            text_input("-", &self.character.prepare_limit.map_or_else(String::new, |limit| limit.to_string()))
+                .width(Length::Fixed(36.0))
+                .on_input(move |limit| message(Message::SetPrepareLimit(limit)))
+                .tooltip("The most leveled spells this character can prepare"),
+        ].align_items(Alignment::Center)
+            .tap_if_some(over_limit, |row, over| row.push_space(6).push(
+                button(text("Trim").size(14))
+                    .on_press(message(Message::TrimPrepared))
+                    .tooltip(format!("Unprepare the {over} most recently prepared spells")),
+            ))
+            .tap_if_some(self.character.concentrating_on.as_ref(), |row, id| row.push_space(12).push(row![
+                text(format!("Concentrating: {}", id.name)).size(16),
+                button(text_icon(Icon::X).size(12))
+                    .style(Location::Transparent)
+                    .padding(0)
+                    .on_press(message(Message::DropConcentration))
+                    .tooltip("Drop concentration"),
+            ].spacing(4)
+                .align_items(Alignment::Center)));
 
         let buttons_row = row![
             Length::Fill,
@@ -333,10 +1967,13 @@ impl CharacterPage {
                 .on_press(message(Message::ToggleCollapse))
                 .tooltip(if *should_collapse_unprepared { "Expand unprepared spells" } else { "Collapse unprepared spells" }),
             button(text_icon(Icon::Check))
-                .on_press(message(Message::PrepareAll(true)))
-                .tooltip("Prepare All"),
+                .on_press(message(Message::PrepareAll(true, None)))
+                .tooltip(prepare_all_limit.map_or_else(
+                    || "Prepare All".to_string(),
+                    |limit| format!("Prepare All, {} over the limit of {limit}", num_leveled - limit),
+                )),
             button(text_icon(Icon::X))
-                .on_press(message(Message::PrepareAll(false)))
+                .on_press(message(Message::PrepareAll(false, None)))
                 .tooltip("Unprepare All"),
             button(text_icon(Icon::ArrowLeft))
                 .on_press(crate::Message::MoveCharacter(index, -1))
@@ -346,10 +1983,183 @@ impl CharacterPage {
                 .tooltip("Move character right"),
             button(text_icon(Icon::Archive))
                 .on_press(crate::Message::CloseCharacter(index))
-                .tooltip("Close character"),
+                .tooltip("Close character. It's kept in Settings to reopen or delete"),
+            button(text("Delete permanently…").size(14))
+                .on_press(message(Message::ConfirmDelete(true)))
+                .tooltip("Delete this character permanently"),
+            button(text("Sorcery Points").size(14))
+                .on_press(message(Message::ToggleSorceryPoints))
+                .tooltip(if sorcery_points.is_some() { "Stop tracking sorcery points" } else { "Track sorcery points" }),
+            button(text("Pact Magic").size(14))
+                .on_press(message(Message::TogglePact))
+                .tooltip(if self.character.pact.is_some() { "Stop tracking pact slots" } else { "Track warlock pact slots" }),
+            button(text("Export").size(14))
+                .on_press(message(Message::ExportCsv))
+                .tooltip("Export spells as csv"),
+            button(text("Import").size(14))
+                .on_press(message(Message::ImportCsv))
+                .tooltip("Import spells from csv"),
+            button(text("Share").size(14))
+                .on_press(message(Message::ExportCharacter))
+                .tooltip("Save this character and their custom spells to a file, to import on another computer"),
+            button(text("Cards").size(14))
+                .on_press(message(Message::ExportPreparedCards))
+                .tooltip("Save every prepared spell as a card image to print"),
+            button(text("Spellbook").size(14))
+                .on_press(message(Message::ExportSpellbook))
+                .tooltip("Save every spell's full text as Markdown, to print"),
+            button(text("Sort A→Z").size(14))
+                .on_press(message(Message::SortLevel(None)))
+                .tooltip("Sort every level's spells by name"),
+            button(text("Set slots…").size(14))
+                .on_press(message(Message::ToggleSlotEntry))
+                .tooltip("Set every level's slots at once"),
+            button(text("Auto-fill slots").size(14))
+                .tap_if(auto_slots.is_some(), |b| b.on_press(message(Message::AutoSlots)))
+                .tooltip(auto_slots.map_or_else(
+                    || "Set a level and a class other than Warlock to fill in their slots".to_string(),
+                    |(class, level)| format!("Set slots to a level {level} {class}'s"),
+                )),
+            button(text(if self.encounter { "Leave encounter" } else { "Encounter" }).size(14))
+                .on_press(message(Message::ToggleEncounter))
+                .tooltip("Show only prepared spells and slots (Ctrl+E)"),
             Length::Fill
         ].spacing(6);
 
+        let loadout_buttons = self.character.loadouts.iter()
+            .enumerate()
+            .map(|(idx, (name, spells))| Element::from(row![
+                button(text(name).size(14))
+                    .on_press(message(Message::ApplyLoadout(idx)))
+                    .tooltip(format!("Prepare just these {} spells", spells.len())),
+                button(text_icon(Icon::X).size(12))
+                    .style(Location::Transparent)
+                    .padding(0)
+                    .on_press(message(Message::DeleteLoadout(idx)))
+                    .tooltip(format!("Delete {name}")),
+            ].spacing(2)
+                .align_items(Alignment::Center)));
+        // there can be more loadouts than fit on one line
+        let loadouts_row = Wrap::new(iter::once(Element::from(row![
+            text("Loadouts").size(14),
+            text_input("Name", &self.loadout_name)
+                .size(14)
+                .width(Length::Fixed(140.0))
+                .on_input(move |name| message(Message::LoadoutName(name)))
+                .on_submit(message(Message::SaveLoadout)),
+            button(text("Save").size(14))
+                .tap_if(!self.loadout_name.trim().is_empty(), |b| b.on_press(message(Message::SaveLoadout)))
+                .tooltip("Save the prepared spells as a loadout, replacing one with the same name"),
+        ].spacing(4)
+            .align_items(Alignment::Center)
+        )).chain(loadout_buttons).collect())
+            .spacing(8.0)
+            .line_spacing(4.0);
+
+        let prepared_changes = self.prepared_at_rest.as_ref()
+            .map(|at_rest| prepared_changes(at_rest, &self.prepared_names()))
+            .filter(|changes| !changes.is_empty())
+            .map(|changes| {
+                const MAX_SHOWN: usize = 5;
+                let summary = format!(
+                    "Since last rest: {}{}",
+                    changes.iter().take(MAX_SHOWN).join(", "),
+                    if changes.len() > MAX_SHOWN { ", …" } else { "" },
+                );
+                text(summary)
+                    .size(14)
+                    .style(Location::Muted)
+                    .tap_if_else(
+                        changes.len() > MAX_SHOWN,
+                        |text| text.tooltip(changes.join("\n")).into(),
+                        Element::from,
+                    )
+            });
+
+        let sorcery_row = sorcery_points.map(|Slots { total, used }| {
+            let level_buttons = |label: &'static str, levels: &[Level], msg: fn(Level) -> Message| levels.iter()
+                .fold(
+                    row![text(label).size(14)].spacing(2).align_items(Alignment::Center),
+                    |row, &level| row.push(button(text(level as u8).size(12))
+                        .padding([2, 6])
+                        .on_press(message(msg(level)))),
+                );
+            row![
+                text("Sorcery Points").size(16),
+                button(text_icon(Icon::ArrowUp).size(10))
+                    .style(Location::Transparent)
+                    .padding(0)
+                    .on_press(message(Message::ChangeSorceryPoints(1)))
+                    .tooltip("Increase max sorcery points"),
+                button(text_icon(Icon::ArrowDown).size(10))
+                    .style(Location::Transparent)
+                    .padding(0)
+                    .on_press(message(Message::ChangeSorceryPoints(-1)))
+                    .tooltip("Decrease max sorcery points"),
+                ClickButton::new(text(format!("{}/{total}", total - used)).size(16))
+                    .style(Location::Transparent)
+                    .padding([2, 3])
+                    .on_left_press(message(Message::SpendSorceryPoints(1)))
+                    .on_right_press(message(Message::SpendSorceryPoints(-1)))
+                    .tooltip("Left click to spend a point, right click to regain one"),
+                12,
+                level_buttons("Create slot:", &Level::ALL[1..=5], Message::CreateSlot)
+                    .tooltip("Costs 2/3/5/6/7 sorcery points, lost on a long rest"),
+                12,
+                level_buttons("Break slot:", &Level::ALL[1..], Message::BreakSlot)
+                    .tooltip("Gain sorcery points equal to the slot's level"),
+            ].spacing(4)
+                .align_items(Alignment::Center)
+        });
+
+        let slot_entry = self.slot_entry.as_deref().map(|entry| {
+            let error = Slots::parse_totals(entry).err();
+            row![
+                text("Slots by level").size(16),
+                text_input("4 3 3 1", entry)
+                    .width(Length::Fixed(160.0))
+                    .on_input(move |entry| message(Message::SlotEntry(entry)))
+                    .on_submit(message(Message::SubmitSlots)),
+                button(text("Set").size(14))
+                    .tap_if(error.is_none(), |b| b.on_press(message(Message::SubmitSlots))),
+                button(text("Cancel").size(14))
+                    .on_press(message(Message::ToggleSlotEntry)),
+            ].spacing(6)
+                .align_items(Alignment::Center)
+                .tap_if_some(error, |row, e| row.push(text(e).size(14)))
+        });
+
+        let status = status.as_ref().map(|status| row![
+            text(status).size(14),
+            6,
+        ].tap_if_some(self.status_tab, |row, level| row.push(
+            button(text("Go").size(12))
+                .padding([0, 6])
+                .on_press(message(Message::SpellTab(Some(level))))
+        ).push_space(6)).push(
+            button(text_icon(Icon::X).size(12))
+                .style(Location::Transparent)
+                .padding(0)
+                .on_press(message(Message::DismissStatus)),
+        ).align_items(Alignment::Center));
+
+        let learnable = self.search_mode == SearchMode::Learnable;
+        let search_chips = row![
+            button(text(if learnable { "Not known" } else { "Known" }).size(14))
+                .padding([0, 4])
+                .style(Location::TriState(learnable.then_some(true)))
+                .on_press(message(Message::ToggleSearchMode))
+                .tooltip(if learnable {
+                    "Searching spells this character doesn't know. Click to search their spells"
+                } else {
+                    "Searching this character's spells. Click to search spells they could add"
+                }),
+        ].spacing(4)
+            .tap_if(!learnable, |row| row.push(button(text("Ritual book").size(14))
+                .padding([0, 4])
+                .style(Location::TriState(self.ritual_book_only.then_some(true)))
+                .on_press(message(Message::ToggleRitualBookFilter))));
+
         // spell tabs
         let make_button = |name, level| {
             let is_selected_tab = level == selected_level;
@@ -366,13 +2176,30 @@ impl CharacterPage {
             .fold(
                 row![
                     Length::Fill,
-                    make_button(" All ".into(), None),
+                    make_button(format!(" All ({}) ", spells.iter().map(Vec::len).sum::<usize>()), None),
                 ],
-                |row, level| row.push(make_button(format!(" {level} "), Some(level))),
-            ).push_space(Length::Fill);
+                |row, level| row.push(make_button(format!(" {level} ({}) ", spells[level].len()), Some(level))),
+            ).tap_if_some(selected_level, |row, level| row.push_space(8).push(
+                button(text("Sort A→Z").size(14))
+                    .on_press(message(Message::SortLevel(Some(level))))
+                    .tooltip("Sort this tab's spells by name"),
+            ))
+                .push_space(Length::Fill);
+
+        let hidden_note = |hidden: usize| row![
+            text(format!("{hidden} spell{} hidden by search", if hidden == 1 { "" } else { "s" }))
+                .size(14)
+                .style(Location::Muted),
+            6,
+            button(text("clear search").size(14))
+                .style(Location::Transparent)
+                .padding(0)
+                .on_press(message(Message::Search(search::Message::ResetSearch))),
+        ].align_items(Alignment::Center);
 
         let page: Element<'_> = if let Some(level) = selected_level {
             let len = search_results[level].len();
+            let hidden = self.hidden_by_search(level);
             let chunks = search_results[level].iter()
                 .map(|&idx| &spells[level][idx])
                 .enumerate()
@@ -383,8 +2210,15 @@ impl CharacterPage {
                         if let Some((idx, (spell, prepared))) = chunk.next() {
                             let button = CharacterPageButtons {
                                 character: index,
+                                character_level: *character_level,
+                                cast_levels: self.character.cast_levels(spell.level()),
+                                choosing_cast: self.casting.as_ref() == Some(&spell.id()),
+                                ritual_book: spell.ritual().then(|| self.character.ritual_book.contains(&*spell.name())),
+                                concentrating: spell.concentration().then(|| self.character.concentrating_on.as_ref() == Some(&spell.id())),
                                 left: idx != 0,
                                 right: idx != len - 1,
+                                top: idx != 0,
+                                bottom: idx != len - 1,
                                 up: idx >= num_cols,
                                 down: len - idx - 1 > {
                                     // this works but really... whyyyyyy is it a block
@@ -392,65 +2226,90 @@ impl CharacterPage {
                                     let bottom_start_idx = if a == 0 { num_cols } else { a };
                                     bottom_start_idx - 1
                                 },
+                                full_text: self.full_text.contains(&spell.id()),
+                                upcast_level: self.upcast_level(spell),
                             };
                             let collapse = *should_collapse_all || (*should_collapse_unprepared && !*prepared);
-                            row.push(spell.view(button, *prepared, collapse).width(Length::Fill))
+                            row.push(spell.view(button, *prepared, collapse, preferences).width(Length::Fill))
                         } else {
                             row.push_space(Length::Fill)
                         }
                     });
                     spells_col.push(row)
                 })
+                .tap_if(hidden != 0, |col| col.push(hidden_note(hidden)))
                 .tap(scrollable)
                 .into()
         } else {
             // 'All' tab
-            let list_spells = search_results.iter()
+            // only hide levels that this character has no spells at, not ones the search filtered out
+            let levels = search_results.iter()
                 .zip(Level::ALL)
-                // .enumerate()
                 // cantrip always have no slot
                 .zip(iter::once(&Slots::default()).chain(slots))
-                .filter(|((indices, _), _)| !indices.is_empty())
-                .map(|((indices, level), slots)| (
-                    level,
-                    slots,
-                    indices.iter()
-                        .map(|&idx| &spells[level as usize][idx])
+                .filter(|((_, level), _)| !spells[*level].is_empty())
+                .map(|((indices, level), slots)| (level, slots, indices))
+                .collect_vec();
+
+            // only build the rows that are (nearly) on screen, see `visible_rows`
+            let (tops, content_height) = all_tab_layout(levels.iter().map(|(_, _, indices)| indices.len()));
+            let scroll_offset = self.all_scroll * (content_height - viewport_height).max(0.0);
+            let visible = (scroll_offset - viewport_height / 2.0, scroll_offset + viewport_height * 1.5);
+
+            let spell_row = |spell: &Spell, prepped: bool| text(&*spell.name())
+                .size(18)
+                .style({
+                    let selected = view_spell.as_ref().filter(|s| s.name == spell.name()).is_some();
+                    let selected_highlight = if selected { 0.8 } else { 1.0 };
+                    let prepared_opacity = if prepped { 1.0 } else { 0.5 };
+                    Color {
+                        r: selected_highlight,
+                        g: selected_highlight,
+                        b: 1.0,
+                        a: prepared_opacity,
+                    }
+                })
+                .tap(|text| button(text))
+                .style(Location::Transparent)
+                .padding(0)
+                .on_press(message(Message::ViewSpell(spell.id())))
+                .tap_if_else(
+                    summary_tooltip,
+                    |b| b.tooltip_at(
+                        Position::Right,
+                        format!("{}     {}", spell.casting_times(), spell.duration().unwrap_or("")),
+                    ).into(),
+                    Element::from,
+                )
+                .tap(|name| row![name].height(Length::Fixed(ALL_ROW_HEIGHT)).align_items(Alignment::Center))
+                .tap_if(spell.scales(), |row| row
+                    .push_space(6)
+                    .push(text("scales")
+                        .size(12)
+                        .style(Location::Muted)));
+
+            let list_spells = levels.into_iter()
+                .zip(tops)
+                .map(|((level, slots, indices), top)| {
+                    let range = visible_rows(top, indices.len(), visible);
+                    #[allow(clippy::cast_precision_loss)]
+                    let (above, below) = (range.start as f32, (indices.len() - range.end) as f32);
+                    let spells_col = indices[range].iter()
+                        .map(|&idx| &spells[level][idx])
                         .fold(
-                            Column::new(),
-                            |col, (spell, prepped)| {
-                                col.push(text(&*spell.name())
-                                    .size(18)
-                                    .style({
-                                        let selected = view_spell.as_ref().filter(|s| s.name == spell.name()).is_some();
-                                        let selected_highlight = if selected { 0.8 } else { 1.0 };
-                                        let prepared_opacity = if *prepped { 1.0 } else { 0.5 };
-                                        Color {
-                                            r: selected_highlight,
-                                            g: selected_highlight,
-                                            b: 1.0,
-                                            a: prepared_opacity,
-                                        }
-                                    })
-                                    .tap(|text| button(text))
-                                    .style(Location::Transparent)
-                                    .padding(0)
-                                    .on_press(message(Message::ViewSpell(spell.id())))
-                                    .tap_if_else(
-                                        summary_tooltip,
-                                        |b| b.tooltip_at(
-                                            Position::Right,
-                                            format!("{}     {}", spell.casting_time(), spell.duration().unwrap_or("")),
-                                        ).into(),
-                                        Element::from,
-                                    )
-                                )
-                            },
-                        )))
+                            Column::new().push_space(Length::Fixed(above * ALL_ROW_HEIGHT)),
+                            |col, (spell, prepped)| col.push(spell_row(spell, *prepped)),
+                        )
+                        .push_space(Length::Fixed(below * ALL_ROW_HEIGHT))
+                        .tap_if(indices.is_empty(), |col| col.push(
+                            hidden_note(self.hidden_by_search(level)).height(Length::Fixed(ALL_ROW_HEIGHT))
+                        ));
+                    (level, slots, spells_col)
+                })
                 .fold(
-                    Column::new().padding(20),
+                    Column::new().padding(ALL_PADDING),
                     move |col, (level, Slots { total, used }, spells_col)| {
-                        let mut slots_row = row![].padding(2).align_items(Alignment::Center);
+                        let mut slots_row = row![].padding(2).height(Length::Fixed(ALL_HEADER_HEIGHT)).align_items(Alignment::Center);
                         if level == Level::Cantrip {
                             slots_row = slots_row
                                 .push(text(Level::Cantrip).size(26));
@@ -500,6 +2359,12 @@ impl CharacterPage {
                                 .padding([2, 3])
                                 .on_left_press(message(Message::SlotsCast(level, 1)))
                                 .on_right_press(message(Message::SlotsCast(level, -1)));
+                            let bonus = bonus_slots[level as usize - 1];
+                            let bonus_slots = button(text("+".repeat(bonus as usize)).size(15))
+                                .style(Location::Transparent)
+                                .padding([2, 3])
+                                .on_press(message(Message::CastBonusSlot(level)))
+                                .tooltip("Slots created from sorcery points, lost on a long rest");
                             let uncast = button(
                                 text_icon(Icon::ArrowDown)
                                     .size(15)
@@ -509,40 +2374,126 @@ impl CharacterPage {
                                 .tap_if(*used != 0, |btn|
                                     btn.on_press(message(Message::SlotsCast(level, -1))),
                                 );
+                            let total_editor: Element<'_> = match &self.editing_slots {
+                                Some((editing, typed, id)) if *editing == level => text_input("0", typed)
+                                    .id(id.clone())
+                                    .size(14)
+                                    .padding(2)
+                                    .width(Length::Fixed(28.0))
+                                    .on_input(move |text| message(Message::EditSlotsText(level, text)))
+                                    .on_submit(message(Message::CommitEditSlots(level)))
+                                    .into(),
+                                _ => button(text(format!("{}/{total}", total - used)).size(14))
+                                    .style(Location::Transparent)
+                                    .padding(0)
+                                    .on_press(message(Message::BeginEditSlots(level)))
+                                    .tooltip("Click to type the number of slots"),
+                            };
                             slots_row = slots_row
                                 .push(row![
                                     text(format!("{level} Level")).size(26),
                                     10,
                                     slot_max_picker,
+                                    4,
+                                    total_editor,
                                     Length::Fill,
                                     slots,
                                 ].align_items(Alignment::Center)
+                                    .tap_if(bonus != 0, |r| r.push(bonus_slots))
                                     .tap_if(*total != 0, |r| r.push(uncast))
                                 );
                         }
                         col.push(horizontal_rule(0))
                             .push(slots_row)
                             .push(horizontal_rule(0))
-                            .spacing(6)
+                            .spacing(ALL_SPACING)
                             .push(spells_col)
                     },
                 );
 
             let view_spell = view_spell.as_ref()
-                .and_then(|id| self.character.spells[id.level]
-                    .iter()
-                    .find(|(s, _)| s.name() == id.name))
+                .and_then(|id| {
+                    let spells = &self.character.spells[id.level];
+                    spells.iter()
+                        .position(|(s, _)| s.name() == id.name)
+                        .map(|idx| (idx, spells.len(), &spells[idx]))
+                })
                 .map_or_else(|| container(""),
-                             |(spell, prepared)| spell.view(CharacterPageButtons {
+                             |(idx, len, (spell, prepared))| spell.view(CharacterPageButtons {
                                  character: index,
+                                 character_level: *character_level,
+                                 cast_levels: self.character.cast_levels(spell.level()),
+                                 choosing_cast: self.casting.as_ref() == Some(&spell.id()),
+                                 ritual_book: spell.ritual().then(|| self.character.ritual_book.contains(&*spell.name())),
+                                 concentrating: spell.concentration().then(|| self.character.concentrating_on.as_ref() == Some(&spell.id())),
                                  left: false,
                                  right: false,
-                                 // todo false if can't move up/down
-                                 up: true,
-                                 down: true,
-                             }, *prepared, false));
+                                 up: idx != 0,
+                                 down: idx != len - 1,
+                                 top: idx != 0,
+                                 bottom: idx != len - 1,
+                                 full_text: self.full_text.contains(&spell.id()),
+                                 upcast_level: self.upcast_level(spell),
+                             }, *prepared, false, preferences)
+                                 .tap_if_some(self.trigger_editor(index, spell), |view, editor| container(
+                                     col![view, editor].spacing(4)
+                                 )));
+            // above the other slots, since they come back at a different time
+            let pact_row = self.character.pact.map(|PactSlots { level, total, used }| {
+                let slots_text = format!(
+                    "{empty}{filled}",
+                    filled = Icon::DiamondFill.to_string().repeat(used as usize),
+                    empty = Icon::Diamond.to_string().repeat((total - used) as usize),
+                );
+                row![
+                    text("Pact").size(26),
+                    10,
+                    col![
+                        button(text_icon(Icon::ArrowUp).size(10))
+                            .style(Location::Transparent)
+                            .padding(0)
+                            .tap_if(total != PactSlots::MAX, |b| b.on_press(message(Message::ChangePactSlots(1)))),
+                        button(text_icon(Icon::ArrowDown).size(10))
+                            .style(Location::Transparent)
+                            .padding(0)
+                            .tap_if(total != 0, |b| b.on_press(message(Message::ChangePactSlots(-1)))),
+                    ].align_items(Alignment::Center),
+                    4,
+                    pick_list(
+                        PactSlots::LEVELS.to_vec(),
+                        Some(level),
+                        move |level| message(Message::SetPactLevel(level)),
+                    ).text_size(14)
+                        .tooltip("The level of every pact slot"),
+                    4,
+                    text(format!("{}/{total}", total - used)).size(14),
+                    Length::Fill,
+                    ClickButton::new(
+                        text(slots_text)
+                            .font(ICON_FONT)
+                            .vertical_alignment(Vertical::Center)
+                            .size(15),
+                    )
+                        .style(Location::Transparent)
+                        .padding([2, 3])
+                        .on_left_press(message(Message::PactCast(1)))
+                        .on_right_press(message(Message::PactCast(-1)))
+                        .tooltip("Left click to spend a pact slot, right click to regain one"),
+                    button(text("Short Rest").size(14))
+                        .on_press(message(Message::ShortRest))
+                        .tooltip("Regain pact slots"),
+                ].spacing(2)
+                    .padding([2, 2 + ALL_PADDING])
+                    .height(Length::Fixed(ALL_HEADER_HEIGHT))
+                    .align_items(Alignment::Center)
+            });
             row![
-                container(scrollable(list_spells)).width(Length::FillPortion(3)),
+                col![]
+                    .tap_if_some(pact_row, |col, row| col.push(row).push(horizontal_rule(0)))
+                    .push(container(scrollable(list_spells)
+                        .on_scroll(move |offset| message(Message::ScrollAll(offset.y)))
+                    ))
+                    .width(Length::FillPortion(3)),
                 container(scrollable(view_spell)).width(Length::FillPortion(4)).padding([0, 0, 10, 0])
             ].align_items(Alignment::Start)
                 .into()
@@ -552,31 +2503,290 @@ impl CharacterPage {
         //     search.view(None, Some(index))
         // ].align_items(Alignment::Center);
 
+        let confirm_delete = self.confirm_delete.then(|| row![
+            text(format!("Delete {name} permanently? Ctrl+Z can still bring them back this session")).size(14),
+            button(text("Delete").size(14))
+                .on_press(crate::Message::DeleteCharacter(index)),
+            button(text("Cancel").size(14))
+                .style(Location::Transparent)
+                .on_press(message(Message::ConfirmDelete(false))),
+        ].spacing(6)
+            .align_items(Alignment::Center));
+
         container(col![
             10,
             name_text,
-            buttons_row,
-            tabs_row,
-            search.view(None, Some(index)),
-            page
-        ].align_items(Alignment::Center)
+        ].tap_if(!self.encounter, |col| col.push(school_chips))
+            .push(buttons_row)
+            .tap_if(!self.encounter, |col| col.push(loadouts_row))
+            .tap_if_some(confirm_delete, Column::push)
+            .tap_if_some(prepared_changes, Column::push)
+            .tap_if_some(slot_entry, |col, row| col.push(row))
+            .tap_if_some(sorcery_row, |col, row| col.push(row))
+            .tap_if_some(status, |col, status| col.push(status))
+            .tap_if_some(self.unresolved_view(index), |col, unresolved| col.push(unresolved))
+            .tap_if_else(
+                self.encounter,
+                |col| col.push(self.encounter_view(index)),
+                |col| col.tap_if(self.search_mode == SearchMode::Known, |col| col.push(tabs_row))
+                    .push(container(search.view(search_chips, Some(index))).padding(2).style(highlight))
+                    .push(match self.search_mode {
+                        SearchMode::Known => page,
+                        SearchMode::Learnable => self.learnable_view(index),
+                    })
+                    .tap_if(!self.recently_removed.is_empty(), |col| col.push(self.recently_removed_view(index))),
+            )
+            .align_items(Alignment::Center)
             .spacing(6))
     }
+
+    /// A warning listing the saved spells that don't match any spell, each with ways to fix it
+    fn unresolved_view<'c>(&self, index: usize) -> Option<Element<'c>> {
+        if self.character.unresolved.is_empty() {
+            return None;
+        }
+        let message = move |message: Message| crate::Message::Character(index, message);
+        let header = text(match self.character.unresolved.len() {
+            1 => "1 saved spell doesn't match any spell, and is kept until you choose what to do with it".to_string(),
+            n => format!("{n} saved spells don't match any spell, and are kept until you choose what to do with them"),
+        }).size(14)
+            .style(Location::Error);
+        let col = self.character.unresolved.iter()
+            .enumerate()
+            .fold(col![header].spacing(4).align_items(Alignment::Center), |col, (idx, entry)| col.push(row![
+                text(&entry.name).size(14),
+                Length::Fill,
+            ].spacing(6)
+                .align_items(Alignment::Center)
+                .tap_if_some(entry.likely.as_ref(), |row, likely| row.push(
+                    button(text(format!("Use {}", likely.name())).size(12))
+                        .on_press(message(Message::RelinkUnresolved(idx)))
+                        .tooltip(format!("Replace {} with {}", entry.name, likely.name()))
+                ))
+                .push(button(text("Make custom spell").size(12))
+                    .on_press(crate::Message::CreateUnresolvedSpell(index, idx))
+                    .tooltip(format!("Make a custom spell called {} to edit", entry.name)))
+                .push(button(text("Discard").size(12))
+                    .on_press(message(Message::DiscardUnresolved(idx)))
+                    .tooltip("Forget this spell"))
+                .width(Length::Fixed(600.0))
+            ));
+        Some(container(col).padding(6).style(Location::Tooltip).into())
+    }
+
+    /// A button to show the spells removed this session, each with a button to put it back
+    fn recently_removed_view<'c>(&self, index: usize) -> Element<'c> {
+        let message = move |message: Message| crate::Message::Character(index, message);
+        let header = button(row![
+            text(format!("Recently removed ({})", self.recently_removed.len())).size(14),
+            4,
+            text_icon(if self.show_recently_removed { Icon::ChevronContract } else { Icon::ChevronExpand }).size(12),
+        ].align_items(Alignment::Center))
+            .style(Location::Transparent)
+            .on_press(message(Message::ToggleRecentlyRemoved));
+        col![header]
+            .tap_if(self.show_recently_removed, |col| self.recently_removed.iter()
+                .enumerate()
+                .fold(col, |col, (idx, removed)| col.push(row![
+                    text(format!("{} ({})", removed.spell.name(), removed.spell.level())).size(14),
+                    button(text("Restore").size(12))
+                        .on_press(message(Message::RestoreRemoved(idx))),
+                ].spacing(6)
+                    .align_items(Alignment::Center))))
+            .spacing(2)
+            .align_items(Alignment::Center)
+            .into()
+    }
+
+    /// The remaining slots, then the prepared spells grouped by casting time, each with buttons to
+    /// cast it at any level it can be
+    fn encounter_view<'s, 'c: 's>(&'s self, index: usize) -> Element<'c> {
+        let message = move |message: Message| crate::Message::Character(index, message);
+        let muted = Location::Muted;
+
+        let slots_row = Level::ALL[1..].iter()
+            .zip(&self.character.slots)
+            .zip(self.character.bonus_slots)
+            .filter(|((_, slots), bonus)| slots.total != 0 || *bonus != 0)
+            .fold(row![Length::Fill].spacing(16), |row, ((&level, &Slots { total, used }), bonus)| row.push(
+                ClickButton::new(text(format!(
+                    "{level}: {}/{total}{}",
+                    total - used,
+                    "+".repeat(bonus as usize),
+                )).size(26))
+                    .style(Location::Transparent)
+                    .padding([2, 3])
+                    .on_left_press(message(Message::SlotsCast(level, 1)))
+                    .on_right_press(message(Message::SlotsCast(level, -1)))
+                    .tooltip("Left click to spend a slot, right click to regain one")
+            ))
+            .tap_if_some(self.character.pact, |row, PactSlots { level, total, used }| row.push(
+                ClickButton::new(text(format!("Pact {level}: {}/{total}", total - used)).size(26))
+                    .style(Location::Transparent)
+                    .padding([2, 3])
+                    .on_left_press(message(Message::PactCast(1)))
+                    .on_right_press(message(Message::PactCast(-1)))
+                    .tooltip("Left click to spend a pact slot, right click to regain one")
+            ))
+            .push_space(Length::Fill);
+
+        let spell_row = |spell: &Spell, prepared: bool| {
+            let details = [
+                Some(match spell.casting_time() {
+                    CastingTime::Reaction(Some(when)) => format!("when {when}"),
+                    time => time.to_string(),
+                }),
+                spell.duration().map(str::to_string),
+                spell.concentration().then(|| "Concentration".to_string()),
+                spell.ritual().then(|| "Ritual".to_string()),
+            ].into_iter()
+                .flatten()
+                .join(", ");
+            let cast_buttons = self.character.cast_levels(spell.level())
+                .into_iter()
+                .fold(row![].spacing(4), |row, (level, available)| row.push(
+                    button(text(level).size(20))
+                        .padding([6, 12])
+                        .tap_if(available, |b| b.on_press(message(Message::CastAt(spell.id(), level))))
+                ));
+            row![
+                col![
+                    text(&*spell.name()).size(22),
+                    text(details).size(14).style(muted),
+                ].width(Length::Fill),
+            ].align_items(Alignment::Center)
+                // unprepared ritual book spells can only be ritual cast, which takes no slot
+                .tap_if_else(
+                    prepared,
+                    |row| row.push(cast_buttons),
+                    |row| row.push(text("Ritual cast only").size(16)),
+                )
+        };
+
+        let prepared = self.character.castable().collect_vec();
+        let groups = CastGroup::ALL.into_iter()
+            .map(|group| (group, prepared.iter()
+                .copied()
+                .filter(|(spell, _)| CastGroup::of(spell.casting_time()) == group)
+                .collect_vec()))
+            .filter(|(_, spells)| !spells.is_empty())
+            .fold(Column::new().spacing(10).padding([0, 20]), |col, (group, spells)| {
+                let collapsed = self.collapsed_groups.contains(&group);
+                let header = button(row![
+                    text(group.name()).size(26),
+                    8,
+                    text(format!("({})", spells.len())).size(18).style(muted),
+                    Length::Fill,
+                    text_icon(if collapsed { Icon::ChevronExpand } else { Icon::ChevronContract }),
+                ].align_items(Alignment::Center))
+                    .style(Location::Transparent)
+                    .width(Length::Fill)
+                    .on_press(message(Message::ToggleCastGroup(group)));
+                col.push(horizontal_rule(0))
+                    .push(header)
+                    .tap_if(!collapsed, |col| spells.into_iter()
+                        .fold(col, |col, (spell, prepared)| col.push(spell_row(spell, *prepared))))
+            })
+            .tap_if(prepared.is_empty(), |col| col.push(
+                text("No spells are prepared").size(18).style(muted)
+            ));
+
+        col![
+            slots_row,
+            scrollable(groups),
+        ].spacing(10)
+            .into()
+    }
+}
+
+/// The All tab's spell names are all this tall, so which ones are on screen can be found from the
+/// scroll offset without laying anything out
+const ALL_ROW_HEIGHT: f32 = 24.0;
+const ALL_HEADER_HEIGHT: f32 = 40.0;
+const ALL_PADDING: u16 = 20;
+const ALL_SPACING: u16 = 6;
+
+/// Where the rows of each level in the All tab start, given how many rows each level has, and the
+/// height of the whole tab
+#[allow(clippy::cast_precision_loss)]
+fn all_tab_layout(rows_per_level: impl IntoIterator<Item=usize>) -> (Vec<f32>, f32) {
+    let mut y = f32::from(ALL_PADDING);
+    let tops = rows_per_level.into_iter()
+        .map(|rows| {
+            // two rules and the header, with spacing after each
+            let top = y + ALL_HEADER_HEIGHT + 3.0 * f32::from(ALL_SPACING);
+            // a level filtered to nothing still has the "hidden by search" row
+            y = top + rows.max(1) as f32 * ALL_ROW_HEIGHT + f32::from(ALL_SPACING);
+            top
+        })
+        .collect_vec();
+    (tops, y - f32::from(ALL_SPACING) + f32::from(ALL_PADDING))
+}
+
+/// Which of the `n_rows` rows starting at `top` overlap the range `(visible_top, visible_bottom)`
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn visible_rows(top: f32, n_rows: usize, (visible_top, visible_bottom): (f32, f32)) -> Range<usize> {
+    let row = |y: f32| ((y - top) / ALL_ROW_HEIGHT).max(0.0);
+    let start = (row(visible_top).floor() as usize).min(n_rows);
+    let end = (row(visible_bottom).ceil() as usize).clamp(start, n_rows);
+    start..end
+}
+
+/// `+Name` for each spell prepared in `now` but not `before`, then `−Name` for each spell that was
+/// unprepared
+fn prepared_changes(before: &BTreeSet<String>, now: &BTreeSet<String>) -> Vec<String> {
+    now.difference(before)
+        .map(|name| format!("+{name}"))
+        .chain(before.difference(now).map(|name| format!("−{name}")))
+        .collect()
 }
 
 #[allow(clippy::struct_excessive_bools)]
 struct CharacterPageButtons {
     character: usize,
+    character_level: Option<u8>,
+    /// see [`Character::cast_levels`]
+    cast_levels: Vec<(Level, bool)>,
+    choosing_cast: bool,
+    /// if the spell is in the ritual book, or `None` if it isn't a ritual
+    ritual_book: Option<bool>,
+    /// if the character is concentrating on the spell, or `None` if it doesn't take concentration
+    concentrating: Option<bool>,
     left: bool,
     right: bool,
     up: bool,
     down: bool,
+    top: bool,
+    bottom: bool,
+    full_text: bool,
+    /// the slot level to show the spell's upcast damage at
+    upcast_level: Level,
 }
 
 impl SpellButtons for CharacterPageButtons {
     /// if this spell is prepared right now
     type Data = bool;
 
+    fn character_level(&self) -> Option<u8> {
+        self.character_level
+    }
+
+    fn full_text(&self, id: SpellId) -> Option<(bool, crate::Message)> {
+        Some((self.full_text, crate::Message::Character(self.character, Message::ToggleFullText(id))))
+    }
+
+    fn upcast(&self, id: &SpellId) -> Option<Upcast> {
+        if id.level == Level::Cantrip {
+            return None;
+        }
+        let levels = iter::once(id.level)
+            .chain(self.cast_levels.iter().map(|&(level, _)| level))
+            .dedup()
+            .map(|level| (level, crate::Message::Character(self.character, Message::Upcast(id.clone(), level))))
+            .collect();
+        Some(Upcast { levels, selected: self.upcast_level })
+    }
+
     fn view<'c>(self, id: SpellId, data: Self::Data) -> (Row<'c>, Element<'c>) {
         let character = self.character;
         let buttons = [
@@ -594,7 +2804,64 @@ impl SpellButtons for CharacterPageButtons {
                         .tooltip(tooltip))
                 } else {
                     row
-                });
+                })
+            .tap(|row| [
+                (self.top, "Top", "Move to the start of the list", MoveSpell::Top),
+                (self.bottom, "Bottom", "Move to the end of the list", MoveSpell::Bottom),
+            ].into_iter()
+                .filter(|(enable, ..)| *enable)
+                .fold(row, |row, (_, label, tooltip, move_spell)| row.push(
+                    button(text(label).size(12))
+                        .on_press(crate::Message::Character(character, Message::MoveSpell(id.clone(), move_spell)))
+                        .tooltip(tooltip)
+                )))
+            .push(button(text("Macro").size(12))
+                .on_press(crate::Message::CopyMacro(id.clone()))
+                .tooltip("Copy Roll20 macro"))
+            .push(button(text("Pop out").size(12))
+                .on_press(crate::Message::PopOut(id.clone()))
+                .tooltip("Keep this spell on screen"))
+            .push(button(text("Card").size(12))
+                .on_press(crate::Message::Character(character, Message::ExportCard(id.clone())))
+                .tooltip("Save as a card image to print"))
+            .tap_if_some(self.ritual_book, |row, in_book| row.push(
+                button(text("Ritual book").size(12))
+                    .style(Location::TriState(in_book.then_some(true)))
+                    .on_press(crate::Message::Character(character, Message::ToggleRitualBook(id.clone())))
+                    .tooltip(if in_book { "Remove from the ritual book" } else { "Add to the ritual book, to ritual cast it without preparing it" })
+            ))
+            .tap_if_some(self.concentrating, |row, concentrating| row.push(
+                button(text("Concentrate").size(12))
+                    .style(Location::TriState(concentrating.then_some(true)))
+                    .on_press(crate::Message::Character(character, Message::Concentrate(id.clone())))
+                    .tooltip(if concentrating { "Drop concentration" } else { "Concentrate on this, dropping any other spell" })
+            ))
+            // characters that don't track slots don't need it
+            .tap_if(id.level == Level::Cantrip || !self.cast_levels.is_empty(), |row| {
+                let cantrip = id.level == Level::Cantrip;
+                let castable = cantrip || self.cast_levels.iter().any(|&(_, available)| available);
+                let (label, message, tooltip) = if self.choosing_cast {
+                    ("Cancel", Message::ChooseCast(None), "Don't cast it")
+                } else if cantrip {
+                    ("Cast", Message::Cast(id.clone()), "Cantrips don't use a slot")
+                } else if castable {
+                    ("Cast", Message::Cast(id.clone()), "Use a slot of its level, or choose a higher one if there are none left")
+                } else {
+                    ("Cast", Message::Cast(id.clone()), "No slots left that can cast it")
+                };
+                row.push(
+                    button(text(label).size(12))
+                        .tap_if(castable || self.choosing_cast, |b| b.on_press(crate::Message::Character(character, message)))
+                        .tooltip(tooltip)
+                )
+            })
+            .tap_if(self.choosing_cast, |row| self.cast_levels.iter().fold(
+                row.push_space(4),
+                |row, &(level, available)| row.push(
+                    button(text(level).size(12))
+                        .tap_if(available, |b| b.on_press(crate::Message::Character(character, Message::CastAt(id.clone(), level))))
+                ),
+            ));
         let name = button(
             text(&*id.name).size(36),
         ).width(Length::FillPortion(23))
@@ -603,4 +2870,612 @@ impl SpellButtons for CharacterPageButtons {
             .into();
         (buttons, name)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn saved(spells: &[(&str, bool)]) -> SerializeCharacter {
+        serde_json::from_value(serde_json::json!({
+            "name": "Merric",
+            "spells": spells,
+            "slots": null,
+            "level": null,
+            "sorcery_points": null,
+        })).unwrap()
+    }
+
+    fn saved_spells(character: &Character) -> Vec<(String, bool)> {
+        character.serialize().spells.into_iter()
+            .map(|(name, prepared)| (name.to_string(), prepared))
+            .collect()
+    }
+
+    fn names(spells: &[(Spell, bool)]) -> Vec<String> {
+        spells.iter().map(|(spell, _)| spell.name().to_string()).collect()
+    }
+
+    #[test]
+    fn unresolved_spells_are_saved_where_they_were() {
+        let spells = [
+            ("Fire Bolt", true),
+            ("Zzyzx's Lost Cantrip", false),
+            ("Magic Missile", true),
+            ("Fireballl", true),
+            ("Shield", false),
+        ];
+        let character = Character::from_serialized(&saved(&spells), &[]);
+        assert_eq!(character.unresolved.iter().map(|entry| &*entry.name).collect_vec(), ["Zzyzx's Lost Cantrip", "Fireballl"]);
+        let expected = spells.map(|(name, prepared)| (name.to_string(), prepared));
+        assert_eq!(saved_spells(&character), expected);
+        // and again after loading what was saved
+        let reloaded = Character::from_serialized(&character.serialize(), &[]);
+        assert_eq!(saved_spells(&reloaded), expected);
+    }
+
+    #[test]
+    fn relinking_puts_the_spell_where_it_was_saved() {
+        let mut character = Character::from_serialized(&saved(&[("Magic Missile", true), ("Shieldd", false), ("Sleep", true)]), &[]);
+        assert_eq!(character.unresolved[0].likely.as_ref().map(Spell::name).as_deref(), Some("Shield"));
+        assert!(character.relink(0));
+        assert!(character.unresolved.is_empty());
+        assert_eq!(names(&character.spells[Level::L1]), ["Magic Missile", "Shield", "Sleep"]);
+        assert!(!character.spells[Level::L1][1].1);
+        // nothing to relink anymore
+        assert!(!character.relink(0));
+    }
+
+    #[test]
+    fn creating_the_spell_resolves_it_in_place() {
+        let mut character = Character::from_serialized(&saved(&[("Magic Missile", true), ("Homebrew Bolt", true), ("Sleep", false)]), &[]);
+        assert_eq!(character.unresolved.len(), 1);
+        let mut spell = CustomSpell::new("Homebrew Bolt".to_string());
+        spell.level = Level::L1;
+        spell.refold();
+        assert!(character.resolve(&[Arc::new(spell)]));
+        assert!(character.unresolved.is_empty());
+        assert_eq!(names(&character.spells[Level::L1]), ["Magic Missile", "Homebrew Bolt", "Sleep"]);
+        assert!(character.spells[Level::L1][1].1);
+    }
+
+    #[test]
+    fn discarding_drops_it_from_the_save() {
+        let character = Character::from_serialized(&saved(&[("Magic Missile", true), ("Homebrew Bolt", true)]), &[]);
+        let mut page = CharacterPage::from(character);
+        assert!(page.update(Message::DiscardUnresolved(0), &[], 2, false));
+        assert!(page.character.unresolved.is_empty());
+        assert_eq!(saved_spells(&page.character), [("Magic Missile".to_string(), true)]);
+        assert!(!page.update(Message::DiscardUnresolved(0), &[], 2, false));
+    }
+
+    #[test]
+    fn counts_spells_hidden_by_search() {
+        let character = Character::from_serialized(&saved(&[("Fire Bolt", true), ("Magic Missile", true), ("Shield", false), ("Sleep", true)]), &[]);
+        let mut page = CharacterPage::from(character);
+        assert!(Level::ALL.into_iter().all(|level| page.hidden_by_search(level) == 0));
+
+        page.update(Message::Search(search::Message::Search("shield".to_string())), &[], 2, false);
+        assert_eq!(page.hidden_by_search(Level::Cantrip), 1);
+        assert_eq!(page.hidden_by_search(Level::L1), 2);
+        assert_eq!(page.hidden_by_search(Level::L2), 0);
+
+        page.update(Message::Search(search::Message::ResetSearch), &[], 2, false);
+        assert_eq!(page.hidden_by_search(Level::L1), 0);
+    }
+
+    #[test]
+    fn slots_summary_shows_remaining_slots() {
+        let mut character = Character::new(Arc::from("Merric"));
+        character.slots = Default::default();
+        assert_eq!(character.slots_summary(), None);
+        character.slots[0] = Slots { total: 4, used: 0 };
+        character.slots[1] = Slots { total: 3, used: 1 };
+        character.slots[2] = Slots { total: 2, used: 2 };
+        assert_eq!(character.slots_summary().as_deref(), Some("[4/2/0]"));
+    }
+
+    #[test]
+    fn slots_summary_is_cut_short_for_many_levels() {
+        let mut character = Character::new(Arc::from("Merric"));
+        for (slots, total) in character.slots.iter_mut().zip([4, 3, 3, 3, 3, 2, 2, 1, 1]) {
+            *slots = Slots { total, used: 0 };
+        }
+        assert_eq!(character.slots_summary().as_deref(), Some("[4/3/3/3/3/2/2/1…]"));
+    }
+
+    fn sorcerer(points: u32) -> Character {
+        let mut character = Character::new(Arc::from("Merric"));
+        character.slots = Default::default();
+        character.slots[0] = Slots { total: 2, used: 0 };
+        character.sorcery_points = Some(Slots { total: points, used: 0 });
+        character
+    }
+
+    #[test]
+    fn creating_slots_costs_sorcery_points() {
+        let mut character = sorcerer(5);
+        assert_eq!(character.create_slot(Level::L2), Ok(()));
+        assert_eq!(character.sorcery_points, Some(Slots { total: 5, used: 3 }));
+        assert_eq!(character.bonus_slots[1], 1);
+        assert_eq!(character.create_slot(Level::L2), Err("Not enough sorcery points"));
+        assert_eq!(character.create_slot(Level::L6), Err("Can only create slots up to 5th level"));
+        assert_eq!(character.create_slot(Level::L1), Ok(()));
+        assert_eq!(character.sorcery_points, Some(Slots { total: 5, used: 5 }));
+        // created slots don't change the normal ones
+        assert_eq!(character.slots[0], Slots { total: 2, used: 0 });
+        assert_eq!(Character::new(Arc::from("Wizard")).create_slot(Level::L1), Err("No sorcery points"));
+    }
+
+    #[test]
+    fn breaking_slots_regains_sorcery_points() {
+        let mut character = sorcerer(4);
+        assert_eq!(character.break_slot(Level::L1), Err("Sorcery points are already (nearly) full"));
+        character.create_slot(Level::L1).unwrap();
+        // the created slot goes first
+        assert_eq!(character.break_slot(Level::L1), Ok(()));
+        assert_eq!(character.bonus_slots[0], 0);
+        assert_eq!(character.slots[0].used, 0);
+        assert_eq!(character.sorcery_points, Some(Slots { total: 4, used: 1 }));
+        character.sorcery_points = Some(Slots { total: 4, used: 4 });
+        assert_eq!(character.break_slot(Level::L1), Ok(()));
+        assert_eq!(character.break_slot(Level::L1), Ok(()));
+        assert_eq!(character.slots[0].used, 2);
+        assert_eq!(character.break_slot(Level::L1), Err("No slots of that level left"));
+        assert_eq!(character.sorcery_points, Some(Slots { total: 4, used: 2 }));
+    }
+
+    #[test]
+    fn created_slots_are_spent_last_and_gone_after_a_long_rest() {
+        let mut character = sorcerer(2);
+        character.create_slot(Level::L1).unwrap();
+        assert!(character.spend_slot(Level::L1));
+        assert!(character.spend_slot(Level::L1));
+        assert_eq!(character.bonus_slots[0], 1);
+        assert!(character.spend_slot(Level::L1));
+        assert_eq!(character.bonus_slots[0], 0);
+        assert!(!character.spend_slot(Level::L1));
+
+        let mut character = sorcerer(2);
+        character.create_slot(Level::L1).unwrap();
+        let reloaded = Character::from_serialized(&character.serialize(), &[]);
+        assert_eq!(reloaded.bonus_slots, character.bonus_slots);
+        let mut page = CharacterPage::from(reloaded);
+        page.update(Message::SlotsReset, &[], 2, false);
+        assert_eq!(page.character.bonus_slots, [0; 9]);
+        assert_eq!(page.character.sorcery_points, Some(Slots { total: 2, used: 0 }));
+        assert_eq!(page.character.slots[0], Slots { total: 2, used: 0 });
+    }
+
+    #[test]
+    fn visible_rows_overlap_the_screen() {
+        // rows at 100, 124, 148, ...
+        assert_eq!(visible_rows(100.0, 10, (0.0, 150.0)), 0..3);
+        assert_eq!(visible_rows(100.0, 10, (130.0, 172.0)), 1..3);
+        assert_eq!(visible_rows(100.0, 10, (124.0, 148.0)), 1..2);
+        assert_eq!(visible_rows(100.0, 10, (0.0, 1000.0)), 0..10);
+    }
+
+    #[test]
+    fn visible_rows_is_empty_off_screen() {
+        // entirely below the screen
+        assert_eq!(visible_rows(100.0, 10, (0.0, 90.0)), 0..0);
+        // entirely above it
+        assert_eq!(visible_rows(100.0, 10, (400.0, 600.0)), 10..10);
+        assert_eq!(visible_rows(100.0, 0, (0.0, 600.0)), 0..0);
+    }
+
+    #[test]
+    fn all_tab_layout_stacks_levels() {
+        let header = ALL_HEADER_HEIGHT + 3.0 * f32::from(ALL_SPACING);
+        let (tops, height) = all_tab_layout([2, 0, 3]);
+        let first = f32::from(ALL_PADDING) + header;
+        // an empty level still has a row for the "hidden by search" note
+        let second = first + 2.0 * ALL_ROW_HEIGHT + f32::from(ALL_SPACING) + header;
+        let third = second + ALL_ROW_HEIGHT + f32::from(ALL_SPACING) + header;
+        let expected = [first, second, third, third + 3.0 * ALL_ROW_HEIGHT + f32::from(ALL_PADDING)];
+        let actual = [tops[0], tops[1], tops[2], height];
+        assert!(actual.iter().zip(expected).all(|(a, e)| (a - e).abs() < 0.01), "{actual:?} != {expected:?}");
+        let (tops, height) = all_tab_layout([]);
+        assert!(tops.is_empty());
+        assert!((height - (2.0 * f32::from(ALL_PADDING) - f32::from(ALL_SPACING))).abs() < 0.01);
+    }
+
+    fn caster(slots: [(u32, u32); 5]) -> Character {
+        let mut character = Character::new(Arc::from("Merric"));
+        character.slots = Default::default();
+        for (slot, (total, used)) in character.slots.iter_mut().zip(slots) {
+            *slot = Slots { total, used };
+        }
+        character
+    }
+
+    #[test]
+    fn cast_levels_are_at_least_the_spell_level() {
+        let character = caster([(4, 4), (3, 3), (3, 3), (3, 3), (2, 1)]);
+        assert_eq!(
+            character.cast_levels(Level::L3),
+            [(Level::L3, false), (Level::L4, false), (Level::L5, true)],
+        );
+        assert_eq!(character.cast_levels(Level::L6), []);
+        assert_eq!(character.cast_levels(Level::Cantrip), []);
+    }
+
+    #[test]
+    fn cast_levels_skip_levels_without_slots_and_count_created_ones() {
+        let mut character = caster([(2, 2), (0, 0), (1, 1), (0, 0), (0, 0)]);
+        assert_eq!(character.cast_levels(Level::L1), [(Level::L1, false), (Level::L3, false)]);
+        character.bonus_slots[1] = 1;
+        assert_eq!(character.cast_levels(Level::L1), [(Level::L1, false), (Level::L2, true), (Level::L3, false)]);
+    }
+
+    #[test]
+    fn casting_at_a_level_spends_that_slot() {
+        let mut character = caster([(4, 0), (3, 0), (3, 3), (0, 0), (1, 0)]);
+        let hold_person = find_spell("Hold Person", &[]).unwrap();
+        let id = hold_person.id();
+        character.spells[Level::L2].push((hold_person, true));
+        let mut page = CharacterPage::from(character);
+
+        assert!(page.update(Message::CastAt(id.clone(), Level::L5), &[], 2, false));
+        assert_eq!(page.character.slots[4], Slots { total: 1, used: 1 });
+        assert_eq!(page.character.concentrating_on, Some(id.clone()));
+
+        // out of slots
+        assert!(!page.update(Message::CastAt(id.clone(), Level::L3), &[], 2, false));
+        assert!(page.status.is_some());
+        // too low for the spell
+        assert!(!page.update(Message::CastAt(id, Level::L1), &[], 2, false));
+        assert_eq!(page.character.slots[0].used, 0);
+    }
+
+    #[test]
+    fn renaming_a_custom_spell_rewrites_saved_names() {
+        let mut old = CustomSpell::new("Homebrew Bolt".to_string());
+        old.level = Level::L1;
+        old.ritual = true;
+        old.refold();
+        let old = Arc::new(old);
+        let mut character = Character::from_serialized(&saved(&[("Magic Missile", true), ("Homebrew Bolt", false)]), &[Arc::clone(&old)]);
+        character.ritual_book.insert("Homebrew Bolt".to_string());
+
+        let mut renamed = (*old).clone();
+        renamed.name = Arc::from("Homebrew Blast");
+        renamed.refold();
+        let renamed = Arc::new(renamed);
+        character.rename_custom_spell("Homebrew Bolt", &renamed);
+        assert_eq!(saved_spells(&character), [("Magic Missile".to_string(), true), ("Homebrew Blast".to_string(), false)]);
+        assert!(character.ritual_book.contains("Homebrew Blast"));
+        assert!(!character.ritual_book.contains("Homebrew Bolt"));
+
+        // closed characters are kept saved, so they have to load with the new name
+        let reopened = Character::from_serialized(&character.serialize(), &[Arc::clone(&renamed)]);
+        assert!(reopened.unresolved.is_empty());
+        assert_eq!(names(&reopened.spells[Level::L1]), ["Magic Missile", "Homebrew Blast"]);
+    }
+
+    #[test]
+    fn changing_a_custom_spells_level_moves_it() {
+        let mut spell = CustomSpell::new("Homebrew Bolt".to_string());
+        spell.level = Level::L1;
+        spell.refold();
+        let spell = Arc::new(spell);
+        let character = Character::from_serialized(&saved(&[("Magic Missile", true), ("Homebrew Bolt", false)]), &[Arc::clone(&spell)]);
+        let mut page = CharacterPage::from(character);
+        page.view_spell = Some(spell.id());
+
+        let mut edited = (*spell).clone();
+        edited.description = "Now with words".to_string();
+        let edited = Arc::new(edited);
+        assert_eq!(page.character.update_custom_spell(&edited), Some(false));
+
+        let mut edited = (*edited).clone();
+        edited.level = Level::L3;
+        let edited = Arc::new(edited);
+        page.update_custom_spell(&edited);
+        assert_eq!(names(&page.character.spells[Level::L1]), ["Magic Missile"]);
+        assert_eq!(names(&page.character.spells[Level::L3]), ["Homebrew Bolt"]);
+        assert!(!page.character.spells[Level::L3][0].1);
+        assert_eq!(page.view_spell, Some(edited.id()));
+        assert_eq!(page.search_results[Level::L3], [0]);
+
+        // and it's still there after saving and loading
+        let reloaded = Character::from_serialized(&page.character.serialize(), &[Arc::clone(&edited)]);
+        assert_eq!(names(&reloaded.spells[Level::L3]), ["Homebrew Bolt"]);
+        assert!(reloaded.spells[Level::L1].iter().all(|(spell, _)| &*spell.name() != "Homebrew Bolt"));
+
+        // characters that don't know it are left alone
+        let mut other = Character::new(Arc::from("Other"));
+        assert_eq!(other.update_custom_spell(&edited), None);
+    }
+
+    #[test]
+    fn parses_slot_totals() {
+        let expected = Ok([4, 3, 3, 1, 0, 0, 0, 0, 0]);
+        assert_eq!(Slots::parse_totals("4 3 3 1"), expected);
+        assert_eq!(Slots::parse_totals("4, 3, 3, 1"), expected);
+        assert_eq!(Slots::parse_totals("4,3,3,1"), expected);
+        assert_eq!(Slots::parse_totals("4/3/3/1"), expected);
+        assert_eq!(Slots::parse_totals("  4 / 3  /3, 1 "), expected);
+        assert_eq!(Slots::parse_totals(""), Ok([0; 9]));
+    }
+
+    #[test]
+    fn slot_totals_are_capped() {
+        assert_eq!(Slots::parse_totals("9 9 9 9 9 9 9 9 9"), Ok(Slots::MAX_BY_LEVEL));
+    }
+
+    #[test]
+    fn bad_slot_totals() {
+        assert_eq!(Slots::parse_totals("4 three 2"), Err("\"three\" isn't a number of slots".to_string()));
+        assert_eq!(Slots::parse_totals("4 -1"), Err("\"-1\" isn't a number of slots".to_string()));
+        assert_eq!(Slots::parse_totals("1 1 1 1 1 1 1 1 1 1"), Err("There are only 9 levels of slots".to_string()));
+    }
+
+    #[test]
+    fn ritual_book_is_optional_in_saves() {
+        // saved before there was a ritual book
+        let character = Character::from_serialized(&saved(&[("Detect Magic", false)]), &[]);
+        assert!(character.ritual_book.is_empty());
+
+        let mut character = character;
+        character.ritual_book.insert("Detect Magic".to_string());
+        let json = serde_json::to_value(character.serialize()).unwrap();
+        assert_eq!(json["ritual_book"], serde_json::json!(["Detect Magic"]));
+        let reloaded = Character::from_serialized(&serde_json::from_value(json).unwrap(), &[]);
+        assert_eq!(reloaded.ritual_book, character.ritual_book);
+    }
+
+    #[test]
+    fn ritual_book_spells_are_castable_unprepared() {
+        let character = Character::from_serialized(&saved(&[
+            ("Detect Magic", false),
+            ("Identify", false),
+            ("Shield", false),
+            ("Magic Missile", true),
+        ]), &[]);
+        let castable = |character: &Character| character.castable()
+            .map(|(spell, _)| spell.name().to_string())
+            .collect_vec();
+        assert_eq!(castable(&character), ["Magic Missile"]);
+
+        let mut page = CharacterPage::from(character);
+        let ids = page.character.spells[Level::L1].iter().map(|(spell, _)| spell.id()).collect_vec();
+        for id in ids {
+            page.update(Message::ToggleRitualBook(id), &[], 2, false);
+        }
+        // Shield isn't a ritual, so it can't be ritual cast even if it's marked
+        assert_eq!(castable(&page.character), ["Detect Magic", "Identify", "Magic Missile"]);
+    }
+
+    #[test]
+    fn set_total_clamps() {
+        let mut slots = Slots { total: 3, used: 3 };
+        slots.set_total(9, Level::L1);
+        assert_eq!(slots, Slots { total: 4, used: 3 });
+        slots.set_total(1, Level::L1);
+        assert_eq!(slots, Slots { total: 1, used: 1 });
+        slots.set_total(5, Level::L9);
+        assert_eq!(slots, Slots { total: 1, used: 1 });
+    }
+
+    #[test]
+    fn typing_a_slot_total() {
+        let mut page = CharacterPage::from(caster([(0, 0); 5]));
+        let update = |page: &mut CharacterPage, message| page.update(message, &[], 2, false);
+
+        assert!(!update(&mut page, Message::BeginEditSlots(Level::L2)));
+        assert!(!update(&mut page, Message::EditSlotsText(Level::L2, "2x".to_string())));
+        assert!(!update(&mut page, Message::EditSlotsText(Level::L2, "123".to_string())));
+        assert!(!update(&mut page, Message::EditSlotsText(Level::L2, "12".to_string())));
+        // the text for another level is ignored
+        assert!(!update(&mut page, Message::EditSlotsText(Level::L3, "1".to_string())));
+        assert!(!update(&mut page, Message::CommitEditSlots(Level::L3)));
+        assert!(page.editing_slots.is_some());
+        assert!(update(&mut page, Message::CommitEditSlots(Level::L2)));
+        assert_eq!(page.character.slots[1].total, 3);
+        assert!(page.editing_slots.is_none());
+
+        // committing the same total doesn't need a save
+        update(&mut page, Message::BeginEditSlots(Level::L2));
+        assert!(!update(&mut page, Message::CommitEditSlots(Level::L2)));
+
+        // clicking away commits it too
+        update(&mut page, Message::BeginEditSlots(Level::L1));
+        update(&mut page, Message::EditSlotsText(Level::L1, "2".to_string()));
+        assert!(update(&mut page, Message::ToggleCollapse));
+        assert_eq!(page.character.slots[0].total, 2);
+
+        // but not if it was cancelled
+        update(&mut page, Message::BeginEditSlots(Level::L1));
+        update(&mut page, Message::EditSlotsText(Level::L1, "4".to_string()));
+        page.cancel_slot_edit();
+        assert!(!update(&mut page, Message::ToggleCollapse));
+        assert_eq!(page.character.slots[0].total, 2);
+    }
+
+    fn id(name: &str) -> SpellId {
+        find_spell(name, &[]).unwrap().id()
+    }
+
+    fn cantrips(page: &CharacterPage) -> Vec<(String, bool)> {
+        page.character.spells[Level::Cantrip].iter()
+            .map(|(spell, prepared)| (spell.name().to_string(), *prepared))
+            .collect()
+    }
+
+    #[test]
+    fn removed_spells_go_back_in_front_of_their_neighbour() {
+        let saved = saved(&[("Fire Bolt", true), ("Light", true), ("Mage Hand", false), ("Prestidigitation", true)]);
+        let mut page = CharacterPage::from(Character::from_serialized(&saved, &[]));
+        let update = |page: &mut CharacterPage, message| page.update(message, &[], 2, false);
+
+        assert!(update(&mut page, Message::RemoveSpell(id("Light"))));
+        assert!(update(&mut page, Message::RemoveSpell(id("Fire Bolt"))));
+        assert!(update(&mut page, Message::AddSpell(id("Ray of Frost"))));
+        update(&mut page, Message::MoveSpell(id("Ray of Frost"), MoveSpell::Top));
+        assert_eq!(names(&page.character.spells[Level::Cantrip]), ["Ray of Frost", "Mage Hand", "Prestidigitation"]);
+
+        // Light was removed last, so it's first
+        assert!(update(&mut page, Message::RestoreRemoved(1)));
+        assert_eq!(cantrips(&page), [
+            ("Ray of Frost".to_string(), true),
+            ("Light".to_string(), true),
+            ("Mage Hand".to_string(), false),
+            ("Prestidigitation".to_string(), true),
+        ]);
+        // Light was already gone when Fire Bolt was removed, so it was in front of Mage Hand too
+        assert!(update(&mut page, Message::RestoreRemoved(0)));
+        assert_eq!(names(&page.character.spells[Level::Cantrip]), ["Ray of Frost", "Light", "Fire Bolt", "Mage Hand", "Prestidigitation"]);
+        assert!(page.recently_removed.is_empty());
+    }
+
+    #[test]
+    fn removed_spells_fall_back_to_their_index() {
+        let saved = saved(&[("Fire Bolt", true), ("Light", true), ("Mage Hand", false)]);
+        let mut page = CharacterPage::from(Character::from_serialized(&saved, &[]));
+        let update = |page: &mut CharacterPage, message| page.update(message, &[], 2, false);
+
+        // the spell after it is gone too
+        update(&mut page, Message::RemoveSpell(id("Light")));
+        update(&mut page, Message::RemoveSpell(id("Mage Hand")));
+        update(&mut page, Message::AddSpell(id("Ray of Frost")));
+        assert!(update(&mut page, Message::RestoreRemoved(1)));
+        assert_eq!(names(&page.character.spells[Level::Cantrip]), ["Fire Bolt", "Light", "Ray of Frost"]);
+
+        // and past the end of the list it's appended
+        update(&mut page, Message::RemoveSpell(id("Ray of Frost")));
+        update(&mut page, Message::RemoveSpell(id("Fire Bolt")));
+        assert!(update(&mut page, Message::RestoreRemoved(1)));
+        assert_eq!(names(&page.character.spells[Level::Cantrip]), ["Light", "Ray of Frost"]);
+    }
+
+    #[test]
+    fn restoring_a_spell_thats_back_does_nothing() {
+        let saved = saved(&[("Fire Bolt", true), ("Light", true)]);
+        let mut page = CharacterPage::from(Character::from_serialized(&saved, &[]));
+        let update = |page: &mut CharacterPage, message| page.update(message, &[], 2, false);
+
+        update(&mut page, Message::RemoveSpell(id("Light")));
+        update(&mut page, Message::AddSpell(id("Light")));
+        assert!(!update(&mut page, Message::RestoreRemoved(0)));
+        assert_eq!(names(&page.character.spells[Level::Cantrip]), ["Fire Bolt", "Light"]);
+        assert!(page.recently_removed.is_empty());
+        assert!(!update(&mut page, Message::RestoreRemoved(0)));
+    }
+
+    #[test]
+    fn restoring_puts_rituals_back_in_the_book() {
+        let saved = saved(&[("Detect Magic", false)]);
+        let mut character = Character::from_serialized(&saved, &[]);
+        character.ritual_book.insert("Detect Magic".to_string());
+        let mut page = CharacterPage::from(character);
+        let update = |page: &mut CharacterPage, message| page.update(message, &[], 2, false);
+
+        update(&mut page, Message::RemoveSpell(id("Detect Magic")));
+        assert!(page.character.ritual_book.is_empty());
+        update(&mut page, Message::RestoreRemoved(0));
+        assert!(page.character.ritual_book.contains("Detect Magic"));
+    }
+
+    #[test]
+    fn only_the_last_few_removed_spells_are_kept() {
+        let all = ["Acid Splash", "Blade Ward", "Dancing Lights", "Fire Bolt", "Friends", "Guidance",
+            "Light", "Mage Hand", "Mending", "Message", "Minor Illusion", "Poison Spray"];
+        let saved = saved(&all.map(|name| (name, false)));
+        let mut page = CharacterPage::from(Character::from_serialized(&saved, &[]));
+        for name in all {
+            page.update(Message::RemoveSpell(id(name)), &[], 2, false);
+        }
+        assert_eq!(page.recently_removed.len(), RemovedSpell::MAX);
+        assert_eq!(&*page.recently_removed[0].spell.name(), "Poison Spray");
+        assert_eq!(&*page.recently_removed[RemovedSpell::MAX - 1].spell.name(), "Dancing Lights");
+    }
+
+    fn on_tab(tab: Option<Level>) -> CharacterPage {
+        let saved = saved(&[("Fire Bolt", true), ("Magic Missile", true)]);
+        let mut page = CharacterPage::from(Character::from_serialized(&saved, &[]));
+        page.tab = tab;
+        page
+    }
+
+    #[test]
+    fn adding_a_spell_can_switch_to_its_tab() {
+        let mut page = on_tab(Some(Level::L1));
+        assert!(page.update(Message::AddSpell(id("Fireball")), &[], 2, true));
+        assert_eq!(page.tab, Some(Level::L3));
+        assert_eq!(page.status, None);
+        assert_eq!(names(&page.character.spells[Level::L3]), ["Fireball"]);
+    }
+
+    #[test]
+    fn adding_a_spell_can_say_which_tab_its_on() {
+        let mut page = on_tab(Some(Level::L1));
+        assert!(page.update(Message::AddSpell(id("Fireball")), &[], 2, false));
+        assert_eq!(page.tab, Some(Level::L1));
+        assert!(page.status.as_ref().unwrap().contains("Fireball"));
+        assert_eq!(page.status_tab, Some(Level::L3));
+
+        // the status's button goes there
+        page.update(Message::SpellTab(page.status_tab), &[], 2, false);
+        assert_eq!(page.tab, Some(Level::L3));
+        assert_eq!(page.status_tab, None);
+    }
+
+    #[test]
+    fn adding_a_spell_to_the_open_tab_says_nothing() {
+        for switch_tab in [true, false] {
+            let mut page = on_tab(Some(Level::L3));
+            page.update(Message::AddSpell(id("Fireball")), &[], 2, switch_tab);
+            assert_eq!(page.tab, Some(Level::L3));
+            assert_eq!(page.status, None);
+        }
+    }
+
+    #[test]
+    fn adding_a_spell_on_the_all_tab_views_it() {
+        for switch_tab in [true, false] {
+            let mut page = on_tab(None);
+            page.update(Message::AddSpell(id("Fireball")), &[], 2, switch_tab);
+            assert_eq!(page.tab, None);
+            assert_eq!(page.status, None);
+            assert_eq!(page.view_spell, Some(id("Fireball")));
+        }
+    }
+
+    #[test]
+    fn school_counts_follow_the_spellbook() {
+        let saved = saved(&[("Fire Bolt", true), ("Mage Hand", true), ("Shield", false), ("Fireball", true), ("Hold Person", false)]);
+        let mut page = CharacterPage::from(Character::from_serialized(&saved, &[]));
+        assert_eq!(page.character.school_counts(), [
+            (School::Abjuration, 1),
+            (School::Conjuration, 1),
+            (School::Enchantment, 1),
+            (School::Evocation, 2),
+        ]);
+
+        page.update(Message::RemoveSpell(id("Shield")), &[], 2, false);
+        page.update(Message::AddSpell(id("Magic Missile")), &[], 2, false);
+        assert_eq!(page.character.school_counts(), [
+            (School::Conjuration, 1),
+            (School::Enchantment, 1),
+            (School::Evocation, 3),
+        ]);
+        assert!(Character::new(Arc::from("Merric")).school_counts().is_empty());
+    }
+
+    #[test]
+    fn picking_a_school_filters_every_level() {
+        let saved = saved(&[("Fire Bolt", true), ("Mage Hand", true), ("Shield", false), ("Magic Missile", true), ("Hold Person", false)]);
+        let mut page = CharacterPage::from(Character::from_serialized(&saved, &[]));
+        let results = |page: &CharacterPage| page.search_results.iter()
+            .zip(&page.character.spells)
+            .flat_map(|(results, spells)| results.iter().map(|&idx| spells[idx].0.name().to_string()))
+            .collect_vec();
+
+        page.update(Message::Search(search::Message::PickSchool(School::Evocation)), &[], 2, false);
+        assert_eq!(results(&page), ["Fire Bolt", "Magic Missile"]);
+        // clicking it again clears it
+        page.update(Message::Search(search::Message::PickSchool(School::Evocation)), &[], 2, false);
+        assert_eq!(results(&page), ["Fire Bolt", "Mage Hand", "Shield", "Magic Missile", "Hold Person"]);
+    }
+}
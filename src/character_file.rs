@@ -0,0 +1,159 @@
+//! Open and closed characters are saved together in one document,
+//! `{"version": 2, "characters": [...], "closed": [...]}`, written all at once so that a crash
+//! can't leave half a character behind.
+//!
+//! Version 1 saved open and closed characters in two files, one character per line. Those are still
+//! read, and are backed up and replaced the first time characters are saved.
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::character::SerializeCharacter;
+use crate::error::{self, Error};
+
+pub const VERSION: u32 = 2;
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Characters {
+    pub version: u32,
+    pub characters: Vec<SerializeCharacter>,
+    #[serde(default)]
+    pub closed: Vec<SerializeCharacter>,
+}
+
+/// Reads the characters saved in `file`, or in `file` and `closed_file` if they're in the old line
+/// format. Missing files have no characters.
+pub fn read(file: &Path, closed_file: &Path) -> error::Result<Characters> {
+    let json = read_if_exists(file)?;
+    if let Ok(document) = serde_json::from_str::<Characters>(&json) {
+        return if document.version > VERSION {
+            Err(Error::NewerCharacterFile(document.version))
+        } else {
+            Ok(document)
+        };
+    }
+    log::info!("reading characters in the old format from {}", file.display());
+    Ok(Characters {
+        version: VERSION,
+        characters: read_lines(&json)?,
+        closed: read_lines(&read_if_exists(closed_file)?)?,
+    })
+}
+
+/// Reads the saved characters like [`read`]. If they can't be read, `file` is backed up and the
+/// error is returned with no characters, so that whatever is in it can't be lost by saving over it.
+pub fn load(file: &Path, closed_file: &Path) -> (Characters, Option<String>) {
+    match read(file, closed_file) {
+        Ok(characters) => (characters, None),
+        Err(e) => {
+            log::error!("failed to read characters: {e}");
+            let error = match back_up(file) {
+                Ok(backup) => format!("Couldn't read saved characters: {e}. They won't be saved over, and a copy is at {}", backup.display()),
+                Err(backup_error) => {
+                    log::error!("failed to back up {}: {backup_error}", file.display());
+                    format!("Couldn't read saved characters: {e}. They won't be saved over")
+                }
+            };
+            (Characters::default(), Some(error))
+        }
+    }
+}
+
+fn back_up(file: &Path) -> error::Result<PathBuf> {
+    let backup = with_suffix(file, ".unreadable.bak");
+    log::info!("backing up {} to {}", file.display(), backup.display());
+    fs::copy(file, &backup)?;
+    Ok(backup)
+}
+
+/// Saves `characters` to `file` by writing a temporary file and renaming it over `file`. If there
+/// are characters in the old format, `file` and `closed_file` are first backed up to `.v1.bak`
+/// files, then `closed_file` is removed.
+pub fn write(file: &Path, closed_file: &Path, characters: &Characters) -> error::Result<()> {
+    if closed_file.exists() {
+        for old in [file, closed_file] {
+            if old.exists() {
+                let backup = with_suffix(old, ".v1.bak");
+                log::info!("backing up {} to {}", old.display(), backup.display());
+                fs::copy(old, backup)?;
+            }
+        }
+    }
+    let temp = with_suffix(file, ".tmp");
+    fs::write(&temp, serde_json::to_vec(characters)?)?;
+    fs::rename(&temp, file)?;
+    match fs::remove_file(closed_file) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+fn read_if_exists(file: &Path) -> error::Result<String> {
+    match fs::read_to_string(file) {
+        Ok(json) => Ok(json),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn read_lines(json: &str) -> error::Result<Vec<SerializeCharacter>> {
+    json.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).map_err(Error::from))
+        .collect()
+}
+
+fn with_suffix(file: &Path, suffix: &str) -> PathBuf {
+    let mut name = file.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(json: &str) -> (tempfile::TempDir, PathBuf, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("characters.json");
+        let closed_file = dir.path().join("closed-characters.json");
+        fs::write(&file, json).unwrap();
+        (dir, file, closed_file)
+    }
+
+    #[test]
+    fn newer_file_is_an_error() {
+        let (_dir, file, closed_file) = files(r#"{"version": 3, "characters": [], "closed": []}"#);
+        assert!(matches!(read(&file, &closed_file), Err(Error::NewerCharacterFile(3))));
+    }
+
+    #[test]
+    fn corrupt_file_is_an_error() {
+        let (_dir, file, closed_file) = files(r#"{"version": 2, "charac"#);
+        assert!(matches!(read(&file, &closed_file), Err(Error::Json(_))));
+    }
+
+    #[test]
+    fn unreadable_files_are_backed_up() {
+        for json in [r#"{"version": 3, "characters": [], "closed": []}"#, r#"{"version": 2, "charac"#] {
+            let (_dir, file, closed_file) = files(json);
+            let (characters, error) = load(&file, &closed_file);
+            assert!(characters.characters.is_empty());
+            assert!(error.is_some());
+            assert_eq!(fs::read_to_string(with_suffix(&file, ".unreadable.bak")).unwrap(), json);
+            // the original is left alone too
+            assert_eq!(fs::read_to_string(&file).unwrap(), json);
+        }
+    }
+
+    #[test]
+    fn missing_file_has_no_characters() {
+        let dir = tempfile::tempdir().unwrap();
+        let (characters, error) = load(&dir.path().join("characters.json"), &dir.path().join("closed-characters.json"));
+        assert!(characters.characters.is_empty() && characters.closed.is_empty());
+        assert!(error.is_none());
+    }
+}
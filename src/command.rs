@@ -0,0 +1,216 @@
+//! A text box for running short commands, like `add "fireball" to Merric`, opened with Ctrl+;.
+//! Commands are parsed here, then turned into the same messages the buttons send.
+
+use iced::{Alignment, Length};
+use iced::widget::{button, container, text, text_input};
+use itertools::Itertools;
+
+use crate::{Container, Level, Location};
+use crate::character::Slots;
+use crate::spells::spell::fold;
+use crate::utils::Tap;
+
+pub const HELP: &str = "\
+add \"spell\" to character
+prepare all [level N] for character
+unprepare all [level N] for character
+slots character 4/3/2
+cast character level
+help
+Quote names with spaces in them";
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Input(String),
+    Submit,
+    Close,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Action {
+    Help,
+    Add { spell: String, character: String },
+    /// prepare (or unprepare) all of a character's spells, or just those of one level
+    Prepare { prepare: bool, level: Option<Level>, character: String },
+    Slots { character: String, totals: [u32; 9] },
+    Cast { character: String, level: Level },
+}
+
+pub struct CommandBox {
+    pub input: String,
+    pub id: text_input::Id,
+    /// the result of the last command, or why it couldn't run
+    pub output: Option<String>,
+}
+
+impl Default for CommandBox {
+    fn default() -> Self {
+        Self {
+            input: String::new(),
+            id: text_input::Id::unique(),
+            output: None,
+        }
+    }
+}
+
+impl CommandBox {
+    pub fn view<'s, 'c: 's>(&'s self) -> Container<'c> {
+        let message = crate::Message::CommandBox;
+        let input = row![
+            text_input("Command, or help", &self.input)
+                .id(self.id.clone())
+                .size(16)
+                .on_input(move |input| message(Message::Input(input)))
+                .on_submit(message(Message::Submit)),
+            button(text("Close").size(14))
+                .style(Location::Transparent)
+                .on_press(message(Message::Close)),
+        ].spacing(6)
+            .align_items(Alignment::Center);
+        col![input]
+            .spacing(4)
+            .tap_if_some(self.output.as_deref(), |col, output| col.push(text(output).size(14)))
+            .tap(container)
+            .padding(6)
+            .width(Length::Fill)
+            .style(Location::Tooltip)
+    }
+}
+
+/// Parses one command. See [`HELP`] for the syntax.
+pub fn parse(input: &str) -> Result<Action, String> {
+    let tokens = tokenize(input)?;
+    let Some((command, args)) = tokens.split_first() else {
+        return Err("Type a command, or help".into());
+    };
+    match command.to_lowercase().as_str() {
+        "help" | "?" => Ok(Action::Help),
+        "add" => {
+            let to = args.iter()
+                .rposition(|arg| arg.eq_ignore_ascii_case("to"))
+                .ok_or("Expected add \"spell\" to character")?;
+            let spell = args[..to].join(" ");
+            let character = args[to + 1..].join(" ");
+            if spell.is_empty() {
+                Err("Which spell should be added?".into())
+            } else if character.is_empty() {
+                Err(format!("Which character should {spell} be added to?"))
+            } else {
+                Ok(Action::Add { spell, character })
+            }
+        }
+        verb @ ("prepare" | "unprepare") => {
+            let prepare = verb == "prepare";
+            let rest = match args.split_first() {
+                Some((all, rest)) if all.eq_ignore_ascii_case("all") => rest,
+                _ => return Err(format!("Expected {verb} all [level N] for character")),
+            };
+            let (level, rest) = match rest {
+                [level, n, rest @ ..] if level.eq_ignore_ascii_case("level") => (Some(parse_level(n, 0)?), rest),
+                rest => (None, rest),
+            };
+            let character = match rest.split_first() {
+                Some((f, name)) if f.eq_ignore_ascii_case("for") && !name.is_empty() => name.join(" "),
+                _ => return Err(format!("Expected {verb} all [level N] for character")),
+            };
+            Ok(Action::Prepare { prepare, level, character })
+        }
+        "slots" => {
+            // the totals are everything at the end that's only digits and separators
+            let split = args.iter()
+                .rposition(|arg| !arg.chars().all(|c| c.is_ascii_digit() || c == '/' || c == ','))
+                .map_or(0, |idx| idx + 1);
+            let character = args[..split].join(" ");
+            if character.is_empty() {
+                return Err("Expected slots character 4/3/2".into());
+            }
+            if split == args.len() {
+                return Err(format!("How many slots should {character} have? Like 4/3/2"));
+            }
+            let totals = Slots::parse_totals(&args[split..].join(" "))?;
+            Ok(Action::Slots { character, totals })
+        }
+        "cast" => match args {
+            [name @ .., level] if !name.is_empty() => Ok(Action::Cast {
+                character: name.join(" "),
+                level: parse_level(level, 1)?,
+            }),
+            _ => Err("Expected cast character level".into()),
+        },
+        other => Err(format!("Unknown command \"{other}\", try help")),
+    }
+}
+
+/// Splits on whitespace, except inside double quotes
+fn tokenize(input: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        let mut token = String::new();
+        if c == '"' {
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some(c) => token.push(c),
+                    None => return Err("Missing a closing \"".into()),
+                }
+            }
+        } else {
+            token.push(c);
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == '"' {
+                    break;
+                }
+                token.push(c);
+                chars.next();
+            }
+        }
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+/// A spell level from `"3"` or `"3rd"`, at least `min`
+fn parse_level(str: &str, min: u8) -> Result<Level, String> {
+    let digits = str.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    digits.parse::<u8>()
+        .ok()
+        .filter(|&level| level >= min)
+        .and_then(Level::from_u8)
+        .ok_or_else(|| format!("\"{str}\" isn't a level from {min} to 9"))
+}
+
+/// The index of the name in `names` that `needle` refers to: the one equal to it ignoring case,
+/// else the only one that starts with it, else the only one that contains it. `what` names what's
+/// being searched for in the error.
+pub fn find<'a>(needle: &str, names: impl IntoIterator<Item = &'a str>, what: &str) -> Result<usize, String> {
+    let needle = fold(needle);
+    let names = names.into_iter().collect_vec();
+    let lower = names.iter()
+        .map(|name| fold(name))
+        .collect_vec();
+    if let Some(idx) = lower.iter().position(|name| *name == needle) {
+        return Ok(idx);
+    }
+    let starting = lower.iter()
+        .positions(|name| name.starts_with(&needle))
+        .collect_vec();
+    let containing = lower.iter()
+        .positions(|name| name.contains(&needle))
+        .collect_vec();
+    for found in [starting, containing] {
+        match found.as_slice() {
+            [] => {}
+            &[idx] => return Ok(idx),
+            several => return Err(format!(
+                "\"{needle}\" could be {}{}",
+                several.iter().take(5).map(|&idx| names[idx]).join(", "),
+                if several.len() > 5 { ", …" } else { "" },
+            )),
+        }
+    }
+    Err(format!("No {what} matches \"{needle}\""))
+}
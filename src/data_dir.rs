@@ -0,0 +1,159 @@
+//! Characters, custom spells, and preferences can be saved in a folder other than the default one,
+//! like a Dropbox or Syncthing folder, so that they follow the user between computers. Which folder
+//! that is gets saved in the default folder.
+
+use std::fs::{self, File};
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// The files kept in the data folder. Logs and spell overrides stay with each install.
+pub const FILES: [&str; 6] = [
+    "characters.json",
+    "closed-characters.json",
+    "custom-spells.json",
+    "preferences.json",
+    "custom-spells-history.json",
+    "search.json",
+];
+
+/// The folder saved in `file`, if one was chosen and it still exists
+pub fn read(file: &Path) -> Option<PathBuf> {
+    let dir = fs::read_to_string(file).ok()?;
+    let dir = PathBuf::from(dir.trim());
+    if dir.as_os_str().is_empty() {
+        None
+    } else if dir.is_dir() {
+        Some(dir)
+    } else {
+        log::warn!("data folder {} is missing, using the default folder", dir.display());
+        None
+    }
+}
+
+/// Saves `dir` as the data folder in `file`, or forgets the choice if `dir` is `default`
+pub fn save(file: &Path, dir: &Path, default: &Path) -> io::Result<()> {
+    if dir == default {
+        match fs::remove_file(file) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    } else {
+        fs::write(file, dir.to_string_lossy().as_bytes())
+    }
+}
+
+/// Copies the data files in `from` to `to`. If both folders have a file, `to` keeps whichever was
+/// modified more recently, first backing its own up to `<file>.bak` if it's replaced. `from` isn't
+/// changed. Copies keep the time they were modified, so that they aren't newer than a copy that
+/// was really changed since.
+///
+/// Empty files are ignored, since every file is created empty on startup and would otherwise
+/// replace the real one.
+pub fn migrate(from: &Path, to: &Path) -> io::Result<()> {
+    if from == to {
+        return Ok(());
+    }
+    for name in FILES {
+        let source = from.join(name);
+        let Ok(source_metadata) = fs::metadata(&source) else { continue };
+        if source_metadata.len() == 0 {
+            continue;
+        }
+        let modified = source_metadata.modified()?;
+        let dest = to.join(name);
+        match fs::metadata(&dest) {
+            Ok(dest_metadata) if dest_metadata.len() != 0 => {
+                if modified > dest_metadata.modified()? {
+                    log::warn!("{} is newer than {}, backing up the latter", source.display(), dest.display());
+                    fs::copy(&dest, to.join(format!("{name}.bak")))?;
+                    copy(&source, &dest, modified)?;
+                }
+            }
+            _ => copy(&source, &dest, modified)?,
+        }
+    }
+    Ok(())
+}
+
+fn copy(source: &Path, dest: &Path, modified: SystemTime) -> io::Result<()> {
+    fs::copy(source, dest)?;
+    File::options().write(true).open(dest)?.set_modified(modified)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    /// writes `contents` to `name` in `dir`, last modified `age` seconds ago
+    fn write(dir: &Path, name: &str, contents: &str, age: u64) {
+        let path = dir.join(name);
+        fs::write(&path, contents).unwrap();
+        File::options().write(true).open(&path).unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(age))
+            .unwrap();
+    }
+
+    fn read(dir: &Path, name: &str) -> String {
+        fs::read_to_string(dir.join(name)).unwrap()
+    }
+
+    #[test]
+    fn copies_missing_files() {
+        let (from, to) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        write(from.path(), "characters.json", "from", 60);
+        migrate(from.path(), to.path()).unwrap();
+        assert_eq!(read(to.path(), "characters.json"), "from");
+        assert!(!to.path().join("characters.json.bak").exists());
+        assert!(!to.path().join("preferences.json").exists());
+    }
+
+    #[test]
+    fn keeps_newer_destination() {
+        let (from, to) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        write(from.path(), "characters.json", "from", 60);
+        write(to.path(), "characters.json", "to", 10);
+        migrate(from.path(), to.path()).unwrap();
+        assert_eq!(read(to.path(), "characters.json"), "to");
+        assert!(!to.path().join("characters.json.bak").exists());
+    }
+
+    #[test]
+    fn replaces_older_destination() {
+        let (from, to) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        write(from.path(), "characters.json", "from", 10);
+        write(to.path(), "characters.json", "to", 60);
+        migrate(from.path(), to.path()).unwrap();
+        assert_eq!(read(to.path(), "characters.json"), "from");
+        assert_eq!(read(to.path(), "characters.json.bak"), "to");
+        assert_eq!(read(from.path(), "characters.json"), "from");
+    }
+
+    #[test]
+    fn ignores_empty_files() {
+        let (from, to) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        write(from.path(), "characters.json", "", 0);
+        write(to.path(), "characters.json", "to", 60);
+        write(from.path(), "preferences.json", "from", 60);
+        write(to.path(), "preferences.json", "", 0);
+        migrate(from.path(), to.path()).unwrap();
+        assert_eq!(read(to.path(), "characters.json"), "to");
+        assert_eq!(read(to.path(), "preferences.json"), "from");
+    }
+
+    #[test]
+    fn copies_keep_their_modified_time() {
+        let (from, to) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        write(from.path(), "characters.json", "from", 600);
+        migrate(from.path(), to.path()).unwrap();
+        let modified = |dir: &Path| fs::metadata(dir.join("characters.json")).unwrap().modified().unwrap();
+        assert_eq!(modified(from.path()), modified(to.path()));
+
+        // so migrating back doesn't think the copy is newer than a real change made after it
+        write(from.path(), "characters.json", "changed", 60);
+        migrate(to.path(), from.path()).unwrap();
+        assert_eq!(read(from.path(), "characters.json"), "changed");
+    }
+}
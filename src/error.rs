@@ -13,6 +13,10 @@ pub enum Error {
     Io(#[from] io::Error),
     #[error("Error updating: {0}")]
     Update(#[from] UpdateError),
+    #[error("Image error {0}")]
+    Image(#[from] image::ImageError),
+    #[error("Characters were saved by a newer version (format {0})")]
+    NewerCharacterFile(u32),
 }
 
 #[derive(Error, Debug)]
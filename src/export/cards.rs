@@ -0,0 +1,260 @@
+//! Spells as card images to print, for people who play at a table.
+//!
+//! Cards are poker card sized at 300 dpi, drawn with the bundled Arial. The description shrinks
+//! until it fits, and is cut off with an ellipsis if it's still too long at the smallest size.
+
+use std::mem;
+use std::path::{Path, PathBuf};
+
+use fontdue::{Font, FontSettings};
+use image::{Rgba, RgbaImage};
+use once_cell::sync::Lazy;
+
+use crate::error;
+use crate::export;
+use crate::export::model::SpellText;
+use crate::spells::spell::Spell;
+
+pub const WIDTH: u32 = 750;
+pub const HEIGHT: u32 = 1050;
+const MARGIN: f32 = 45.0;
+/// line height as a multiple of the font size
+const LINE_SPACING: f32 = 1.25;
+
+const NAME_SIZE: u16 = 48;
+const SUBTITLE_SIZE: u16 = 26;
+const STATS_SIZE: u16 = 24;
+const DESC_SIZE: u16 = 26;
+const MIN_DESC_SIZE: u16 = 12;
+
+const BACKGROUND: Rgba<u8> = Rgba([250, 247, 240, 255]);
+const INK: Rgba<u8> = Rgba([30, 30, 30, 255]);
+
+static FONT: Lazy<Font> = Lazy::new(|| Font::from_bytes(
+    include_bytes!("../../resources/arial.ttf") as &[u8],
+    FontSettings::default(),
+).expect("failed to read arial.ttf"));
+
+/// Splits `text` into lines no wider than `max_width`, breaking between words. Each line of `text`
+/// starts a new line, and a word wider than `max_width` gets a line to itself. `width` measures a
+/// string.
+pub fn wrap(text: &str, max_width: f32, width: impl Fn(&str) -> f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            if line.is_empty() {
+                line.push_str(word);
+                continue;
+            }
+            let longer = format!("{line} {word}");
+            if width(&longer) <= max_width {
+                line = longer;
+            } else {
+                lines.push(mem::replace(&mut line, word.to_string()));
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// The largest size from `max_size` down to `min_size` at which `text` wraps into `max_width`
+/// without being taller than `max_height`, and its lines at that size. If it doesn't fit even at
+/// `min_size`, the lines that do fit are returned, with the last one ending in an ellipsis.
+/// `width` measures a string at a size.
+pub fn fit(
+    text: &str,
+    max_width: f32,
+    max_height: f32,
+    max_size: u16,
+    min_size: u16,
+    width: impl Fn(&str, f32) -> f32,
+) -> (f32, Vec<String>) {
+    let max_lines = |size: f32| (max_height / (size * LINE_SPACING)) as usize;
+    for size in (min_size..=max_size).rev() {
+        let size = f32::from(size);
+        let lines = wrap(text, max_width, |str| width(str, size));
+        if lines.len() <= max_lines(size) {
+            return (size, lines);
+        }
+    }
+
+    let size = f32::from(min_size);
+    let mut lines = wrap(text, max_width, |str| width(str, size));
+    lines.truncate(max_lines(size));
+    if let Some(last) = lines.last_mut() {
+        while !last.is_empty() && width(&format!("{last}…"), size) > max_width {
+            let end = last.rfind(' ').unwrap_or(0);
+            last.truncate(end);
+        }
+        last.push('…');
+    }
+    (size, lines)
+}
+
+fn text_width(text: &str, size: f32) -> f32 {
+    text.chars()
+        .map(|c| FONT.metrics(c, size).advance_width)
+        .sum()
+}
+
+/// An image being drawn on from the top down
+struct Card {
+    image: RgbaImage,
+    /// the top of the next line
+    y: f32,
+}
+
+impl Card {
+    fn new() -> Self {
+        let mut image = RgbaImage::from_pixel(WIDTH, HEIGHT, BACKGROUND);
+        // a border, so the cards are easy to cut out
+        for x in 0..WIDTH {
+            for y in (0..4).chain(HEIGHT - 4..HEIGHT) {
+                image.put_pixel(x, y, INK);
+            }
+        }
+        for y in 0..HEIGHT {
+            for x in (0..4).chain(WIDTH - 4..WIDTH) {
+                image.put_pixel(x, y, INK);
+            }
+        }
+        Self { image, y: MARGIN }
+    }
+
+    fn line(&mut self, text: &str, size: f32, centered: bool) {
+        let x = if centered {
+            (WIDTH as f32 - text_width(text, size)) / 2.0
+        } else {
+            MARGIN
+        };
+        self.draw(text, x, size);
+        self.y += size * LINE_SPACING;
+    }
+
+    fn rule(&mut self) {
+        let y = (self.y + 6.0) as u32;
+        for x in MARGIN as u32..WIDTH - MARGIN as u32 {
+            for y in y..y + 2 {
+                self.image.put_pixel(x, y, INK);
+            }
+        }
+        self.y += 16.0;
+    }
+
+    /// Draws `text` with its top left corner at `x`, [`Self::y`]
+    fn draw(&mut self, text: &str, mut x: f32, size: f32) {
+        let ascent = FONT.horizontal_line_metrics(size)
+            .map_or(size, |metrics| metrics.ascent);
+        let baseline = self.y + ascent;
+        for c in text.chars() {
+            let (metrics, coverage) = FONT.rasterize(c, size);
+            let left = x + metrics.xmin as f32;
+            let top = baseline - metrics.height as f32 - metrics.ymin as f32;
+            for (idx, &coverage) in coverage.iter().enumerate() {
+                let px = left + (idx % metrics.width) as f32;
+                let py = top + (idx / metrics.width) as f32;
+                if coverage == 0 || px < 0.0 || py < 0.0 {
+                    continue;
+                }
+                let (px, py) = (px as u32, py as u32);
+                if px >= WIDTH || py >= HEIGHT {
+                    continue;
+                }
+                let alpha = f32::from(coverage) / 255.0;
+                let pixel = self.image.get_pixel_mut(px, py);
+                for (channel, ink) in pixel.0.iter_mut().zip(INK.0).take(3) {
+                    *channel = (f32::from(*channel) * (1.0 - alpha) + f32::from(ink) * alpha) as u8;
+                }
+            }
+            x += metrics.advance_width;
+        }
+    }
+}
+
+/// Draws `spell` as a card
+pub fn render(spell: &Spell) -> RgbaImage {
+    let mut card = Card::new();
+    let max_width = WIDTH as f32 - 2.0 * MARGIN;
+
+    let text = SpellText::from(spell);
+    let (size, name_lines) = fit(&text.name, max_width, 2.0 * f32::from(NAME_SIZE) * LINE_SPACING, NAME_SIZE, SUBTITLE_SIZE, text_width);
+    for line in &name_lines {
+        card.line(line, size, true);
+    }
+    let subtitle = if text.ritual { format!("{} (ritual)", text.level_line) } else { text.level_line };
+    card.line(&subtitle, f32::from(SUBTITLE_SIZE), true);
+    card.rule();
+
+    let stats = [
+        Some(format!("Casting time: {}", text.casting_time)),
+        text.range.map(|range| format!("Range: {range}")),
+        text.components.map(|components| format!("Components: {components}")),
+        text.duration.map(|duration| format!("Duration: {duration}")),
+    ];
+    let size = f32::from(STATS_SIZE);
+    for stat in stats.into_iter().flatten() {
+        for line in wrap(&stat, max_width, |str| text_width(str, size)) {
+            card.line(&line, size, false);
+        }
+    }
+    card.rule();
+
+    let description = match text.higher_levels {
+        Some(higher) => format!("{}\nAt higher levels. {higher}", text.description),
+        None => text.description,
+    };
+    let max_height = HEIGHT as f32 - MARGIN - card.y;
+    let (size, lines) = fit(&description, max_width, max_height, DESC_SIZE, MIN_DESC_SIZE, text_width);
+    for line in &lines {
+        card.line(line, size, false);
+    }
+
+    card.image
+}
+
+/// Saves `spell`'s card in `dir`, named after the spell
+pub fn save(spell: &Spell, dir: &Path) -> error::Result<PathBuf> {
+    let path = dir.join(format!("{}.png", export::file_name(&spell.name())));
+    render(spell).save(&path)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// every character is half of `size` wide
+    fn half_width(str: &str, size: f32) -> f32 {
+        str.chars().map(|_| size / 2.0).sum()
+    }
+
+    #[test]
+    fn wrap_breaks_between_words() {
+        let lines = wrap("the quick brown fox jumps", 11.0, |str| half_width(str, 2.0));
+        assert_eq!(lines, ["the quick", "brown fox", "jumps"]);
+    }
+
+    #[test]
+    fn wrap_keeps_lines_and_long_words() {
+        let lines = wrap("a\nincomprehensibilities b", 5.0, |str| half_width(str, 2.0));
+        assert_eq!(lines, ["a", "incomprehensibilities", "b"]);
+    }
+
+    #[test]
+    fn fit_shrinks_until_it_fits() {
+        // one word per line until 8, where two fit per line and two lines are 20 tall
+        let (size, lines) = fit("aaaa aaaa aaaa aaaa", 40.0, 40.0, 20, 4, half_width);
+        assert!((size - 8.0).abs() < f32::EPSILON);
+        assert_eq!(lines, ["aaaa aaaa", "aaaa aaaa"]);
+    }
+
+    #[test]
+    fn fit_cuts_off_with_an_ellipsis() {
+        // only one line fits, and "aaaa aaaa…" is one character too wide
+        let (size, lines) = fit("aaaa aaaa aaaa aaaa", 39.0, 10.0, 8, 8, half_width);
+        assert!((size - 8.0).abs() < f32::EPSILON);
+        assert_eq!(lines, ["aaaa…"]);
+    }
+}
@@ -0,0 +1,49 @@
+//! One character in a file of their own, to move them to another computer without copying every
+//! character.
+
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::character::{Character, SerializeCharacter};
+use crate::error;
+use crate::spells::spell::{CustomSpell, Spell};
+
+/// Files are named `<name>.spellchar.json`
+pub const EXTENSION: &str = "spellchar.json";
+
+#[derive(Serialize, Deserialize)]
+pub struct CharacterFile {
+    pub character: SerializeCharacter,
+    /// the custom spells the character knows, since the other computer might not have them
+    #[serde(default)]
+    pub custom_spells: Vec<CustomSpell>,
+}
+
+impl CharacterFile {
+    pub fn new(character: &Character) -> Self {
+        let custom_spells = character.spells.iter()
+            .flatten()
+            .filter_map(|(spell, _)| match spell {
+                Spell::Custom(custom) => Some((**custom).clone()),
+                Spell::Static(_) => None,
+            })
+            .collect();
+        Self {
+            character: character.serialize(),
+            custom_spells,
+        }
+    }
+
+    pub fn read(file: &Path) -> error::Result<Self> {
+        let json = fs::read_to_string(file)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    pub fn save(&self, file: &Path) -> error::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(file, json)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,208 @@
+//! Spell lists as csv, for people who manage their characters in spreadsheets.
+
+use std::sync::Arc;
+
+use crate::character::Character;
+use crate::export::model::{CharacterText, KnownSpellText};
+use crate::spells::spell::{CustomSpell, find_spell, fold, Spell};
+use crate::SPELLS;
+
+const HEADER: [&str; 9] = ["Name", "Level", "School", "Prepared", "Casting Time", "Concentration", "Ritual", "Ritual Book", "Source"];
+
+/// One row per spell `character` knows
+pub fn export(character: &Character) -> String {
+    let mut csv = String::new();
+    write_record(&mut csv, HEADER);
+    let yes_no = |b: bool| if b { "yes" } else { "no" };
+    for level in CharacterText::from(character).levels {
+        for KnownSpellText { spell: text, prepared, in_ritual_book } in level.spells {
+            write_record(&mut csv, [
+                &text.name,
+                &level.level.to_string(),
+                &text.school,
+                yes_no(prepared),
+                &text.casting_time,
+                yes_no(text.concentration),
+                yes_no(text.ritual),
+                yes_no(in_ritual_book),
+                &text.source,
+            ]);
+        }
+    }
+    csv
+}
+
+pub struct Import {
+    /// the spell, and if it's prepared (if there was a prepared column)
+    pub spells: Vec<(Spell, Option<bool>)>,
+    /// names that didn't match any spell
+    pub unmatched: Vec<String>,
+    /// names of the spells marked as in the ritual book
+    pub ritual_book: Vec<String>,
+}
+
+/// Reads spells by name from `csv`. The header row is optional, but without it the first column
+/// has to be the spell name and prepared state can't be read.
+pub fn import(csv: &str, custom: &[Arc<CustomSpell>]) -> Import {
+    let mut records = parse(csv).into_iter().peekable();
+    let header = records.next_if(|record| record.first()
+        .filter(|first| first.trim().eq_ignore_ascii_case("name"))
+        .is_some());
+    let column = |name: &str| header.as_ref()
+        .and_then(|header| header.iter().position(|col| col.trim().eq_ignore_ascii_case(name)));
+    let name_col = column("name").unwrap_or(0);
+    let prepared_col = column("prepared");
+    let ritual_book_col = column("ritual book");
+    let is_yes = |field: &str| matches!(field.trim().to_lowercase().as_str(), "yes" | "y" | "true" | "x" | "1");
+
+    let mut import = Import { spells: Vec::new(), unmatched: Vec::new(), ritual_book: Vec::new() };
+    for record in records {
+        let Some(name) = record.get(name_col).map(|name| name.trim()).filter(|name| !name.is_empty()) else {
+            continue;
+        };
+        let prepared = prepared_col
+            .and_then(|col| record.get(col))
+            .map(|prepared| is_yes(prepared));
+        let in_ritual_book = ritual_book_col
+            .and_then(|col| record.get(col))
+            .map_or(false, |in_book| is_yes(in_book));
+        match find_spell(name, custom).or_else(|| find_spell_ignore_case(name, custom)) {
+            Some(spell) => {
+                if in_ritual_book && spell.ritual() {
+                    import.ritual_book.push(spell.name().to_string());
+                }
+                import.spells.push((spell, prepared));
+            }
+            None => import.unmatched.push(name.to_string()),
+        }
+    }
+    import
+}
+
+fn find_spell_ignore_case(name: &str, custom: &[Arc<CustomSpell>]) -> Option<Spell> {
+    let name = fold(name);
+    SPELLS.iter()
+        .find(|spell| spell.name_lower == name)
+        .map(Spell::Static)
+        .or_else(|| custom.iter()
+            .find(|spell| spell.name_lower == name)
+            .cloned()
+            .map(Spell::Custom))
+}
+
+fn write_record<'a, I: IntoIterator<Item=&'a str>>(csv: &mut String, fields: I) {
+    for (i, field) in fields.into_iter().enumerate() {
+        if i != 0 {
+            csv.push(',');
+        }
+        if field.contains([',', '"', '\n', '\r']) {
+            csv.push('"');
+            csv.push_str(&field.replace('"', "\"\""));
+            csv.push('"');
+        } else {
+            csv.push_str(field);
+        }
+    }
+    csv.push_str("\r\n");
+}
+
+/// Splits `csv` into records of fields, handling quoted fields (which can contain commas, escaped
+/// `""` quotes, and newlines). Blank lines are skipped.
+fn parse(csv: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = csv.chars().peekable();
+    while let Some(char) = chars.next() {
+        match (char, in_quotes) {
+            ('"', true) => if chars.next_if_eq(&'"').is_some() {
+                field.push('"');
+            } else {
+                in_quotes = false;
+            },
+            ('"', false) if field.is_empty() => in_quotes = true,
+            (',', false) => record.push(std::mem::take(&mut field)),
+            ('\r' | '\n', false) => {
+                if char == '\r' {
+                    chars.next_if_eq(&'\n');
+                }
+                if !field.is_empty() || !record.is_empty() {
+                    record.push(std::mem::take(&mut field));
+                    records.push(std::mem::take(&mut record));
+                }
+            }
+            (char, _) => field.push(char),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::spells::data::Level;
+
+    use super::*;
+
+    fn custom(name: &str, level: Level) -> Arc<CustomSpell> {
+        let mut spell = CustomSpell::new(name.to_string());
+        spell.level = level;
+        spell.refold();
+        Arc::new(spell)
+    }
+
+    fn spell_set(spells: impl IntoIterator<Item=(Spell, bool)>) -> Vec<(String, bool)> {
+        let mut set = spells.into_iter()
+            .map(|(spell, prepared)| (spell.name().to_string(), prepared))
+            .collect::<Vec<_>>();
+        set.sort();
+        set
+    }
+
+    #[test]
+    fn export_then_import_round_trips() {
+        let custom = [custom("Bolt, \"Greater\"", Level::L2)];
+        let mut character = Character::new(Arc::from("Merric"));
+        character.spells[Level::Cantrip].push((find_spell("Fire Bolt", &[]).unwrap(), true));
+        character.spells[Level::L1].push((find_spell("Magic Missile", &[]).unwrap(), true));
+        character.spells[Level::L1].push((find_spell("Detect Magic", &[]).unwrap(), false));
+        character.spells[Level::L2].push((Spell::Custom(Arc::clone(&custom[0])), false));
+        character.ritual_book.insert("Detect Magic".into());
+
+        let csv = export(&character);
+        assert!(csv.contains("\"Bolt, \"\"Greater\"\"\",2,"));
+        let import = import(&csv, &custom);
+        assert!(import.unmatched.is_empty());
+        assert_eq!(import.ritual_book, ["Detect Magic"]);
+        let imported = import.spells.into_iter()
+            .map(|(spell, prepared)| (spell, prepared.unwrap()));
+        let known = character.spells.iter().flatten().cloned();
+        assert_eq!(spell_set(imported), spell_set(known));
+    }
+
+    #[test]
+    fn import_reports_unmatched_names() {
+        let import = import("Name,Prepared\nfireball,yes\nNot A Spell,no\n", &[]);
+        assert_eq!(spell_set(import.spells.into_iter().map(|(spell, prepared)| (spell, prepared.unwrap()))), [("Fireball".to_string(), true)]);
+        assert_eq!(import.unmatched, ["Not A Spell"]);
+    }
+
+    #[test]
+    fn import_without_a_header_reads_names() {
+        let import = import("Shield\r\n\r\nSleep,extra\r\n", &[]);
+        assert!(import.spells.iter().all(|(_, prepared)| prepared.is_none()));
+        assert_eq!(import.spells.iter().map(|(spell, _)| spell.name().to_string()).collect::<Vec<_>>(), ["Shield", "Sleep"]);
+    }
+
+    #[test]
+    fn parses_quoted_fields() {
+        assert_eq!(
+            parse("a,\"b, c\",\"say \"\"hi\"\"\"\n\"two\nlines\",d"),
+            [vec!["a", "b, c", "say \"hi\""], vec!["two\nlines", "d"]],
+        );
+    }
+}
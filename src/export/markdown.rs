@@ -0,0 +1,41 @@
+//! A character's spellbook as Markdown, to print or read away from the program.
+
+use std::fmt::Write as _;
+
+use crate::character::Character;
+use crate::export::model::{CharacterText, KnownSpellText};
+
+/// Every spell `character` knows, by level, with its full text. Prepared leveled spells are marked.
+pub fn spellbook(character: &Character) -> String {
+    let character = CharacterText::from(character);
+    let mut md = format!("# {}'s Spellbook\n", character.name);
+    for level in character.levels {
+        let _ = write!(md, "\n## {}\n", level.heading);
+        for KnownSpellText { spell: text, prepared, .. } in level.spells {
+            // cantrips are always prepared
+            let prepared = if prepared && level.level != 0 { " (prepared)" } else { "" };
+            let _ = write!(md, "\n### {}{prepared}\n\n", text.name);
+            let ritual = if text.ritual { " (ritual)" } else { "" };
+            let _ = writeln!(md, "*{}{ritual}*\n", text.level_line);
+            let stats = [
+                Some(("Casting Time", text.casting_time)),
+                text.range.map(|range| ("Range", range)),
+                text.components.map(|components| ("Components", components)),
+                text.duration.map(|duration| ("Duration", duration)),
+            ];
+            for (label, stat) in stats.into_iter().flatten() {
+                // two trailing spaces are a line break
+                let _ = writeln!(md, "**{label}:** {stat}  ");
+            }
+            md.push('\n');
+            for paragraph in text.description.lines().filter(|line| !line.trim().is_empty()) {
+                let _ = writeln!(md, "{paragraph}\n");
+            }
+            if let Some(higher) = text.higher_levels {
+                let _ = writeln!(md, "***At Higher Levels.*** {higher}\n");
+            }
+            let _ = writeln!(md, "*{}*", text.about);
+        }
+    }
+    md
+}
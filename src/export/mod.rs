@@ -0,0 +1,53 @@
+//! Writing characters and spells to files for use outside of this program.
+
+pub mod cards;
+pub mod character;
+pub mod csv;
+pub mod markdown;
+pub mod model;
+
+/// `name` with the characters that some systems don't allow in file names, like `/` and `:`,
+/// replaced by `_`, for naming an exported file after a spell or character
+pub fn file_name(name: &str) -> String {
+    let name = name.chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>();
+    // Windows drops these from the end of a file name
+    let name = name.trim_end_matches(['.', ' ']);
+    if name.is_empty() {
+        "_".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_names_keep_ordinary_names() {
+        assert_eq!(file_name("Tasha's Hideous Laughter"), "Tasha's Hideous Laughter");
+        assert_eq!(file_name("Merric (imported 2)"), "Merric (imported 2)");
+        assert_eq!(file_name("Mêlée"), "Mêlée");
+    }
+
+    #[test]
+    fn file_names_replace_separators_and_reserved_characters() {
+        assert_eq!(file_name("Fire/Ice Bolt"), "Fire_Ice Bolt");
+        assert_eq!(file_name("..\\..\\evil"), ".._.._evil");
+        assert_eq!(file_name("What? A: <spell>*"), "What_ A_ _spell__");
+        assert_eq!(file_name("tab\there"), "tab_here");
+    }
+
+    #[test]
+    fn file_names_are_never_empty_or_end_in_dots() {
+        assert_eq!(file_name("Spell..."), "Spell");
+        assert_eq!(file_name(""), "_");
+        assert_eq!(file_name(". ."), "_");
+    }
+}
@@ -0,0 +1,179 @@
+//! A spell's display text, worked out in one place so that the spell view and every exporter show
+//! spells the same way, and a character's spells in that form.
+
+use serde::Serialize;
+
+use crate::character::Character;
+use crate::spells::data::Level;
+use crate::spells::spell::Spell;
+use crate::utils::ListGrammaticallyExt;
+
+/// Everything about a spell as it's shown. Serializes with its fields in this order.
+#[derive(Debug, Clone, Serialize)]
+pub struct SpellText {
+    pub name: String,
+    /// `Cantrip`, `1st`, `2nd`, ...
+    pub level: String,
+    /// like `3rd-level evocation` or `Evocation cantrip`
+    pub level_line: String,
+    pub school: String,
+    /// both casting times, if there's an alternative
+    pub casting_time: String,
+    pub range: Option<String>,
+    pub components: Option<String>,
+    pub duration: Option<String>,
+    pub ritual: bool,
+    pub concentration: bool,
+    /// like `Bard, Sorcerer, and Wizard`
+    pub classes: String,
+    pub source: String,
+    /// like `Player's Handbook page 241`
+    pub source_line: String,
+    /// like `A Bard, Sorcerer, and Wizard spell, from Player's Handbook page 241`
+    pub about: String,
+    pub description: String,
+    pub higher_levels: Option<String>,
+}
+
+impl From<&Spell> for SpellText {
+    fn from(spell: &Spell) -> Self {
+        let school = spell.school().to_string();
+        let level_line = match spell.level() {
+            Level::Cantrip => format!("{school} cantrip"),
+            level => format!("{level}-level {}", school.to_lowercase()),
+        };
+        let classes = spell.classes().iter().list_grammatically();
+        let source = spell.source().to_string();
+        let source_line = match spell.page() {
+            Some(page) => format!("{source} page {page}"),
+            None => source.clone(),
+        };
+        let an = if classes.starts_with('A') { "n" } else { "" };
+        let about = format!("A{an} {classes} spell, from {source_line}");
+        Self {
+            name: spell.name().to_string(),
+            level: spell.level().to_string(),
+            level_line,
+            school,
+            casting_time: spell.casting_times().to_string(),
+            range: spell.range().map(str::to_string),
+            components: spell.components().map(ToString::to_string),
+            duration: spell.duration().map(str::to_string),
+            ritual: spell.ritual(),
+            concentration: spell.concentration(),
+            classes,
+            source,
+            source_line,
+            about,
+            description: spell.description().to_string(),
+            higher_levels: spell.higher_levels().map(str::to_string),
+        }
+    }
+}
+
+/// A character's spells as they're exported, by level. Serializes with its fields in this order.
+#[derive(Debug, Clone, Serialize)]
+pub struct CharacterText {
+    pub name: String,
+    /// character level, if the user has set it
+    pub level: Option<u8>,
+    pub class: Option<String>,
+    /// only the levels they know spells of
+    pub levels: Vec<LevelText>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LevelText {
+    /// 0 for cantrips
+    pub level: u8,
+    /// `Cantrips`, `1st Level`, ...
+    pub heading: String,
+    pub spells: Vec<KnownSpellText>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KnownSpellText {
+    pub spell: SpellText,
+    /// as it's saved. Cantrips are always prepared, whatever this says
+    pub prepared: bool,
+    pub in_ritual_book: bool,
+}
+
+impl From<&Character> for CharacterText {
+    fn from(character: &Character) -> Self {
+        let levels = Level::ALL.into_iter()
+            .zip(&character.spells)
+            .filter(|(_, spells)| !spells.is_empty())
+            .map(|(level, spells)| LevelText {
+                level: level as u8,
+                heading: match level {
+                    Level::Cantrip => "Cantrips".to_string(),
+                    level => format!("{level} Level"),
+                },
+                spells: spells.iter()
+                    .map(|(spell, prepared)| KnownSpellText {
+                        spell: SpellText::from(spell),
+                        prepared: *prepared,
+                        in_ritual_book: character.ritual_book.contains(&*spell.name()),
+                    })
+                    .collect(),
+            })
+            .collect();
+        Self {
+            name: character.name.to_string(),
+            level: character.level,
+            class: character.class.map(|class| class.to_string()),
+            levels,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::spells::data::{CastingTime, Class, Components, School};
+    use crate::spells::spell::{CustomSpell, find_spell};
+
+    use super::*;
+
+    fn json<T: Serialize>(text: &T) -> String {
+        serde_json::to_string_pretty(text).unwrap()
+    }
+
+    #[test]
+    fn static_spell_golden() {
+        let fireball = find_spell("Fireball", &[]).unwrap();
+        assert_eq!(json(&SpellText::from(&fireball)), include_str!("golden/fireball.json").trim_end());
+    }
+
+    #[test]
+    fn custom_spell_golden() {
+        let mut spell = CustomSpell::new("Frost Lance".to_string());
+        spell.level = Level::L2;
+        spell.school = School::Evocation;
+        spell.casting_time = CastingTime::BonusAction;
+        spell.alt_casting_time = Some(CastingTime::Minute(1));
+        spell.range = Some("60 feet".to_string());
+        spell.components = Some(Components { v: true, s: true, m: Some("a shard of ice".to_string()) });
+        spell.duration = Some("Instantaneous".to_string());
+        spell.description = "A lance of ice flies at a creature within range.\nIt takes 3d8 cold damage.".to_string();
+        spell.higher_levels = Some("The damage increases by 1d8 for each slot level above 2nd.".to_string());
+        spell.classes = vec![Class::Sorcerer, Class::Wizard];
+        spell.refold();
+        let spell = Spell::Custom(Arc::new(spell));
+        assert_eq!(json(&SpellText::from(&spell)), include_str!("golden/frost_lance.json").trim_end());
+    }
+
+    #[test]
+    fn small_character_golden() {
+        let mut character = Character::new(Arc::from("Merric"));
+        character.level = Some(3);
+        character.class = Some(Class::Wizard);
+        character.spells[Level::Cantrip].push((find_spell("Fire Bolt", &[]).unwrap(), true));
+        character.spells[Level::L1].push((find_spell("Magic Missile", &[]).unwrap(), true));
+        let text = CharacterText::from(&character);
+        assert_eq!(text.levels.iter().map(|level| &*level.heading).collect::<Vec<_>>(), ["Cantrips", "1st Level"]);
+        assert_eq!(crate::export::markdown::spellbook(&character), include_str!("golden/spellbook.md"));
+    }
+}
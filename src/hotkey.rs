@@ -1,4 +1,5 @@
 use iced::keyboard::{self, KeyCode, Modifiers};
+use iced_native::event;
 
 use crate::Level;
 
@@ -20,49 +21,90 @@ pub enum Message {
     Redo,
     CharacterTab(Option<Level>),
     AddSpell(usize),
+    /// choose which character to add the selected search result to
+    AddSpellPicker,
+    Escape,
     /// true -> forwards, false -> backwards
     CustomSpellNextField(bool),
     /// ±1 up or down
     CharacterSpellUpDown(isize),
+    /// go to the closed characters on the settings page
+    ClosedCharacters,
+    /// open the selected closed character
+    OpenClosedCharacter,
+    /// toggle encounter mode on the current character
+    Encounter,
+    /// open or close the command box
+    CommandBox,
 }
 
-pub fn handle(event: keyboard::Event) -> Option<crate::Message> {
+/// The digit on a top row or numpad key
+fn digit(key_code: KeyCode) -> Option<u8> {
+    let digit = match key_code {
+        KeyCode::Key0 | KeyCode::Numpad0 => 0,
+        KeyCode::Key1 | KeyCode::Numpad1 => 1,
+        KeyCode::Key2 | KeyCode::Numpad2 => 2,
+        KeyCode::Key3 | KeyCode::Numpad3 => 3,
+        KeyCode::Key4 | KeyCode::Numpad4 => 4,
+        KeyCode::Key5 | KeyCode::Numpad5 => 5,
+        KeyCode::Key6 | KeyCode::Numpad6 => 6,
+        KeyCode::Key7 | KeyCode::Numpad7 => 7,
+        KeyCode::Key8 | KeyCode::Numpad8 => 8,
+        KeyCode::Key9 | KeyCode::Numpad9 => 9,
+        _ => return None,
+    };
+    Some(digit)
+}
+
+/// Ctrl digit shortcuts also work with shift, since some layouts (like AZERTY) need it to type
+/// digits at all. Shift and a digit alone types a symbol, so it isn't a shortcut.
+fn digit_hotkey(digit: u8, modifiers: Modifiers) -> Option<Message> {
+    const CTRL_SHIFT: Modifiers = Modifiers::CTRL.union(Modifiers::SHIFT);
+    const CTRL_ALT: Modifiers = Modifiers::CTRL.union(Modifiers::ALT);
+    const CTRL_ALT_SHIFT: Modifiers = CTRL_ALT.union(Modifiers::SHIFT);
+    const NONE: Modifiers = Modifiers::empty();
+
+    match modifiers {
+        Modifiers::CTRL | CTRL_SHIFT => Some(Message::ToCharacter(digit.into())),
+        CTRL_ALT | CTRL_ALT_SHIFT => (1..=7).contains(&digit).then(|| Message::AddSpell(usize::from(digit) - 1)),
+        NONE => Level::from_u8(digit).map(|level| Message::CharacterTab(Some(level))),
+        _ => None,
+    }
+}
+
+/// `status` is [`Captured`](event::Status::Captured) if a text input has focus, in which case
+/// shortcuts without Ctrl or Alt that type something, like digits, are left to the text input
+pub fn handle(event: keyboard::Event, status: event::Status) -> Option<crate::Message> {
     const CTRL_ALT: Modifiers = Modifiers::CTRL.union(Modifiers::ALT);
     const CTRL_SHIFT: Modifiers = Modifiers::CTRL.union(Modifiers::SHIFT);
     const NONE: Modifiers = Modifiers::empty();
 
     match event {
         keyboard::Event::KeyPressed { key_code, modifiers } => {
+            let typing = status == event::Status::Captured
+                && !modifiers.intersects(Modifiers::CTRL | Modifiers::ALT | Modifiers::LOGO);
+            if let Some(digit) = digit(key_code) {
+                return digit_hotkey(digit, modifiers)
+                    .filter(|_| !typing)
+                    .map(crate::Message::Hotkey);
+            }
             let message = match modifiers {
                 #[allow(clippy::match_same_arms)]
                 Modifiers::CTRL => match key_code {
                     KeyCode::Grave => Some(Message::Find(true)),
-                    KeyCode::Key1 => Some(Message::ToCharacter(1)),
-                    KeyCode::Key2 => Some(Message::ToCharacter(2)),
-                    KeyCode::Key3 => Some(Message::ToCharacter(3)),
-                    KeyCode::Key4 => Some(Message::ToCharacter(4)),
-                    KeyCode::Key5 => Some(Message::ToCharacter(5)),
-                    KeyCode::Key6 => Some(Message::ToCharacter(6)),
-                    KeyCode::Key7 => Some(Message::ToCharacter(7)),
-                    KeyCode::Key8 => Some(Message::ToCharacter(8)),
-                    KeyCode::Key9 => Some(Message::ToCharacter(9)),
-                    KeyCode::Key0 => Some(Message::ToCharacter(0)),
                     KeyCode::F => Some(Message::Find(false)),
                     KeyCode::S => Some(Message::Find(true)),
                     KeyCode::Tab => Some(Message::Move(Move::Right, true)),
                     KeyCode::Insert | KeyCode::N => Some(Message::NewCharacter),
                     KeyCode::Z => Some(Message::Undo),
                     KeyCode::Y => Some(Message::Redo),
+                    KeyCode::O => Some(Message::ClosedCharacters),
+                    KeyCode::E => Some(Message::Encounter),
+                    KeyCode::Semicolon => Some(Message::CommandBox),
                     _ => None,
                 }
                 CTRL_ALT => match key_code {
-                    KeyCode::Key1 => Some(Message::AddSpell(0)),
-                    KeyCode::Key2 => Some(Message::AddSpell(1)),
-                    KeyCode::Key3 => Some(Message::AddSpell(2)),
-                    KeyCode::Key4 => Some(Message::AddSpell(3)),
-                    KeyCode::Key5 => Some(Message::AddSpell(4)),
-                    KeyCode::Key6 => Some(Message::AddSpell(5)),
-                    KeyCode::Key7 => Some(Message::AddSpell(6)),
+                    KeyCode::A => Some(Message::AddSpellPicker),
                     KeyCode::Left => Some(Message::Move(Move::Left, false)),
                     KeyCode::Right => Some(Message::Move(Move::Right, false)),
                     _ => None,
@@ -78,24 +120,18 @@ pub fn handle(event: keyboard::Event) -> Option<crate::Message> {
                     _ => None,
                 }
                 Modifiers::SHIFT => match key_code {
-                    KeyCode::Tab | KeyCode::Enter | KeyCode::NumpadEnter => Some(Message::CustomSpellNextField(false)),
+                    KeyCode::Tab => Some(Message::CustomSpellNextField(false)),
+                    KeyCode::Enter | KeyCode::NumpadEnter if typing => Some(Message::CustomSpellNextField(false)),
                     _ => None,
                 }
                 NONE => match key_code {
-                    KeyCode::Grave | KeyCode::A => Some(Message::CharacterTab(None)),
-                    KeyCode::Key0 => Some(Message::CharacterTab(Some(Level::Cantrip))),
-                    KeyCode::Key1 => Some(Message::CharacterTab(Some(Level::L1))),
-                    KeyCode::Key2 => Some(Message::CharacterTab(Some(Level::L2))),
-                    KeyCode::Key3 => Some(Message::CharacterTab(Some(Level::L3))),
-                    KeyCode::Key4 => Some(Message::CharacterTab(Some(Level::L4))),
-                    KeyCode::Key5 => Some(Message::CharacterTab(Some(Level::L5))),
-                    KeyCode::Key6 => Some(Message::CharacterTab(Some(Level::L6))),
-                    KeyCode::Key7 => Some(Message::CharacterTab(Some(Level::L7))),
-                    KeyCode::Key8 => Some(Message::CharacterTab(Some(Level::L8))),
-                    KeyCode::Key9 => Some(Message::CharacterTab(Some(Level::L9))),
-                    KeyCode::Tab | KeyCode::Enter | KeyCode::NumpadEnter => Some(Message::CustomSpellNextField(true)),
+                    KeyCode::Grave | KeyCode::A if !typing => Some(Message::CharacterTab(None)),
+                    KeyCode::Enter | KeyCode::NumpadEnter if !typing => Some(Message::OpenClosedCharacter),
+                    KeyCode::Enter | KeyCode::NumpadEnter => Some(Message::CustomSpellNextField(true)),
+                    KeyCode::Tab => Some(Message::CustomSpellNextField(true)),
                     KeyCode::Up => Some(Message::CharacterSpellUpDown(-1)),
                     KeyCode::Down => Some(Message::CharacterSpellUpDown(1)),
+                    KeyCode::Escape => Some(Message::Escape),
                     _ => None,
                 }
                 _ => None
@@ -104,4 +140,69 @@ pub fn handle(event: keyboard::Event) -> Option<crate::Message> {
         }
         _ => None
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(key_code: KeyCode, modifiers: Modifiers, status: event::Status) -> Option<Message> {
+        match handle(keyboard::Event::KeyPressed { key_code, modifiers }, status) {
+            Some(crate::Message::Hotkey(message)) => Some(message),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn only_enter_opens_closed_characters() {
+        let ignored = event::Status::Ignored;
+        assert!(matches!(press(KeyCode::Enter, Modifiers::empty(), ignored), Some(Message::OpenClosedCharacter)));
+        assert!(matches!(press(KeyCode::NumpadEnter, Modifiers::empty(), ignored), Some(Message::OpenClosedCharacter)));
+        assert!(matches!(press(KeyCode::Tab, Modifiers::empty(), ignored), Some(Message::CustomSpellNextField(true))));
+        // submitting a text input moves to the next one instead
+        let typing = event::Status::Captured;
+        assert!(matches!(press(KeyCode::Enter, Modifiers::empty(), typing), Some(Message::CustomSpellNextField(true))));
+    }
+
+    #[test]
+    fn shift_digits_are_left_alone() {
+        let ignored = event::Status::Ignored;
+        assert!(press(KeyCode::Key1, Modifiers::SHIFT, ignored).is_none());
+        assert!(press(KeyCode::Numpad3, Modifiers::SHIFT, ignored).is_none());
+        assert!(matches!(press(KeyCode::Key1, Modifiers::empty(), ignored), Some(Message::CharacterTab(Some(Level::L1)))));
+    }
+
+    #[test]
+    fn ctrl_digits_work_with_shift() {
+        let ignored = event::Status::Ignored;
+        let ctrl_shift = Modifiers::CTRL | Modifiers::SHIFT;
+        assert!(matches!(press(KeyCode::Key2, Modifiers::CTRL, ignored), Some(Message::ToCharacter(2))));
+        assert!(matches!(press(KeyCode::Key2, ctrl_shift, ignored), Some(Message::ToCharacter(2))));
+        assert!(matches!(press(KeyCode::Key3, ctrl_shift | Modifiers::ALT, ignored), Some(Message::AddSpell(2))));
+        assert!(press(KeyCode::Key8, ctrl_shift | Modifiers::ALT, ignored).is_none());
+    }
+
+    #[test]
+    fn typing_keys_go_to_the_focused_input() {
+        let typing = event::Status::Captured;
+        assert!(press(KeyCode::Key1, Modifiers::empty(), typing).is_none());
+        assert!(press(KeyCode::Numpad4, Modifiers::empty(), typing).is_none());
+        assert!(press(KeyCode::A, Modifiers::empty(), typing).is_none());
+        assert!(press(KeyCode::Grave, Modifiers::empty(), typing).is_none());
+        assert!(press(KeyCode::Key1, Modifiers::SHIFT, typing).is_none());
+        // but Ctrl shortcuts still work
+        assert!(matches!(press(KeyCode::F, Modifiers::CTRL, typing), Some(Message::Find(false))));
+        assert!(matches!(press(KeyCode::Tab, Modifiers::CTRL, typing), Some(Message::Move(Move::Right, true))));
+        assert!(matches!(press(KeyCode::Key1, Modifiers::CTRL, typing), Some(Message::ToCharacter(1))));
+    }
+
+    #[test]
+    fn enter_only_moves_fields_while_typing() {
+        let ignored = event::Status::Ignored;
+        let typing = event::Status::Captured;
+        assert!(matches!(press(KeyCode::Enter, Modifiers::empty(), typing), Some(Message::CustomSpellNextField(true))));
+        assert!(matches!(press(KeyCode::Enter, Modifiers::SHIFT, typing), Some(Message::CustomSpellNextField(false))));
+        assert!(press(KeyCode::Enter, Modifiers::SHIFT, ignored).is_none());
+        assert!(matches!(press(KeyCode::Tab, Modifiers::SHIFT, ignored), Some(Message::CustomSpellNextField(false))));
+        assert!(matches!(press(KeyCode::Tab, Modifiers::empty(), typing), Some(Message::CustomSpellNextField(true))));
+    }
+}
@@ -11,7 +11,7 @@ pub struct State {
 }
 
 #[derive(Debug, Default, Copy, Clone)]
-pub struct Pt(f32, f32);
+pub struct Pt(pub f32, pub f32);
 
 impl Sub for Pt {
     type Output = Self;
@@ -0,0 +1,80 @@
+//! Shown instead of the normal window when the bundled spell data can't be read. A panic would go
+//! unseen on Windows, where there's no console.
+
+use iced::{Alignment, Application, Command, Length, Settings};
+use iced::widget::{button, container, Column, text};
+use self_update::cargo_crate_version;
+
+use crate::theme::Theme;
+
+const RELEASES: &str = "https://github.com/Andrew-Schwartz/spells/releases";
+
+type Element<'a> = iced::Element<'a, Message, iced::Renderer<Theme>>;
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    OpenReleases,
+}
+
+pub struct LoadError {
+    error: String,
+}
+
+/// Opens the error window and blocks until it's closed.
+pub fn run(error: String) {
+    LoadError::run(Settings {
+        window: iced::window::Settings {
+            size: (640, 320),
+            icon: Some(crate::icon()),
+            ..Default::default()
+        },
+        flags: error,
+        default_text_size: 18.0,
+        antialiasing: true,
+        ..Default::default()
+    }).unwrap();
+}
+
+impl Application for LoadError {
+    type Executor = iced::executor::Default;
+    type Message = Message;
+    type Theme = Theme;
+    type Flags = String;
+
+    fn new(error: Self::Flags) -> (Self, Command<Message>) {
+        (Self { error }, Command::none())
+    }
+
+    fn title(&self) -> String {
+        "D&D Spells - Error".into()
+    }
+
+    fn update(&mut self, message: Message) -> Command<Message> {
+        match message {
+            Message::OpenReleases => {
+                // nothing else to do if there's no browser
+                let _ = open::that(RELEASES);
+            }
+        }
+        Command::none()
+    }
+
+    fn view(&self) -> Element<'_> {
+        let version = cargo_crate_version!();
+        let col = Column::new()
+            .spacing(8)
+            .align_items(Alignment::Center)
+            .push(text("Couldn't read the spell data").size(28))
+            .push(text(format!("Spells v{version} expected spell data for v{version}, but parsing it failed:")).size(16))
+            .push(text(&self.error).size(14))
+            .push(text("Downloading the latest release should fix this.").size(16))
+            .push(button(text("Open releases page")).on_press(Message::OpenReleases));
+        container(col)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .padding(20)
+            .center_x()
+            .center_y()
+            .into()
+    }
+}
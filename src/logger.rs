@@ -0,0 +1,79 @@
+//! Logs to `spells.log` in the save directory, since there's no console on Windows. When the log
+//! gets too big it's moved to `spells.log.1`, so at most two files are kept. Failing to write a log
+//! is ignored.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+/// bytes before the log is rotated
+const MAX_LEN: u64 = 1024 * 1024;
+
+struct FileLogger {
+    path: PathBuf,
+    file: Mutex<Option<File>>,
+}
+
+/// Starts logging to `path`. Does nothing if a logger was already set.
+pub fn init(path: PathBuf) {
+    let file = open(&path);
+    let logger = FileLogger { path, file: Mutex::new(file) };
+    if log::set_boxed_logger(Box::new(logger)).is_ok() {
+        log::set_max_level(if cfg!(debug_assertions) { LevelFilter::Debug } else { LevelFilter::Info });
+    }
+}
+
+fn open(path: &Path) -> Option<File> {
+    OpenOptions::new().create(true).append(true).open(path).ok()
+}
+
+/// The last `lines` lines of the log at `path`
+pub fn tail(path: &Path, lines: usize) -> String {
+    let log = fs::read_to_string(path).unwrap_or_default();
+    let start = log.lines().count().saturating_sub(lines);
+    log.lines()
+        .skip(start)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let Ok(mut file) = self.file.lock() else { return };
+        let too_big = file.as_ref()
+            .and_then(|file| file.metadata().ok())
+            .map_or(false, |metadata| metadata.len() > MAX_LEN);
+        if too_big {
+            *file = None;
+            let _ = fs::rename(&self.path, self.path.with_extension("log.1"));
+        }
+        if file.is_none() {
+            *file = open(&self.path);
+        }
+        if let Some(file) = file.as_mut() {
+            let secs = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |time| time.as_secs());
+            let _ = writeln!(file, "{secs} {:<5} [{}] {}", record.level(), record.target(), record.args());
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            if let Some(file) = file.as_mut() {
+                let _ = file.flush();
+            }
+        }
+    }
+}
@@ -0,0 +1,123 @@
+//! Chat macros for virtual tabletops.
+
+use std::fmt::Write;
+
+use crate::export::model::SpellText;
+use crate::spells::data::Components;
+use crate::spells::spell::Spell;
+
+/// Builds a Roll20 `&{template:spell}` macro for `spell`, all on one line.
+pub fn roll20(spell: &Spell) -> String {
+    let mut out = String::from("&{template:spell}");
+    let mut field = |name: &str, value: &str| {
+        // writing to a String never fails
+        let _ = write!(out, " {{{{{name}={}}}}}", escape(value));
+    };
+
+    let text = SpellText::from(spell);
+    field("name", &text.name);
+    field("level", &text.level_line);
+    field("school", &text.school);
+    field("castingtime", &text.casting_time);
+    if let Some(range) = &text.range {
+        field("range", range);
+    }
+    if let Some(Components { v, s, m }) = spell.components() {
+        if *v {
+            field("v", "1");
+        }
+        if *s {
+            field("s", "1");
+        }
+        if let Some(material) = m {
+            field("m", "1");
+            field("material", material);
+        }
+    }
+    if let Some(duration) = &text.duration {
+        field("duration", duration);
+    }
+    if text.concentration {
+        field("concentration", "1");
+    }
+    if text.ritual {
+        field("ritual", "1");
+    }
+    field("description", &text.description);
+    if let Some(higher) = &text.higher_levels {
+        field("athigherlevels", higher);
+    }
+    out
+}
+
+/// Condenses all whitespace (including newlines) to single spaces, and replaces the characters
+/// that Roll20 would interpret as template fields or inline rolls with html entities.
+fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for (i, word) in value.split_whitespace().enumerate() {
+        if i != 0 {
+            out.push(' ');
+        }
+        for char in word.chars() {
+            match char {
+                '{' => out.push_str("&#123;"),
+                '}' => out.push_str("&#125;"),
+                '[' => out.push_str("&#91;"),
+                ']' => out.push_str("&#93;"),
+                char => out.push(char),
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::spells::data::{CastingTime, School};
+    use crate::spells::spell::{CustomSpell, find_spell};
+
+    use super::*;
+
+    #[test]
+    fn static_spell_golden() {
+        let fireball = find_spell("Fireball", &[]).unwrap();
+        assert_eq!(roll20(&fireball), concat!(
+            "&{template:spell} {{name=Fireball}} {{level=3rd-level evocation}} {{school=Evocation}} ",
+            "{{castingtime=1 Action}} {{range=150 feet}} {{v=1}} {{s=1}} {{m=1}} ",
+            "{{material=a tiny ball of bat guano and sulfur}} {{duration=Instantaneous}} ",
+            "{{description=A bright streak flashes from your pointing finger to a point you choose within range then ",
+            "blossoms with a low roar into an explosion of flame. Each creature in a 20-foot radius must make a ",
+            "Dexterity saving throw. A target takes 8d6 fire damage on a failed save, or half as much damage on a ",
+            "successful one. The fire spreads around corners. It ignites flammable objects in the area that aren’t ",
+            "being worn or carried.}} ",
+            "{{athigherlevels=When you cast this spell using a spell slot of 4th level or higher, the damage ",
+            "increases by 1d6 for each slot level above 3rd.}}",
+        ));
+    }
+
+    #[test]
+    fn custom_spell_golden() {
+        let mut spell = CustomSpell::new("Warding Chant".to_string());
+        spell.school = School::Abjuration;
+        spell.casting_time = CastingTime::Minute(10);
+        spell.ritual = true;
+        spell.conc = true;
+        spell.description = "Roll [[1d4]] and add it to {the} save.\n\n  Then rest.".to_string();
+        spell.refold();
+        let spell = Spell::Custom(Arc::new(spell));
+        assert_eq!(roll20(&spell), concat!(
+            "&{template:spell} {{name=Warding Chant}} {{level=Abjuration cantrip}} {{school=Abjuration}} ",
+            "{{castingtime=10 Minutes}} {{concentration=1}} {{ritual=1}} ",
+            "{{description=Roll &#91;&#91;1d4&#93;&#93; and add it to &#123;the&#125; save. Then rest.}}",
+        ));
+    }
+
+    #[test]
+    fn escapes_brackets_and_newlines() {
+        assert_eq!(escape("a\nb\r\n\tc"), "a b c");
+        assert_eq!(escape("{{x}} [[y]]"), "&#123;&#123;x&#125;&#125; &#91;&#91;y&#93;&#93;");
+        assert_eq!(escape("  padded  "), "padded");
+    }
+}
@@ -25,20 +25,22 @@ clippy::wildcard_imports,
 #![warn(elided_lifetimes_in_paths)]
 
 use std::{fs::{self, File}, mem};
-use std::cmp::min;
+use std::borrow::Cow;
+use std::cmp::{min, Reverse};
 use std::convert::{From, Into};
 use std::default::Default;
 use std::fmt::Debug;
 use std::io::{BufRead, BufReader, ErrorKind, Write as _};
 use std::ops::Not;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant};
 
-use iced::{Alignment, alignment::Vertical, Application, Command, Length, mouse::ScrollDelta, Settings, widget::{
+use iced::{Alignment, alignment::Vertical, Application, Command, Length, mouse::ScrollDelta, Point, Settings, widget::{
     button,
     container,
     progress_bar,
+    Space,
     text,
     tooltip::Position,
 }, widget};
@@ -48,28 +50,37 @@ use iced_native::{Event, Font, Subscription, window};
 use iced_native::widget::slider;
 use itertools::{Either, Itertools};
 use once_cell::sync::Lazy;
+use once_cell::unsync::OnceCell;
 use self_update::cargo_crate_version;
 use serde::Deserialize;
 
-use search::SearchPage;
+use search::{SearchPage, SerializeSearchOptions, TextMatch};
 pub use theme::types::*;
 use utils::ListGrammaticallyExt;
 
+use crate::cards::FloatingCard;
 use crate::character::{Character, CharacterPage, SerializeCharacter};
+use crate::character_file::Characters;
+use crate::export::character::CharacterFile;
 use crate::hotkey::Move;
 use crate::hotmouse::{ButtonPress, Pt};
 use crate::icon::Icon;
+use crate::command::CommandBox;
+use crate::onboarding::{NO_HIGHLIGHT, Onboarding, Step};
+use crate::preferences::Preferences;
 use crate::settings::{ClosedCharacter, Edit, SettingsPage, SpellEditor};
 use crate::spells::data::GetLevel;
-use crate::spells::spell::{find_spell, SpellId};
+use crate::spells::history::SpellHistory;
+use crate::spells::overrides::{self, Spells};
+use crate::spells::spell::{find_spell, fold, Spell, SpellId, SummaryField, Upcast};
 // use crate::style::{SettingsBarStyle, Style};
-use crate::tab::Tab;
+use crate::tab::{Tab, TabContext};
 use crate::theme::{Location, Theme};
-use crate::utils::{Tap, text_icon, Toggle, TooltipExt, TryRemoveExt};
+use crate::utils::{SpacingExt, Tap, text_icon, Toggle, TooltipExt, TryRemoveExt};
+use crate::widgets::floating::Floating;
 
 use self::spells::data::{CastingTime, Class, Components, Level, School, Source};
 use self::spells::spell::{CustomSpell, StaticSpell};
-use self::spells::static_arc::StArc;
 
 #[macro_use]
 mod utils;
@@ -87,10 +98,33 @@ mod spells;
 mod error;
 mod widgets;
 mod icon;
+mod preferences;
+mod overlay;
+mod macros;
+mod export;
+mod cards;
+mod statistics;
+mod notification;
+mod load_error;
+mod onboarding;
+mod logger;
+mod data_dir;
+mod command;
+mod character_file;
+mod party;
 
 const JSON: &str = include_str!("../resources/spells.json");
 
-pub static SPELLS: Lazy<Vec<StaticSpell>> = Lazy::new(|| serde_json::from_str(JSON).expect("json error in `data/spells.json`"));
+/// the bundled spells, or why they couldn't be parsed
+static SPELL_DATA: Lazy<Result<Vec<StaticSpell>, String>> = Lazy::new(|| StaticSpell::parse_static(JSON));
+
+/// Empty if [`SPELL_DATA`] failed to parse, in which case `main` shows [`load_error`] instead.
+fn bundled_spells() -> &'static [StaticSpell] {
+    SPELL_DATA.as_deref().unwrap_or_default()
+}
+
+/// The bundled spells, with any from [`SPELL_OVERRIDE_FILE`] merged in
+pub static SPELLS: Spells = Spells::new(|| RwLock::new(bundled_spells()));
 
 static SAVE_DIR: Lazy<PathBuf> = Lazy::new(|| {
     let path = dirs::data_local_dir().unwrap_or_default()
@@ -99,16 +133,55 @@ static SAVE_DIR: Lazy<PathBuf> = Lazy::new(|| {
     path
 });
 
+/// Which folder [`DATA_DIR`] is, since that can't be saved in itself
+static DATA_DIR_FILE: Lazy<PathBuf> = Lazy::new(|| SAVE_DIR.join("data-dir.txt"));
+
+/// Where characters, custom spells, and preferences are saved: [`SAVE_DIR`], unless the user chose
+/// another folder to sync them between computers
+static DATA_DIR: Lazy<RwLock<PathBuf>> = Lazy::new(|| {
+    RwLock::new(data_dir::read(&DATA_DIR_FILE).unwrap_or_else(|| SAVE_DIR.clone()))
+});
+
+fn current_data_dir() -> PathBuf {
+    DATA_DIR.read().unwrap().clone()
+}
+
 fn get_file(name: &str) -> PathBuf {
-    let mut path = SAVE_DIR.clone();
+    let mut path = current_data_dir();
     path.push(name);
     fs::OpenOptions::new().create(true).append(true).open(&path).unwrap();
     path
 }
 
-static CHARACTER_FILE: Lazy<PathBuf> = Lazy::new(|| get_file("characters.json"));
-static CLOSED_CHARACTER_FILE: Lazy<PathBuf> = Lazy::new(|| get_file("closed-characters.json"));
-static SPELL_FILE: Lazy<PathBuf> = Lazy::new(|| get_file("custom-spells.json"));
+fn character_file() -> PathBuf {
+    get_file("characters.json")
+}
+
+/// Only exists if characters haven't been saved since updating from the old format, see
+/// [`character_file`](crate::character_file)
+fn closed_character_file() -> PathBuf {
+    current_data_dir().join("closed-characters.json")
+}
+
+fn spell_file() -> PathBuf {
+    get_file("custom-spells.json")
+}
+
+fn preferences_file() -> PathBuf {
+    get_file("preferences.json")
+}
+
+fn search_file() -> PathBuf {
+    get_file("search.json")
+}
+
+fn spell_history_file() -> PathBuf {
+    get_file("custom-spells-history.json")
+}
+
+// not created if it doesn't exist, since then there's nothing to override
+static SPELL_OVERRIDE_FILE: Lazy<PathBuf> = Lazy::new(|| SAVE_DIR.join("spells-override.json"));
+static LOG_FILE: Lazy<PathBuf> = Lazy::new(|| SAVE_DIR.join("spells.log"));
 
 // static SEARCH_ID: Lazy<text_input::Id> = Lazy::new(text_input::Id::unique);
 
@@ -143,13 +216,20 @@ pub const ICON_FONT: Font = Font::External {
 // const COLUMN_WIDTH: f32 = WIDTH as f32 * 1.1 / 2.0;
 
 fn main() {
-    println!("std::env::current_exe() = {:?}", std::env::current_exe());
-
     if let Some("TARGET") = std::env::args().nth(1).as_deref() {
         println!("{}", self_update::get_target());
         return
     }
 
+    logger::init(LOG_FILE.clone());
+    log::info!("starting v{} from {:?}", cargo_crate_version!(), std::env::current_exe());
+
+    if let Err(e) = &*SPELL_DATA {
+        log::error!("failed to load the bundled spells: {e}");
+        load_error::run(e.clone());
+        return
+    }
+
     DndSpells::run(Settings {
         window: iced::window::Settings {
             min_size: Some((1024 / 2, 500)),
@@ -161,6 +241,8 @@ fn main() {
         // default_font: Some(include_bytes!("../resources/arial.ttf")),
         default_text_size: 18.0,
         antialiasing: true,
+        // to save the custom spell being edited first
+        exit_on_close_request: false,
         ..Default::default()
     }).unwrap();
 }
@@ -170,12 +252,20 @@ pub enum UpdateState {
     Checking,
     Ready,
     Downloading(f32),
+    /// the download was cancelled, and can be retried
+    Cancelled,
     UpToDate,
     Downloaded,
     Errored(String),
 }
 
 impl UpdateState {
+    /// If the download subscription should be running, which stops when it's cancelled
+    #[must_use]
+    pub fn downloads(&self) -> bool {
+        matches!(self, Self::Ready | Self::Downloading(_))
+    }
+
     #[must_use]
     pub fn view<'s, 'c: 's>(&'s self) -> Container<'c> {
         const VER: &str = cargo_crate_version!();
@@ -187,7 +277,22 @@ impl UpdateState {
                     progress_bar(0.0..=100.0, pct)
                         .style(Location::SettingsBar)
                         .height(Length::Fixed(12.0)) // bottom bar is 20 pts
-                        .width(Length::Fixed(100.0))
+                        .width(Length::Fixed(100.0)),
+                    5,
+                    button(text("Cancel").size(10))
+                        .style(Location::Transparent)
+                        .padding(0)
+                        .on_press(Message::Update(update::Message::Cancel)),
+                ].align_items(Alignment::Center))
+            }
+            Self::Cancelled => {
+                container(row![
+                    text("Download cancelled — retry?").size(11),
+                    5,
+                    button(text("Retry").size(10))
+                        .style(Location::Transparent)
+                        .padding(0)
+                        .on_press(Message::Update(update::Message::CheckForUpdate)),
                 ].align_items(Alignment::Center))
             }
             view_as_text => match view_as_text {
@@ -202,7 +307,7 @@ impl UpdateState {
                 }),
                 Self::UpToDate => text(format!("Spells v{VER}")),
                 Self::Errored(_) => text(format!("Error downloading new version. Running v{VER}")),
-                Self::Downloading(_) => unreachable!(),
+                Self::Downloading(_) | Self::Cancelled => unreachable!(),
             }.size(11).tap(container)
         }.style(Location::SettingsBar)
     }
@@ -211,22 +316,51 @@ impl UpdateState {
 pub struct DndSpells {
     update_state: UpdateState,
     update_url: String,
+    update_version: String,
     spell_tooltips: bool,
     num_cols: usize,
     theme: Theme,
     tab: Tab,
     width: u16,
     height: u16,
+    /// choosing which character to add the first search result to
+    add_spell_picker: bool,
+    /// false when the window is minimized, which stops listening to input
+    window_active: bool,
+    /// false when another window is focused, so that notifications are worth sending
+    window_focused: bool,
     control_pressed: bool,
     search_page: SearchPage,
     characters: Vec<CharacterPage>,
     closed_characters: Vec<ClosedCharacter>,
     settings_page: SettingsPage,
-    /// Vec<(characters, closed_characters)>
-    save_states: Vec<(Vec<SerializeCharacter>, Vec<SerializeCharacter>)>,
+    /// Vec<(characters, closed_characters, what was on screen)>
+    save_states: Vec<(Vec<SerializeCharacter>, Vec<SerializeCharacter>, TabContext)>,
     state: Option<usize>,
-    custom_spells: Vec<CustomSpell>,
+    custom_spells: Vec<Arc<CustomSpell>>,
     mouse: hotmouse::State,
+    preferences: Preferences,
+    /// shuts the overlay server down when dropped
+    overlay: Option<overlay::Handle>,
+    overlay_snapshot: overlay::Shared,
+    /// spells floating over every tab
+    floating_cards: Vec<FloatingCard>,
+    /// the card being dragged, and where in it the mouse grabbed it
+    dragging_card: Option<(usize, Pt)>,
+    /// the character whose tab is being dragged along the tab bar
+    dragging_tab: Option<usize>,
+    /// why the last link couldn't be opened
+    link_error: Option<String>,
+    /// why the saved characters couldn't be read. Characters aren't saved while this is set
+    characters_error: Option<String>,
+    /// why the last save failed
+    save_error: Option<String>,
+    /// the search filters as they were last saved, to only save them when they change
+    saved_search_options: SerializeSearchOptions,
+    spell_history: SpellHistory,
+    /// the first run steps, until they're finished or skipped
+    onboarding: Option<Onboarding>,
+    command_box: Option<CommandBox>,
 }
 
 #[derive(Debug, Clone)]
@@ -240,39 +374,146 @@ pub enum Message {
     Character(usize, character::Message),
     MoveCharacter(usize, isize),
     CloseCharacter(usize),
+    /// delete an open character without closing it first
+    DeleteCharacter(usize),
     Hotkey(hotkey::Message),
     MouseState(hotmouse::StateMessage),
     ScrollIGuessHopefully(Pt),
     Resize(u16, u16),
+    WindowActive(bool),
+    WindowFocused(bool),
     SelectTab(usize),
     ToggleSpellTooltip,
+    CopyMacro(SpellId),
+    AddToPickedCharacter(usize),
+    CloseAddSpellPicker,
+    /// port, result
+    OverlayStopped(u16, Result<(), String>),
+    PopOut(SpellId),
+    CloseCard(usize),
+    GrabCard(usize),
+    OpenUrl(String),
+    CloseRequested,
+    Onboarding(onboarding::Message),
+    CommandBox(command::Message),
+    /// set a custom reaction spell's trigger from a character page
+    EditReactionTrigger(SpellId, String),
+    /// go to a character's tab and view one of their spells
+    ShowInCharacter(usize, SpellId),
+    /// character, unresolved spell. Make a custom spell with its name and edit it
+    CreateUnresolvedSpell(usize, usize),
+    /// add the spells checked on the Search tab to a character
+    CharacterBulkAdd(usize, Vec<SpellId>),
 }
 
 impl DndSpells {
     fn add_character<C: Into<CharacterPage>>(&mut self, character: C) -> Command<Message> {
         self.characters.push(character.into());
         self.tab = Tab::Character { index: self.characters.len() - 1 };
-        self.save().expect("failed to save");
+        // the tabs it was dragged between have moved
+        self.dragging_tab = None;
+        self.try_save();
         self.refresh_search()
     }
 
+    /// Adds the character in `file`, and the custom spells they know that there isn't already a
+    /// spell with the same name for. Their name gets " (imported)" added if it's taken, or
+    /// " (imported 2)" and so on if that is too.
+    fn import_character(&mut self, file: CharacterFile) -> Command<Message> {
+        let CharacterFile { character, custom_spells } = file;
+        for mut spell in custom_spells {
+            spell.refold();
+            if !self.custom_spells.iter().any(|known| known.name_lower == spell.name_lower) {
+                self.custom_spells.push(Arc::new(spell));
+            }
+        }
+        let mut character = Character::from_serialized(&character, &self.custom_spells);
+        let taken = |name: &str| self.characters.iter()
+            .map(|page| &page.character.name)
+            .chain(self.closed_characters.iter().map(|closed| &closed.character.name))
+            .any(|taken| &**taken == name);
+        if taken(&character.name) {
+            let name = (1..)
+                .map(|n| match n {
+                    1 => format!("{} (imported)", character.name),
+                    n => format!("{} (imported {n})", character.name),
+                })
+                .find(|name| !taken(name))
+                .expect("there are finitely many characters");
+            character.name = Arc::from(name);
+        }
+        self.add_character(character)
+    }
+
     fn swap_characters(&mut self, a: usize, b: usize) -> Command<Message> {
         self.characters.swap(a, b);
-        self.save().expect("blah");
+        self.try_save();
         self.refresh_search()
     }
 
+    /// Moves a character's tab to `to`, shifting the ones in between over one
+    fn move_character(&mut self, from: usize, to: usize) -> Command<Message> {
+        let commands = if from < to {
+            (from..to).map(|idx| self.swap_characters(idx, idx + 1)).collect_vec()
+        } else {
+            (to..from).rev().map(|idx| self.swap_characters(idx, idx + 1)).collect_vec()
+        };
+        self.tab = Tab::Character { index: to };
+        Command::batch(commands)
+    }
+
+    /// The index of the tab under `pt`, if it's on the tab bar. Only works out where the bar is
+    /// when nothing is shown above it.
+    fn tab_at(&self, Pt(x, y): Pt) -> Option<usize> {
+        let bar_on_top = self.command_box.is_none()
+            && self.onboarding.is_none()
+            && !self.add_spell_picker;
+        (bar_on_top && (0.0..f32::from(tab::BAR_HEIGHT)).contains(&y))
+            .then(|| Tab::index_at(x, f32::from(self.width), self.characters.len(), self.preferences.party_tab))
+    }
+
+    /// Where the character whose tab is being dragged from `from` would be dropped, if the mouse is
+    /// on the tab bar and somewhere else. Dropping it past Search or Settings puts it at the end.
+    fn tab_drop(&self, from: usize) -> Option<usize> {
+        let num_characters = self.characters.len();
+        if num_characters == 0 || from >= num_characters {
+            return None;
+        }
+        let tab = self.tab_at(self.mouse.pt)?;
+        let to = tab.clamp(1, num_characters) - 1;
+        (to != from).then_some(to)
+    }
+
     fn close_character(&mut self, character: usize) -> Command<Message> {
+        let mut character = self.take_character(character);
+        character.character.closed_at = Some(settings::unix_now());
+        self.closed_characters.insert(0, character.character.into());
+        self.try_save();
+        self.refresh_search()
+    }
+
+    /// Closes and deletes a character at once, so that undoing it opens the character again
+    fn delete_character(&mut self, character: usize) -> Command<Message> {
+        self.take_character(character);
+        self.try_save();
+        self.refresh_search()
+    }
+
+    /// Removes an open character, moving off its tab
+    fn take_character(&mut self, character: usize) -> CharacterPage {
         let character = self.characters.remove(character);
+        // the tabs it was dragged between have moved
+        self.dragging_tab = None;
+        if self.search_page.default_character.0.as_ref() == Some(&character.character.name) {
+            self.search_page.default_character = search::DefaultCharacter(None);
+        }
         self.tab = match self.tab {
             Tab::Character { index } if index >= self.characters.len() => Tab::Character {
                 index: self.characters.len().saturating_sub(1)
             },
             tab => tab,
         };
-        self.closed_characters.insert(0, character.character.into());
-        self.save().expect("waa haa");
-        self.refresh_search()
+        character
     }
 
     // todo spells save state, then key binds should do that when the spell editor is open3
@@ -286,11 +527,13 @@ impl DndSpells {
         let closed = self.closed_characters.iter()
             .map(|closed| closed.character.serialize())
             .collect();
-        self.save_states.push((characters, closed));
+        let context = TabContext::new(self.tab, &self.characters);
+        self.save_states.push((characters, closed, context));
+        self.refresh_overlay();
     }
 
     fn load_state(&mut self, idx: usize) {
-        let (characters, closed) = self.save_states.get(idx).unwrap();
+        let (characters, closed, context) = self.save_states.get(idx).unwrap();
         let custom = &self.custom_spells;
         self.characters = characters.iter()
             .map(|c| Character::from_serialized(c, custom))
@@ -300,30 +543,34 @@ impl DndSpells {
             .map(|c| Character::from_serialized(c, custom))
             .map(ClosedCharacter::from)
             .collect();
+        self.tab = context.restore(self.tab, &mut self.characters);
+        self.refresh_overlay();
     }
 
-    fn read_characters<C: From<Character>>(file: &Path, custom: &[CustomSpell]) -> error::Result<Vec<C>> {
-        match File::open(file) {
-            Ok(file) => {
-                let reader = BufReader::new(file);
-                let mut characters = Vec::new();
-                for line in reader.lines() {
-                    let line = line.unwrap();
-                    let serialized = serde_json::from_str(&line)?;
-                    let c = Character::from_serialized(&serialized, custom);
-                    characters.push(C::from(c));
-                }
-                Ok(characters)
+    fn refresh_overlay(&self) {
+        if self.overlay.is_some() {
+            if let Ok(mut snapshot) = self.overlay_snapshot.write() {
+                *snapshot = overlay::Snapshot::new(&self.characters);
             }
-            Err(e) if matches!(e.kind(), ErrorKind::NotFound) => {
-                File::create(file)?;
-                Ok(Vec::default())
-            }
-            Err(e) => Err(e.into()),
         }
     }
 
-    fn read_spells(file: &Path) -> error::Result<Vec<CustomSpell>> {
+    fn start_overlay(&mut self) -> Command<Message> {
+        self.settings_page.overlay_error = None;
+        let (handle, server) = overlay::serve(self.preferences.overlay_port, Arc::clone(&self.overlay_snapshot));
+        self.overlay = Some(handle);
+        self.refresh_overlay();
+        let port = self.preferences.overlay_port;
+        Command::perform(server, move |result| Message::OverlayStopped(port, result))
+    }
+
+    fn save_preferences(&self) {
+        if let Err(e) = self.preferences.save(&preferences_file()) {
+            log::error!("failed to save preferences: {e}");
+        }
+    }
+
+    fn read_spells(file: &Path) -> error::Result<Vec<Arc<CustomSpell>>> {
         match File::open(file) {
             Ok(file) => {
                 let reader = BufReader::new(file);
@@ -343,27 +590,62 @@ impl DndSpells {
     }
 
     fn set_spells_characters(&mut self) {
-        self.custom_spells = Self::read_spells(&SPELL_FILE)
-            .unwrap_or_default();
-        self.characters = Self::read_characters(&CHARACTER_FILE, &self.custom_spells)
-            .unwrap_or_default();
-        self.closed_characters = Self::read_characters(&CLOSED_CHARACTER_FILE, &self.custom_spells)
+        let override_errors = overrides::reload(&SPELLS, bundled_spells(), &SPELL_OVERRIDE_FILE);
+        self.custom_spells = Self::read_spells(&spell_file())
             .unwrap_or_default();
+        // nothing else has these yet, so this doesn't clone them
+        self.custom_spells.iter_mut().for_each(|spell| Arc::make_mut(spell).refold());
+        let (saved, characters_error) = character_file::load(&character_file(), &closed_character_file());
+        self.characters_error = characters_error;
+        let custom = &self.custom_spells;
+        self.characters = saved.characters.iter()
+            .map(|c| Character::from_serialized(c, custom))
+            .map(CharacterPage::from)
+            .collect();
+        self.closed_characters = saved.closed.iter()
+            .map(|c| Character::from_serialized(c, custom))
+            .map(ClosedCharacter::from)
+            .collect();
+        log::info!(
+            "loaded {} spells, {} custom spells, {} characters, {} closed characters",
+            SPELLS.len(),
+            self.custom_spells.len(),
+            self.characters.len(),
+            self.closed_characters.len(),
+        );
+        for e in &override_errors {
+            log::warn!("spell override: {e}");
+        }
         self.settings_page = SettingsPage::new(&self.custom_spells);
-        self.search_page = SearchPage::new(&self.custom_spells, &self.characters);
+        self.settings_page.override_errors = override_errors;
+        // keep the filters when switching data folders
+        let search = mem::take(&mut self.search_page.search);
+        self.search_page = SearchPage::new(&self.custom_spells, &self.characters, search);
     }
 
     fn open() -> Self {
         let (width, height) = iced::window::Settings::default().size;
+        // checked before `preferences_file` creates it
+        let no_preferences = !current_data_dir().join("preferences.json").exists();
+        let preferences = Preferences::read(&preferences_file()).unwrap_or_default();
+        let search = SerializeSearchOptions::read(&search_file())
+            .unwrap_or_else(|e| {
+                log::error!("failed to read search options: {e}");
+                SerializeSearchOptions::default()
+            });
         let mut window = Self {
             update_state: UpdateState::Checking,
             update_url: String::new(),
+            update_version: String::new(),
             spell_tooltips: false,
             num_cols: 2,
             theme: Default::default(),
             tab: Tab::Search,
             width: width as u16,
             height: height as u16,
+            add_spell_picker: false,
+            window_active: true,
+            window_focused: true,
             control_pressed: false,
             search_page: Default::default(),
             characters: vec![],
@@ -373,32 +655,220 @@ impl DndSpells {
             state: None,
             custom_spells: vec![],
             mouse: Default::default(),
+            onboarding: (!preferences.onboarded).then(Onboarding::default),
+            command_box: None,
+            preferences,
+            overlay: None,
+            overlay_snapshot: Default::default(),
+            floating_cards: Vec::new(),
+            dragging_card: None,
+            dragging_tab: None,
+            link_error: None,
+            characters_error: None,
+            save_error: None,
+            saved_search_options: search.clone(),
+            spell_history: SpellHistory::read(&spell_history_file()).unwrap_or_default(),
         };
+        window.search_page.search = search.into();
         window.set_spells_characters();
         window.save_state();
+        // someone who already has characters or spells has used this before, and just lost (or
+        // never synced) their preferences
+        let first_run = no_preferences
+            && window.characters.is_empty()
+            && window.closed_characters.is_empty()
+            && window.custom_spells.is_empty()
+            && window.characters_error.is_none();
+        if first_run {
+            window.preferences.onboarded = false;
+            window.onboarding = Some(Onboarding::default());
+            // so the steps keep showing until they're finished, even after a restart
+            window.save_preferences();
+        }
         window
     }
 
+    /// [`save`](Self::save)s, showing why if it fails instead of crashing and losing what hasn't
+    /// been saved
+    fn try_save(&mut self) {
+        match self.save() {
+            Ok(()) => self.save_error = None,
+            Err(e) => {
+                log::error!("failed to save: {e}");
+                self.save_error = Some(format!("Couldn't save: {e}"));
+            }
+        }
+    }
+
     fn save(&mut self) -> error::Result<()> {
+        let start = Instant::now();
         self.save_state();
-        let mut file = File::create(&*CHARACTER_FILE)?;
-        for c in &self.characters {
-            serde_json::to_writer(&mut file, &c.character.serialize())?;
-            file.write_all(b"\n")?;
-        }
-        let mut file = File::create(&*CLOSED_CHARACTER_FILE)?;
-        for c in &self.closed_characters {
-            serde_json::to_writer(&mut file, &c.character.serialize())?;
-            file.write_all(b"\n")?;
+        let characters = Characters {
+            version: character_file::VERSION,
+            characters: self.characters.iter()
+                .map(|page| page.character.serialize())
+                .collect(),
+            closed: self.closed_characters.iter()
+                .map(|closed| closed.character.serialize())
+                .collect(),
+        };
+        if self.characters_error.is_none() {
+            character_file::write(&character_file(), &closed_character_file(), &characters)?;
+        } else {
+            log::warn!("not saving characters over a file that couldn't be read");
         }
-        let mut file = File::create(&*SPELL_FILE)?;
+        // custom spells might have changed
+        self.settings_page.statistics = OnceCell::new();
+        let mut file = File::create(spell_file())?;
         for spell in &self.custom_spells {
             serde_json::to_writer(&mut file, &spell)?;
             file.write_all(b"\n")?;
         }
+        log::debug!("saved in {:?}", start.elapsed());
         Ok(())
     }
 
+    /// Records the custom spell being edited in its history, since the edit session is ending
+    fn end_spell_edit(&mut self) {
+        self.settings_page.show_spell_history = false;
+        self.settings_page.show_diff = false;
+        self.settings_page.spell_rename = None;
+        self.settings_page.spell_rename_error = None;
+        if let SpellEditor::Editing { spell, .. } = &self.settings_page.spell_editor {
+            if self.spell_history.record(spell) {
+                self.save_spell_history();
+            }
+        }
+    }
+
+    fn save_spell_history(&self) {
+        if let Err(e) = self.spell_history.save(&spell_history_file()) {
+            log::error!("failed to save spell history: {e}");
+        }
+    }
+
+    /// The messages that carry out a command from the command box, and what to tell the user
+    fn command_messages(&self, action: command::Action) -> Result<(Vec<Message>, String), String> {
+        use command::Action;
+        let find_character = |name: &str| command::find(
+            name,
+            self.characters.iter().map(|page| &*page.character.name),
+            "open character",
+        );
+        match action {
+            Action::Help => Ok((Vec::new(), command::HELP.to_string())),
+            Action::Add { spell, character } => {
+                let index = find_character(&character)?;
+                let names = SPELLS.iter()
+                    .map(|spell| spell.name)
+                    .chain(self.custom_spells.iter().map(|spell| &*spell.name));
+                let found = command::find(&spell, names, "spell")?;
+                let id = SPELLS.get(found)
+                    .map_or_else(|| self.custom_spells[found - SPELLS.len()].id(), StaticSpell::id);
+                let output = format!("Added {} to {}", id.name, self.characters[index].character.name);
+                Ok((vec![Message::Character(index, character::Message::AddSpell(id))], output))
+            }
+            Action::Prepare { prepare, level, character } => {
+                let index = find_character(&character)?;
+                let page = &self.characters[index];
+                let changed = page.character.spells.iter()
+                    .enumerate()
+                    .filter(|&(spell_level, _)| level.map_or(true, |level| level as usize == spell_level))
+                    .flat_map(|(_, spells)| spells)
+                    .filter(|&&(_, prepared)| prepared != prepare)
+                    .count();
+                let output = format!(
+                    "{} {changed} spells for {}",
+                    if prepare { "Prepared" } else { "Unprepared" },
+                    page.character.name,
+                );
+                // one message, so it's saved and can be undone all at once
+                Ok((vec![Message::Character(index, character::Message::PrepareAll(prepare, level))], output))
+            }
+            Action::Slots { character, totals } => {
+                let index = find_character(&character)?;
+                let entry = totals.iter().join(" ");
+                let output = format!("Set {}'s slots to {}", self.characters[index].character.name, totals.iter().join("/"));
+                Ok((vec![
+                    Message::Character(index, character::Message::SlotEntry(entry)),
+                    Message::Character(index, character::Message::SubmitSlots),
+                ], output))
+            }
+            Action::Cast { character, level } => {
+                let index = find_character(&character)?;
+                let page = &self.characters[index];
+                let slots = &page.character.slots[level as usize - 1];
+                if slots.used >= slots.total && page.character.bonus_slots[level as usize - 1] == 0 {
+                    return Err(format!("{} has no {level} level slots left", page.character.name));
+                }
+                let output = format!("{} spent a {level} level slot", page.character.name);
+                Ok((vec![Message::Character(index, character::Message::SpendSlot(level))], output))
+            }
+        }
+    }
+
+    /// Copies everything saved to `dir` and reloads it from there, keeping whichever copy of each
+    /// file is newer. Everything is already saved as it changes, and saving again here would make
+    /// the current folder's copies look newer than `dir`'s even if they aren't.
+    fn set_data_dir(&mut self, dir: PathBuf) {
+        let from = current_data_dir();
+        if from == dir {
+            return;
+        }
+        let result = data_dir::migrate(&from, &dir)
+            .and_then(|()| data_dir::save(&DATA_DIR_FILE, &dir, &SAVE_DIR));
+        if let Err(e) = result {
+            log::error!("failed to change the data folder to {}: {e}", dir.display());
+            self.settings_page.data_dir_error = Some(format!("Couldn't use {}: {e}", dir.display()));
+            return;
+        }
+        log::info!("data folder changed to {}", dir.display());
+        *DATA_DIR.write().unwrap() = dir;
+        self.preferences = Preferences::read(&preferences_file()).unwrap_or_default();
+        self.spell_history = SpellHistory::read(&spell_history_file()).unwrap_or_default();
+        self.set_spells_characters();
+    }
+
+    /// adds the selected search result, or the first one, to `character`
+    fn add_search_result(&mut self, character: usize) -> Command<Message> {
+        self.search_page.flush(&self.custom_spells, &self.characters);
+        if let Some(spell) = self.search_page.selected_spell().map(|s| s.spell.id()) {
+            if let Some(page) = self.characters.get_mut(character) {
+                let spell = find_spell(&spell.name, &self.custom_spells).unwrap();
+                page.add_spell(spell, self.preferences.switch_tab_on_add);
+                return self.refresh_search();
+            }
+        }
+        Command::none()
+    }
+
+    /// Saves an edited custom spell, and replaces every copy of it. Characters and pop out cards
+    /// share the saved one, but the spell editor keeps its own to edit.
+    fn update_custom_spell(&mut self, edited: &CustomSpell) {
+        if let SpellEditor::Editing { spell, .. } = &mut self.settings_page.spell_editor {
+            if spell.name == edited.name {
+                (**spell).clone_from(edited);
+            }
+        }
+        let edited = &Arc::new(edited.clone());
+        if let Some(saved_spell) = self.custom_spells.iter_mut().find(|s| s.name == edited.name) {
+            *saved_spell = Arc::clone(edited);
+        } else {
+            self.custom_spells.push(Arc::clone(edited));
+        }
+        for page in &mut self.characters {
+            page.update_custom_spell(edited);
+        }
+        for closed in &mut self.closed_characters {
+            closed.character.update_custom_spell(edited);
+        }
+        for card in &mut self.floating_cards {
+            if matches!(&card.spell, Spell::Custom(custom) if custom.name == edited.name) {
+                card.spell = Spell::Custom(Arc::clone(edited));
+            }
+        }
+    }
+
     fn refresh_search(&mut self) -> Command<Message> {
         self.search_page.update(search::Message::Refresh, &self.custom_spells, &self.characters)
     }
@@ -412,7 +882,7 @@ impl Application for DndSpells {
     type Flags = ();
 
     fn new((): Self::Flags) -> (Self, Command<Message>) {
-        let window = Self::open();
+        let mut window = Self::open();
         // let commands = Command::batch([
         //     async { Message::Search(search::Message::Refresh) }.into(),
         //     async {
@@ -421,22 +891,28 @@ impl Application for DndSpells {
         //         Message::Update(update::Message::CheckForUpdate)
         //     }.into(),
         // ]);
-        let commands = Command::perform(
+        let mut commands = vec![Command::perform(
             tokio::time::sleep(Duration::from_millis(500)),
             |()| Message::Update(update::Message::CheckForUpdate),
-        );
-        (window, commands)
+        )];
+        if window.preferences.overlay_server {
+            commands.push(window.start_overlay());
+        }
+        (window, Command::batch(commands))
     }
 
     fn title(&self) -> String {
         const SPELLS: &str = "D&D Spells";
         match self.tab {
             Tab::Search | Tab::Settings => SPELLS.into(),
-            Tab::Character { index } => format!(
-                "{SPELLS} - {}",
-                self.characters.get(index)
-                    .map_or("Character", |c| &c.character.name)
-            )
+            Tab::Party => format!("{SPELLS} - Party"),
+            Tab::Character { index } => match self.characters.get(index) {
+                Some(page) => match page.character.slots_summary() {
+                    Some(slots) => format!("{SPELLS} - {} {slots}", page.character.name),
+                    None => format!("{SPELLS} - {}", page.character.name),
+                },
+                None => format!("{SPELLS} - Character"),
+            }
         }
     }
 
@@ -447,9 +923,19 @@ impl Application for DndSpells {
                 if let update::Message::CheckForUpdate = &msg {
                     commands.push(text_input::focus(self.search_page.search.id.clone()));
                 }
+                let download_ended = matches!(
+                    &msg,
+                    update::Message::Progress(update::Progress::Finished(Some(_)) | update::Progress::Errored(_)),
+                );
                 if let Err(e) = update::handle(self, msg) {
+                    log::error!("update failed: {e}");
                     self.update_state = UpdateState::Errored(e.to_string());
                 }
+                if download_ended && !(self.window_active && self.window_focused) && self.preferences.notifications {
+                    if let Some(body) = notification::update_ended(&self.update_state, &self.update_version) {
+                        notification::send(&body);
+                    }
+                }
                 if let UpdateState::Downloaded = &self.update_state {
                     self.set_spells_characters();
                 }
@@ -465,9 +951,21 @@ impl Application for DndSpells {
                 // println!("mult = {:?}", mult);
                 self.num_cols = n as usize;
             }
+            Message::Search(search::Message::Submit) => {
+                if let Some(index) = self.search_page.default_character_index(&self.characters) {
+                    commands.push(self.add_search_result(index));
+                }
+            }
             Message::Search(msg) => {
                 let command = self.search_page.update(msg, &self.custom_spells, &self.characters);
                 commands.push(command);
+                let options = SerializeSearchOptions::from(&self.search_page.search);
+                if options != self.saved_search_options {
+                    match options.save(&search_file()) {
+                        Ok(()) => self.saved_search_options = options,
+                        Err(e) => log::error!("failed to save search options: {e}"),
+                    }
+                }
             },
             Message::Settings(message) => {
                 use settings::Message;
@@ -483,10 +981,29 @@ impl Application for DndSpells {
                             commands.push(self.add_character(name));
                         } else {
                             // todo notify in gui somehow
-                            println!("{name} is already a character");
+                            log::warn!("{name} is already a character");
+                        }
+                    }
+                    Message::ImportCharacter => {
+                        let path = rfd::FileDialog::new()
+                            .add_filter("Character", &["json"])
+                            .pick_file();
+                        if let Some(path) = path {
+                            match CharacterFile::read(&path) {
+                                Ok(file) => {
+                                    self.settings_page.import_error = None;
+                                    commands.push(self.import_character(file));
+                                }
+                                Err(e) => {
+                                    log::error!("failed to import {}: {e}", path.display());
+                                    self.settings_page.import_error = Some(format!("Couldn't import {}: {e}", path.display()));
+                                }
+                            }
                         }
                     }
                     Message::Open(index) => {
+                        self.settings_page.selected_closed = None;
+                        self.settings_page.viewing_closed = None;
                         let character = self.closed_characters.remove(index);
                         commands.push(self.add_character(character.character));
                     }
@@ -499,7 +1016,7 @@ impl Application for DndSpells {
                                 if !name.is_empty() {
                                     let name = mem::take(name);
                                     self.closed_characters[index].character.name = Arc::from(name);
-                                    self.save().expect("to do lol");
+                                    self.try_save();
                                 }
                                 Either::Left(())
                             }
@@ -512,35 +1029,100 @@ impl Application for DndSpells {
                         }
                     }
                     Message::DeleteCharacter(index) => {
+                        self.settings_page.viewing_closed = None;
                         self.closed_characters.remove(index);
-                        self.save().expect("todoooooo");
+                        self.try_save();
+                    }
+                    Message::SortClosed => {
+                        self.settings_page.viewing_closed = None;
+                        self.settings_page.selected_closed = None;
+                        // most recently closed first, and ones closed before this was tracked last
+                        self.closed_characters.sort_by_key(|closed| Reverse(closed.character.closed_at));
+                        self.try_save();
+                    }
+                    Message::EmptyArchive => {
+                        self.settings_page.empty_archive_step += 1;
+                        if self.settings_page.empty_archive_step > settings::EMPTY_ARCHIVE_CONFIRMATIONS {
+                            self.settings_page.empty_archive_step = 0;
+                            self.settings_page.viewing_closed = None;
+                            self.settings_page.selected_closed = None;
+                            self.closed_characters.clear();
+                            self.try_save();
+                        }
+                    }
+                    Message::CancelEmptyArchive => self.settings_page.empty_archive_step = 0,
+                    Message::MoveClosed(index, delta) => {
+                        self.settings_page.viewing_closed = None;
+                        let new_index = index.checked_add_signed(delta)
+                            .filter(|&new| new < self.closed_characters.len());
+                        if let Some(new_index) = new_index {
+                            self.closed_characters.swap(index, new_index);
+                            self.try_save();
+                        }
+                    }
+                    Message::ToggleSelecting => {
+                        self.settings_page.selecting_closed.toggle();
+                        for closed in &mut self.closed_characters {
+                            closed.selected = false;
+                        }
+                    }
+                    Message::ToggleSelectClosed(index) => {
+                        if let Some(closed) = self.closed_characters.get_mut(index) {
+                            closed.selected.toggle();
+                        }
+                    }
+                    Message::ReopenSelected => {
+                        self.settings_page.viewing_closed = None;
+                        let (reopen, closed): (Vec<_>, Vec<_>) = mem::take(&mut self.closed_characters)
+                            .into_iter()
+                            .partition(|closed| closed.selected);
+                        self.closed_characters = closed;
+                        self.settings_page.selecting_closed = false;
+                        if !reopen.is_empty() {
+                            self.characters.extend(reopen.into_iter().map(|closed| CharacterPage::from(closed.character)));
+                            self.tab = Tab::Character { index: self.characters.len() - 1 };
+                            self.try_save();
+                            commands.push(self.refresh_search());
+                        }
+                    }
+                    Message::ViewClosed(index) => self.settings_page.viewing_closed = Some((index, Vec::new())),
+                    Message::CloseView => self.settings_page.viewing_closed = None,
+                    Message::ExpandViewed(id) => {
+                        if let Some((_, expanded)) = &mut self.settings_page.viewing_closed {
+                            if let Some(idx) = expanded.iter().position(|expanded| *expanded == id) {
+                                expanded.remove(idx);
+                            } else {
+                                expanded.push(id);
+                            }
+                        }
                     }
                     Message::SpellName(name) => {
+                        self.end_spell_edit();
                         let name = {
-                            let lower = name.to_lowercase();
+                            let lower = fold(&name);
                             self.settings_page.spell_name = name;
                             lower
                         };
                         if let Some(spell) = self.custom_spells.iter()
                             .find(|spell| spell.name_lower == name)
-                            .cloned()
-                            .map(Box::new) {
-                            self.settings_page.spell_editor = SpellEditor::Editing { spell }
+                            .map(|spell| Box::new((**spell).clone())) {
+                            self.settings_page.spell_editor = SpellEditor::editing(spell)
                         } else {
                             self.settings_page.spell_editor = SpellEditor::searching(&name, &self.custom_spells);
                         }
                     }
                     Message::SubmitSpell => {
+                        self.end_spell_edit();
                         let name = mem::take(&mut self.settings_page.spell_name);
                         let spell = CustomSpell::new(name);
-                        self.custom_spells.push(spell.clone());
-                        self.settings_page.spell_editor = SpellEditor::Editing { spell: Box::new(spell) };
-                        self.save().unwrap();
+                        self.custom_spells.push(Arc::new(spell.clone()));
+                        self.settings_page.spell_editor = SpellEditor::editing(Box::new(spell));
+                        self.try_save();
                     }
                     Message::OpenSpell(index) => {
                         if let SpellEditor::Searching { spells } = &mut self.settings_page.spell_editor {
-                            if let Some(spell) = spells.try_remove(index).map(Box::new) {
-                                self.settings_page.spell_editor = SpellEditor::Editing { spell };
+                            if let Some(spell) = spells.try_remove(index).map(|spell| Box::new((*spell).clone())) {
+                                self.settings_page.spell_editor = SpellEditor::editing(spell);
                             }
                         }
                     }
@@ -550,17 +1132,17 @@ impl Application for DndSpells {
                             if let Some(index) = self.custom_spells.iter().position(|cs| *cs == spell) {
                                 self.custom_spells.remove(index);
                             }
-                            self.save().unwrap();
+                            self.try_save();
                         }
                     }
                     Message::EditSpell(edit) => match &mut self.settings_page.spell_editor {
                         SpellEditor::Searching { .. } => unreachable!(),
-                        SpellEditor::Editing { spell } => {
+                        SpellEditor::Editing { spell, scratch } => {
                             let nullify = |s: String| s.is_empty().not().then_some(s);
                             match edit {
                                 Edit::School(school) => spell.school = school,
                                 Edit::Level(level) => spell.level = level,
-                                Edit::CastingTime(time) => spell.casting_time = time,
+                                Edit::CastingTime(time) => spell.casting_time = scratch.switch_casting_time(&spell.casting_time, time),
                                 Edit::CastingTimeN(new) => {
                                     if let Ok(new) = new.parse() {
                                         match &mut spell.casting_time {
@@ -570,8 +1152,14 @@ impl Application for DndSpells {
                                     }
                                 }
                                 Edit::CastingTimeWhen(new) => if let CastingTime::Reaction(when) = &mut spell.casting_time {
-                                    *when = Some(StArc::Arc(Arc::from(new)));
+                                    *when = CastingTime::trigger(&new);
                                 },
+                                Edit::AltCastingTime(alt) => spell.alt_casting_time = alt,
+                                Edit::AltCastingTimeN(new) => {
+                                    if let (Ok(new), Some(CastingTime::Minute(n) | CastingTime::Hour(n))) = (new.parse(), &mut spell.alt_casting_time) {
+                                        *n = new;
+                                    }
+                                }
                                 Edit::Range(range) => spell.range = (!range.is_empty()).then_some(range),
                                 Edit::ComponentV(v) => match &mut spell.components {
                                     Some(components) => components.v = v,
@@ -610,6 +1198,7 @@ impl Application for DndSpells {
                                 //     println!("spell.description = {:?}", spell.description);
                                 // }
                                 Edit::HigherLevels(higher) => spell.higher_levels = nullify(higher),
+                                Edit::BasedOn(name) => spell.based_on = nullify(name),
                                 Edit::Class(class) => {
                                     if let Some(idx) = spell.classes.iter().position(|&c| c == class) {
                                         spell.classes.remove(idx);
@@ -624,34 +1213,209 @@ impl Application for DndSpells {
                                 //     spell.page = Some(page);
                                 // },
                             };
-                            if let Some(saved_spell) = self.custom_spells.iter_mut().find(|s| s.name == spell.name) {
-                                saved_spell.clone_from(spell);
-                                // *saved_spell = *spell.clone();
-                            } else {
-                                self.custom_spells.push(*spell.clone());
-                            }
+                            spell.refold();
+                            let edited = (**spell).clone();
+                            self.update_custom_spell(&edited);
                             commands.push(self.refresh_search());
-                            self.save().unwrap();
+                            self.try_save();
                         }
                     },
                     Message::CloseSpell => {
+                        self.end_spell_edit();
                         self.settings_page.spell_editor = SpellEditor::searching(
-                            &self.settings_page.spell_name.to_lowercase(),
+                            &fold(&self.settings_page.spell_name),
                             &self.custom_spells,
                         );
                     }
+                    Message::RenameSpell => {
+                        if let SpellEditor::Editing { spell, .. } = &mut self.settings_page.spell_editor {
+                            match self.settings_page.spell_rename.take() {
+                                None => {
+                                    self.settings_page.spell_rename = Some(spell.name.to_string());
+                                }
+                                Some(name) => {
+                                    let name = name.trim();
+                                    let name_lower = fold(name);
+                                    let taken = SPELLS.iter().any(|s| s.name_lower == name_lower)
+                                        || self.custom_spells.iter().any(|s| s.name_lower == name_lower && s.name != spell.name);
+                                    self.settings_page.spell_rename_error = None;
+                                    if taken {
+                                        self.settings_page.spell_rename_error = Some(format!("There's already a spell named {name}"));
+                                        self.settings_page.spell_rename = Some(name.to_string());
+                                    } else if !name.is_empty() && name != &*spell.name {
+                                        let old = Arc::clone(&spell.name);
+                                        spell.name = Arc::from(name);
+                                        spell.name_lower = name_lower;
+                                        let renamed = Arc::new((**spell).clone());
+                                        if let Some(saved_spell) = self.custom_spells.iter_mut().find(|s| s.name == old) {
+                                            *saved_spell = Arc::clone(&renamed);
+                                        }
+                                        // characters save their spells by name
+                                        let characters = self.characters.iter_mut()
+                                            .map(|page| &mut page.character)
+                                            .chain(self.closed_characters.iter_mut().map(|closed| &mut closed.character));
+                                        for character in characters {
+                                            character.rename_custom_spell(&old, &renamed);
+                                        }
+                                        for page in &mut self.characters {
+                                            if page.view_spell.as_ref().map_or(false, |id| *id.name == *old) {
+                                                page.view_spell = Some(renamed.id());
+                                            }
+                                        }
+                                        self.spell_history.rename(&old, &renamed);
+                                        self.save_spell_history();
+                                        commands.push(self.refresh_search());
+                                        self.try_save();
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    Message::RenameSpellString(name) => self.settings_page.spell_rename = Some(name),
+                    Message::ReloadOverrides => {
+                        self.settings_page.override_errors = overrides::reload(&SPELLS, bundled_spells(), &SPELL_OVERRIDE_FILE);
+                        for page in &mut self.characters {
+                            page.reload_spells();
+                        }
+                        for closed in &mut self.closed_characters {
+                            closed.character.reload_spells();
+                        }
+                        for card in &mut self.floating_cards {
+                            card.spell = card.spell.clone().reloaded();
+                        }
+                        self.settings_page.statistics = OnceCell::new();
+                        commands.push(self.refresh_search());
+                    }
+                    Message::ToggleSpellHistory => self.settings_page.show_spell_history.toggle(),
+                    Message::ToggleDiff => self.settings_page.show_diff.toggle(),
+                    Message::ChooseDataDir => {
+                        let dir = rfd::FileDialog::new()
+                            .set_directory(current_data_dir())
+                            .pick_folder();
+                        if let Some(dir) = dir {
+                            self.set_data_dir(dir);
+                            commands.push(self.refresh_search());
+                        }
+                    }
+                    Message::ResetDataDir => {
+                        self.set_data_dir(SAVE_DIR.clone());
+                        commands.push(self.refresh_search());
+                    }
+                    Message::ToggleLog => {
+                        self.settings_page.log = match self.settings_page.log {
+                            Some(_) => None,
+                            None => Some(logger::tail(&LOG_FILE, 200)),
+                        };
+                    }
+                    Message::CopyLog => {
+                        if let Some(log) = &self.settings_page.log {
+                            commands.push(iced::clipboard::write(log.clone()));
+                        }
+                    }
+                    Message::RestoreRevision(index) => {
+                        if let SpellEditor::Editing { spell, .. } = &mut self.settings_page.spell_editor {
+                            if self.spell_history.restore(spell, index) {
+                                self.save_spell_history();
+                                let restored = (**spell).clone();
+                                self.update_custom_spell(&restored);
+                                commands.push(self.refresh_search());
+                                self.try_save();
+                            }
+                        }
+                    }
+                    Message::Preferences(message) => {
+                        use preferences::Message;
+                        match message {
+                            Message::ToggleOverlayServer(enable) => {
+                                self.preferences.overlay_server = enable;
+                                if enable {
+                                    commands.push(self.start_overlay());
+                                } else {
+                                    // dropping the handle shuts the server down
+                                    self.overlay = None;
+                                }
+                            }
+                            Message::ToggleKeepRunning(keep_running) => {
+                                self.preferences.keep_running_in_background = keep_running;
+                            }
+                            Message::ToggleNotifications(notify) => {
+                                self.preferences.notifications = notify;
+                            }
+                            Message::ToggleSwitchTabOnAdd(switch) => {
+                                self.preferences.switch_tab_on_add = switch;
+                            }
+                            Message::TogglePartyTab(party) => {
+                                self.preferences.party_tab = party;
+                                if !party && self.tab == Tab::Party {
+                                    self.tab = Tab::Settings;
+                                }
+                            }
+                            Message::SourceLink(source, link) => {
+                                self.preferences.source_links.insert(source, link);
+                            }
+                            Message::SearchUrl(url) => self.preferences.search_url = url,
+                            Message::ToggleShortenDescriptions(shorten) => {
+                                self.preferences.shorten_long_descriptions = shorten;
+                            }
+                            Message::ToggleHoverPreviews(previews) => {
+                                self.preferences.hover_previews = previews;
+                            }
+                            Message::ToggleSummaryField(field) => {
+                                let fields = &mut self.preferences.summary_fields;
+                                if let Some(idx) = fields.iter().position(|&f| f == field) {
+                                    fields.remove(idx);
+                                } else if fields.len() < SummaryField::MAX {
+                                    fields.push(field);
+                                }
+                            }
+                            Message::OverlayPort(port) => {
+                                if let Ok(port) = port.parse() {
+                                    self.preferences.overlay_port = port;
+                                    if self.overlay.is_some() {
+                                        // restart on the new port
+                                        self.overlay = None;
+                                        commands.push(self.start_overlay());
+                                    }
+                                }
+                            }
+                        }
+                        self.save_preferences();
+                    }
+                }
+            }
+            Message::Character(index, character::Message::Rename(name)) => {
+                let taken = self.characters.iter()
+                    .enumerate()
+                    .filter(|&(idx, _)| idx != index)
+                    .map(|(_, page)| &page.character.name)
+                    .chain(self.closed_characters.iter().map(|closed| &closed.character.name))
+                    .any(|other| **other == *name.trim());
+                let renamed = self.characters.get_mut(index).and_then(|page| {
+                    let old = Arc::clone(&page.character.name);
+                    page.rename(&name, taken).then(|| (old, Arc::clone(&page.character.name)))
+                });
+                match renamed {
+                    Some((old, new)) => {
+                        if self.search_page.default_character.0.as_ref() == Some(&old) {
+                            self.search_page.default_character = search::DefaultCharacter(Some(new));
+                        }
+                        commands.push(self.refresh_search());
+                        self.try_save();
+                    }
+                    None => log::warn!("couldn't rename a character to {name}"),
                 }
             }
             Message::Character(index, msg) => {
                 let add = matches!(msg, character::Message::AddSpell(_));
                 let num_cols = self.num_cols;
                 let custom = &self.custom_spells;
+                let switch_tab = self.preferences.switch_tab_on_add;
                 let must_save = self.characters.get_mut(index)
-                    .map(|c| c.update(msg, custom, num_cols));
+                    .map(|c| c.update(msg, custom, num_cols, switch_tab));
                 // let must_save = self.character_pages.get_mut(&name)
                 //     .map(|c| c.update(msg, num_cols));
                 if let Some(c) = self.characters.get(index) {
-                    commands.push(text_input::focus(c.search.id.clone()));
+                    commands.push(text_input::focus(c.focus_id()));
                 }
                 if add {
                     // have to update after adding the spell
@@ -659,7 +1423,18 @@ impl Application for DndSpells {
                 }
                 if let Some(true) = must_save {
                     commands.push(self.refresh_search());
-                    self.save().expect("todo #2");
+                    self.try_save();
+                }
+            }
+            Message::CharacterBulkAdd(index, ids) => {
+                let custom = &self.custom_spells;
+                let added = self.characters.get_mut(index).map(|page| page.add_spells(
+                    ids.iter().filter_map(|id| find_spell(&id.name, custom)),
+                ));
+                self.search_page.clear_bulk();
+                if added.map_or(false, |added| added != 0) {
+                    commands.push(self.refresh_search());
+                    self.try_save();
                 }
             }
             Message::MoveCharacter(idx, delta) => {
@@ -675,6 +1450,11 @@ impl Application for DndSpells {
                 // todo currently just goes to next tab, is that good?
                 commands.push(self.close_character(index));
             }
+            Message::DeleteCharacter(index) => {
+                if index < self.characters.len() {
+                    commands.push(self.delete_character(index));
+                }
+            }
             Message::Hotkey(message) => {
                 use hotkey::Message;
                 match message {
@@ -702,7 +1482,7 @@ impl Application for DndSpells {
                                 // commands.push(command);
                                 commands.push(text_input::select_all(self.search_page.search.id.clone()));
                             }
-                            (false, Tab::Settings | Tab::Search) => {
+                            (false, Tab::Settings | Tab::Party | Tab::Search) => {
                                 self.tab = Tab::Search;
                                 commands.push(self.refresh_search());
                                 commands.push(text_input::select_all(self.search_page.search.id.clone()));
@@ -720,33 +1500,11 @@ impl Application for DndSpells {
                     Message::NewCharacter => self.tab = Tab::Character { index: self.characters.len() + 1 },
                     Message::Move(dir, tab_only) => {
                         if tab_only {
-                            let new_tab_idx = self.characters.len() + 1;
-                            let orig_idx = match &self.tab {
-                                Tab::Search => 0,
-                                // Tab::Character(name) => self.characters.iter()
-                                //     .position(|c| c == name)
-                                //     .unwrap() + 1,
-                                Tab::Character { index } => index + 1,
-                                Tab::Settings => new_tab_idx,
-                            };
-                            let idx = match dir {
-                                Move::Left => min(orig_idx.wrapping_sub(1), new_tab_idx),
-                                Move::Right => {
-                                    let idx = orig_idx + 1;
-                                    if idx > new_tab_idx { 0 } else { idx }
-                                }
-                            };
-                            match idx {
-                                0 => {
-                                    self.tab = Tab::Search;
-                                    commands.push(text_input::focus(self.search_page.search.id.clone()));
-                                },
-                                new_tab if new_tab == new_tab_idx => self.tab = Tab::Settings,
-                                idx => {
-                                    // let character = &self.characters[idx - 1];
-                                    self.tab = Tab::Character { index: idx - 1 };
-                                    commands.push(text_input::focus(self.characters[idx - 1].search.id.clone()));
-                                }
+                            self.tab = self.tab.step(dir, self.characters.len(), self.preferences.party_tab);
+                            match self.tab {
+                                Tab::Search => commands.push(text_input::focus(self.search_page.search.id.clone())),
+                                Tab::Character { index } => commands.push(text_input::focus(self.characters[index].search.id.clone())),
+                                Tab::Party | Tab::Settings => {}
                             }
                         } else {
                             let tabs_by_character = self.characters.iter()
@@ -754,8 +1512,9 @@ impl Application for DndSpells {
                                     .filter(|spells| !spells.is_empty())
                                     .count() + 1)
                                 .collect_vec();
-                            // add 1 bc empty is [search, settings] which has max idx 1
-                            let max_tab_idx = tabs_by_character.iter().sum::<usize>() + 1;
+                            // add 1 bc empty is [search, settings] which has max idx 1, and 1 more for party
+                            let party = self.preferences.party_tab;
+                            let max_tab_idx = tabs_by_character.iter().sum::<usize>() + 1 + usize::from(party);
                             let orig_idx = match self.tab {
                                 Tab::Search => 0,
                                 Tab::Character { index } => {
@@ -764,6 +1523,7 @@ impl Application for DndSpells {
                                         + tabs_by_character[..index].iter().sum::<usize>() // previous characters
                                         + character.tab_index() // index in this character
                                 }
+                                Tab::Party => max_tab_idx - 1,
                                 Tab::Settings => max_tab_idx,
                             };
                             let idx = match dir {
@@ -782,6 +1542,7 @@ impl Application for DndSpells {
                                     commands.push(text_input::focus(self.search_page.search.id.clone()));
                                 },
                                 settings_tab if settings_tab == max_tab_idx => self.tab = Tab::Settings,
+                                party_tab if party && party_tab == max_tab_idx - 1 => self.tab = Tab::Party,
                                 mut tab => {
                                     // search tab
                                     tab -= 1;
@@ -835,6 +1596,10 @@ impl Application for DndSpells {
                             }
                         }
                     }
+                    Message::CharacterTab(Some(level)) if self.add_spell_picker && level != Level::Cantrip => {
+                        commands.push(self.add_search_result(level as usize - 1));
+                        self.add_spell_picker = false;
+                    }
                     Message::CharacterTab(tab) => {
                         if let Tab::Character { index } = self.tab {
                             if let Some(page) = self.characters.get_mut(index) {
@@ -843,14 +1608,62 @@ impl Application for DndSpells {
                         }
                     }
                     Message::AddSpell(idx) => {
-                        if let Some(spell) = self.search_page.spells.first().map(|s| s.spell.id()) {
-                            if let Some(character) = self.characters.get_mut(idx) {
-                                let spell = find_spell(&spell.name, &self.custom_spells).unwrap();
-                                character.add_spell(spell);
-                                commands.push(self.refresh_search());
+                        if idx < self.characters.len() {
+                            commands.push(self.add_search_result(idx));
+                        } else if !self.search_page.spells.is_empty() {
+                            // no character there, so let them pick one
+                            self.add_spell_picker = true;
+                        }
+                    }
+                    Message::AddSpellPicker => {
+                        self.search_page.flush(&self.custom_spells, &self.characters);
+                        self.add_spell_picker = !self.search_page.spells.is_empty() && !self.characters.is_empty();
+                    }
+                    Message::Escape => {
+                        for page in &mut self.characters {
+                            page.cancel_slot_edit();
+                            page.cancel_rename();
+                        }
+                        self.add_spell_picker = false;
+                        self.command_box = None;
+                        self.settings_page.selected_closed = None;
+                    }
+                    Message::ClosedCharacters => {
+                        self.tab = Tab::Settings;
+                        self.settings_page.select_closed(0, self.closed_characters.len());
+                    }
+                    Message::CommandBox => {
+                        if self.command_box.is_some() {
+                            self.command_box = None;
+                        } else {
+                            let command_box = CommandBox::default();
+                            commands.push(text_input::focus(command_box.id.clone()));
+                            self.command_box = Some(command_box);
+                        }
+                    }
+                    Message::Encounter => {
+                        if let Tab::Character { index } = self.tab {
+                            if let Some(page) = self.characters.get_mut(index) {
+                                page.update(character::Message::ToggleEncounter, &self.custom_spells, self.num_cols, self.preferences.switch_tab_on_add);
                             }
                         }
                     }
+                    Message::OpenClosedCharacter => if let Tab::Settings = self.tab {
+                        match self.settings_page.selected_closed.take() {
+                            Some(index) => if index < self.closed_characters.len() {
+                                let character = self.closed_characters.remove(index);
+                                commands.push(self.add_character(character.character));
+                            },
+                            // like Tab, so Enter still gets to the first field of the spell editor
+                            None => commands.push(widget::focus_next()),
+                        }
+                    },
+                    Message::CharacterSpellUpDown(delta) if self.tab == Tab::Settings => {
+                        self.settings_page.select_closed(delta, self.closed_characters.len());
+                    }
+                    Message::CharacterSpellUpDown(delta) if self.tab == Tab::Search => {
+                        commands.push(self.search_page.update(search::Message::MoveSelection(delta), &self.custom_spells, &self.characters));
+                    }
                     Message::CustomSpellNextField(forwards) => {
                         if let Tab::Settings = self.tab {
                             commands.push(if forwards { widget::focus_next() } else { widget::focus_previous() });
@@ -899,22 +1712,43 @@ impl Application for DndSpells {
                 }
             }
             Message::Resize(width, height) => {
+                // restoring a minimized window resizes it back, without necessarily focusing it
+                self.window_active = true;
                 self.width = width;
                 self.height = height;
             }
+            Message::WindowActive(active) => {
+                self.window_active = active;
+                // letting go of the mouse while minimized isn't listened for, so don't keep dragging
+                if !active {
+                    self.dragging_card = None;
+                    self.dragging_tab = None;
+                }
+            }
+            Message::WindowFocused(focused) => self.window_focused = focused,
             Message::MouseState(msg) => {
                 // println!("self.mouse = {:?}", self.mouse);
                 // println!("msg = {:?}", msg);
                 match msg {
-                    hotmouse::StateMessage::MoveTo(pt) => self.mouse.pt = pt,
+                    hotmouse::StateMessage::MoveTo(pt) => {
+                        self.mouse.pt = pt;
+                        if let Some((card, grab)) = self.dragging_card {
+                            self.floating_cards[card].position = pt - grab;
+                        }
+                    }
                     hotmouse::StateMessage::ButtonPress(ctor) => {
                         self.mouse.press = ctor(Instant::now(), self.mouse.pt);
                         match self.mouse.press {
                             ButtonPress::Middle(_, pt) => {
                                 return Command::perform(async move { pt }, Message::ScrollIGuessHopefully);
                             }
-                            ButtonPress::Left(_, _)
-                            | ButtonPress::Right(_, _) => {}
+                            ButtonPress::Left(_, pt) => {
+                                let num_characters = self.characters.len();
+                                self.dragging_tab = self.tab_at(pt)
+                                    .filter(|&index| Tab::closable(index, num_characters))
+                                    .map(|index| index - 1);
+                            }
+                            ButtonPress::Right(_, _) => {}
                             ButtonPress::None => unreachable!("Pressed a non-existent button?!?"),
                         }
                     }
@@ -928,6 +1762,15 @@ impl Application for DndSpells {
                         if self.mouse.press == button {
                             self.mouse.press = ButtonPress::None;
                         }
+                        if button == Button::Left {
+                            self.dragging_card = None;
+                            if let Some(from) = self.dragging_tab.take() {
+                                // let go off the bar, or where it started, leaves it be
+                                if let Some(to) = self.tab_drop(from) {
+                                    return self.move_character(from, to);
+                                }
+                            }
+                        }
                     }
                     hotmouse::StateMessage::Scroll(delta) => {
                         if self.control_pressed {
@@ -935,27 +1778,173 @@ impl Application for DndSpells {
                                 ScrollDelta::Lines { y, .. }
                                 | ScrollDelta::Pixels { y, .. } => y,
                             }.signum() as usize;
-                            println!("delta = {delta:?}");
+                            log::debug!("columns changed by {delta:?}");
                             self.num_cols += delta;
                         }
                     }
                 }
             }
             Message::ScrollIGuessHopefully(pt) => {
-                println!("matched: {pt:?}");
+                log::debug!("matched: {pt:?}");
             }
             Message::SelectTab(index) => {
-                self.tab = match index {
-                    0 => Tab::Search,
-                    last if last == self.characters.len() + 1 => Tab::Settings,
-                    index => Tab::Character { index: index - 1 }
-                }
+                self.tab = Tab::from_index(index, self.characters.len(), self.preferences.party_tab);
             }
             Message::ToggleSpellTooltip => self.spell_tooltips.toggle(),
+            Message::AddToPickedCharacter(index) => {
+                commands.push(self.add_search_result(index));
+                self.add_spell_picker = false;
+            }
+            Message::CloseAddSpellPicker => self.add_spell_picker = false,
+            Message::PopOut(id) => {
+                let already_open = self.floating_cards.iter().any(|card| card.spell.id() == id);
+                if !already_open && self.floating_cards.len() < FloatingCard::MAX {
+                    if let Some(spell) = find_spell(&id.name, &self.custom_spells) {
+                        self.floating_cards.push(FloatingCard::new(spell, self.floating_cards.len()));
+                    }
+                }
+            }
+            Message::ShowInCharacter(index, id) => if let Some(page) = self.characters.get_mut(index) {
+                let edited_trigger = page.show_spell(id);
+                self.tab = Tab::Character { index };
+                if edited_trigger {
+                    self.try_save();
+                }
+            },
+            Message::CreateUnresolvedSpell(character, index) => {
+                let name = self.characters.get(character)
+                    .and_then(|page| page.character.unresolved.get(index))
+                    .map(|entry| entry.name.clone());
+                if let Some(name) = name {
+                    self.end_spell_edit();
+                    let spell = CustomSpell::new(name);
+                    self.custom_spells.push(Arc::new(spell.clone()));
+                    // every character that knew it gets it back
+                    for page in &mut self.characters {
+                        page.resolve(&self.custom_spells);
+                    }
+                    for closed in &mut self.closed_characters {
+                        closed.character.resolve(&self.custom_spells);
+                    }
+                    self.settings_page.spell_editor = SpellEditor::editing(Box::new(spell));
+                    self.tab = Tab::Settings;
+                    commands.push(self.refresh_search());
+                    self.try_save();
+                }
+            }
+            Message::EditReactionTrigger(id, when) => {
+                let spell = self.custom_spells.iter()
+                    .find(|spell| *spell.name == *id.name)
+                    .map(|spell| (**spell).clone());
+                if let Some(mut spell) = spell {
+                    if let CastingTime::Reaction(trigger) = &mut spell.casting_time {
+                        // saved once editing is done, not on every key
+                        *trigger = CastingTime::trigger(&when);
+                        self.update_custom_spell(&spell);
+                        commands.push(self.refresh_search());
+                    }
+                }
+            }
+            Message::CloseCard(index) => {
+                self.floating_cards.remove(index);
+                self.dragging_card = None;
+            }
+            Message::GrabCard(index) => {
+                self.dragging_card = Some((index, self.mouse.pt - self.floating_cards[index].position));
+            }
+            Message::CommandBox(message) => {
+                use command::Message;
+                match message {
+                    Message::Input(input) => if let Some(command_box) = &mut self.command_box {
+                        command_box.input = input;
+                    },
+                    Message::Submit => if let Some(input) = self.command_box.as_ref().map(|command_box| command_box.input.clone()) {
+                        let result = command::parse(&input)
+                            .and_then(|action| self.command_messages(action));
+                        let output = match result {
+                            Ok((messages, output)) => {
+                                for message in messages {
+                                    commands.push(self.update(message));
+                                }
+                                if let Some(command_box) = &mut self.command_box {
+                                    command_box.input.clear();
+                                }
+                                output
+                            }
+                            Err(e) => e,
+                        };
+                        if let Some(command_box) = &mut self.command_box {
+                            command_box.output = Some(output);
+                            commands.push(text_input::focus(command_box.id.clone()));
+                        }
+                    },
+                    Message::Close => self.command_box = None,
+                }
+            }
+            Message::Onboarding(message) => {
+                use onboarding::Message;
+                if let Some(onboarding) = &mut self.onboarding {
+                    let done = match message {
+                        Message::ShowMe => {
+                            onboarding.show_me();
+                            match onboarding.step {
+                                Step::Search => {
+                                    self.tab = Tab::Search;
+                                    commands.push(text_input::focus(self.search_page.search.id.clone()));
+                                }
+                                Step::CreateCharacter => {
+                                    self.tab = Tab::Settings;
+                                    commands.push(text_input::focus(self.settings_page.character_name_id.clone()));
+                                }
+                                // need a character first
+                                Step::AddSpells if self.characters.is_empty() => self.tab = Tab::Settings,
+                                Step::AddSpells => self.tab = Tab::Character { index: 0 },
+                            }
+                            false
+                        }
+                        Message::Next => !onboarding.next(),
+                        Message::Skip => true,
+                        Message::Pulse => {
+                            onboarding.pulse();
+                            false
+                        }
+                    };
+                    if done {
+                        self.onboarding = None;
+                        self.preferences.onboarded = true;
+                        self.save_preferences();
+                    }
+                }
+            }
+            Message::CloseRequested => {
+                self.end_spell_edit();
+                commands.push(iced::window::close());
+            }
+            Message::OpenUrl(url) => {
+                self.link_error = open::that(&url).err()
+                    .map(|e| format!("Couldn't open {url}: {e}"));
+            }
+            Message::CopyMacro(id) => {
+                if let Some(spell) = find_spell(&id.name, &self.custom_spells) {
+                    commands.push(iced::clipboard::write(macros::roll20(&spell)));
+                }
+            }
+            Message::OverlayStopped(port, result) => {
+                // ignore servers that were already replaced by one on a different port
+                if let (Err(e), true) = (result, port == self.preferences.overlay_port) {
+                    self.overlay = None;
+                    self.preferences.overlay_server = false;
+                    self.settings_page.overlay_error = Some(e);
+                    self.save_preferences();
+                }
+            }
         };
-        // println!("commands = {:?}", commands);
-        commands.try_remove(0)
-            .unwrap_or_else(Command::none)
+        // a half confirmed Empty archive shouldn't still be waiting when settings are opened again
+        if self.tab != Tab::Settings {
+            self.settings_page.empty_archive_step = 0;
+        }
+        // every message's commands run, like the command box's own focus after the commands it sent
+        Command::batch(commands)
     }
 
     fn view(&self) -> Element<'_> {
@@ -963,23 +1952,35 @@ impl Application for DndSpells {
         let num_characters = self.characters.len();
 
         let height = self.height
-            .saturating_sub(26)  // height of tab bar
+            .saturating_sub(tab::BAR_HEIGHT)
             .saturating_sub(20); // height of bottom bar
 
-        let tabs = iced_aw::Tabs::new(self.tab.index(num_characters), Message::SelectTab)
-            .push(TabLabel::Text("Search".into()), self.search_page.view().max_height(height));
+        let highlight = |step| self.onboarding.as_ref().map_or(NO_HIGHLIGHT, |onboarding| onboarding.highlight(step));
+
+        let tabs = iced_aw::Tabs::new(self.tab.index(num_characters, self.preferences.party_tab), Message::SelectTab)
+            .push(TabLabel::Text("Search".into()), self.search_page.view(&self.characters, &self.preferences, highlight(Step::Search)).max_height(height));
         let tabs = self.characters.iter()
             .enumerate()
             .map(|(index, page)| (
                 TabLabel::Text(page.character.name.to_string()),
-                page.view(index, num_cols, self.spell_tooltips).max_height(height)
+                page.view(
+                    index,
+                    num_cols,
+                    self.spell_tooltips,
+                    f32::from(height),
+                    &self.preferences,
+                    if index == 0 { highlight(Step::AddSpells) } else { NO_HIGHLIGHT },
+                ).max_height(height)
             )).fold(
             tabs,
             |tabs, (label, tab)| tabs.push(label, tab),
-        ).push(TabLabel::Text("Settings".into()), self.settings_page.view(&self.closed_characters, self.width).max_height(height))
+        ).tap_if(self.preferences.party_tab, |tabs| tabs.push(
+            TabLabel::Text("Party".into()),
+            party::view(&self.characters).max_height(height),
+        )).push(TabLabel::Text("Settings".into()), self.settings_page.view(&self.closed_characters, &self.preferences, &self.custom_spells, &self.spell_history, highlight(Step::CreateCharacter), self.width).max_height(height))
             .icon_size(10.0)
             .icon_font(ICON_FONT)
-            .on_close(move |i| if i == 0 || i == num_characters + 1 { None } else { Some(Message::CloseCharacter(i - 1)) })
+            .on_close(move |i| Tab::closable(i, num_characters).then(|| Message::CloseCharacter(i - 1)))
             ;
 
         let toggle_spell_tooltip = button(
@@ -1030,6 +2031,7 @@ impl Application for DndSpells {
         let bottom_bar = container(row![
             2,
             self.update_state.view(),
+            text(self.link_error.as_deref().unwrap_or_default()).size(11),
             Length::Fill,
             toggle_spell_tooltip,
             3,
@@ -1049,16 +2051,77 @@ impl Application for DndSpells {
             .height(Length::Fill)
             .width(Length::FillPortion(18));
 
-        let content = col![
-            main_content,
-            bottom_bar
-        ];
+        let add_spell_picker = self.search_page.selected_spell()
+            .filter(|_| self.add_spell_picker)
+            .map(|spell| self.characters.iter()
+                .enumerate()
+                .fold(
+                    row![text(format!("Add {} to:", spell.spell.name())).size(16)]
+                        .spacing(6)
+                        .align_items(Alignment::Center),
+                    |row, (index, page)| row.push(
+                        button(text(format!("{} {}", index + 1, page.character.name)).size(14))
+                            .on_press(Message::AddToPickedCharacter(index))
+                    ),
+                )
+                .push_space(Length::Fill)
+                .push(button(text("Cancel").size(14))
+                    .style(Location::Transparent)
+                    .on_press(Message::CloseAddSpellPicker))
+                .tap(container)
+                .padding(6)
+                .width(Length::Fill)
+                .style(Location::Tooltip));
+
+        let error = self.characters_error.as_ref()
+            .or(self.save_error.as_ref())
+            .map(|error| container(text(error).size(14).style(Location::Error))
+                .padding(6)
+                .width(Length::Fill)
+                .style(Location::Tooltip));
+
+        let content = col![]
+            .tap_if_some(error, Column::push)
+            .tap_if_some(self.command_box.as_ref().map(CommandBox::view), Column::push)
+            .tap_if_some(self.onboarding.as_ref().map(|onboarding| onboarding.view()), Column::push)
+            .tap_if_some(add_spell_picker, Column::push)
+            .push(main_content)
+            .push(bottom_bar);
 
-        container(content)
+        let content = container(content)
             .width(Length::Fill)
             .height(Length::Fill)
             .center_x()
-            .align_y(Vertical::Top)
+            .align_y(Vertical::Top);
+
+        // a bar between the tabs where the dragged one will go
+        let drop_indicator = self.dragging_tab
+            .and_then(|from| self.tab_drop(from).map(|to| (from, to)))
+            .map(|(from, to)| {
+                let num_tabs = Tab::Settings.index(num_characters, self.preferences.party_tab) + 1;
+                let tab_width = f32::from(self.width) / num_tabs as f32;
+                // after the tab it lands on when moving right, before it when moving left
+                let edge = to + 1 + usize::from(to > from);
+                let bar = container(Space::new(Length::Fixed(4.0), Length::Fixed(f32::from(tab::BAR_HEIGHT))))
+                    .style(Location::TriState(Some(true)));
+                (Point::new(tab_width * edge as f32 - 2.0, 0.0), bar, to + 1)
+            });
+
+        self.floating_cards.iter()
+            .enumerate()
+            .fold(
+                Floating::new(content),
+                |floating, (index, card)| floating.push(
+                    Point::new(card.position.0, card.position.1),
+                    card.view(index, &self.preferences),
+                    Message::GrabCard(index),
+                ),
+            )
+            .tap_if_some(drop_indicator, |floating, (position, bar, tab)| floating.push(
+                position,
+                bar,
+                Message::SelectTab(tab),
+            ))
             .into()
     }
 
@@ -1067,28 +2130,55 @@ impl Application for DndSpells {
     }
 
     fn subscription(&self) -> Subscription<Self::Message> {
-        let listeners = iced::subscription::events_with(|event, _status| {
+        fn window_event(event: window::Event) -> Option<Message> {
             match event {
-                Event::Keyboard(e) => hotkey::handle(e),
-                Event::Window(e) => match e {
-                    window::Event::Resized { width, height } => Some(Message::Resize(width as u16, height as u16)),
-                    _ => None,
-                },
-                Event::Mouse(e) => hotmouse::handle(e),
-                Event::Touch(_) => None,
-                // Event::PlatformSpecific(_) => None,
+                // minimizing resizes the window to 0x0 on some platforms
+                window::Event::Resized { width: 0, height: 0 } => Some(Message::WindowActive(false)),
+                window::Event::Resized { width, height } => Some(Message::Resize(width as u16, height as u16)),
+                window::Event::Focused => Some(Message::WindowFocused(true)),
+                window::Event::Unfocused => Some(Message::WindowFocused(false)),
+                window::Event::CloseRequested => Some(Message::CloseRequested),
+                _ => None,
             }
-        });
-        match &self.update_state {
-            UpdateState::Ready | UpdateState::Downloading(_) => {
-                let download = Subscription::from_recipe(update::Download { url: self.update_url.clone() })
-                    .map(|p| Message::Update(update::Message::Progress(p)));
-                Subscription::batch([
-                    listeners,
-                    download,
-                ])
-            }
-            _ => listeners
+        }
+
+        let running = self.window_active || self.preferences.keep_running_in_background;
+        let listeners = if running {
+            iced::subscription::events_with(|event, status| {
+                match event {
+                    Event::Keyboard(e) => hotkey::handle(e, status),
+                    Event::Window(e) => window_event(e),
+                    Event::Mouse(e) => hotmouse::handle(e),
+                    Event::Touch(_) => None,
+                    // Event::PlatformSpecific(_) => None,
+                }
+            })
+        } else {
+            // every message rebuilds the view, so while minimized only listen for the window
+            // being restored
+            iced::subscription::events_with(|event, _status| match event {
+                Event::Window(e) => window_event(e),
+                _ => None,
+            })
+        };
+        // nobody can see it pulse while minimized
+        let pulse = self.onboarding.as_ref()
+            .filter(|onboarding| running && onboarding.is_pulsing())
+            .map(|_| iced_futures::backend::default::time::every(Duration::from_millis(400))
+                .map(|_| Message::Onboarding(onboarding::Message::Pulse)));
+        let listeners = match pulse {
+            Some(pulse) => Subscription::batch([listeners, pulse]),
+            None => listeners,
+        };
+        if self.update_state.downloads() {
+            let download = Subscription::from_recipe(update::Download { url: self.update_url.clone(), version: self.update_version.clone() })
+                .map(|p| Message::Update(update::Message::Progress(p)));
+            Subscription::batch([
+                listeners,
+                download,
+            ])
+        } else {
+            listeners
         }
     }
 }
@@ -1099,13 +2189,19 @@ impl From<School> for String {
     }
 }
 
+/// Borrows its strings when parsing from a `&'static str` like the bundled json, and owns them
+/// otherwise
 #[derive(Deserialize)]
-struct DeserializeSpell {
-    name: &'static str,
+struct DeserializeSpell<'a> {
+    #[serde(borrow)]
+    name: Cow<'a, str>,
     level: Level,
-    casting_time: &'static str,
-    range: &'static str,
-    duration: &'static str,
+    #[serde(borrow)]
+    casting_time: Cow<'a, str>,
+    #[serde(borrow)]
+    range: Cow<'a, str>,
+    #[serde(borrow)]
+    duration: Cow<'a, str>,
     components: Components,
     school: School,
     ritual: bool,
@@ -1117,8 +2213,60 @@ struct DeserializeSpell {
     page: u32,
 }
 
+impl DeserializeSpell<'_> {
+    fn into_owned(self) -> DeserializeSpell<'static> {
+        DeserializeSpell {
+            name: Cow::Owned(self.name.into_owned()),
+            casting_time: Cow::Owned(self.casting_time.into_owned()),
+            range: Cow::Owned(self.range.into_owned()),
+            duration: Cow::Owned(self.duration.into_owned()),
+            level: self.level,
+            components: self.components,
+            school: self.school,
+            ritual: self.ritual,
+            conc: self.conc,
+            description: self.description,
+            higher_levels: self.higher_levels,
+            classes: self.classes,
+            source: self.source,
+            page: self.page,
+        }
+    }
+}
+
 pub trait SpellButtons {
     type Data;
 
     fn view<'c>(self, id: SpellId, data: Self::Data) -> (Row<'c>, Element<'c>);
+
+    /// the level of the character viewing the spell, for showing scaled cantrip damage
+    fn character_level(&self) -> Option<u8> {
+        None
+    }
+
+    /// details to show under the spell's name when it's collapsed
+    fn summary_fields(&self) -> &[SummaryField] {
+        &[]
+    }
+
+    /// if a long description is shown in full, and the message that toggles it. `None` always
+    /// shows all of it
+    fn full_text(&self, _id: SpellId) -> Option<(bool, Message)> {
+        None
+    }
+
+    /// where a text search found this spell, to point out when it's not in the name
+    fn matched_in(&self) -> Option<TextMatch> {
+        None
+    }
+
+    /// the text search's terms, already folded, to highlight in the description
+    fn highlight(&self) -> &[String] {
+        &[]
+    }
+
+    /// the slot levels to show this spell's upcast damage at, if the viewer can pick one
+    fn upcast(&self, _id: &SpellId) -> Option<Upcast> {
+        None
+    }
 }
\ No newline at end of file
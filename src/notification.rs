@@ -0,0 +1,78 @@
+//! Desktop notifications, for when something finishes while the window is in the background.
+
+use notify_rust::Notification;
+
+use crate::UpdateState;
+
+/// what every notification is titled
+const SUMMARY: &str = "D&D Spells";
+
+pub fn update_downloaded(version: &str) -> String {
+    format!("Spells v{version} downloaded — restart to apply")
+}
+
+pub fn update_failed(error: &str) -> String {
+    format!("Failed to download update: {error}")
+}
+
+/// What to say about a download of `version` that just ended in `state`, if it ended at all
+pub fn update_ended(state: &UpdateState, version: &str) -> Option<String> {
+    match state {
+        UpdateState::Downloaded => Some(update_downloaded(version)),
+        UpdateState::Errored(e) => Some(update_failed(e)),
+        _ => None,
+    }
+}
+
+/// Shows `body` as a desktop notification. Does nothing if there's no notification backend.
+pub fn send(body: &str) {
+    send_with(body, |summary, body| {
+        let _ = Notification::new()
+            .summary(summary)
+            .body(body)
+            .show();
+    });
+}
+
+/// [`send`], with `show` showing the notification's summary and body on this platform
+fn send_with(body: &str, show: impl FnOnce(&str, &str)) {
+    show(SUMMARY, body);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// the summary and body of each notification that would have been shown
+    fn sent(bodies: &[&str]) -> Vec<(String, String)> {
+        let mut shown = Vec::new();
+        for body in bodies {
+            send_with(body, |summary, body| shown.push((summary.to_string(), body.to_string())));
+        }
+        shown
+    }
+
+    #[test]
+    fn update_messages() {
+        assert_eq!(update_downloaded("1.2.3"), "Spells v1.2.3 downloaded — restart to apply");
+        assert_eq!(update_failed("timed out"), "Failed to download update: timed out");
+    }
+
+    #[test]
+    fn only_finished_downloads_are_told_about() {
+        assert_eq!(update_ended(&UpdateState::Downloaded, "1.2.3"), Some(update_downloaded("1.2.3")));
+        assert_eq!(update_ended(&UpdateState::Errored("timed out".into()), "1.2.3"), Some(update_failed("timed out")));
+        for state in [UpdateState::Checking, UpdateState::Ready, UpdateState::Downloading(50.0), UpdateState::Cancelled, UpdateState::UpToDate] {
+            assert_eq!(update_ended(&state, "1.2.3"), None, "{state:?}");
+        }
+    }
+
+    #[test]
+    fn notifications_are_titled() {
+        let body = update_downloaded("1.2.3");
+        assert_eq!(sent(&[&body, "second"]), [
+            (SUMMARY.to_string(), body.clone()),
+            (SUMMARY.to_string(), "second".to_string()),
+        ]);
+    }
+}
@@ -0,0 +1,106 @@
+//! A few steps shown on the first run, pointing at the search bar, creating characters, and
+//! character pages.
+
+use iced::{Alignment, Length};
+use iced::widget::{button, container, text};
+
+use crate::{Container, Location};
+use crate::utils::Tap;
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    /// go to this step's tab and point at its control
+    ShowMe,
+    Next,
+    Skip,
+    Pulse,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Step {
+    Search,
+    CreateCharacter,
+    AddSpells,
+}
+
+impl Step {
+    const ALL: [Self; 3] = [Self::Search, Self::CreateCharacter, Self::AddSpells];
+
+    fn explanation(self) -> &'static str {
+        match self {
+            Self::Search => "Search for spells here by name, or open the advanced search to filter them",
+            Self::CreateCharacter => "Create a character on the Settings tab",
+            Self::AddSpells => "Add spells to your character from the search results, then track its spell slots on its page",
+        }
+    }
+}
+
+pub struct Onboarding {
+    pub step: Step,
+    /// ticks left of pointing at this step's control
+    pulses: u8,
+}
+
+impl Default for Onboarding {
+    fn default() -> Self {
+        Self { step: Step::Search, pulses: 0 }
+    }
+}
+
+impl Onboarding {
+    const PULSES: u8 = 6;
+
+    /// Moves to the next step. Returns `false` if this was the last one.
+    pub fn next(&mut self) -> bool {
+        self.pulses = 0;
+        match Step::ALL.iter().skip_while(|&&step| step != self.step).nth(1) {
+            Some(&step) => {
+                self.step = step;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn show_me(&mut self) {
+        self.pulses = Self::PULSES;
+    }
+
+    pub fn pulse(&mut self) {
+        self.pulses = self.pulses.saturating_sub(1);
+    }
+
+    pub fn is_pulsing(&self) -> bool {
+        self.pulses != 0
+    }
+
+    /// The style for the control that `step` points at
+    pub fn highlight(&self, step: Step) -> Location {
+        Location::Highlight { on: self.step == step && self.pulses % 2 == 1 }
+    }
+
+    pub fn view<'s, 'c: 's>(&'s self) -> Container<'c> {
+        let message = crate::Message::Onboarding;
+        let number = Step::ALL.iter().position(|&step| step == self.step).unwrap_or(0) + 1;
+        let last = number == Step::ALL.len();
+        row![
+            text(format!("{number}/{}: {}", Step::ALL.len(), self.step.explanation())).size(16),
+            Length::Fill,
+            button(text("Show me").size(14))
+                .on_press(message(Message::ShowMe)),
+            button(text(if last { "Done" } else { "Next" }).size(14))
+                .on_press(message(Message::Next)),
+            button(text("Skip").size(14))
+                .style(Location::Transparent)
+                .on_press(message(Message::Skip)),
+        ].spacing(6)
+            .align_items(Alignment::Center)
+            .tap(container)
+            .padding(6)
+            .width(Length::Fill)
+            .style(Location::Tooltip)
+    }
+}
+
+/// No highlight, for when there's no onboarding
+pub const NO_HIGHLIGHT: Location = Location::Highlight { on: false };
@@ -0,0 +1,118 @@
+//! A tiny read-only http server on localhost, so that streamers can show their characters in a
+//! browser source.
+
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+
+use hyper::{Body, header, Method, Request, Response, Server, StatusCode};
+use hyper::service::{make_service_fn, service_fn};
+use serde::Serialize;
+use tokio::sync::oneshot;
+
+use crate::character::{CharacterPage, Slots};
+use crate::Level;
+
+const PAGE: &str = include_str!("../resources/overlay.html");
+
+pub type Shared = Arc<RwLock<Snapshot>>;
+
+#[derive(Debug, Default, Serialize)]
+pub struct Snapshot {
+    characters: Vec<CharacterSnapshot>,
+}
+
+#[derive(Debug, Serialize)]
+struct CharacterSnapshot {
+    name: Arc<str>,
+    slots: Vec<SlotsSnapshot>,
+    prepared: Vec<String>,
+    concentration: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct SlotsSnapshot {
+    level: u8,
+    used: u32,
+    total: u32,
+}
+
+impl Snapshot {
+    pub fn new(characters: &[CharacterPage]) -> Self {
+        let characters = characters.iter()
+            .map(|page| &page.character)
+            .map(|character| CharacterSnapshot {
+                name: Arc::clone(&character.name),
+                slots: Level::ALL[1..].iter()
+                    .zip(&character.slots)
+                    .filter(|(_, slots)| slots.total != 0)
+                    .map(|(&level, &Slots { total, used })| SlotsSnapshot { level: level as u8, used, total })
+                    .collect(),
+                prepared: character.spells.iter()
+                    .flatten()
+                    .filter(|(_, prepared)| *prepared)
+                    .map(|(spell, _)| spell.name().to_string())
+                    .collect(),
+                concentration: character.concentrating_on.as_ref().map(|id| id.name.to_string()),
+            })
+            .collect();
+        Self { characters }
+    }
+}
+
+/// Stops the server when dropped
+pub struct Handle {
+    _shutdown: oneshot::Sender<()>,
+}
+
+/// Returns a handle that shuts the server down when dropped, and the server future itself, which
+/// should be run on the tokio runtime (ie, via `Command::perform`).
+pub fn serve(port: u16, snapshot: Shared) -> (Handle, impl Future<Output=Result<(), String>>) {
+    let (shutdown, rx) = oneshot::channel::<()>();
+    // never bind to anything but localhost
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    let server = async move {
+        let make_service = make_service_fn(move |_conn| {
+            let snapshot = Arc::clone(&snapshot);
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req| respond(req, Arc::clone(&snapshot))))
+            }
+        });
+        Server::try_bind(&addr)
+            .map_err(|e| e.to_string())?
+            .serve(make_service)
+            .with_graceful_shutdown(async {
+                // either an explicit shutdown or the handle was dropped
+                let _ = rx.await;
+            })
+            .await
+            .map_err(|e| e.to_string())
+    };
+    (Handle { _shutdown: shutdown }, server)
+}
+
+#[allow(clippy::unused_async)]
+async fn respond(req: Request<Body>, snapshot: Shared) -> Result<Response<Body>, Infallible> {
+    let response = match (req.method(), req.uri().path()) {
+        (&Method::GET, "/" | "/index.html") => Response::builder()
+            .header(header::CONTENT_TYPE, "text/html; charset=utf-8")
+            .body(Body::from(PAGE)),
+        (&Method::GET, "/characters.json") => {
+            let json = snapshot.read()
+                .map_or_else(
+                    |_| Ok(String::from("{}")),
+                    |snapshot| serde_json::to_string(&*snapshot),
+                )
+                .unwrap_or_else(|_| String::from("{}"));
+            Response::builder()
+                .header(header::CONTENT_TYPE, "application/json")
+                .header(header::CACHE_CONTROL, "no-store")
+                .body(Body::from(json))
+        }
+        _ => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty()),
+    };
+    Ok(response.expect("all headers are valid"))
+}
@@ -0,0 +1,115 @@
+//! The Party tab: every open character's slots and concentration spells on one read only page,
+//! for a DM's screen. Turned on in preferences.
+
+use std::iter;
+
+use iced::{Alignment, Length};
+use iced::widget::{container, horizontal_rule, scrollable, text};
+use itertools::Itertools;
+
+use crate::{Column, Container, Level, Location};
+use crate::character::{CharacterPage, Slots};
+use crate::utils::Tap;
+
+pub fn view<'c>(characters: &[CharacterPage]) -> Container<'c> {
+    let pool_row = slots_row(&pool(characters), 20);
+
+    let character_col = |page: &CharacterPage| {
+        let character = &page.character;
+        let slots = iter::zip(&character.slots, character.bonus_slots)
+            .map(|(slots, bonus)| Slots { total: slots.total + bonus, used: slots.used })
+            .collect_vec();
+        let concentration = character.spells[1..].iter()
+            .flatten()
+            .filter(|(spell, prepared)| *prepared && spell.concentration())
+            .map(|(spell, _)| spell.name())
+            .join(", ");
+        col![
+            text(&*character.name).size(24),
+            slots_row(&slots, 16),
+        ].spacing(4)
+            .align_items(Alignment::Center)
+            .tap_if_some(character.sorcery_points, |col, Slots { total, used }| col.push(
+                text(format!("Sorcery points: {}/{total}", total - used)).size(14)
+            ))
+            .tap_if(!concentration.is_empty(), |col| col.push(
+                text(format!("Concentration prepared: {concentration}")).size(14).style(Location::Muted)
+            ))
+    };
+
+    let characters_col = characters.iter()
+        .fold(Column::new().spacing(12).align_items(Alignment::Center), |col, page| col
+            .push(horizontal_rule(0))
+            .push(character_col(page)));
+
+    col![
+        10,
+        text("Party slots").size(30),
+        pool_row,
+    ].spacing(6)
+        .align_items(Alignment::Center)
+        .tap_if(characters.is_empty(), |col| col.push(text("No characters are open").size(18).style(Location::Muted)))
+        .push(scrollable(characters_col.width(Length::Fill)))
+        .tap(container)
+        .width(Length::Fill)
+}
+
+/// Remaining and total slots at each level, summed over the party
+fn pool(characters: &[CharacterPage]) -> [Slots; 9] {
+    characters.iter()
+        .fold([Slots::default(); 9], |mut pool, page| {
+            let character = &page.character;
+            for ((pool, slots), bonus) in pool.iter_mut().zip(&character.slots).zip(character.bonus_slots) {
+                pool.total += slots.total + bonus;
+                pool.used += slots.used;
+            }
+            pool
+        })
+}
+
+/// remaining/total for each level that has any slots
+fn slots_row<'c>(slots: &[Slots], size: u16) -> crate::Row<'c> {
+    Level::ALL[1..].iter()
+        .zip(slots)
+        .filter(|(_, slots)| slots.total != 0)
+        .fold(row![].spacing(16), |row, (level, Slots { total, used })| row.push(
+            text(format!("{level}: {}/{total}", total.saturating_sub(*used))).size(size)
+        ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::character::Character;
+
+    use super::*;
+
+    fn page(name: &str, slots: &[(u32, u32)], bonus: u32) -> CharacterPage {
+        let mut character = Character::new(Arc::from(name));
+        character.slots = Default::default();
+        for (slot, &(total, used)) in character.slots.iter_mut().zip(slots) {
+            *slot = Slots { total, used };
+        }
+        character.bonus_slots[0] = bonus;
+        CharacterPage::from(character)
+    }
+
+    #[test]
+    fn pool_adds_up_everyones_slots() {
+        let party = [
+            page("Merric", &[(4, 1), (3, 3), (2, 0)], 0),
+            page("Lia", &[(2, 2)], 1),
+        ];
+        let pool = pool(&party);
+        assert_eq!(pool[0], Slots { total: 7, used: 3 });
+        assert_eq!(pool[1], Slots { total: 3, used: 3 });
+        assert_eq!(pool[2], Slots { total: 2, used: 0 });
+        assert!(pool[3..].iter().all(|&slots| slots == Slots::default()));
+    }
+
+    #[test]
+    fn no_party_no_slots() {
+        assert_eq!(pool(&[]), [Slots::default(); 9]);
+    }
+}
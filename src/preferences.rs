@@ -0,0 +1,297 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use iced::{Alignment, Length};
+use iced::widget::{checkbox, text, text_input};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::{Column, error, Source};
+use crate::spells::spell::SummaryField;
+use crate::utils::Tap;
+
+/// For each source, a url that its page references link to. `{page}` and `{name}` are replaced by
+/// the page number and a slug of the spell's name (`"Tasha's Hideous Laughter"` becomes
+/// `"tashas-hideous-laughter"`).
+pub type SourceLinks = BTreeMap<Source, String>;
+
+/// The url a spell's page reference links to, if there's a link set for its source. Templates
+/// that use `{page}` don't link spells without a page number.
+pub fn source_url(links: &SourceLinks, source: Source, name: &str, page: Option<u32>) -> Option<String> {
+    let template = links.get(&source)
+        .map(|template| template.trim())
+        .filter(|template| !template.is_empty())?;
+    let url = if template.contains("{page}") {
+        template.replace("{page}", &page?.to_string())
+    } else {
+        template.to_string()
+    };
+    Some(url.replace("{name}", &slug(name)))
+}
+
+/// Lowercase, with apostrophes dropped and every other run of non-alphanumerics replaced by `-`
+fn slug(name: &str) -> String {
+    name.split(|c: char| !c.is_alphanumeric() && c != '\'' && c != '’')
+        .map(|word| word.chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect::<String>())
+        .filter(|word| !word.is_empty())
+        .join("-")
+}
+
+/// Where a spell's "Search online" button goes, with `{name}` replaced by the spell's name. `None`
+/// if `template` is empty, which hides the button, or doesn't have a `{name}`.
+pub fn search_url(template: &str, name: &str) -> Option<String> {
+    let template = template.trim();
+    template.contains("{name}")
+        .then(|| template.replace("{name}", &encode_query(name)))
+}
+
+/// Percent encodes `str` to go in a url's query, with spaces as `+`
+fn encode_query(str: &str) -> String {
+    str.bytes()
+        .map(|b| match b {
+            b' ' => "+".to_string(),
+            b if b.is_ascii_alphanumeric() || b"-_.~".contains(&b) => char::from(b).to_string(),
+            b => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    ToggleOverlayServer(bool),
+    OverlayPort(String),
+    ToggleKeepRunning(bool),
+    ToggleNotifications(bool),
+    SourceLink(Source, String),
+    SearchUrl(String),
+    ToggleSummaryField(SummaryField),
+    ToggleSwitchTabOnAdd(bool),
+    TogglePartyTab(bool),
+    ToggleShortenDescriptions(bool),
+    ToggleHoverPreviews(bool),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preferences {
+    /// serve the open characters on `127.0.0.1:overlay_port` for stream overlays
+    pub overlay_server: bool,
+    pub overlay_port: u16,
+    /// keep handling all events while the window is minimized
+    pub keep_running_in_background: bool,
+    /// desktop notifications when an update finishes downloading while the window isn't focused
+    pub notifications: bool,
+    pub source_links: SourceLinks,
+    /// details shown on collapsed search results, at most [`SummaryField::MAX`]
+    pub summary_fields: Vec<SummaryField>,
+    /// finished or skipped the first run steps. Missing means this save is from before they
+    /// existed, so they aren't shown
+    pub onboarded: bool,
+    /// after adding a spell to a character who's on another level's tab, switch to the spell's
+    /// level, instead of just saying where it went
+    pub switch_tab_on_add: bool,
+    /// show a tab summing up every open character, for the DM's screen
+    pub party_tab: bool,
+    /// see [`search_url`]. Empty hides the "Search online" button
+    pub search_url: String,
+    /// cut off long descriptions behind a "Show full text" button, see [`paragraphs`](crate::spells::spell::paragraphs)
+    pub shorten_long_descriptions: bool,
+    /// resting the cursor on a collapsed search result's name shows a bit of the spell
+    pub hover_previews: bool,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            overlay_server: false,
+            overlay_port: 7357,
+            keep_running_in_background: false,
+            notifications: true,
+            source_links: SourceLinks::new(),
+            summary_fields: Vec::new(),
+            onboarded: true,
+            switch_tab_on_add: true,
+            party_tab: false,
+            search_url: "https://duckduckgo.com/?q=dnd+5e+{name}".to_string(),
+            shorten_long_descriptions: true,
+            hover_previews: true,
+        }
+    }
+}
+
+impl Preferences {
+    pub fn read(file: &Path) -> error::Result<Self> {
+        let json = fs::read_to_string(file)?;
+        if json.trim().is_empty() {
+            Ok(Self::default())
+        } else {
+            Ok(serde_json::from_str(&json)?)
+        }
+    }
+
+    pub fn save(&self, file: &Path) -> error::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(file, json)?;
+        Ok(())
+    }
+
+    pub fn view<'s, 'c: 's>(&'s self, overlay_error: Option<&'s str>) -> Column<'c> {
+        let message = |message: Message| crate::Message::Settings(crate::settings::Message::Preferences(message));
+
+        let overlay = row![
+            checkbox(
+                "Serve characters for stream overlays",
+                self.overlay_server,
+                move |b| message(Message::ToggleOverlayServer(b)),
+            ),
+            Length::Fill,
+            text("Port:").size(16),
+            4,
+            text_input(
+                "7357",
+                &self.overlay_port.to_string(),
+            ).width(Length::Fixed(60.0))
+                .on_input(move |port| message(Message::OverlayPort(port))),
+        ].align_items(Alignment::Center);
+
+        let overlay_url = text(if self.overlay_server {
+            format!("Add http://127.0.0.1:{}/ as a browser source", self.overlay_port)
+        } else {
+            String::new()
+        }).size(12);
+
+        let keep_running = checkbox(
+            "Keep running while minimized",
+            self.keep_running_in_background,
+            move |b| message(Message::ToggleKeepRunning(b)),
+        );
+
+        let notifications = checkbox(
+            "Notify me when updates download in the background",
+            self.notifications,
+            move |b| message(Message::ToggleNotifications(b)),
+        );
+
+        let switch_tab = checkbox(
+            "Switch to a spell's level tab after adding it",
+            self.switch_tab_on_add,
+            move |b| message(Message::ToggleSwitchTabOnAdd(b)),
+        );
+
+        let party_tab = checkbox(
+            "Show a Party tab with every character's slots",
+            self.party_tab,
+            move |b| message(Message::TogglePartyTab(b)),
+        );
+
+        let shorten = checkbox(
+            "Shorten long spell descriptions until they're expanded",
+            self.shorten_long_descriptions,
+            move |b| message(Message::ToggleShortenDescriptions(b)),
+        );
+
+        let hover_previews = checkbox(
+            "Preview collapsed search results when hovering over their names",
+            self.hover_previews,
+            move |b| message(Message::ToggleHoverPreviews(b)),
+        );
+
+        let source_links = Source::ALL.into_iter()
+            .fold(
+                col![
+                    text("Page links").size(16),
+                    text("Links page references to a url, with {page} and {name} filled in").size(12),
+                ].spacing(4),
+                |col, source| col.push(row![
+                    text(source).size(14).width(Length::Fixed(220.0)),
+                    text_input(
+                        "https://example.com/{name}",
+                        self.source_links.get(&source).map_or("", String::as_str),
+                    ).size(14)
+                        .on_input(move |link| message(Message::SourceLink(source, link))),
+                ].align_items(Alignment::Center)),
+            );
+
+        let search_url = col![
+            text("Search online").size(16),
+            text("Where a spell's Search online button goes, with {name} filled in. Leave it empty to hide the button").size(12),
+            text_input(
+                "https://duckduckgo.com/?q=dnd+5e+{name}",
+                &self.search_url,
+            ).size(14)
+                .on_input(move |url| message(Message::SearchUrl(url))),
+        ].spacing(4)
+            .tap_if(
+                !self.search_url.trim().is_empty() && !self.search_url.contains("{name}"),
+                |col| col.push(text("Needs a {name} where the spell's name goes").size(12)),
+            );
+
+        col![
+            overlay,
+            overlay_url,
+            keep_running,
+            notifications,
+            switch_tab,
+            party_tab,
+            shorten,
+            hover_previews,
+            source_links,
+            search_url,
+        ].spacing(4)
+            .tap_if_some(overlay_error, |col, e| col.push(text(format!("Overlay server stopped: {e}")).size(12)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn links(template: &str) -> SourceLinks {
+        SourceLinks::from([(Source::PlayersHandbook, template.to_string())])
+    }
+
+    #[test]
+    fn slugs_drop_apostrophes_and_punctuation() {
+        assert_eq!(slug("Tasha's Hideous Laughter"), "tashas-hideous-laughter");
+        assert_eq!(slug("Tasha’s Hideous Laughter"), "tashas-hideous-laughter");
+        assert_eq!(slug("Antipathy/Sympathy"), "antipathy-sympathy");
+        assert_eq!(slug("Snilloc's Snowball Swarm"), "snillocs-snowball-swarm");
+        assert_eq!(slug("  Hold   Person!  "), "hold-person");
+        assert_eq!(slug("Power Word: Kill"), "power-word-kill");
+    }
+
+    #[test]
+    fn source_urls_fill_in_the_template() {
+        let url = |template: &str, page| source_url(&links(template), Source::PlayersHandbook, "Tasha's Hideous Laughter", page);
+        assert_eq!(url("https://example.com/phb#page={page}", Some(280)), Some("https://example.com/phb#page=280".to_string()));
+        assert_eq!(url("https://example.com/spell:{name}", None), Some("https://example.com/spell:tashas-hideous-laughter".to_string()));
+        assert_eq!(url("https://example.com/{name}/{page}", Some(280)), Some("https://example.com/tashas-hideous-laughter/280".to_string()));
+        assert_eq!(url("  https://example.com/phb  ", None), Some("https://example.com/phb".to_string()));
+    }
+
+    #[test]
+    fn missing_pages_and_templates_have_no_url() {
+        let url = |links: &SourceLinks, source, page| source_url(links, source, "Shield", page);
+        assert_eq!(url(&links("https://example.com/phb#page={page}"), Source::PlayersHandbook, None), None);
+        assert_eq!(url(&links(""), Source::PlayersHandbook, Some(275)), None);
+        assert_eq!(url(&links("   "), Source::PlayersHandbook, Some(275)), None);
+        assert_eq!(url(&links("https://example.com/{page}"), Source::TashasCauldronOfEverything, Some(275)), None);
+        assert_eq!(url(&SourceLinks::new(), Source::PlayersHandbook, Some(275)), None);
+    }
+
+    #[test]
+    fn search_urls_encode_the_name() {
+        assert_eq!(
+            search_url("https://www.google.com/search?q={name}+5e", "Tasha's Hideous Laughter"),
+            Some("https://www.google.com/search?q=Tasha%27s+Hideous+Laughter+5e".to_string()),
+        );
+        assert_eq!(search_url("https://example.com/?q={name}", "Antipathy/Sympathy & café"), Some("https://example.com/?q=Antipathy%2FSympathy+%26+caf%C3%A9".to_string()));
+        assert_eq!(search_url("https://example.com/?q={name}", "a-b_c.d~e"), Some("https://example.com/?q=a-b_c.d~e".to_string()));
+        assert_eq!(search_url("", "Shield"), None);
+        assert_eq!(search_url("https://example.com/", "Shield"), None);
+    }
+}
@@ -1,33 +1,75 @@
+use std::collections::{HashMap, HashSet};
 use std::convert::identity;
-use std::fmt::Debug;
+use std::fmt::{self, Debug, Display};
+use std::fs;
+use std::io::ErrorKind;
 use std::iter;
+use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 
 use iced::{Alignment, Length};
-use iced::widget::{button, container, scrollable, text, text_input};
+use iced::widget::{button, checkbox, container, pick_list, scrollable, text, text_input};
 use iced_native::Command;
 use iced_native::widget::column;
+use iced::widget::scrollable::RelativeOffset;
 use itertools::Itertools;
+use levenshtein::levenshtein;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
 
-use crate::{character, Container, Element, ICON_FONT, Location, Row, Scrollable, SpellButtons, SpellId, SPELLS, Theme};
+use crate::{character, Container, error, Element, ICON_FONT, Location, Row, Scrollable, SpellButtons, SpellId, SPELLS, Theme};
 use crate::character::CharacterPage;
+use crate::export::model::SpellText;
 use crate::icon::Icon;
-use crate::spells::data::{CastingTime, Class, Components, Level, School, Source};
-use crate::spells::spell::{CustomSpell, Spell};
+use crate::preferences::Preferences;
+use crate::spells::data::{Ability, CastingTime, Class, Components, DamageType, Level, School, Source, Tag};
+use crate::spells::spell::{CustomSpell, fold, Spell, SummaryField};
 use crate::theme::types::Button;
 use crate::utils::{IterExt, SpacingExt, Tap, text_icon, Toggle, TooltipExt};
+use crate::widgets::hover_preview::HoverPreview;
 
 #[derive(Clone, Debug)]
 pub enum Message {
     Refresh,
     CollapseAll,
+    /// show or hide the checkboxes for which details collapsed spells show
+    ToggleFieldPicker,
     Collapse(SpellId),
+    /// show all of a long description, or shorten it again
+    ToggleFullText(SpellId),
+    PickDefaultCharacter(DefaultCharacter),
+    /// show which characters know each spell
+    ToggleKnownBadges,
+    PickKnownFilter(KnownFilter),
+    /// show another [`PAGE_SIZE`] results
+    LoadMore,
+    /// move the keyboard selection through the results, ±1 down or up
+    MoveSelection(isize),
+    /// select and expand a random result
+    Random,
+    /// typing paused long enough to search, if nothing was typed since this generation
+    Debounced(u64),
+    /// the results of a search run in the background, and its generation
+    Results(Vec<Spell>, u64),
+    /// enter in the search bar
+    Submit,
     Search(String),
     // PickMode(Mode),
     ToggleAdvanced,
+    /// open advanced search, from a filter chip
+    ShowAdvanced,
+    /// clear one of [`SearchOptions::searchers`]
+    ClearSearcher(usize),
+    SetSortOrder(SortOrder),
+    /// fall back to names with typos when no name contains the search
+    ToggleFuzzy,
+    PickFuzzyTypos(usize),
     ResetSearch,
     PickLevel(Level),
     PickCastingTime(CastingTime),
+    PickDuration(DurationBucket),
+    PickRange(RangeBucket),
     PickClass(Class),
     PickSchool(School),
     PickSource(Source),
@@ -35,9 +77,27 @@ pub enum Message {
     ToggleRitualEnabled,
     ToggleConcentration,
     ToggleConcentrationEnabled,
+    ToggleHigherLevels,
+    ToggleHigherLevelsEnabled,
+    ToggleScales,
+    ToggleScalesEnabled,
     SearchText(String),
+    ToggleTextField(usize),
+    SearchMaterial(String),
+    PickDamage(DamageType),
+    PickSave(Ability),
     ToggleComponent(usize),
     ToggleComponentEnabled(usize),
+    /// component, `None` to not filter, `Some(required)` to filter
+    SetComponent(usize, Option<bool>),
+    ComponentPreset(ComponentPreset),
+    /// only bonus action spells and cantrips that take an action
+    ToggleBonusActionTurn,
+    /// check or uncheck a result, to add it to a character with the other checked ones
+    ToggleBulk(SpellId),
+    /// the character the checked results are added to
+    PickBulkCharacter(DefaultCharacter),
+    ClearBulk,
 }
 
 // pub trait PLNone {
@@ -78,6 +138,9 @@ pub trait Searcher: Debug {
 
     fn is_empty(&self) -> bool;
 
+    /// What this is filtering for, like `Level: 3rd, 5th`, for when it isn't [empty](Self::is_empty)
+    fn describe(&self) -> String;
+
     fn matches(&self, spell: &Spell) -> bool;
 
     fn view<'s, 'c: 's>(&'s self, character: Option<usize>) -> Row<'c>;
@@ -90,7 +153,7 @@ fn wrap_character(character: Option<usize>, message: Message) -> crate::Message
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct LevelSearch {
     pub levels: [bool; 10],
 }
@@ -104,6 +167,13 @@ impl Searcher for LevelSearch {
         self.levels.into_iter().none(identity)
     }
 
+    fn describe(&self) -> String {
+        format!("Level: {}", iter::zip(self.levels, Level::ALL)
+            .filter(|&(enabled, _)| enabled)
+            .map(|(_, level)| level)
+            .join(", "))
+    }
+
     fn matches(&self, spell: &Spell) -> bool {
         self.levels[spell.level() as usize]
     }
@@ -122,7 +192,7 @@ impl Searcher for LevelSearch {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct ClassSearch {
     pub classes: Vec<Class>,
 }
@@ -136,6 +206,10 @@ impl Searcher for ClassSearch {
         self.classes.is_empty()
     }
 
+    fn describe(&self) -> String {
+        format!("Class: {}", self.classes.iter().join(", "))
+    }
+
     fn matches(&self, spell: &Spell) -> bool {
         spell.classes().iter()
             .any(|class| self.classes.iter().any(|t| class == t))
@@ -155,7 +229,7 @@ impl Searcher for ClassSearch {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct CastingTimeSearch {
     pub times: Vec<CastingTime>,
 }
@@ -169,9 +243,14 @@ impl Searcher for CastingTimeSearch {
         self.times.is_empty()
     }
 
+    fn describe(&self) -> String {
+        format!("Casting time: {}", self.times.iter().join(", "))
+    }
+
     fn matches(&self, spell: &Spell) -> bool {
         self.times.iter().any(|t|
             t.equals_ignore_reaction(spell.casting_time())
+                || spell.alt_casting_time().is_some_and(|alt| t.equals_ignore_reaction(alt))
         )
     }
 
@@ -202,7 +281,227 @@ impl Searcher for CastingTimeSearch {
     }
 }
 
-#[derive(Debug, Default)]
+/// The common spell durations, for [`DurationSearch`]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+pub enum DurationBucket {
+    Instantaneous,
+    Round,
+    Minute,
+    TenMinutes,
+    Hour,
+    EightHours,
+    Day,
+    UntilDispelled,
+    Special,
+}
+
+impl DurationBucket {
+    pub const ALL: [Self; 9] = [
+        Self::Instantaneous,
+        Self::Round,
+        Self::Minute,
+        Self::TenMinutes,
+        Self::Hour,
+        Self::EightHours,
+        Self::Day,
+        Self::UntilDispelled,
+        Self::Special,
+    ];
+
+    /// The bucket a spell's duration falls in, ignoring concentration, so that `"Concentration, up
+    /// to 1 minute"` is [`DurationBucket::Minute`]. `None` for less common durations, like `"7 days"`.
+    pub fn of(duration: &str) -> Option<Self> {
+        let duration = fold(duration);
+        let duration = duration.trim();
+        let duration = duration.strip_prefix("concentration,").unwrap_or(duration).trim_start();
+        let duration = duration.strip_prefix("up to ").unwrap_or(duration);
+        if duration.starts_with("instantaneous") {
+            return Some(Self::Instantaneous);
+        }
+        if duration.starts_with("until dispelled") {
+            return Some(Self::UntilDispelled);
+        }
+        if duration.starts_with("special") {
+            return Some(Self::Special);
+        }
+        let mut words = duration.split_whitespace();
+        let n = words.next()?.parse::<u32>().ok()?;
+        let unit = words.next()?.trim_end_matches(|c: char| !c.is_alphabetic());
+        match (n, unit.strip_suffix('s').unwrap_or(unit)) {
+            (1, "round") => Some(Self::Round),
+            (1, "minute") => Some(Self::Minute),
+            (10, "minute") => Some(Self::TenMinutes),
+            (1, "hour") => Some(Self::Hour),
+            (8, "hour") => Some(Self::EightHours),
+            (24, "hour") | (1, "day") => Some(Self::Day),
+            _ => None,
+        }
+    }
+}
+
+impl Display for DurationBucket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Instantaneous => "Instantaneous",
+            Self::Round => "1 round",
+            Self::Minute => "1 minute",
+            Self::TenMinutes => "10 minutes",
+            Self::Hour => "1 hour",
+            Self::EightHours => "8 hours",
+            Self::Day => "24 hours",
+            Self::UntilDispelled => "Until dispelled",
+            Self::Special => "Special",
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct DurationSearch {
+    pub durations: Vec<DurationBucket>,
+}
+
+impl Searcher for DurationSearch {
+    fn clear(&mut self) {
+        self.durations.clear();
+    }
+
+    fn is_empty(&self) -> bool {
+        self.durations.is_empty()
+    }
+
+    fn describe(&self) -> String {
+        format!("Duration: {}", self.durations.iter().join(", "))
+    }
+
+    fn matches(&self, spell: &Spell) -> bool {
+        spell.duration()
+            .and_then(DurationBucket::of)
+            .is_some_and(|bucket| self.durations.contains(&bucket))
+    }
+
+    fn view<'s, 'c: 's>(&'s self, character: Option<usize>) -> Row<'c> {
+        DurationBucket::ALL.into_iter()
+            .fold(
+                row!["Duration:"].align_items(Alignment::Center).spacing(4),
+                |row, duration| row.push(
+                    button(text(duration).size(14))
+                        .padding(0)
+                        .style(Location::AdvancedSearch { enabled: self.durations.contains(&duration) })
+                        .on_press(wrap_character(character, Message::PickDuration(duration)))
+                ),
+            )
+    }
+}
+
+/// Spell ranges, grouped, for [`RangeSearch`]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+pub enum RangeBucket {
+    Personal,
+    Touch,
+    Feet30,
+    Feet60,
+    Feet120,
+    Mile,
+    Special,
+}
+
+impl RangeBucket {
+    pub const ALL: [Self; 7] = [
+        Self::Personal,
+        Self::Touch,
+        Self::Feet30,
+        Self::Feet60,
+        Self::Feet120,
+        Self::Mile,
+        Self::Special,
+    ];
+
+    /// The bucket a spell's range falls in. `"Self (15-foot cone)"` is [`RangeBucket::Personal`], a
+    /// range in feet goes in whichever of 30, 60, or 120 feet is closest, and a mile or more is
+    /// [`RangeBucket::Mile`]. Sight, unlimited, and anything else that isn't a distance is
+    /// [`RangeBucket::Special`].
+    pub fn of(range: &str) -> Self {
+        const MILE: u32 = 5280;
+
+        let range = fold(range);
+        let range = range.trim();
+        if range.starts_with("self") {
+            return Self::Personal;
+        }
+        if range.starts_with("touch") {
+            return Self::Touch;
+        }
+        let mut words = range.split_whitespace();
+        let distance = words.next()
+            .and_then(|n| n.replace(',', "").parse::<u32>().ok())
+            .zip(words.next());
+        match distance {
+            Some((_, unit)) if unit.starts_with("mile") => Self::Mile,
+            Some((feet, unit)) if unit.starts_with('f') => if feet >= MILE {
+                Self::Mile
+            } else {
+                [(30, Self::Feet30), (60, Self::Feet60), (120, Self::Feet120)].into_iter()
+                    .min_by_key(|(bucket, _)| feet.abs_diff(*bucket))
+                    .map_or(Self::Special, |(_, bucket)| bucket)
+            },
+            _ => Self::Special,
+        }
+    }
+}
+
+impl Display for RangeBucket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Personal => "Self",
+            Self::Touch => "Touch",
+            Self::Feet30 => "30 ft",
+            Self::Feet60 => "60 ft",
+            Self::Feet120 => "120 ft",
+            Self::Mile => "1 mile+",
+            Self::Special => "Special/Sight",
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct RangeSearch {
+    pub ranges: Vec<RangeBucket>,
+}
+
+impl Searcher for RangeSearch {
+    fn clear(&mut self) {
+        self.ranges.clear();
+    }
+
+    fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    fn describe(&self) -> String {
+        format!("Range: {}", self.ranges.iter().join(", "))
+    }
+
+    fn matches(&self, spell: &Spell) -> bool {
+        spell.range()
+            .map(RangeBucket::of)
+            .is_some_and(|bucket| self.ranges.contains(&bucket))
+    }
+
+    fn view<'s, 'c: 's>(&'s self, character: Option<usize>) -> Row<'c> {
+        RangeBucket::ALL.into_iter()
+            .fold(
+                row!["Range:"].align_items(Alignment::Center).spacing(4),
+                |row, range| row.push(
+                    button(text(range).size(14))
+                        .padding(0)
+                        .style(Location::AdvancedSearch { enabled: self.ranges.contains(&range) })
+                        .on_press(wrap_character(character, Message::PickRange(range)))
+                ),
+            )
+    }
+}
+
+#[derive(Debug, Default, Clone)]
 pub struct SchoolSearch {
     pub schools: Vec<School>,
 }
@@ -216,6 +515,10 @@ impl Searcher for SchoolSearch {
         self.schools.is_empty()
     }
 
+    fn describe(&self) -> String {
+        format!("School: {}", self.schools.iter().join(", "))
+    }
+
     fn matches(&self, spell: &Spell) -> bool {
         self.schools.iter().any(|t| *t == spell.school())
     }
@@ -234,7 +537,7 @@ impl Searcher for SchoolSearch {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct RitualSearch {
     pub ritual: Enable<bool>,
 }
@@ -248,6 +551,10 @@ impl Searcher for RitualSearch {
         !self.ritual.enabled
     }
 
+    fn describe(&self) -> String {
+        format!("Ritual: {}", if self.ritual.value { "yes" } else { "no" })
+    }
+
     fn matches(&self, spell: &Spell) -> bool {
         self.ritual == spell.ritual()
     }
@@ -276,7 +583,7 @@ impl Searcher for RitualSearch {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct ConcentrationSearch {
     pub concentration: Enable<bool>,
 }
@@ -290,6 +597,10 @@ impl Searcher for ConcentrationSearch {
         !self.concentration.enabled
     }
 
+    fn describe(&self) -> String {
+        format!("Concentration: {}", if self.concentration.value { "yes" } else { "no" })
+    }
+
     fn matches(&self, spell: &Spell) -> bool {
         self.concentration == spell.concentration()
     }
@@ -318,10 +629,104 @@ impl Searcher for ConcentrationSearch {
     }
 }
 
-#[derive(Debug)]
+/// Spells that do, or don't, get better when cast with a higher level slot
+#[derive(Debug, Default, Clone)]
+pub struct HigherLevelsSearch {
+    pub higher_levels: Enable<bool>,
+}
+
+impl Searcher for HigherLevelsSearch {
+    fn clear(&mut self) {
+        self.higher_levels.clear();
+    }
+
+    fn is_empty(&self) -> bool {
+        !self.higher_levels.enabled
+    }
+
+    fn describe(&self) -> String {
+        format!("At higher levels: {}", if self.higher_levels.value { "yes" } else { "no" })
+    }
+
+    fn matches(&self, spell: &Spell) -> bool {
+        self.higher_levels == spell.higher_levels().is_some()
+    }
+
+    fn view<'s, 'c: 's>(&'s self, character: Option<usize>) -> Row<'c> {
+        let Enable { value: higher_levels, enabled } = self.higher_levels;
+        row![
+            button(
+                text("At Higher Levels:")
+            ).padding(0)
+                .style(Location::AdvancedSearch { enabled })
+                .on_press(wrap_character(character, Message::ToggleHigherLevelsEnabled))
+                .tooltip("Enable filtering by whether spells get better with higher level slots"),
+            button(
+                text(if higher_levels { Icon::Check } else { Icon::X })
+                    .font(ICON_FONT)
+                    .size(15)
+            ).padding(0)
+                .style(Location::AdvancedSearch { enabled })
+                .tap_if(enabled, |b|
+                    b.on_press(wrap_character(character, Message::ToggleHigherLevels))
+                )
+        ].align_items(Alignment::End)
+            .spacing(4)
+    }
+}
+
+/// Cantrips that do, or spells that don't, get stronger as the character levels up
+#[derive(Debug, Default, Clone)]
+pub struct ScalingSearch {
+    pub scales: Enable<bool>,
+}
+
+impl Searcher for ScalingSearch {
+    fn clear(&mut self) {
+        self.scales.clear();
+    }
+
+    fn is_empty(&self) -> bool {
+        !self.scales.enabled
+    }
+
+    fn describe(&self) -> String {
+        format!("Scales with level: {}", if self.scales.value { "yes" } else { "no" })
+    }
+
+    fn matches(&self, spell: &Spell) -> bool {
+        self.scales == spell.scales()
+    }
+
+    fn view<'s, 'c: 's>(&'s self, character: Option<usize>) -> Row<'c> {
+        let Enable { value: scales, enabled } = self.scales;
+        row![
+            button(
+                text("Scales With Level:")
+            ).padding(0)
+                .style(Location::AdvancedSearch { enabled })
+                .on_press(wrap_character(character, Message::ToggleScalesEnabled))
+                .tooltip("Enable filtering by whether cantrips' damage goes up at 5th, 11th, and 17th level"),
+            button(
+                text(if scales { Icon::Check } else { Icon::X })
+                    .font(ICON_FONT)
+                    .size(15)
+            ).padding(0)
+                .style(Location::AdvancedSearch { enabled })
+                .tap_if(enabled, |b|
+                    b.on_press(wrap_character(character, Message::ToggleScales))
+                )
+        ].align_items(Alignment::End)
+            .spacing(4)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct TextSearch {
     pub text: String,
     pub id: text_input::Id,
+    /// which of [`TextSearch::FIELDS`] are searched
+    pub fields: [bool; 5],
 }
 
 impl Default for TextSearch {
@@ -329,43 +734,250 @@ impl Default for TextSearch {
         Self {
             text: Default::default(),
             id: text_input::Id::unique(),
+            fields: Self::DEFAULT_FIELDS,
+        }
+    }
+}
+
+impl TextSearch {
+    const FIELDS: [&'static str; 5] = ["Name", "Description", "Higher levels", "Material", "Reaction trigger"];
+    const DEFAULT_FIELDS: [bool; 5] = [true, true, true, false, false];
+
+    /// Parses the text into groups that must all match, each of which matches if any of its terms
+    /// do: `int|wis & -save` is spells with `int` or `wis`, that don't have `save`. Whitespace
+    /// around the operators is ignored, but text without `&` or `|` is searched for as typed.
+    fn groups(&self) -> Vec<Vec<Term<'_>>> {
+        let operators = self.text.contains(['&', '|']);
+        self.text.split('&')
+            .map(|group| group.split('|')
+                .map(|term| if operators { term.trim() } else { term })
+                .map(|term| match term.strip_prefix('-') {
+                    Some(text) => Term { text, exclude: true },
+                    None => Term { text: term, exclude: false },
+                })
+                .collect())
+            .collect()
+    }
+
+    /// Finds the first of the searched fields, in [`TextSearch::FIELDS`] order, that contains some text
+    fn field_containing<'s>(&self, spell: &'s Spell) -> impl Fn(&str) -> Option<TextMatch> + 's {
+        let [name, desc, higher, material, trigger] = self.fields;
+        // these are short enough to lowercase on the fly
+        let material = material.then(|| spell.material().map(fold)).flatten();
+        let trigger = trigger.then(|| spell.reaction_trigger().map(fold)).flatten();
+        move |search: &str| {
+            let contains = |field: Option<&str>| field.filter(|lower| lower.contains(search)).is_some();
+            [
+                (name && spell.name_lower().contains(search), TextMatch::Name),
+                (desc && spell.desc_lower().contains(search), TextMatch::Description),
+                (higher && contains(spell.higher_levels_lower()), TextMatch::HigherLevels),
+                (contains(material.as_deref()), TextMatch::Material),
+                (contains(trigger.as_deref()), TextMatch::Trigger),
+            ].into_iter()
+                .find_map(|(matched, field)| matched.then_some(field))
+        }
+    }
+
+    /// The terms spells have to contain, to point out in their descriptions. None if descriptions
+    /// aren't searched.
+    pub fn highlight_terms(&self) -> Vec<String> {
+        if !self.fields[1] {
+            return Vec::new();
         }
+        self.groups()
+            .into_iter()
+            .flatten()
+            .filter(|term| !term.exclude && !term.text.is_empty())
+            .map(|term| term.text.to_string())
+            .collect()
+    }
+
+    /// Where the first term that isn't excluded was found, for a spell that [`matches`](Searcher::matches)
+    pub fn matched_in(&self, spell: &Spell) -> Option<TextMatch> {
+        let field_containing = self.field_containing(spell);
+        self.groups()
+            .into_iter()
+            .flatten()
+            .filter(|term| !term.exclude)
+            .find_map(|term| field_containing(term.text))
+    }
+}
+
+/// A piece of a [`TextSearch`] between `&` and `|`s
+#[derive(Debug, Copy, Clone)]
+struct Term<'a> {
+    text: &'a str,
+    /// it started with a `-`, so spells containing `text` are left out
+    exclude: bool,
+}
+
+/// Which part of a spell [`TextSearch`] found its text in
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum TextMatch {
+    Name,
+    Description,
+    HigherLevels,
+    Material,
+    Trigger,
+}
+
+impl Display for TextMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Name => "Name",
+            Self::Description => "Description",
+            Self::HigherLevels => "At Higher Levels",
+            Self::Material => "Material",
+            Self::Trigger => "Reaction trigger",
+        })
     }
 }
 
 impl Searcher for TextSearch {
     fn clear(&mut self) {
         self.text.clear();
+        self.fields = Self::DEFAULT_FIELDS;
     }
 
     fn is_empty(&self) -> bool {
         self.text.is_empty()
     }
 
+    fn describe(&self) -> String {
+        format!("Text: {}", self.text)
+    }
+
     fn matches(&self, spell: &Spell) -> bool {
-        self.text.split('|')
-            .any(|search|
-                spell.desc_lower().contains(search) ||
-                    spell.higher_levels_lower()
-                        .as_ref()
-                        .filter(|lower| lower.contains(search))
-                        .is_some()
+        let field_containing = self.field_containing(spell);
+        self.groups()
+            .into_iter()
+            .all(|group| group.into_iter()
+                // a lone `-` doesn't rule out everything while the rest is being typed
+                .any(|term| (term.exclude && term.text.is_empty())
+                    || field_containing(term.text).is_some() != term.exclude))
+    }
+
+    fn view<'s, 'c: 's>(&'s self, character: Option<usize>) -> Row<'c> {
+        iter::zip(self.fields, Self::FIELDS)
+            .enumerate()
+            .fold(
+                row![
+                    text("Spell Text:")
+                        .tooltip("| matches either side, & matches both, and - leaves out spells with that text"),
+                    text_input(
+                        "int|wis & -save",
+                        &self.text,
+                    ).on_input(move |s| wrap_character(character, Message::SearchText(s)))
+                ].align_items(Alignment::Center)
+                    .spacing(4),
+                |row, (i, (enabled, label))| row.push(
+                    checkbox(
+                        label,
+                        enabled,
+                        move |_| wrap_character(character, Message::ToggleTextField(i)),
+                    )
+                ),
             )
     }
+}
+
+/// Searches only the material components, so that a word like "diamond" doesn't also find every
+/// spell that mentions it in its description
+#[derive(Debug, Default, Clone)]
+pub struct MaterialSearch {
+    pub text: String,
+}
+
+impl Searcher for MaterialSearch {
+    fn clear(&mut self) {
+        self.text.clear();
+    }
+
+    fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    fn describe(&self) -> String {
+        format!("Material: {}", self.text)
+    }
+
+    fn matches(&self, spell: &Spell) -> bool {
+        spell.material()
+            .map_or(false, |material| fold(material).contains(&self.text))
+    }
 
     fn view<'s, 'c: 's>(&'s self, character: Option<usize>) -> Row<'c> {
         row![
-            "Spell Text:",
+            "Material contains:",
             text_input(
-                "int|wis",
+                "diamond",
                 &self.text,
-            ).on_input(move |s| wrap_character(character, Message::SearchText(s)))
+            ).on_input(move |s| wrap_character(character, Message::SearchMaterial(s)))
         ].align_items(Alignment::Center)
             .spacing(4)
     }
 }
 
-#[derive(Debug, Default)]
+/// Spells by the [`Tag`]s found in their descriptions. Spells need one of the damage types and one of
+/// the saves, if any of either are picked.
+#[derive(Debug, Default, Clone)]
+pub struct TagSearch {
+    pub damage: Vec<DamageType>,
+    pub saves: Vec<Ability>,
+}
+
+impl Searcher for TagSearch {
+    fn clear(&mut self) {
+        self.damage.clear();
+        self.saves.clear();
+    }
+
+    fn is_empty(&self) -> bool {
+        self.damage.is_empty() && self.saves.is_empty()
+    }
+
+    fn describe(&self) -> String {
+        self.damage.iter()
+            .map(|damage| format!("{damage} damage"))
+            .chain(self.saves.iter().map(|ability| format!("{ability} save")))
+            .join(", ")
+    }
+
+    fn matches(&self, spell: &Spell) -> bool {
+        let tags = spell.tags();
+        let damage = self.damage.is_empty() || self.damage.iter()
+            .any(|&damage| tags.contains(&Tag::Damage(damage)));
+        let save = self.saves.is_empty() || self.saves.iter()
+            .any(|&ability| tags.contains(&Tag::Save(ability)));
+        damage && save
+    }
+
+    fn view<'s, 'c: 's>(&'s self, character: Option<usize>) -> Row<'c> {
+        let damage = DamageType::ALL.into_iter()
+            .fold(
+                row!["Damage:"].align_items(Alignment::Center).spacing(4),
+                |row, damage| row.push(
+                    button(text(damage).size(14))
+                        .padding(0)
+                        .style(Location::AdvancedSearch { enabled: self.damage.contains(&damage) })
+                        .on_press(wrap_character(character, Message::PickDamage(damage)))
+                ),
+            );
+        let saves = Ability::ALL.into_iter()
+            .fold(
+                row!["Save:"].align_items(Alignment::Center).spacing(4),
+                |row, ability| row.push(
+                    button(text(ability).size(14))
+                        .padding(0)
+                        .style(Location::AdvancedSearch { enabled: self.saves.contains(&ability) })
+                        .on_press(wrap_character(character, Message::PickSave(ability)))
+                ),
+            );
+        row![col![damage, saves].spacing(1)]
+    }
+}
+
+#[derive(Debug, Default, Clone)]
 pub struct SourceSearch {
     pub sources: Vec<Source>,
 }
@@ -379,6 +991,10 @@ impl Searcher for SourceSearch {
         self.sources.is_empty()
     }
 
+    fn describe(&self) -> String {
+        format!("Source: {}", self.sources.iter().join(", "))
+    }
+
     fn matches(&self, spell: &Spell) -> bool {
         self.sources.iter().any(|&t| t == spell.source())
     }
@@ -397,11 +1013,101 @@ impl Searcher for SourceSearch {
     }
 }
 
-#[derive(Debug, Default)]
+/// Spells for a turn with a bonus action spell in it: casting a spell as a bonus action leaves only
+/// cantrips that take 1 action for the rest of the turn
+#[derive(Debug, Default, Clone)]
+pub struct BonusActionTurnSearch {
+    pub enabled: bool,
+}
+
+impl BonusActionTurnSearch {
+    fn button<'c>(&self, character: Option<usize>) -> Element<'c> {
+        button(text("Bonus-action turn").size(14))
+            .padding([0, 4])
+            .style(Location::AdvancedSearch { enabled: self.enabled })
+            .on_press(wrap_character(character, Message::ToggleBonusActionTurn))
+            .tooltip("Bonus action spells and 1 action cantrips. If you cast a spell as a bonus action, \
+                the only other spell you can cast that turn is a cantrip with a casting time of 1 action")
+    }
+}
+
+impl Searcher for BonusActionTurnSearch {
+    fn clear(&mut self) {
+        self.enabled = false;
+    }
+
+    fn is_empty(&self) -> bool {
+        !self.enabled
+    }
+
+    fn describe(&self) -> String {
+        "Bonus-action turn".to_string()
+    }
+
+    fn matches(&self, spell: &Spell) -> bool {
+        iter::once(spell.casting_time())
+            .chain(spell.alt_casting_time())
+            .any(|time| match time {
+                CastingTime::BonusAction => true,
+                CastingTime::Action => spell.level() == Level::Cantrip,
+                _ => false,
+            })
+    }
+
+    fn view<'s, 'c: 's>(&'s self, character: Option<usize>) -> Row<'c> {
+        row!["Turn:", self.button(character)]
+            .align_items(Alignment::Center)
+            .spacing(4)
+    }
+}
+
+#[derive(Debug, Default, Clone)]
 pub struct ComponentSearch {
     vsm: [Enable<bool>; 3],
 }
 
+/// Common situations that rule out casting spells with some components
+#[derive(Debug, Copy, Clone)]
+pub enum ComponentPreset {
+    /// can't speak, so no verbal components
+    Silenced,
+    /// no free hands, so no somatic components
+    HandsBound,
+}
+
+impl ComponentPreset {
+    const ALL: [Self; 2] = [Self::Silenced, Self::HandsBound];
+
+    /// index into [`ComponentSearch::vsm`] this preset excludes
+    fn component(self) -> usize {
+        match self {
+            Self::Silenced => 0,
+            Self::HandsBound => 1,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Silenced => "Silenced",
+            Self::HandsBound => "Hands bound",
+        }
+    }
+
+    fn explanation(self) -> &'static str {
+        match self {
+            Self::Silenced => "Hide spells with a verbal component",
+            Self::HandsBound => "Hide spells with a somatic component (assumes nothing like War Caster lets you ignore it)",
+        }
+    }
+}
+
+impl ComponentSearch {
+    /// Excludes the preset's component, leaving the other components as they were
+    fn apply(&mut self, preset: ComponentPreset) {
+        self.vsm[preset.component()] = Enable { enabled: true, value: false };
+    }
+}
+
 impl Searcher for ComponentSearch {
     fn clear(&mut self) {
         self.vsm.iter_mut()
@@ -413,6 +1119,13 @@ impl Searcher for ComponentSearch {
             .none(|e| e.enabled)
     }
 
+    fn describe(&self) -> String {
+        format!("Components: {}", iter::zip(self.vsm, ["V", "S", "M"])
+            .filter(|(component, _)| component.enabled)
+            .map(|(component, letter)| if component.value { letter.to_string() } else { format!("no {letter}") })
+            .join(", "))
+    }
+
     fn matches(&self, spell: &Spell) -> bool {
         let vsm = match spell.components() {
             Some(Components { v, s, m }) => [*v, *s, m.is_some()],
@@ -448,22 +1161,43 @@ impl Searcher for ComponentSearch {
                             )
                     ),
             )
+            .push_space(12)
+            .tap(|row| ComponentPreset::ALL.into_iter().fold(row, |row, preset| row.push(
+                button(text(preset.label()).size(15))
+                    .padding([0, 4])
+                    .style(Location::Transparent)
+                    .on_press(wrap_character(character, Message::ComponentPreset(preset)))
+                    .tooltip(preset.explanation())
+            )))
     }
 }
 
+#[derive(Clone)]
 pub struct SearchOptions {
     pub search: String,
     pub id: text_input::Id,
     pub show_advanced_search: bool,
+    pub sort_order: SortOrder,
+    /// if no spell's name contains the search, show the names that are at most
+    /// [`Self::fuzzy_typos`] edits away from it
+    pub fuzzy: bool,
+    pub fuzzy_typos: usize,
     pub level_search: LevelSearch,
     pub class_search: ClassSearch,
     pub casting_time_search: CastingTimeSearch,
+    pub duration_search: DurationSearch,
+    pub range_search: RangeSearch,
     pub school_search: SchoolSearch,
     pub ritual_search: RitualSearch,
     pub concentration_search: ConcentrationSearch,
+    pub higher_levels_search: HigherLevelsSearch,
+    pub scaling_search: ScalingSearch,
     pub source_search: SourceSearch,
     pub text_search: TextSearch,
     pub component_search: ComponentSearch,
+    pub material_search: MaterialSearch,
+    pub tag_search: TagSearch,
+    pub bonus_action_turn_search: BonusActionTurnSearch,
 }
 
 impl Default for SearchOptions {
@@ -472,67 +1206,211 @@ impl Default for SearchOptions {
             search: Default::default(),
             id: text_input::Id::unique(),
             show_advanced_search: false,
+            sort_order: SortOrder::default(),
+            fuzzy: false,
+            fuzzy_typos: 2,
             level_search: Default::default(),
             class_search: Default::default(),
             casting_time_search: Default::default(),
+            duration_search: Default::default(),
+            range_search: Default::default(),
             school_search: Default::default(),
             ritual_search: Default::default(),
             concentration_search: Default::default(),
+            higher_levels_search: Default::default(),
+            scaling_search: Default::default(),
             text_search: Default::default(),
             source_search: Default::default(),
             component_search: Default::default(),
+            material_search: Default::default(),
+            tag_search: Default::default(),
+            bonus_action_turn_search: Default::default(),
         }
     }
 }
 
+/// The search page's filters, saved between runs. The search bar, spell text, and material are left
+/// out, since they're usually only wanted for one search.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SerializeSearchOptions {
+    show_advanced_search: bool,
+    sort_order: SortOrder,
+    fuzzy: bool,
+    fuzzy_typos: usize,
+    levels: [bool; 10],
+    classes: Vec<Class>,
+    casting_times: Vec<CastingTime>,
+    durations: Vec<DurationBucket>,
+    ranges: Vec<RangeBucket>,
+    schools: Vec<School>,
+    sources: Vec<Source>,
+    damage: Vec<DamageType>,
+    saves: Vec<Ability>,
+    /// `None` if not filtering
+    ritual: Option<bool>,
+    concentration: Option<bool>,
+    higher_levels: Option<bool>,
+    scales: Option<bool>,
+    /// verbal, somatic, and material
+    components: [Option<bool>; 3],
+    bonus_action_turn: bool,
+    text_fields: [bool; 5],
+}
+
+impl Default for SerializeSearchOptions {
+    fn default() -> Self {
+        Self::from(&SearchOptions::default())
+    }
+}
+
+impl From<&SearchOptions> for SerializeSearchOptions {
+    fn from(options: &SearchOptions) -> Self {
+        let state = |Enable { value, enabled }: Enable<bool>| enabled.then_some(value);
+        Self {
+            show_advanced_search: options.show_advanced_search,
+            sort_order: options.sort_order,
+            fuzzy: options.fuzzy,
+            fuzzy_typos: options.fuzzy_typos,
+            levels: options.level_search.levels,
+            classes: options.class_search.classes.clone(),
+            casting_times: options.casting_time_search.times.clone(),
+            durations: options.duration_search.durations.clone(),
+            ranges: options.range_search.ranges.clone(),
+            schools: options.school_search.schools.clone(),
+            sources: options.source_search.sources.clone(),
+            damage: options.tag_search.damage.clone(),
+            saves: options.tag_search.saves.clone(),
+            ritual: state(options.ritual_search.ritual),
+            concentration: state(options.concentration_search.concentration),
+            higher_levels: state(options.higher_levels_search.higher_levels),
+            scales: state(options.scaling_search.scales),
+            components: options.component_search.vsm.map(state),
+            bonus_action_turn: options.bonus_action_turn_search.enabled,
+            text_fields: options.text_search.fields,
+        }
+    }
+}
+
+impl From<SerializeSearchOptions> for SearchOptions {
+    fn from(saved: SerializeSearchOptions) -> Self {
+        let enable = |state: Option<bool>| Enable {
+            value: state.unwrap_or_default(),
+            enabled: state.is_some(),
+        };
+        let mut options = Self::default();
+        options.show_advanced_search = saved.show_advanced_search;
+        options.sort_order = saved.sort_order;
+        options.fuzzy = saved.fuzzy;
+        options.fuzzy_typos = saved.fuzzy_typos;
+        options.level_search.levels = saved.levels;
+        options.class_search.classes = saved.classes;
+        options.casting_time_search.times = saved.casting_times;
+        options.duration_search.durations = saved.durations;
+        options.range_search.ranges = saved.ranges;
+        options.school_search.schools = saved.schools;
+        options.source_search.sources = saved.sources;
+        options.tag_search.damage = saved.damage;
+        options.tag_search.saves = saved.saves;
+        options.ritual_search.ritual = enable(saved.ritual);
+        options.concentration_search.concentration = enable(saved.concentration);
+        options.higher_levels_search.higher_levels = enable(saved.higher_levels);
+        options.scaling_search.scales = enable(saved.scales);
+        options.component_search.vsm = saved.components.map(enable);
+        options.bonus_action_turn_search.enabled = saved.bonus_action_turn;
+        options.text_search.fields = saved.text_fields;
+        options
+    }
+}
+
+impl SerializeSearchOptions {
+    /// The saved filters, or the defaults if none have been saved yet
+    pub fn read(file: &Path) -> error::Result<Self> {
+        let json = match fs::read_to_string(file) {
+            Ok(json) => json,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e.into()),
+        };
+        if json.trim().is_empty() {
+            Ok(Self::default())
+        } else {
+            Ok(serde_json::from_str(&json)?)
+        }
+    }
+
+    pub fn save(&self, file: &Path) -> error::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(file, json)?;
+        Ok(())
+    }
+}
+
 impl SearchOptions {
-    pub fn searchers(&self) -> [&dyn Searcher; 9] {
+    pub fn searchers(&self) -> [&dyn Searcher; 16] {
         [
             &self.level_search as &dyn Searcher,
             &self.class_search as &dyn Searcher,
             &self.school_search as &dyn Searcher,
             &self.casting_time_search as &dyn Searcher,
+            &self.duration_search as &dyn Searcher,
+            &self.range_search as &dyn Searcher,
             &self.ritual_search as &dyn Searcher,
             &self.concentration_search as &dyn Searcher,
+            &self.higher_levels_search as &dyn Searcher,
+            &self.scaling_search as &dyn Searcher,
             &self.component_search as &dyn Searcher,
+            &self.material_search as &dyn Searcher,
+            &self.tag_search as &dyn Searcher,
+            &self.bonus_action_turn_search as &dyn Searcher,
             &self.source_search as &dyn Searcher,
             &self.text_search as &dyn Searcher,
         ]
     }
 
-    pub fn searchers_mut(&mut self) -> [&mut dyn Searcher; 9] {
+    pub fn searchers_mut(&mut self) -> [&mut dyn Searcher; 16] {
         [
             &mut self.level_search as &mut dyn Searcher,
             &mut self.class_search as &mut dyn Searcher,
             &mut self.school_search as &mut dyn Searcher,
             &mut self.casting_time_search as &mut dyn Searcher,
+            &mut self.duration_search as &mut dyn Searcher,
+            &mut self.range_search as &mut dyn Searcher,
             &mut self.ritual_search as &mut dyn Searcher,
             &mut self.concentration_search as &mut dyn Searcher,
+            &mut self.higher_levels_search as &mut dyn Searcher,
+            &mut self.scaling_search as &mut dyn Searcher,
             &mut self.component_search as &mut dyn Searcher,
+            &mut self.material_search as &mut dyn Searcher,
+            &mut self.tag_search as &mut dyn Searcher,
+            &mut self.bonus_action_turn_search as &mut dyn Searcher,
             &mut self.source_search as &mut dyn Searcher,
             &mut self.text_search as &mut dyn Searcher,
         ]
     }
 
-    pub fn search(&self, custom: &[CustomSpell], characters: &[CharacterPage]) -> Vec<SearchSpell> {
+    /// All the spells that match every searcher, sorted by [`rank`] for `class`. If [`Self::fuzzy`]
+    /// is on and no name contains the search, names close to it are used instead, closest first.
+    pub fn search(&self, custom: &[Arc<CustomSpell>], class: Option<Class>) -> Vec<Spell> {
         let needle = &self.search;
-        SPELLS.iter()
+        let (mut spells, mut fallback): (Vec<_>, Vec<_>) = SPELLS.iter()
             .map(Spell::Static)
             .chain(custom.iter()
-                // todo not clone them
-                //  could Cow it?
                 .cloned()
                 .map(Spell::Custom))
             .filter(|spell| self.searchers()
                 .into_iter()
                 .filter(|searcher| !searcher.is_empty())
                 .all(|searcher| searcher.matches(spell)))
-            .filter(|spell| spell.name_lower().contains(needle))
-            .sorted_unstable_by_key(Spell::name)
-            // .sorted_unstable_by_key(|spell| levenshtein(spell.name_lower(), needle))
-            .map(|spell| SearchSpell::from(spell, characters))
-            .take(100)
-            .collect()
+            .partition(|spell| spell.name_lower().contains(needle));
+        if spells.is_empty() && self.fuzzy {
+            fallback.retain(|spell| typos(spell.name_lower(), needle) <= self.fuzzy_typos);
+            fallback.sort_by_cached_key(|spell| (typos(spell.name_lower(), needle), rank(spell, needle, self.sort_order, class)));
+            spells = fallback;
+        } else {
+            // relevance takes a levenshtein distance, so only work it out once per spell
+            spells.sort_by_cached_key(|spell| rank(spell, needle, self.sort_order, class));
+        }
+        spells
     }
 
     pub fn update(&mut self, message: Message) -> bool {
@@ -547,7 +1425,7 @@ impl SearchOptions {
 
         match message {
             Message::Search(needle) => {
-                self.search = needle.to_lowercase();
+                self.search = fold(&needle);
                 true
             }
             Message::Refresh => {
@@ -576,6 +1454,14 @@ impl SearchOptions {
                 toggle(&mut self.casting_time_search.times, casting_time);
                 true
             }
+            Message::PickDuration(duration) => {
+                toggle(&mut self.duration_search.durations, duration);
+                true
+            }
+            Message::PickRange(range) => {
+                toggle(&mut self.range_search.ranges, range);
+                true
+            }
             Message::PickSource(source) => {
                 toggle(&mut self.source_search.sources, source);
                 true
@@ -596,8 +1482,41 @@ impl SearchOptions {
                 self.concentration_search.concentration.enabled.toggle();
                 true
             }
+            Message::ToggleHigherLevels => {
+                self.higher_levels_search.higher_levels.value.toggle();
+                true
+            }
+            Message::ToggleHigherLevelsEnabled => {
+                self.higher_levels_search.higher_levels.enabled.toggle();
+                true
+            }
+            Message::ToggleScales => {
+                self.scaling_search.scales.value.toggle();
+                true
+            }
+            Message::ToggleScalesEnabled => {
+                self.scaling_search.scales.enabled.toggle();
+                true
+            }
             Message::SearchText(text) => {
-                self.text_search.text = text.to_lowercase();
+                self.text_search.text = fold(&text);
+                true
+            }
+            Message::ToggleTextField(field) => {
+                self.text_search.fields[field].toggle();
+                // only changes the results if there's text to search for
+                !self.text_search.is_empty()
+            }
+            Message::SearchMaterial(material) => {
+                self.material_search.text = fold(&material);
+                true
+            }
+            Message::PickDamage(damage) => {
+                toggle(&mut self.tag_search.damage, damage);
+                true
+            }
+            Message::PickSave(ability) => {
+                toggle(&mut self.tag_search.saves, ability);
                 true
             }
             Message::ToggleComponent(vsm) => {
@@ -608,13 +1527,66 @@ impl SearchOptions {
                 self.component_search.vsm[vsm].enabled.toggle();
                 true
             }
+            Message::SetComponent(vsm, state) => {
+                let component = &mut self.component_search.vsm[vsm];
+                match state {
+                    Some(required) => {
+                        component.enabled = true;
+                        component.value = required;
+                    }
+                    None => component.clear(),
+                }
+                true
+            }
+            Message::ToggleBonusActionTurn => {
+                self.bonus_action_turn_search.enabled.toggle();
+                true
+            }
+            Message::ComponentPreset(preset) => {
+                self.component_search.apply(preset);
+                true
+            }
             Message::ToggleAdvanced => {
                 self.show_advanced_search.toggle();
                 false
             }
+            Message::ShowAdvanced => {
+                self.show_advanced_search = true;
+                false
+            }
+            Message::ClearSearcher(idx) => {
+                self.searchers_mut()[idx].clear();
+                true
+            }
+            Message::SetSortOrder(order) => {
+                self.sort_order = order;
+                true
+            }
+            Message::ToggleFuzzy => {
+                self.fuzzy.toggle();
+                !self.search.is_empty()
+            }
+            Message::PickFuzzyTypos(typos) => {
+                self.fuzzy_typos = typos;
+                self.fuzzy && !self.search.is_empty()
+            }
             // {Search,Character}Page specific options
             Message::CollapseAll
-            | Message::Collapse(_) => false,
+            | Message::ToggleFieldPicker
+            | Message::Collapse(_)
+            | Message::ToggleFullText(_)
+            | Message::PickDefaultCharacter(_)
+            | Message::ToggleKnownBadges
+            | Message::PickKnownFilter(_)
+            | Message::LoadMore
+            | Message::MoveSelection(_)
+            | Message::Random
+            | Message::ToggleBulk(_)
+            | Message::PickBulkCharacter(_)
+            | Message::ClearBulk
+            | Message::Debounced(_)
+            | Message::Results(..)
+            | Message::Submit => false,
         }
     }
 
@@ -628,6 +1600,7 @@ impl SearchOptions {
             self.search.as_str(),
         )
             .on_input(move |s| wrap_character(character, Message::Search(s)))
+            .tap_if(character.is_none(), |input| input.on_submit(crate::Message::Search(Message::Submit)))
             .width(Length::FillPortion(4))
             .id(self.id.clone());
         let reset_modes = button(
@@ -643,6 +1616,60 @@ impl SearchOptions {
         let toggle_advanced = button(text("Advanced Search").size(16))
             .on_press(wrap_character(character, Message::ToggleAdvanced));
 
+        // character pages keep their spells in the order they were added
+        let sort_order = character.is_none().then(|| row![
+            text("Sort by").size(14),
+            pick_list(
+                SortOrder::ALL.to_vec(),
+                Some(self.sort_order),
+                move |order| wrap_character(character, Message::SetSortOrder(order)),
+            ).text_size(14),
+        ].spacing(4)
+            .align_items(Alignment::Center)
+            .tooltip("The default character's class's spells still come first"));
+
+        // quicker access to the component filters
+        let component_chips = iter::zip(self.component_search.vsm, ["Verbal", "Somatic", "Material"])
+            .enumerate()
+            .fold(row![].spacing(2), |row, (i, (Enable { value, enabled }, label))| {
+                let state = enabled.then_some(value);
+                let (next, tooltip) = match state {
+                    None => (Some(true), format!("Any {}", label.to_ascii_lowercase())),
+                    Some(true) => (Some(false), format!("Requires {}", label.to_ascii_lowercase())),
+                    Some(false) => (None, format!("No {}", label.to_ascii_lowercase())),
+                };
+                row.push(
+                    button(text(&label[..1]).size(14))
+                        .padding([0, 4])
+                        .style(Location::TriState(state))
+                        .on_press(wrap_character(character, Message::SetComponent(i, next)))
+                        .tooltip(tooltip)
+                )
+            });
+
+        // so filters left on are easy to spot, and turn off, with advanced search closed
+        let filter_chips = self.searchers()
+            .into_iter()
+            .enumerate()
+            .filter(|(_, searcher)| !searcher.is_empty())
+            .fold(row![].spacing(4).align_items(Alignment::Center), |row, (i, searcher)| row.push(row![
+                button(text(searcher.describe()).size(12))
+                    .style(Location::Transparent)
+                    .padding([0, 2])
+                    .on_press(wrap_character(character, Message::ShowAdvanced)),
+                button(text_icon(Icon::X).size(10))
+                    .style(Location::Transparent)
+                    .padding(0)
+                    .on_press(wrap_character(character, Message::ClearSearcher(i)))
+                    .tooltip("Stop filtering by this"),
+            ].align_items(Alignment::Center)
+                .tap(container)
+                .padding([1, 4])
+                .style(Location::TriState(Some(true)))));
+        let any_filters = self.searchers()
+            .into_iter()
+            .any(|searcher| !searcher.is_empty());
+
         let advanced_search = if self.show_advanced_search {
             column(
                 self.searchers()
@@ -650,6 +1677,21 @@ impl SearchOptions {
                     .map(|s| s.view(character).into())
                     .collect()
             ).spacing(1)
+                // character pages only have a few spells to look through
+                .tap_if(character.is_none(), |col| col.push(row![
+                    checkbox(
+                        "Fuzzy names",
+                        self.fuzzy,
+                        |_| wrap_character(None, Message::ToggleFuzzy),
+                    ).tooltip("When no spell's name has the search in it, show names that are close to it"),
+                    pick_list(
+                        vec![1, 2, 3],
+                        Some(self.fuzzy_typos),
+                        |typos| wrap_character(None, Message::PickFuzzyTypos(typos)),
+                    ),
+                    "typos at most",
+                ].align_items(Alignment::Center)
+                    .spacing(4)))
         } else {
             col!()
         };
@@ -660,155 +1702,1178 @@ impl SearchOptions {
                     Length::Fill,
                     toggle_advanced,
                     search,
+                    component_chips.tap_if(character.is_some(), |row| row
+                        .push_space(6)
+                        .push(self.bonus_action_turn_search.button(character))),
                     reset_modes,
                 ].align_items(Alignment::Center)
+                 .tap_if_some(sort_order, Row::push)
                  .spacing(8)
                  .tap_if_some(before_search_bar.into(), Row::push)
                  .push_space(Length::Fill),
-                row![
+            ].tap_if(any_filters, |col| col.push(filter_chips))
+                .push(row![
                     Length::Fill,
                     advanced_search.width(Length::FillPortion(18)),
                     Length::Fill,
-                ]
-            ]
+                ])
+                .align_items(Alignment::Center)
         )
     }
 }
 
+/// If `spell` isn't on `class`'s spell list. Never, without a class.
+pub fn off_class_list(spell: &Spell, class: Option<Class>) -> bool {
+    class.map_or(false, |class| !spell.classes().contains(&class))
+}
+
+/// The fewest edits to turn some part of `name` into `needle`, so that a typo in a partly typed name
+/// still counts as close
+pub fn typos(name: &str, needle: &str) -> usize {
+    let chars = &name.char_indices()
+        .map(|(idx, _)| idx)
+        .chain(iter::once(name.len()))
+        .collect_vec();
+    let len = needle.chars().count();
+    // parts of the name one letter shorter or longer than the search, for missed or doubled letters
+    (len.saturating_sub(1)..=len + 1)
+        .flat_map(|width| chars.windows(width + 1)
+            .map(move |window| (window[0], window[width])))
+        .map(|(start, end)| levenshtein(&name[start..end], needle))
+        .chain(iter::once(levenshtein(name, needle)))
+        .min()
+        .unwrap_or(usize::MAX)
+}
+
+/// How search results are ordered
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default, Serialize, Deserialize)]
+pub enum SortOrder {
+    /// names that start with the search first, then by where in the name it is, then by how
+    /// different the name is from it
+    #[default]
+    Relevance,
+    Alphabetical,
+    Level,
+    School,
+    Source,
+}
+
+impl SortOrder {
+    const ALL: [Self; 5] = [Self::Relevance, Self::Alphabetical, Self::Level, Self::School, Self::Source];
+}
+
+impl Display for SortOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Relevance => "Relevance",
+            Self::Alphabetical => "Name",
+            Self::Level => "Level",
+            Self::School => "School",
+            Self::Source => "Source",
+        })
+    }
+}
+
+/// How search results are sorted: spells on `class`'s list first, then the rest, each in `order`
+/// for the search text `needle`, with ties broken by name
+pub fn rank(spell: &Spell, needle: &str, order: SortOrder, class: Option<Class>) -> (bool, [usize; 3], String) {
+    let key = match order {
+        // with nothing typed every name matches equally well
+        SortOrder::Relevance if !needle.is_empty() => {
+            let name = spell.name_lower();
+            let position = name.find(needle).unwrap_or(name.len());
+            [usize::from(position != 0), position, levenshtein(name, needle)]
+        }
+        SortOrder::Relevance | SortOrder::Alphabetical => [0; 3],
+        SortOrder::Level => [spell.level() as usize, 0, 0],
+        // schools aren't declared in alphabetical order
+        SortOrder::School => {
+            let school = spell.school().to_string();
+            [School::ALL.iter().filter(|other| other.to_string() < school).count(), 0, 0]
+        }
+        SortOrder::Source => [spell.source() as usize, 0, 0],
+    };
+    // not `StArc`, which would sort every custom spell after the official ones
+    (off_class_list(spell, class), key, spell.name().to_string())
+}
+
+/// The character that the Search tab's `+` buttons add spells to, by name
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct DefaultCharacter(pub Option<Arc<str>>);
+
+impl Display for DefaultCharacter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Some(name) => f.write_str(name),
+            None => f.write_str("No default character"),
+        }
+    }
+}
+
+/// How many more results each "Load more" shows
+const PAGE_SIZE: usize = 100;
+
+/// How long typing has to pause before searching
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// the search results' scrollable, to keep the selected result in view
+static RESULTS_ID: Lazy<scrollable::Id> = Lazy::new(scrollable::Id::unique);
+
 #[derive(Default)]
 pub struct SearchPage {
     collapse_all: bool,
+    /// results that were collapsed or expanded by hand, so that they stay that way when the search
+    /// changes and they show up again
+    collapsed: HashMap<SpellId, bool>,
     pub search: SearchOptions,
+    /// every result, of which the first [`Self::shown`] are shown
     pub spells: Vec<SearchSpell>,
+    shown: usize,
+    /// the result picked with the arrow keys, which hotkeys add instead of the first result
+    selected: Option<usize>,
+    /// goes up with every search, so that only the latest one's results are shown
+    generation: u64,
+    /// if what was typed is waiting to be searched for, or being searched for in the background
+    unsearched: bool,
+    /// session only, so that adding lots of spells to one character is easier
+    pub default_character: DefaultCharacter,
+    show_field_picker: bool,
+    /// show which characters know each spell
+    show_known: bool,
+    known_filter: KnownFilter,
+    /// the default character's class, whose spells are sorted first
+    class: Option<Class>,
+    /// checked results, to add to a character all at once
+    bulk: HashSet<SpellId>,
+    /// who the checked results are added to, if not the default character
+    bulk_character: DefaultCharacter,
 }
 
 impl SearchPage {
-    pub fn new(custom: &[CustomSpell], characters: &[CharacterPage]) -> Self {
-        let search = SearchOptions::default();
-        let spells = search.search(custom, characters);
-        Self {
+    pub fn new(custom: &[Arc<CustomSpell>], characters: &[CharacterPage], search: SearchOptions) -> Self {
+        let spells = search.search(custom, None);
+        let mut page = Self {
             collapse_all: false,
+            collapsed: HashMap::new(),
             search,
-            spells,
+            spells: Vec::new(),
+            shown: PAGE_SIZE,
+            selected: None,
+            generation: 0,
+            unsearched: false,
+            default_character: DefaultCharacter::default(),
+            show_field_picker: false,
+            show_known: false,
+            known_filter: KnownFilter::Any,
+            class: None,
+            bulk: HashSet::new(),
+            bulk_character: DefaultCharacter::default(),
+        };
+        page.show_results(spells, characters, true);
+        page
+    }
+
+    /// Shows the results of [`SearchOptions::search`]. If `from_start`, which is whenever the search
+    /// changed rather than only being refreshed, only the first page is shown again and nothing is
+    /// selected. Otherwise the selected spell stays selected if it's still a result.
+    fn show_results(&mut self, spells: Vec<Spell>, characters: &[CharacterPage], from_start: bool) {
+        let selected = self.selected
+            .filter(|_| !from_start)
+            .and_then(|idx| self.spells.get(idx))
+            .map(|spell| spell.spell.id());
+        let text_search = &self.search.text_search;
+        self.spells = spells.into_iter()
+            .map(|spell| SearchSpell {
+                matched_in: (!text_search.is_empty())
+                    .then(|| text_search.matched_in(&spell))
+                    .flatten(),
+                collapse: self.collapsed.get(&spell.id()).copied(),
+                ..SearchSpell::from(spell, characters)
+            })
+            .collect();
+        // needs to know about the characters, so it isn't a `Searcher`
+        if let KnownFilter::Character(name) = &self.known_filter {
+            // they were closed
+            if !characters.iter().any(|page| page.character.name == *name) {
+                self.known_filter = KnownFilter::Any;
+            }
         }
+        let known_filter = &self.known_filter;
+        self.spells.retain(|spell| known_filter.matches(spell));
+        if from_start {
+            self.shown = PAGE_SIZE;
+        }
+        self.selected = selected.and_then(|id| self.spells.iter().position(|spell| spell.spell.id() == id));
+        if let Some(idx) = self.selected {
+            // it might have moved to a page that isn't loaded
+            self.shown = self.shown.max((idx / PAGE_SIZE + 1) * PAGE_SIZE);
+        }
+        self.unsearched = false;
+    }
+
+    /// Searches for what was typed right away if it hasn't been yet, so that adding a result from
+    /// the keyboard straight after typing doesn't add one of the old results
+    pub fn flush(&mut self, custom: &[Arc<CustomSpell>], characters: &[CharacterPage]) {
+        if self.unsearched {
+            // the debounce or background search for it is out of date now
+            self.generation += 1;
+            let spells = self.search.search(custom, self.class);
+            self.show_results(spells, characters, true);
+        }
+    }
+
+    /// Runs the search on another thread, since searching every spell on every keystroke can make
+    /// typing lag
+    fn search_in_background(&self, custom: &[Arc<CustomSpell>]) -> Command<crate::Message> {
+        let options = self.search.clone();
+        let custom = custom.to_vec();
+        let class = self.class;
+        let generation = self.generation;
+        Command::perform(
+            async move {
+                tokio::task::spawn_blocking(move || options.search(&custom, class))
+                    .await
+                    .unwrap_or_else(|e| {
+                        log::error!("search failed: {e}");
+                        Vec::new()
+                    })
+            },
+            move |spells| crate::Message::Search(Message::Results(spells, generation)),
+        )
+    }
+
+    /// The result that adding from the keyboard adds: the selected one, or else the first
+    pub fn selected_spell(&self) -> Option<&SearchSpell> {
+        self.selected
+            .and_then(|idx| self.spells.get(idx))
+            .or_else(|| self.spells.first())
+    }
+
+    /// if the results are close names because none contain the search
+    fn fuzzy_fallback(&self) -> bool {
+        let needle = &self.search.search;
+        self.search.fuzzy
+            && !self.spells.is_empty()
+            && !self.spells.iter().any(|spell| spell.spell.name_lower().contains(needle))
+    }
+
+    /// the index of the default character, if it's still open
+    pub fn default_character_index(&self, characters: &[CharacterPage]) -> Option<usize> {
+        let name = self.default_character.0.as_ref()?;
+        characters.iter().position(|page| page.character.name == *name)
+    }
+
+    /// The character the checked results are added to: the one picked for that if it's open, or else
+    /// the default character
+    fn bulk_character_index(&self, characters: &[CharacterPage]) -> Option<usize> {
+        self.bulk_character.0.as_ref()
+            .and_then(|name| characters.iter().position(|page| page.character.name == *name))
+            .or_else(|| self.default_character_index(characters))
+    }
+
+    /// Unchecks every result, like after they've been added
+    pub fn clear_bulk(&mut self) {
+        self.bulk.clear();
     }
 }
 
 pub struct SearchSpell {
     pub spell: Spell,
     collapse: Option<bool>,
-    buttons: Vec<(Arc<str>, bool)>,
+    /// show all of a long description
+    full_text: bool,
+    /// each character's name, and if they have this spell prepared, or `None` if they don't know it
+    known: Vec<(Arc<str>, Option<bool>)>,
+    /// where the spell text search found this spell
+    matched_in: Option<TextMatch>,
 }
 
 impl SearchSpell {
     fn from(spell: Spell, characters: &[CharacterPage]) -> Self {
-        let buttons = characters.iter()
+        let known = characters.iter()
             .map(|page| {
-                let active = !page.character.spells.iter()
-                    .flatten()
-                    .any(|(s, _)| *s == spell);
-                (Arc::clone(&page.character.name), active)
+                let prepared = page.character.spells[spell.level()].iter()
+                    .find(|(s, _)| *s == spell)
+                    .map(|&(_, prepared)| prepared);
+                (Arc::clone(&page.character.name), prepared)
             })
             .collect();
         Self {
             spell,
             collapse: None,
-            buttons,
+            full_text: false,
+            known,
+            matched_in: None,
+        }
+    }
+
+    fn known_by_anyone(&self) -> bool {
+        self.known.iter().any(|(_, prepared)| prepared.is_some())
+    }
+}
+
+/// Search results filtered by which open characters know them. Not a [`Searcher`], since it needs
+/// to know about the characters.
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub enum KnownFilter {
+    #[default]
+    Any,
+    /// known by at least one open character
+    Anyone,
+    /// known by no open character
+    Nobody,
+    /// known by the character with this name
+    Character(Arc<str>),
+}
+
+impl KnownFilter {
+    fn matches(&self, spell: &SearchSpell) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Anyone => spell.known_by_anyone(),
+            Self::Nobody => !spell.known_by_anyone(),
+            Self::Character(name) => spell.known.iter()
+                .any(|(character, prepared)| character == name && prepared.is_some()),
+        }
+    }
+}
+
+impl Display for KnownFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Any => f.write_str("Any spell"),
+            Self::Anyone => f.write_str("Known by anyone"),
+            Self::Nobody => f.write_str("Known by nobody"),
+            Self::Character(name) => write!(f, "Known by {name}"),
         }
     }
 }
 
 impl SearchPage {
-    pub fn update(&mut self, message: Message, custom: &[CustomSpell], characters: &[CharacterPage]) -> Command<crate::Message> {
-        let searched_text = matches!(message, Message::SearchText(_));
+    pub fn update(&mut self, message: Message, custom: &[Arc<CustomSpell>], characters: &[CharacterPage]) -> Command<crate::Message> {
+        match message {
+            Message::Debounced(generation) => return if generation == self.generation {
+                self.search_in_background(custom)
+            } else {
+                Command::none()
+            },
+            Message::Results(spells, generation) => {
+                if generation == self.generation {
+                    self.show_results(spells, characters, true);
+                }
+                return Command::none();
+            }
+            _ => {}
+        }
+
+        let searched_text = matches!(message, Message::SearchText(_) | Message::SearchMaterial(_));
+        let typed = matches!(message, Message::Search(_) | Message::SearchText(_) | Message::SearchMaterial(_));
+        let moved = matches!(message, Message::MoveSelection(_) | Message::Random);
 
         match &message {
             Message::CollapseAll => {
                 self.collapse_all.toggle();
                 self.spells.iter_mut().for_each(|spell| spell.collapse = None);
+                self.collapsed.clear();
+            }
+            Message::ToggleFieldPicker => self.show_field_picker.toggle(),
+            Message::LoadMore => self.shown += PAGE_SIZE,
+            Message::Random => if !self.spells.is_empty() {
+                let idx = fastrand::usize(..self.spells.len());
+                // load as many pages as it takes to show it
+                self.shown = self.shown.max((idx / PAGE_SIZE + 1) * PAGE_SIZE);
+                for (i, spell) in self.spells.iter_mut().enumerate() {
+                    spell.collapse = Some(i != idx);
+                    self.collapsed.insert(spell.spell.id(), i != idx);
+                }
+                self.selected = Some(idx);
+            },
+            &Message::MoveSelection(delta) => {
+                let last = self.spells.len().min(self.shown).checked_sub(1);
+                self.selected = match (self.selected, last) {
+                    (_, None) => None,
+                    (None, Some(_)) if delta < 0 => None,
+                    (None, Some(_)) => Some(0),
+                    (Some(idx), Some(last)) => Some(idx.saturating_add_signed(delta).min(last)),
+                };
+            }
+            Message::ToggleKnownBadges => self.show_known.toggle(),
+            Message::ToggleBulk(id) => if !self.bulk.remove(id) {
+                self.bulk.insert(id.clone());
+            },
+            Message::PickBulkCharacter(character) => self.bulk_character = character.clone(),
+            Message::ClearBulk => self.bulk.clear(),
+            Message::PickKnownFilter(filter) => self.known_filter = filter.clone(),
+            Message::PickDefaultCharacter(character) => {
+                self.default_character = character.clone();
+            }
+            Message::ToggleFullText(id) => {
+                if let Some(spell) = self.spells.iter_mut()
+                    .find(|spell| spell.spell.id() == *id) {
+                    spell.full_text.toggle();
+                }
             }
             Message::Collapse(id) => {
                 if let Some(spell) = self.spells.iter_mut()
                     .find(|spell| spell.spell.id() == *id) {
-                    if let Some(collapse) = &mut spell.collapse {
-                        collapse.toggle();
-                    } else {
-                        spell.collapse = Some(!self.collapse_all);
-                    }
+                    let collapse = spell.collapse.map_or(!self.collapse_all, |collapse| !collapse);
+                    spell.collapse = Some(collapse);
+                    self.collapsed.insert(id.clone(), collapse);
                 }
             }
             _ => {}
         };
+        let refilter = matches!(message, Message::PickKnownFilter(_) | Message::PickDefaultCharacter(_));
+        // spells were added or changed, but the search is the same
+        let refresh = matches!(message, Message::Refresh);
         let search = self.search.update(message);
 
-        if search {
-            self.spells = self.search.search(custom, characters);
+        let mut debounce = Command::none();
+        if search || refilter {
+            self.class = self.default_character_index(characters)
+                .and_then(|idx| characters[idx].character.class);
+            // also makes any search still running out of date
+            self.generation += 1;
+            if typed {
+                self.unsearched = true;
+                let generation = self.generation;
+                debounce = Command::perform(
+                    tokio::time::sleep(DEBOUNCE),
+                    move |()| crate::Message::Search(Message::Debounced(generation)),
+                );
+            } else {
+                let spells = self.search.search(custom, self.class);
+                self.show_results(spells, characters, !refresh);
+            }
         }
 
-        if searched_text {
-            Command::none()
+        let focus = if searched_text {
+            debounce
         } else {
-            text_input::focus(self.search.id.clone())
+            Command::batch([text_input::focus(self.search.id.clone()), debounce])
+        };
+        match self.selected.filter(|_| moved) {
+            Some(idx) => {
+                // results aren't all the same height, so this is only roughly where it is
+                #[allow(clippy::cast_precision_loss)]
+                let y = idx as f32 / self.spells.len().min(self.shown).saturating_sub(1).max(1) as f32;
+                Command::batch([focus, scrollable::snap_to(RESULTS_ID.clone(), RelativeOffset { x: 0.0, y })])
+            }
+            None => focus,
         }
     }
 
-    pub fn view<'s, 'c: 's>(&'s self) -> Container<'c> {
+    pub fn view<'s, 'c: 's>(
+        &'s self,
+        characters: &[CharacterPage],
+        preferences: &Preferences,
+        highlight: Location,
+    ) -> Container<'c> {
         let collapse_button = button(
             text_icon(if self.collapse_all { Icon::ArrowsExpand } else { Icon::ArrowsCollapse })
                 .size(15),
         ).on_press(crate::Message::Search(Message::CollapseAll));
+        let random_button = button(text("Random").size(14))
+            .tap_if(!self.spells.is_empty(), |b| b.on_press(crate::Message::Search(Message::Random)))
+            .tooltip("Pick a random spell from the results");
+
+        let default_character = self.default_character_index(characters);
+        let default_options = iter::once(DefaultCharacter(None))
+            .chain(characters.iter().map(|page| DefaultCharacter(Some(Arc::clone(&page.character.name)))))
+            .collect_vec();
+        let default_picker = pick_list(
+            default_options,
+            // show "No default character" if the default was closed
+            Some(DefaultCharacter(default_character.map(|idx| Arc::clone(&characters[idx].character.name)))),
+            |character| crate::Message::Search(Message::PickDefaultCharacter(character)),
+        ).text_size(14);
 
         // scroll bar of spells
         let collapse_all = self.collapse_all;
+        let highlight = self.search.text_search.highlight_terms();
+        // between the default character's class's spells and the rest
+        let divider = self.spells.iter()
+            .position(|spell| off_class_list(&spell.spell, self.class))
+            .filter(|&idx| idx != 0);
         let spells_col = self.spells.iter()
-            .fold(col!().align_items(Alignment::Center), |col, spell| {
+            .take(self.shown)
+            .enumerate()
+            .fold(col!().align_items(Alignment::Center), |col, (idx, spell)| {
+                let col = col.tap_if(divider == Some(idx), |col| col
+                    .push(text("— not on your class list —")
+                        .size(16)
+                        .style(Location::Muted))
+                    .push_space(20));
                 let collapse = match spell.collapse {
                     Some(collapse) => collapse,
                     None => collapse_all,
                 };
-                col.push(spell.spell.view(SearchPageButtons(&spell.buttons), (), collapse))
-                    .push_space(40)
-            });
-        let scroll: Scrollable<'_> = scrollable::<'_, _, iced::Renderer<Theme>>(spells_col);
+                let buttons = SearchPageButtons {
+                    bulk: self.bulk.contains(&spell.spell.id()),
+                    characters: &spell.known,
+                    default_character,
+                    collapsed: collapse,
+                    show_known: self.show_known,
+                    summary_fields: &preferences.summary_fields,
+                    full_text: spell.full_text,
+                    preview: (collapse && preferences.hover_previews).then(|| preview(&spell.spell)),
+                    matched_in: spell.matched_in,
+                    highlight: &highlight,
+                    info: collapse.then(|| info_line(&spell.spell)),
+                };
+                col.push(container(spell.spell.view(buttons, (), collapse, preferences))
+                    .padding(4)
+                    .style(Location::Highlight { on: self.selected == Some(idx) }))
+                    .push_space(36)
+            })
+            .tap_if(self.spells.len() > self.shown, |col| col
+                .push(button(text("Load more").size(16))
+                    .on_press(crate::Message::Search(Message::LoadMore)))
+                .push_space(40));
+        let scroll: Scrollable<'_> = scrollable::<'_, _, iced::Renderer<Theme>>(spells_col)
+            .id(RESULTS_ID.clone());
+
+        // adds every checked result at once
+        let bulk_footer = (!self.bulk.is_empty()).then(|| {
+            let target = self.bulk_character_index(characters);
+            let new = target.map_or(self.bulk.len(), |idx| self.bulk.iter()
+                .filter(|id| !characters[idx].character.spells[id.level].iter().any(|(spell, _)| *spell.name() == *id.name))
+                .count());
+            row![
+                text(format!("{} checked", self.bulk.len())).size(14),
+                pick_list(
+                    characters.iter()
+                        .map(|page| DefaultCharacter(Some(Arc::clone(&page.character.name))))
+                        .collect_vec(),
+                    target.map(|idx| DefaultCharacter(Some(Arc::clone(&characters[idx].character.name)))),
+                    |character| crate::Message::Search(Message::PickBulkCharacter(character)),
+                ).text_size(14)
+                    .placeholder("Character"),
+                button(text(if new == 1 { "Add 1 spell".to_string() } else { format!("Add {new} spells") }).size(14))
+                    .tap_if_some(target.filter(|_| new != 0), |b, idx| b.on_press(crate::Message::CharacterBulkAdd(
+                        idx,
+                        self.bulk.iter().cloned().collect(),
+                    )))
+                    .tooltip("Spells the character already knows are skipped"),
+                button(text("Clear").size(14))
+                    .style(Location::Transparent)
+                    .on_press(crate::Message::Search(Message::ClearBulk)),
+            ].spacing(6)
+                .align_items(Alignment::Center)
+        });
+
+        let field_picker = || SummaryField::ALL.into_iter()
+            .fold(row![].spacing(10), |row, field| row.push(checkbox(
+                field.to_string(),
+                preferences.summary_fields.contains(&field),
+                move |_| crate::Message::Settings(crate::settings::Message::Preferences(
+                    crate::preferences::Message::ToggleSummaryField(field)
+                )),
+            ).size(14).text_size(14)));
 
         col![
             10,
-            self.search.view(collapse_button, None),
-            scroll
+            container(self.search.view(row![collapse_button, random_button].spacing(4), None)).padding(2).style(highlight),
+            row![
+                Length::Fill,
+                default_picker,
+                button(text("Details").size(14))
+                    .on_press(crate::Message::Search(Message::ToggleFieldPicker))
+                    .tooltip(format!("Pick up to {} details to show on collapsed spells", SummaryField::MAX)),
+                button(text("Known by").size(14))
+                    .style(Location::TriState(self.show_known.then_some(true)))
+                    .on_press(crate::Message::Search(Message::ToggleKnownBadges))
+                    .tooltip("Show which characters know each spell"),
+                pick_list(
+                    [KnownFilter::Any, KnownFilter::Anyone, KnownFilter::Nobody].into_iter()
+                        .chain(characters.iter().map(|page| KnownFilter::Character(Arc::clone(&page.character.name))))
+                        .collect_vec(),
+                    Some(self.known_filter.clone()),
+                    |filter| crate::Message::Search(Message::PickKnownFilter(filter)),
+                ).text_size(14)
+                    .tooltip("Filter by which open characters know the spell"),
+                Length::Fill,
+            ].spacing(6),
         ].spacing(6)
+            .tap_if(self.show_field_picker, |col| col.push(field_picker()))
+            .push(text(match self.spells.len() {
+                1 => "1 result".to_string(),
+                total if total > self.shown => format!("Showing {} of {total} results", self.shown),
+                total => format!("{total} results"),
+            }.tap_if(self.fuzzy_fallback(), |count| format!("No names contain \"{}\", {} with similar names", self.search.search, count.to_lowercase()))).size(14))
+            .push(scroll)
+            .tap_if_some(bulk_footer, |col, footer| col.push(footer))
             .align_items(Alignment::Center)
             .tap(container)
     }
 }
 
-struct SearchPageButtons<'a>(&'a [(Arc<str>, bool)]);
+struct SearchPageButtons<'a> {
+    /// if this spell is checked to be added with the others
+    bulk: bool,
+    /// see [`SearchSpell::known`]
+    characters: &'a [(Arc<str>, Option<bool>)],
+    default_character: Option<usize>,
+    /// collapsed spells only get a button for the default character, if there is one
+    collapsed: bool,
+    /// show a badge for each character that knows this spell
+    show_known: bool,
+    summary_fields: &'a [SummaryField],
+    full_text: bool,
+    /// shown when the cursor rests on a collapsed spell's name, see [`preview`]
+    preview: Option<(String, String)>,
+    matched_in: Option<TextMatch>,
+    /// see [`TextSearch::highlight_terms`]
+    highlight: &'a [String],
+    /// shown under the name of a collapsed spell, see [`info_line`]
+    info: Option<String>,
+}
+
+/// Where a spell is from and what it is, like `PHB p. 241 · Evocation · Level 3`
+fn info_line(spell: &Spell) -> String {
+    let source = spell.source().abbreviation();
+    let source = match spell.page() {
+        Some(page) => format!("{source} p. {page}"),
+        None => source.to_string(),
+    };
+    let level = match spell.level() {
+        Level::Cantrip => "Cantrip".to_string(),
+        level => format!("Level {}", level as u8),
+    };
+    format!("{source} · {} · {level}", spell.school())
+}
+
+/// How many characters of the description a hover preview shows
+const PREVIEW_LENGTH: usize = 300;
+
+/// A compact line of a spell's details, and the start of its description
+fn preview(spell: &Spell) -> (String, String) {
+    let text = SpellText::from(spell);
+    let details = iter::once(text.level_line)
+        .chain(iter::once(text.casting_time))
+        .chain(text.range)
+        .chain(text.duration)
+        .join(" · ");
+    let description = match text.description.char_indices().nth(PREVIEW_LENGTH) {
+        Some((end, _)) => {
+            let cut = &text.description[..end];
+            // don't stop partway through a word
+            let cut = cut.rfind(char::is_whitespace).map_or(cut, |space| &cut[..space]);
+            format!("{}…", cut.trim_end())
+        }
+        None => text.description,
+    };
+    (details, description)
+}
 
 impl SpellButtons for SearchPageButtons<'_> {
     type Data = ();
 
+    fn summary_fields(&self) -> &[SummaryField] {
+        self.summary_fields
+    }
+
+    fn full_text(&self, id: SpellId) -> Option<(bool, crate::Message)> {
+        Some((self.full_text, crate::Message::Search(Message::ToggleFullText(id))))
+    }
+
+    fn matched_in(&self) -> Option<TextMatch> {
+        self.matched_in
+    }
+
+    fn highlight(&self) -> &[String] {
+        self.highlight
+    }
+
     fn view<'c>(self, id: SpellId, (): Self::Data) -> (Row<'c>, Element<'c>) {
-        let mut buttons = row!();
-        if !self.0.is_empty() {
+        let bulk_id = id.clone();
+        let mut buttons = row!()
+            .push(checkbox("", self.bulk, move |_| crate::Message::Search(Message::ToggleBulk(bulk_id.clone())))
+                .size(16)
+                .tooltip("Check spells to add them to a character all at once"))
+            .push_space(10);
+        // `characters` is from the last search, so it can be missing a character opened since
+        if let Some((character, (name, known))) = self.default_character
+            .and_then(|idx| self.characters.get(idx).map(|known| (idx, known))) {
+            buttons = buttons.push(
+                button(text("+").size(20))
+                    .padding([0, 12])
+                    .tap_if(known.is_none(), |b| b.on_press(crate::Message::Character(character, character::Message::AddSpell(id.clone()))))
+                    .tooltip(format!("Add to {name}"))
+            ).push_space(15);
+        }
+        let characters: &[_] = if self.collapsed && self.default_character.is_some() {
+            &[]
+        } else {
+            self.characters
+        };
+        if !characters.is_empty() {
             buttons = buttons.push("Add to:")
                 .push_space(15);
         }
-        let buttons = self.0.iter()
+        let buttons = characters.iter()
             .enumerate()
-            .fold(buttons, |row, (character, (name, active))|
+            .fold(buttons, |row, (character, (name, known))|
                 row.push({
                     let mut button = button(text(name.as_ref()).size(12));
-                    if *active {
+                    if known.is_none() {
                         button = button.on_press(crate::Message::Character(character, character::Message::AddSpell(id.clone())));
                     }
                     button
                 }).push_space(5),
-            );
+            )
+            .push_space(10)
+            .push(button(text("Macro").size(12))
+                .on_press(crate::Message::CopyMacro(id.clone()))
+                .tooltip("Copy Roll20 macro"))
+            .push_space(5)
+            .push(button(text("Pop out").size(12))
+                .on_press(crate::Message::PopOut(id.clone()))
+                .tooltip("Keep this spell on screen"))
+            .tap_if(self.show_known, |row| self.characters.iter()
+                .enumerate()
+                .filter_map(|(character, (name, known))| known.map(|prepared| (character, name, prepared)))
+                .fold(row.push_space(10).push("Known by:"), |row, (character, name, prepared)| row
+                    .push_space(5)
+                    .push(button(text(name.as_ref()).size(12))
+                        .style(Location::TriState(prepared.then_some(true)))
+                        .on_press(crate::Message::ShowInCharacter(character, id.clone()))
+                        .tooltip(if prepared { "Prepared, click to view" } else { "Known, click to view" }))));
         let name = button(
             text(&*id.name).size(36),
         ).width(Length::FillPortion(18))
             .on_press(crate::Message::Search(Message::Collapse(id)))
-            .style(Location::Transparent)
-            .into();
+            .style(Location::Transparent);
+        let name = match self.preview {
+            Some((details, description)) => HoverPreview::new(
+                name,
+                container(col![
+                    text(details).size(14),
+                    text(description).size(14),
+                ].spacing(4))
+                    .width(Length::Fixed(400.0))
+                    .padding(8)
+                    .style(Location::Tooltip),
+            ).into(),
+            None => name.into(),
+        };
+        let name = match self.info {
+            Some(info) => col![
+                name,
+                text(info).size(13).style(Location::Muted),
+            ].width(Length::FillPortion(18))
+                .into(),
+            None => name,
+        };
         (buttons, name)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use crate::spells::spell::find_spell;
+
+    use super::*;
+
+    fn custom(name: &str, description: &str, higher_levels: Option<&str>) -> Spell {
+        let mut spell = CustomSpell::new(name.to_string());
+        spell.description = description.to_string();
+        spell.higher_levels = higher_levels.map(str::to_string);
+        spell.refold();
+        Spell::Custom(Arc::new(spell))
+    }
+
+    fn text_search(text: &str) -> TextSearch {
+        TextSearch {
+            text: fold(text),
+            ..Default::default()
+        }
+    }
+
+    fn frost_lance() -> Spell {
+        custom(
+            "Frost Lance",
+            "A lance of ice strikes a creature you can see within range.",
+            Some("The damage increases by 1d8 for each slot level above 2nd."),
+        )
+    }
+
+    fn classed(name: &str, level: Level, classes: &[Class]) -> Spell {
+        let mut spell = CustomSpell::new(name.to_string());
+        spell.level = level;
+        spell.classes = classes.to_vec();
+        Spell::Custom(Arc::new(spell))
+    }
+
+    fn ranked(spells: &[Spell], needle: &str, order: SortOrder, class: Option<Class>) -> Vec<String> {
+        let mut spells = spells.to_vec();
+        spells.sort_by_cached_key(|spell| rank(spell, needle, order, class));
+        spells.iter().map(|spell| spell.name().to_string()).collect()
+    }
+
+    #[test]
+    fn rank_puts_the_class_list_first() {
+        let spells = [
+            classed("Acid Splash", Level::Cantrip, &[Class::Sorcerer]),
+            classed("Blade Ward", Level::Cantrip, &[Class::Wizard]),
+            classed("Chill Touch", Level::Cantrip, &[Class::Sorcerer, Class::Wizard]),
+        ];
+        let order = SortOrder::Relevance;
+        assert_eq!(ranked(&spells, "", order, Some(Class::Wizard)), ["Blade Ward", "Chill Touch", "Acid Splash"]);
+        assert_eq!(ranked(&spells, "", order, Some(Class::Sorcerer)), ["Acid Splash", "Chill Touch", "Blade Ward"]);
+        assert_eq!(ranked(&spells, "", order, None), ["Acid Splash", "Blade Ward", "Chill Touch"]);
+    }
+
+    #[test]
+    fn rank_groups_before_matching_the_name() {
+        let spells = [
+            classed("Acid Splash", Level::Cantrip, &[Class::Sorcerer]),
+            classed("Caustic Acid", Level::L1, &[Class::Wizard]),
+        ];
+        let order = SortOrder::Relevance;
+        assert_eq!(ranked(&spells, "acid", order, None), ["Acid Splash", "Caustic Acid"]);
+        assert_eq!(ranked(&spells, "acid", order, Some(Class::Wizard)), ["Caustic Acid", "Acid Splash"]);
+    }
+
+    #[test]
+    fn rank_breaks_ties_by_name() {
+        let spells = [
+            classed("Bolt Blue", Level::L1, &[Class::Wizard]),
+            classed("Bolt Aqua", Level::L1, &[Class::Wizard]),
+        ];
+        assert_eq!(ranked(&spells, "bolt", SortOrder::Relevance, Some(Class::Wizard)), ["Bolt Aqua", "Bolt Blue"]);
+
+        // official and custom spells are sorted together
+        let fireball = find_spell("Fireball", &[]).unwrap();
+        let spells = [fireball, classed("Aardvark Blast", Level::L3, &[Class::Wizard])];
+        assert_eq!(ranked(&spells, "", SortOrder::Level, None), ["Aardvark Blast", "Fireball"]);
+        assert_eq!(ranked(&spells, "", SortOrder::Alphabetical, Some(Class::Wizard)), ["Aardvark Blast", "Fireball"]);
+    }
+
+    #[test]
+    fn matched_in_finds_the_field() {
+        let spell = frost_lance();
+        assert_eq!(text_search("lance").matched_in(&spell), Some(TextMatch::Name));
+        assert_eq!(text_search("creature").matched_in(&spell), Some(TextMatch::Description));
+        assert_eq!(text_search("slot level").matched_in(&spell), Some(TextMatch::HigherLevels));
+        assert_eq!(text_search("fire").matched_in(&spell), None);
+    }
+
+    #[test]
+    fn matched_in_skips_excluded_and_missing_terms() {
+        let spell = frost_lance();
+        assert_eq!(text_search("-fire & slot").matched_in(&spell), Some(TextMatch::HigherLevels));
+        assert_eq!(text_search("fire | creature").matched_in(&spell), Some(TextMatch::Description));
+    }
+
+    #[test]
+    fn matched_in_only_looks_in_searched_fields() {
+        let spell = frost_lance();
+        let mut search = text_search("slot level");
+        search.fields[2] = false;
+        assert_eq!(search.matched_in(&spell), None);
+        assert!(!search.matches(&spell));
+    }
+
+    #[test]
+    fn bonus_action_turn_takes_bonus_actions_and_action_cantrips() {
+        let search = BonusActionTurnSearch { enabled: true };
+        for name in ["Healing Word", "Misty Step", "Shillelagh", "Fire Bolt"] {
+            assert!(search.matches(&find_spell(name, &[]).unwrap()), "{name}");
+        }
+        for name in ["Fireball", "Magic Missile", "Shield"] {
+            assert!(!search.matches(&find_spell(name, &[]).unwrap()), "{name}");
+        }
+    }
+
+    #[test]
+    fn bonus_action_turn_checks_the_other_casting_time() {
+        let search = BonusActionTurnSearch { enabled: true };
+        let mut spell = CustomSpell::new("Quick Ward".to_string());
+        spell.level = Level::L2;
+        spell.casting_time = CastingTime::Minute(1);
+        assert!(!search.matches(&Spell::Custom(Arc::new(spell.clone()))));
+        spell.alt_casting_time = Some(CastingTime::BonusAction);
+        assert!(search.matches(&Spell::Custom(Arc::new(spell))));
+    }
+
+    #[test]
+    fn bonus_action_turn_toggles_and_resets() {
+        let mut options = SearchOptions::default();
+        assert!(options.bonus_action_turn_search.is_empty());
+        options.update(Message::ToggleBonusActionTurn);
+        assert!(!options.bonus_action_turn_search.is_empty());
+        assert_eq!(options.bonus_action_turn_search.describe(), "Bonus-action turn");
+        options.update(Message::ResetSearch);
+        assert!(options.bonus_action_turn_search.is_empty());
+    }
+
+    fn matching(search: &str, names: &[&str]) -> Vec<String> {
+        let search = text_search(search);
+        names.iter()
+            .map(|name| find_spell(name, &[]).unwrap())
+            .filter(|spell| search.matches(spell))
+            .map(|spell| spell.name().to_string())
+            .collect()
+    }
+
+    const SPELLS: [&str; 6] = ["Fireball", "Fire Bolt", "Hold Person", "Mind Sliver", "Mage Hand", "Shield"];
+
+    #[test]
+    fn groups_split_on_operators() {
+        let groups = |text: &str| text_search(text).groups()
+            .into_iter()
+            .map(|group| group.into_iter()
+                .map(|term| (term.text.to_string(), term.exclude))
+                .collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            groups("int|wis & -save"),
+            [vec![("int".to_string(), false), ("wis".to_string(), false)], vec![("save".to_string(), true)]],
+        );
+        // without operators, spaces are part of the text
+        assert_eq!(groups(" fire "), [vec![(" fire ".to_string(), false)]]);
+        assert_eq!(groups("-fire"), [vec![("fire".to_string(), true)]]);
+    }
+
+    #[test]
+    fn and_needs_every_group() {
+        assert_eq!(matching("fire & damage", &SPELLS), ["Fireball", "Fire Bolt"]);
+        assert_eq!(matching("fire & hand", &SPELLS), Vec::<String>::new());
+    }
+
+    #[test]
+    fn or_needs_any_term() {
+        assert_eq!(
+            matching("wisdom saving throw | intelligence saving throw", &SPELLS),
+            ["Hold Person", "Mind Sliver"],
+        );
+    }
+
+    #[test]
+    fn minus_excludes() {
+        assert_eq!(matching("fire & -saving", &SPELLS), ["Fire Bolt"]);
+        assert_eq!(matching("-fire", &SPELLS), ["Hold Person", "Mind Sliver", "Mage Hand", "Shield"]);
+        // while the rest is being typed
+        assert_eq!(matching("-", &SPELLS), SPELLS);
+    }
+
+    #[test]
+    fn mixed_operators() {
+        assert_eq!(matching("int|wis & -save", &SPELLS), ["Hold Person", "Mind Sliver", "Mage Hand"]);
+        assert_eq!(matching("dexterity saving throw", &SPELLS), ["Fireball"]);
+    }
+
+    #[test]
+    fn text_search_only_looks_in_checked_fields() {
+        let mut spell = CustomSpell::new("Alpha".to_string());
+        spell.description = "Bravo".to_string();
+        spell.higher_levels = Some("Charlie".to_string());
+        spell.components = Some(Components { v: true, s: false, m: Some("Delta".to_string()) });
+        spell.casting_time = CastingTime::Reaction(CastingTime::trigger("Echo"));
+        spell.refold();
+        let spell = Spell::Custom(Arc::new(spell));
+
+        let words = ["alpha", "bravo", "charlie", "delta", "echo"];
+        for checked in 0..1_u8 << words.len() {
+            let fields = std::array::from_fn(|i| checked & (1 << i) != 0);
+            for (i, word) in words.into_iter().enumerate() {
+                let search = TextSearch { fields, ..text_search(word) };
+                assert_eq!(search.matches(&spell), fields[i], "{word} with {fields:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn reset_checks_the_default_fields() {
+        let mut options = SearchOptions::default();
+        options.update(Message::ToggleTextField(0));
+        options.update(Message::ToggleTextField(3));
+        assert_eq!(options.text_search.fields, [false, true, true, true, false]);
+        options.update(Message::ResetSearch);
+        assert_eq!(options.text_search.fields, TextSearch::DEFAULT_FIELDS);
+    }
+
+    mod allocations {
+        use std::alloc::{GlobalAlloc, Layout, System};
+        use std::cell::Cell;
+
+        /// Counts the bytes allocated on each thread, so that tests running at the same time don't
+        /// count each other's allocations
+        struct Counting;
+
+        thread_local! {
+            static ALLOCATED: Cell<usize> = const { Cell::new(0) };
+        }
+
+        unsafe impl GlobalAlloc for Counting {
+            unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+                let _ = ALLOCATED.try_with(|allocated| allocated.set(allocated.get() + layout.size()));
+                System.alloc(layout)
+            }
+
+            unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+                System.dealloc(ptr, layout);
+            }
+        }
+
+        #[global_allocator]
+        static COUNTING: Counting = Counting;
+
+        /// How many bytes `f` allocated
+        pub fn allocated(f: impl FnOnce()) -> usize {
+            let before = ALLOCATED.with(Cell::get);
+            f();
+            ALLOCATED.with(Cell::get) - before
+        }
+    }
+
+    #[test]
+    fn searching_doesnt_copy_custom_spells() {
+        let customs = |description: &str| (0..50)
+            .map(|i| {
+                let mut spell = CustomSpell::new(format!("Homebrew {i}"));
+                spell.description = description.repeat(100);
+                spell.refold();
+                Arc::new(spell)
+            })
+            .collect::<Vec<_>>();
+        let short = customs("Short.");
+        let long = customs("A much, much longer description of what the spell does.");
+        let options = SearchOptions::default();
+        // load the bundled spells first
+        options.search(&short, None);
+
+        let mut found = 0;
+        let short_bytes = allocations::allocated(|| found = options.search(&short, None).len());
+        let long_bytes = allocations::allocated(|| assert_eq!(options.search(&long, None).len(), found));
+        assert_eq!(short_bytes, long_bytes);
+    }
+
+    #[test]
+    fn shared_custom_spells_compare_and_save_like_owned_ones() {
+        let mut spell = CustomSpell::new("Homebrew Bolt".to_string());
+        spell.description = "Zap.".to_string();
+        spell.refold();
+        let mut edited = spell.clone();
+        edited.description = "Zap!".to_string();
+
+        let shared = Arc::new(spell.clone());
+        assert_eq!(Spell::Custom(shared.clone()), Spell::Custom(Arc::new(edited)));
+        assert_eq!(serde_json::to_value(&shared).unwrap(), serde_json::to_value(&spell).unwrap());
+        let loaded = serde_json::from_value::<Arc<CustomSpell>>(serde_json::to_value(&spell).unwrap()).unwrap();
+        assert_eq!(loaded.description, "Zap.");
+    }
+
+    #[test]
+    fn either_casting_time_matches() {
+        let mut spell = CustomSpell::new("Homebrew Ward".to_string());
+        spell.alt_casting_time = Some(CastingTime::Hour(8));
+        let spell = Spell::Custom(Arc::new(spell));
+        let search = |times: &[CastingTime]| CastingTimeSearch { times: times.to_vec() }.matches(&spell);
+        assert!(search(&[CastingTime::Action]));
+        assert!(search(&[CastingTime::Hour(8)]));
+        assert!(search(&[CastingTime::Minute(1), CastingTime::Hour(8)]));
+        assert!(!search(&[CastingTime::Minute(1)]));
+        assert!(!search(&[CastingTime::Hour(1)]));
+        assert!(!CastingTimeSearch { times: vec![CastingTime::Hour(8)] }.matches(&find_spell("Fireball", &[]).unwrap()));
+    }
+
+    fn search_page() -> SearchPage {
+        let page = SearchPage::new(&[], &[], SearchOptions::default());
+        assert!(page.spells.len() > 2 * PAGE_SIZE);
+        page
+    }
+
+    #[test]
+    fn refreshing_keeps_the_loaded_pages() {
+        let mut page = search_page();
+        let _ = page.update(Message::LoadMore, &[], &[]);
+        let _ = page.update(Message::Refresh, &[], &[]);
+        assert_eq!(page.shown, 2 * PAGE_SIZE);
+
+        // but a different search starts from the first page again
+        let _ = page.update(Message::PickSchool(School::Evocation), &[], &[]);
+        assert_eq!(page.shown, PAGE_SIZE);
+    }
+
+    #[test]
+    fn refreshing_keeps_the_selection() {
+        let mut page = search_page();
+        let _ = page.update(Message::MoveSelection(3), &[], &[]);
+        let selected = page.selected_spell().unwrap().spell.id();
+        let _ = page.update(Message::Refresh, &[], &[]);
+        assert_eq!(page.selected_spell().unwrap().spell.id(), selected);
+
+        let _ = page.update(Message::Random, &[], &[]);
+        let picked = page.selected_spell().unwrap().spell.id();
+        let _ = page.update(Message::Refresh, &[], &[]);
+        assert_eq!(page.selected_spell().unwrap().spell.id(), picked);
+        assert!(page.selected.unwrap() < page.shown);
+
+        let _ = page.update(Message::PickSchool(School::Evocation), &[], &[]);
+        assert_eq!(page.selected, None);
+    }
+
+    fn component_states(options: &SearchOptions) -> [Option<bool>; 3] {
+        options.component_search.vsm.map(|Enable { value, enabled }| enabled.then_some(value))
+    }
+
+    fn names_found(options: &SearchOptions) -> Vec<String> {
+        options.search(&[], None).iter()
+            .map(|spell| spell.name().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn presets_only_change_their_component() {
+        let states = [None, Some(true), Some(false)];
+        for preset in ComponentPreset::ALL {
+            for hand in states.into_iter().cartesian_product(states).cartesian_product(states) {
+                let ((v, s), m) = hand;
+                let mut options = SearchOptions::default();
+                for (i, state) in [v, s, m].into_iter().enumerate() {
+                    options.update(Message::SetComponent(i, state));
+                }
+                assert!(options.update(Message::ComponentPreset(preset)));
+
+                let mut expected = [v, s, m];
+                expected[preset.component()] = Some(false);
+                assert_eq!(component_states(&options), expected, "{preset:?} over {hand:?}");
+
+                // the same as picking that by hand
+                let mut by_hand = SearchOptions::default();
+                for (i, state) in expected.into_iter().enumerate() {
+                    by_hand.update(Message::SetComponent(i, state));
+                }
+                assert_eq!(names_found(&options), names_found(&by_hand), "{preset:?} over {hand:?}");
+                assert!(options.search(&[], None).iter()
+                    .all(|spell| options.component_search.matches(spell)));
+            }
+        }
+    }
+
+    #[test]
+    fn presets_stack() {
+        let mut options = SearchOptions::default();
+        options.update(Message::SetComponent(2, Some(true)));
+        options.update(Message::ComponentPreset(ComponentPreset::Silenced));
+        options.update(Message::ComponentPreset(ComponentPreset::HandsBound));
+        assert_eq!(component_states(&options), [Some(false), Some(false), Some(true)]);
+        // only spells with nothing but a material component are left
+        let found = options.search(&[], None);
+        assert!(found.iter().all(|spell| spell.components().is_some_and(|c| !c.v && !c.s && c.m.is_some())));
+
+        // applying a preset twice doesn't undo it
+        options.update(Message::ComponentPreset(ComponentPreset::Silenced));
+        assert_eq!(component_states(&options), [Some(false), Some(false), Some(true)]);
+
+        let silenced = {
+            let mut options = SearchOptions::default();
+            options.update(Message::ComponentPreset(ComponentPreset::Silenced));
+            options.search(&[], None)
+        };
+        assert!(!silenced.is_empty());
+        assert!(silenced.iter().all(|spell| spell.components().map_or(true, |c| !c.v)));
+        assert!(silenced.iter().any(|spell| spell.components().is_some_and(|c| c.s)));
+    }
+}
@@ -1,28 +1,72 @@
+use std::fmt::{self, Display};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
 use iced::{Alignment, Length};
 use iced_native::widget::{button, checkbox, container, horizontal_rule, pick_list, scrollable, text, text_input, vertical_rule};
 use itertools::{Either, Itertools};
+use once_cell::unsync::OnceCell;
 
-use crate::{Column, Container, Element, Level, Location, Row};
+use crate::{Column, Container, Element, Level, Location, Row, SpellButtons, SpellId, SPELLS};
 use crate::character::Character;
+use crate::preferences::{self, Preferences};
 use crate::spells::data::{CastingTime, Class, Components, School};
-use crate::spells::spell::CustomSpell;
+use crate::spells::diff::{self, Change};
+use crate::spells::history::SpellHistory;
+use crate::spells::spell::{CustomSpell, fold};
+use crate::spells::static_arc::StArc;
+use crate::statistics::Statistics;
 // use crate::style::Style;
-use crate::utils::{ListGrammaticallyExt, SpacingExt, Tap};
+use crate::icon::Icon;
+use crate::utils::{ListGrammaticallyExt, SpacingExt, Tap, text_icon, TooltipExt};
 
 #[derive(Debug, Clone)]
 pub enum Message {
     CharacterName(String),
     SubmitCharacter,
+    /// add a character from a file another computer exported
+    ImportCharacter,
     Open(usize),
     Rename(usize),
     RenameString(usize, String),
     DeleteCharacter(usize),
+    /// put the most recently closed characters first
+    SortClosed,
+    /// delete every closed character, after asking [`EMPTY_ARCHIVE_CONFIRMATIONS`] times
+    EmptyArchive,
+    CancelEmptyArchive,
+    /// index, delta
+    MoveClosed(usize, isize),
+    ToggleSelecting,
+    ToggleSelectClosed(usize),
+    ReopenSelected,
+    /// look at a closed character's spells without opening it
+    ViewClosed(usize),
+    CloseView,
+    /// expand or collapse a spell of the closed character being viewed
+    ExpandViewed(SpellId),
     SpellName(String),
     OpenSpell(usize),
     SubmitSpell,
     DeleteSpell(usize),
     EditSpell(Edit),
     CloseSpell,
+    /// start renaming the open spell, or submit the new name
+    RenameSpell,
+    RenameSpellString(String),
+    ReloadOverrides,
+    ToggleSpellHistory,
+    /// compare the open spell to the official spell it's based on
+    ToggleDiff,
+    /// index into the open spell's revisions
+    RestoreRevision(usize),
+    /// pick a folder to save characters, custom spells, and preferences in
+    ChooseDataDir,
+    ResetDataDir,
+    /// show or hide the end of the log
+    ToggleLog,
+    CopyLog,
+    Preferences(preferences::Message),
 }
 
 #[derive(Debug, Clone)]
@@ -32,6 +76,8 @@ pub enum Edit {
     CastingTime(CastingTime),
     CastingTimeN(String),
     CastingTimeWhen(String),
+    AltCastingTime(Option<CastingTime>),
+    AltCastingTimeN(String),
     Range(String),
     ComponentV(bool),
     ComponentS(bool),
@@ -44,13 +90,38 @@ pub enum Edit {
     // DescEnter,
     HigherLevels(String),
     Class(Class),
+    BasedOn(String),
     // Source(String),
     // Page(String),
 }
 
+/// How many times Empty archive asks before deleting every closed character
+pub const EMPTY_ARCHIVE_CONFIRMATIONS: u8 = 2;
+
+/// Seconds since the unix epoch, for [`Character::closed_at`]
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+/// Like `5 minutes ago`, for a time from [`unix_now`]
+fn ago(time: u64) -> String {
+    let minutes = unix_now().saturating_sub(time) / 60;
+    let (n, unit) = match minutes {
+        0 => return "just now".to_string(),
+        1..=59 => (minutes, "minute"),
+        60..=1439 => (minutes / 60, "hour"),
+        _ => (minutes / 1440, "day"),
+    };
+    format!("{n} {unit}{} ago", if n == 1 { "" } else { "s" })
+}
+
 pub struct ClosedCharacter {
     pub character: Character,
     pub rename: Either<(), String>,
+    /// selected to be reopened with [`Message::ReopenSelected`]
+    pub selected: bool,
 }
 
 impl From<Character> for ClosedCharacter {
@@ -58,6 +129,7 @@ impl From<Character> for ClosedCharacter {
         Self {
             character,
             rename: Either::Left(()),
+            selected: false,
         }
     }
 }
@@ -68,6 +140,34 @@ pub struct SettingsPage {
     pub spell_name: String,
     pub spell_name_id: text_input::Id,
     pub spell_editor: SpellEditor,
+    pub overlay_error: Option<String>,
+    /// why entries in `spells-override.json` were skipped
+    pub override_errors: Vec<String>,
+    /// showing checkboxes to reopen several closed characters at once
+    pub selecting_closed: bool,
+    /// the closed character chosen with the arrow keys, opened by Enter
+    pub selected_closed: Option<usize>,
+    /// showing the open spell's earlier revisions
+    pub show_spell_history: bool,
+    /// showing what the open spell changed from the one it's based on
+    pub show_diff: bool,
+    /// the closed character whose spells are shown instead of the spell editor, and which of them
+    /// are expanded
+    pub viewing_closed: Option<(usize, Vec<SpellId>)>,
+    /// the new name while renaming the open spell
+    pub spell_rename: Option<String>,
+    /// why the last rename didn't happen
+    pub spell_rename_error: Option<String>,
+    /// why the data folder couldn't be changed
+    pub data_dir_error: Option<String>,
+    /// why the last character import failed
+    pub import_error: Option<String>,
+    /// the end of the log, read when it's shown
+    pub log: Option<String>,
+    /// computed the first time it's viewed, reset when custom spells change
+    pub statistics: OnceCell<Statistics>,
+    /// how many times Empty archive has been pressed without cancelling
+    pub empty_archive_step: u8,
 }
 
 impl Default for SettingsPage {
@@ -78,32 +178,144 @@ impl Default for SettingsPage {
             spell_name: Default::default(),
             spell_name_id: text_input::Id::unique(),
             spell_editor: Default::default(),
+            overlay_error: None,
+            override_errors: Vec::new(),
+            selecting_closed: false,
+            selected_closed: None,
+            show_spell_history: false,
+            show_diff: false,
+            viewing_closed: None,
+            spell_rename: None,
+            spell_rename_error: None,
+            data_dir_error: None,
+            import_error: None,
+            log: None,
+            statistics: OnceCell::new(),
+            empty_archive_step: 0,
         }
     }
 }
 
 impl SettingsPage {
-    pub fn new(custom_spells: &[CustomSpell]) -> Self {
+    pub fn new(custom_spells: &[Arc<CustomSpell>]) -> Self {
         Self {
             character_name: Default::default(),
             character_name_id: text_input::Id::unique(),
             spell_name: Default::default(),
             spell_name_id: text_input::Id::unique(),
             spell_editor: SpellEditor::searching("", custom_spells),
+            overlay_error: None,
+            override_errors: Vec::new(),
+            selecting_closed: false,
+            selected_closed: None,
+            show_spell_history: false,
+            show_diff: false,
+            viewing_closed: None,
+            spell_rename: None,
+            spell_rename_error: None,
+            data_dir_error: None,
+            import_error: None,
+            log: None,
+            statistics: OnceCell::new(),
+            empty_archive_step: 0,
         }
     }
+
+    fn data_dir_view<'c>(&self) -> Column<'c> {
+        let dir = crate::current_data_dir();
+        let is_default = dir == *crate::SAVE_DIR;
+        col![
+            row![
+                text("Data folder").size(16),
+                Length::Fill,
+                button(text("Choose…").size(14))
+                    .on_press(crate::Message::Settings(Message::ChooseDataDir))
+                    .tooltip("Save characters, custom spells, and preferences in a synced folder, like Dropbox"),
+            ].spacing(4)
+                .align_items(Alignment::Center)
+                .tap_if(!is_default, |row| row.push(
+                    button(text("Use default").size(14))
+                        .on_press(crate::Message::Settings(Message::ResetDataDir))
+                )),
+            text(dir.display()).size(12),
+        ].spacing(2)
+            .tap_if_some(self.data_dir_error.as_deref(), |col, e| col.push(text(e).size(12)))
+    }
+
+    /// Moves the keyboard selection of closed characters by `delta`, starting at the top one
+    pub fn select_closed(&mut self, delta: isize, num_closed: usize) {
+        self.selected_closed = match self.selected_closed {
+            _ if num_closed == 0 => None,
+            None => Some(0),
+            Some(idx) => Some(idx.saturating_add_signed(delta).min(num_closed - 1)),
+        };
+    }
 }
 
 pub enum SpellEditor {
     Searching {
         /// Vec<(spell, open, delete)>
-        spells: Vec<CustomSpell>,
+        spells: Vec<Arc<CustomSpell>>,
     },
     Editing {
         spell: Box<CustomSpell>,
+        scratch: EditScratch,
     },
 }
 
+/// What the spell editor remembers that isn't part of the spell, so that trying out another
+/// casting time and switching back doesn't lose what was typed
+#[derive(Debug, Default)]
+pub struct EditScratch {
+    /// the last number of minutes or hours
+    pub casting_time_n: Option<usize>,
+    /// the last reaction trigger
+    pub trigger: Option<StArc<str>>,
+}
+
+impl EditScratch {
+    /// The casting time to switch to when `picked` is chosen, with whatever `current` had saved
+    /// for later, and brought back from earlier
+    pub fn switch_casting_time(&mut self, current: &CastingTime, picked: CastingTime) -> CastingTime {
+        match current {
+            &(CastingTime::Minute(n) | CastingTime::Hour(n)) => self.casting_time_n = Some(n),
+            CastingTime::Reaction(Some(when)) => self.trigger = Some(when.clone()),
+            _ => {}
+        }
+        match picked {
+            CastingTime::Minute(n) => CastingTime::Minute(self.casting_time_n.unwrap_or(n)),
+            CastingTime::Hour(n) => CastingTime::Hour(self.casting_time_n.unwrap_or(n)),
+            CastingTime::Reaction(when) => CastingTime::Reaction(when.or_else(|| self.trigger.clone())),
+            picked => picked,
+        }
+    }
+}
+
+/// A choice in the alternate casting time picker, which can be none
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AltCastingTime(Option<CastingTime>);
+
+impl AltCastingTime {
+    /// Reactions are left out, since their trigger belongs with the main casting time
+    const ALL: [Self; 6] = [
+        Self(None),
+        Self(Some(CastingTime::Action)),
+        Self(Some(CastingTime::BonusAction)),
+        Self(Some(CastingTime::Minute(1))),
+        Self(Some(CastingTime::Hour(1))),
+        Self(Some(CastingTime::Special)),
+    ];
+}
+
+impl Display for AltCastingTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Some(time) => time.fmt(f),
+            None => f.write_str("None"),
+        }
+    }
+}
+
 impl Default for SpellEditor {
     fn default() -> Self {
         Self::Searching { spells: Default::default() }
@@ -111,7 +323,11 @@ impl Default for SpellEditor {
 }
 
 impl SpellEditor {
-    pub fn searching(needle: &str, spells: &[CustomSpell]) -> Self {
+    pub fn editing(spell: Box<CustomSpell>) -> Self {
+        Self::Editing { spell, scratch: EditScratch::default() }
+    }
+
+    pub fn searching(needle: &str, spells: &[Arc<CustomSpell>]) -> Self {
         let spells = spells.iter()
             .map(|spell| (&spell.name_lower, spell))
             .filter(|(name, _)| name.contains(needle))
@@ -129,6 +345,10 @@ impl SettingsPage {
     pub fn view<'s, 'c: 's>(
         &'s self,
         closed_characters: &[ClosedCharacter],
+        preferences: &'s Preferences,
+        custom_spells: &[Arc<CustomSpell>],
+        spell_history: &SpellHistory,
+        highlight: Location,
         width: u16,
     ) -> Container<'c> {
         const PADDING: u16 = 12;
@@ -153,15 +373,25 @@ impl SettingsPage {
             text("Create").size(16),
         )
             .on_press(crate::Message::Settings(Message::SubmitCharacter));
+        let import_character_button = button(
+            text("Import character").size(16),
+        )
+            .on_press(crate::Message::Settings(Message::ImportCharacter))
+            .tooltip("Add a character someone shared, along with their custom spells");
         #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss, clippy::cast_lossless)]
             let text_width = width as f32 / 2.0
             - PADDING as f32
             - RULE_SPACING as f32
             - NAME_PADDING as f32
             - 45.0 // open button
+            - 43.0 // view button
             - (2 * SPACING) as f32
             - 51.0 // delete button
+            - 44.0 // move buttons
+            - if self.selecting_closed { 24.0 } else { 0.0 } // checkbox
+            - if self.selected_closed.is_some() { 14.0 } else { 0.0 } // selection marker
             ;
+        let num_closed = closed_characters.len();
         let closed_character_buttons = closed_characters.iter()
             .enumerate()
             .fold(col!(), |col, (idx, closed)| {
@@ -174,11 +404,19 @@ impl SettingsPage {
                     .on_press(crate::Message::Settings(Message::Open(idx)));
                 let name = container(name)
                     .max_width(text_width)
-                    .style(highlight);
+                    .style(highlight)
+                    .tooltip(closed.character.closed_at.map_or_else(
+                        || "Closed a while ago".to_string(),
+                        |closed_at| format!("Closed {}", ago(closed_at)),
+                    ));
                 let open = button(
                     text("Open").size(15),
                 ).style(highlight)
                     .on_press(crate::Message::Settings(Message::Open(idx)));
+                let view = button(
+                    text("View").size(15),
+                ).style(highlight)
+                    .on_press(crate::Message::Settings(Message::ViewClosed(idx)));
                 let rename = match &closed.rename {
                     Either::Left(()) => {
                         let button = button(
@@ -211,28 +449,114 @@ impl SettingsPage {
                     text("Delete").size(15),
                 ).style(highlight)
                     .on_press(crate::Message::Settings(Message::DeleteCharacter(idx)));
+                let move_buttons = row![
+                    button(text_icon(Icon::ArrowUp).size(12))
+                        .style(highlight)
+                        .tap_if(idx != 0, |b| b.on_press(crate::Message::Settings(Message::MoveClosed(idx, -1)))),
+                    button(text_icon(Icon::ArrowDown).size(12))
+                        .style(highlight)
+                        .tap_if(idx + 1 != num_closed, |b| b.on_press(crate::Message::Settings(Message::MoveClosed(idx, 1)))),
+                ];
+                let select = self.selecting_closed.then(|| checkbox(
+                    "",
+                    closed.selected,
+                    move |_| crate::Message::Settings(Message::ToggleSelectClosed(idx)),
+                ));
+                let marker = (self.selected_closed == Some(idx)).then(|| text_icon(Icon::DiamondFill).size(12));
                 col.push(container(
-                    row![
-                        NAME_PADDING,
-                        name,
-                        Length::Fill,
-                        open,
-                        rename,
-                        delete
-                    ].spacing(SPACING)
+                    row![NAME_PADDING]
+                        .tap_if_some(marker, Row::push)
+                        .tap_if_some(select, Row::push)
+                        .push(row![
+                            name,
+                            Length::Fill,
+                            open,
+                            view,
+                            rename,
+                            delete,
+                            move_buttons,
+                        ].spacing(SPACING)
+                            .width(Length::Fill)
+                            .align_items(Alignment::Center))
                         .align_items(Alignment::Center)
                 ).style(highlight))
             });
 
-        let character_col = col![
-            row![
+        let any_selected = closed_characters.iter().any(|closed| closed.selected);
+        let select_row = row![
+            Length::Fill,
+            button(text(if self.selecting_closed { "Cancel" } else { "Select" }).size(15))
+                .on_press(crate::Message::Settings(Message::ToggleSelecting)),
+        ].spacing(SPACING)
+            .tap_if(self.selecting_closed, |row| row.push(
+                button(text("Reopen selected").size(15))
+                    .tap_if(any_selected, |b| b.on_press(crate::Message::Settings(Message::ReopenSelected)))
+            ))
+            .push(button(text("Sort by closed").size(15))
+                .tap_if(num_closed > 1, |b| b.on_press(crate::Message::Settings(Message::SortClosed)))
+                .tooltip("Put the most recently closed characters first"))
+            .push(button(text(match self.empty_archive_step {
+                0 => "Empty archive".to_string(),
+                1 => format!("Delete all {num_closed} closed characters?"),
+                _ => format!("Really delete all {num_closed}? Only Ctrl+Z can undo it"),
+            }).size(15))
+                .tap_if(num_closed != 0, |b| b.on_press(crate::Message::Settings(Message::EmptyArchive))))
+            .tap_if(self.empty_archive_step != 0, |row| row.push(
+                button(text("Cancel").size(15))
+                    .style(Location::Transparent)
+                    .on_press(crate::Message::Settings(Message::CancelEmptyArchive))
+            ));
+
+        let new_character = col![
+            container(row![
                 character_name_input,
                 4,
                 create_character_button,
-            ].align_items(Alignment::Center),
+                4,
+                import_character_button,
+            ].align_items(Alignment::Center)).padding(2).style(highlight),
+        ].tap_if_some(self.import_error.as_deref(), |col, e| col.push(text(e).size(12).style(Location::Error)));
+
+        let character_col = col![
+            new_character,
             14,
+            select_row,
             closed_character_buttons,
+            horizontal_rule(RULE_SPACING),
+            row![
+                Length::Fill,
+                text("Preferences").size(30),
+                Length::Fill,
+            ],
+            preferences.view(self.overlay_error.as_deref()),
+            self.data_dir_view(),
+            horizontal_rule(RULE_SPACING),
+            row![
+                Length::Fill,
+                text("Database").size(30),
+                Length::Fill,
+            ],
+            self.statistics.get_or_init(|| Statistics::new(custom_spells)).view(),
+            row![
+                Length::Fill,
+                button(text("Reload spell overrides").size(15))
+                    .on_press(crate::Message::Settings(Message::ReloadOverrides)),
+                4,
+                button(text(if self.log.is_some() { "Hide log" } else { "View log" }).size(15))
+                    .on_press(crate::Message::Settings(Message::ToggleLog)),
+            ],
         ].spacing(4)
+            .tap(|col| self.override_errors.iter().fold(col, |col, e| col.push(text(e).size(12))))
+            .tap_if_some(self.log.as_deref(), |col, log| col
+                .push(row![
+                    Length::Fill,
+                    button(text("Copy").size(12))
+                        .on_press(crate::Message::Settings(Message::CopyLog)),
+                ])
+                .push(container(scrollable(text(log).size(12)))
+                    .max_height(300.0)
+                    .padding(4)
+                    .style(Location::Tooltip)))
             // for some reason the scrollbar was overlapping?
             .padding([0, 8]);
 
@@ -296,7 +620,7 @@ impl SettingsPage {
                     });
                 spells_col.push(scrollable(col))
             }
-            SpellEditor::Editing { spell } => {
+            SpellEditor::Editing { spell, .. } => {
                 fn make_row<'a, T: Into<Element<'a>>, L: Into<String>>(
                     label: L,
                     content: T,
@@ -321,19 +645,35 @@ impl SettingsPage {
                     move |t: T| crate::Message::Settings(Message::EditSpell(edit_ctor(t)))
                 }
 
-                let title = text(&*spell.name).size(36);
+                let title: Element<'_> = match &self.spell_rename {
+                    None => text(&*spell.name).size(36).into(),
+                    Some(name) => text_input("Submit now to cancel", name)
+                        .size(24)
+                        .width(Length::Fixed(260.0))
+                        .on_input(|name| crate::Message::Settings(Message::RenameSpellString(name)))
+                        .on_submit(crate::Message::Settings(Message::RenameSpell))
+                        .into(),
+                };
+                let rename_button = button(
+                    text(if self.spell_rename.is_some() { "Submit" } else { "Rename" }).size(15),
+                ).on_press(crate::Message::Settings(Message::RenameSpell));
                 let close_button = button(
                     "Close",
                 ).on_press(crate::Message::Settings(Message::CloseSpell));
 
                 let title = row![
-                    Length::Fill,
+                    container(row![
+                        rename_button,
+                        Length::Fill,
+                    ]).width(Length::Fill),
                     title,
                     container(row![
                         Length::Fill,
                         close_button,
                     ]).width(Length::Fill)
                 ].align_items(Alignment::Center);
+                let rename_error = self.spell_rename_error.as_ref()
+                    .map(|e| make_row("", text(e).size(14)));
 
                 let school = pick_list(
                     &School::ALL[..],
@@ -375,6 +715,24 @@ impl SettingsPage {
                     )),
                 };
 
+                const ALT_CASTING_TIMES: &'static [AltCastingTime] = &AltCastingTime::ALL;
+                let alt_casting_time = pick_list(
+                    ALT_CASTING_TIMES,
+                    Some(AltCastingTime(spell.alt_casting_time.clone())),
+                    edit_message(|AltCastingTime(alt)| Edit::AltCastingTime(alt)),
+                );
+
+                let alt_casting_time_n = match &spell.alt_casting_time {
+                    &Some(CastingTime::Minute(n) | CastingTime::Hour(n)) => Some(make_row(
+                        if matches!(&spell.alt_casting_time, Some(CastingTime::Minute(_))) { "Minutes:" } else { "Hours:" },
+                        text_input(
+                            "",
+                            &n.to_string(),
+                        ).on_input(edit_message(Edit::AltCastingTimeN)),
+                    )),
+                    _ => None,
+                };
+
                 let range = text_input(
                     "",
                     spell.range.as_deref().unwrap_or(""),
@@ -446,6 +804,38 @@ impl SettingsPage {
                 )
                     .placeholder("Class");
 
+                let revisions = spell_history.revisions(&spell.name);
+                let history_button = button(
+                    text(format!("History ({})", revisions.len())).size(14),
+                ).on_press(crate::Message::Settings(Message::ToggleSpellHistory));
+                let history = self.show_spell_history.then(|| revisions.iter()
+                    .enumerate()
+                    // newest first
+                    .rev()
+                    .fold(col!().spacing(2).width(Length::Fill), |col, (idx, revision)| col.push(row![
+                        text(revision.age()).size(14),
+                        Length::Fill,
+                        button(text("Restore").size(14))
+                            .on_press(crate::Message::Settings(Message::RestoreRevision(idx))),
+                    ].align_items(Alignment::Center))));
+
+                let based_on = text_input(
+                    "Official spell",
+                    spell.based_on.as_deref().unwrap_or(""),
+                ).on_input(edit_message(Edit::BasedOn));
+                let base = spell.based_on.as_deref()
+                    .map(fold)
+                    .and_then(|name| SPELLS.iter().find(|base| base.name_lower == name));
+                let diff_button = base.map(|_| button(
+                    text(if self.show_diff { "Hide diff" } else { "Diff vs original" }).size(14),
+                ).on_press(crate::Message::Settings(Message::ToggleDiff)));
+                let comparison = base.filter(|_| self.show_diff)
+                    .map(|base| diff::fields(base, spell)
+                        .into_iter()
+                        .fold(col!().spacing(2).width(Length::Fill), |col, change| col.push(text(change).size(14)))
+                        .push_space(6)
+                        .push(diff_view(&diff::words(base.description, &spell.description))));
+
                 // let page = TextInput::new(
                 //     &mut spell.page_state,
                 //     "278",
@@ -457,12 +847,15 @@ impl SettingsPage {
                 let column = col!()
                     .spacing(3)
                     .push(make_row("", title))
+                    .tap_if_some(rename_error, Column::push)
                     .push(horizontal_rule(8))
                     .push(make_row("", school))
                     .push_space(2)
                     .push(make_row("Level:", level))
                     .push(make_row("Casting Time:", casting_time))
                     .tap_if_some(casting_time_extra, Column::push)
+                    .push(make_row("Or:", alt_casting_time))
+                    .tap_if_some(alt_casting_time_n, Column::push)
                     .push(make_row("Range:", range))
                     .push(make_row("Components:", components))
                     .tap_if_some(material_component, |col, mat| col.push(make_row("Material:", mat)))
@@ -476,6 +869,13 @@ impl SettingsPage {
                     .push(horizontal_rule(8))
                     .push(make_row("Classes:", classes))
                     .push(make_row("", text(spell.classes.iter().list_grammatically()).size(16)))
+                    .push(horizontal_rule(8))
+                    .push(make_row("", history_button))
+                    .tap_if_some(history, |col, history| col.push(make_row("", history)))
+                    .push(horizontal_rule(8))
+                    .push(make_row("Based on:", based_on))
+                    .tap_if_some(diff_button, |col, button| col.push(make_row("", button)))
+                    .tap_if_some(comparison, |col, comparison| col.push(make_row("", comparison)))
                     // .push(Rule::horizontal(8))
                     // .push(row("Source:", source))
                     // .push(row("Page:", page))
@@ -484,6 +884,33 @@ impl SettingsPage {
             }
         };
 
+        let viewing = self.viewing_closed.as_ref()
+            .and_then(|(idx, expanded)| Some((&closed_characters.get(*idx)?.character, expanded)));
+        let (spells_label, spells_col) = match viewing {
+            Some((character, expanded)) => {
+                let label = row![
+                    Length::Fill,
+                    text(format!("{}'s Spells", character.name)).size(30),
+                    container(row![
+                        Length::Fill,
+                        button("Close").on_press(crate::Message::Settings(Message::CloseView)),
+                    ]).width(Length::Fill),
+                ].align_items(Alignment::Center);
+                let spells = character.spells.iter()
+                    .zip(Level::ALL)
+                    .filter(|(spells, _)| !spells.is_empty())
+                    .fold(col!().spacing(4), |col, (spells, level)| spells.iter().fold(
+                        col.push(text(level).size(22)),
+                        |col, (spell, _)| {
+                            let collapse = !expanded.contains(&spell.id());
+                            col.push(spell.view(ClosedSpellButtons, (), collapse, preferences))
+                        },
+                    ));
+                (label, spells)
+            }
+            None => (spells_label, spells_col),
+        };
+
         let row = row![
             col![
                 character_label.height(Length::Fill),
@@ -500,4 +927,105 @@ impl SettingsPage {
 
         container(row.height(Length::Shrink))
     }
-}
\ No newline at end of file
+}
+
+/// No buttons, since a closed character's spells can only be looked at
+struct ClosedSpellButtons;
+
+impl SpellButtons for ClosedSpellButtons {
+    type Data = ();
+
+    fn view<'c>(self, id: SpellId, (): Self::Data) -> (Row<'c>, Element<'c>) {
+        let name = button(text(&*id.name).size(26))
+            .style(Location::Transparent)
+            .on_press(crate::Message::Settings(Message::ExpandViewed(id)))
+            .into();
+        (row!(), name)
+    }
+}
+
+/// Lays out the runs of a word diff in lines, styling added words as a success and removed words as
+/// an error
+fn diff_view<'c>(runs: &[(Change, String)]) -> Column<'c> {
+    /// roughly how many characters fit on a line of the spell editor
+    const LINE_LEN: usize = 70;
+
+    let style = |change| match change {
+        Change::Same => None,
+        Change::Added => Some(Location::Success),
+        Change::Removed => Some(Location::Error),
+    };
+    let mut lines = vec![Vec::<(Change, String)>::new()];
+    let mut line_len = 0;
+    for (change, run) in runs {
+        for word in run.split(' ') {
+            if line_len != 0 && line_len + word.len() > LINE_LEN {
+                lines.push(Vec::new());
+                line_len = 0;
+            }
+            line_len += word.len() + 1;
+            let line = lines.last_mut().unwrap();
+            match line.last_mut() {
+                Some((last, words)) if last == change => {
+                    words.push(' ');
+                    words.push_str(word);
+                }
+                _ => line.push((*change, word.to_string())),
+            }
+        }
+    }
+    lines.into_iter()
+        .fold(col!().spacing(2), |col, line| col.push(line.into_iter()
+            .fold(row!().spacing(4), |row, (change, words)| row.push(
+                text(words).size(16)
+                    .tap_if_some(style(change), |text, style| text.style(style))
+            ))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Picks each of `picks` in order, starting from `time`, like the editor's casting time picker
+    fn pick(scratch: &mut EditScratch, time: CastingTime, picks: impl IntoIterator<Item=CastingTime>) -> CastingTime {
+        picks.into_iter()
+            .fold(time, |time, picked| scratch.switch_casting_time(&time, picked))
+    }
+
+    #[test]
+    fn minutes_and_hours_keep_their_count() {
+        let mut scratch = EditScratch::default();
+        let time = pick(&mut scratch, CastingTime::Minute(10), [CastingTime::Hour(1)]);
+        assert_eq!(time, CastingTime::Hour(10));
+        let time = pick(&mut scratch, time, [CastingTime::Minute(1)]);
+        assert_eq!(time, CastingTime::Minute(10));
+    }
+
+    #[test]
+    fn counts_come_back_after_other_casting_times() {
+        let mut scratch = EditScratch::default();
+        let time = pick(&mut scratch, CastingTime::Hour(8), [CastingTime::Action, CastingTime::BonusAction, CastingTime::Minute(1)]);
+        assert_eq!(time, CastingTime::Minute(8));
+
+        // the count typed last is the one that's kept
+        let time = pick(&mut scratch, CastingTime::Minute(2), [CastingTime::Special, CastingTime::Hour(1)]);
+        assert_eq!(time, CastingTime::Hour(2));
+    }
+
+    #[test]
+    fn reaction_triggers_come_back() {
+        let trigger = CastingTime::trigger("which you take when you are hit by an attack");
+        let mut scratch = EditScratch::default();
+        let time = pick(&mut scratch, CastingTime::Reaction(trigger.clone()), [CastingTime::Action, CastingTime::Minute(1)]);
+        assert_eq!(time, CastingTime::Minute(1));
+        let time = pick(&mut scratch, time, [CastingTime::Reaction(None)]);
+        assert_eq!(time, CastingTime::Reaction(trigger));
+    }
+
+    #[test]
+    fn new_edits_start_from_the_picked_value() {
+        let mut scratch = EditScratch::default();
+        assert_eq!(pick(&mut scratch, CastingTime::Action, [CastingTime::Hour(1)]), CastingTime::Hour(1));
+        assert_eq!(pick(&mut scratch, CastingTime::Action, [CastingTime::Reaction(None)]), CastingTime::Reaction(None));
+    }
+}
@@ -1,5 +1,7 @@
+use std::cmp::Ordering;
 use std::fmt;
 use std::fmt::Display;
+use std::hash::{Hash, Hasher};
 use std::intrinsics::transmute;
 use std::ops::{Index, IndexMut};
 use std::sync::Arc;
@@ -92,7 +94,9 @@ impl Display for School {
     }
 }
 
-#[derive(Eq, PartialEq, Clone, Hash, Debug, Ord, PartialOrd)]
+/// A blank reaction trigger is the same as no trigger, so they compare, hash, and serialize the
+/// same
+#[derive(Clone, Debug)]
 pub enum CastingTime {
     Special,
     Action,
@@ -141,6 +145,24 @@ impl CastingTime {
         }
     }
 
+    /// A reaction trigger, or `None` if `when` is blank, so that there's only one way to not have a
+    /// trigger
+    pub fn trigger(when: &str) -> Option<StArc<str>> {
+        (!when.trim().is_empty()).then(|| StArc::Arc(Arc::from(when)))
+    }
+
+    /// The variant's position, its trigger (if it isn't blank), and its amount of time
+    fn key(&self) -> (u8, Option<&str>, usize) {
+        match self {
+            Self::Special => (0, None, 0),
+            Self::Action => (1, None, 0),
+            Self::BonusAction => (2, None, 0),
+            Self::Reaction(when) => (3, when.as_deref().filter(|when| !when.trim().is_empty()), 0),
+            &Self::Minute(n) => (4, None, n),
+            &Self::Hour(n) => (5, None, n),
+        }
+    }
+
     pub fn equals_ignore_reaction(&self, other: &Self) -> bool {
         #[allow(clippy::match_same_arms)]
         match (self, other) {
@@ -155,16 +177,41 @@ impl CastingTime {
     }
 }
 
+impl PartialEq for CastingTime {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for CastingTime {}
+
+impl Hash for CastingTime {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+impl PartialOrd for CastingTime {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for CastingTime {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
 impl Display for CastingTime {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Special => f.write_str("Special"),
             Self::Action => f.write_str("1 Action"),
             Self::BonusAction => f.write_str("1 Bonus Action"),
-            Self::Reaction(when) => if let Some(when) = when {
-                write!(f, "1 Reaction, which you take when {when}")
-            } else {
-                f.write_str("1 Reaction")
+            Self::Reaction(when) => match when.as_deref().filter(|when| !when.trim().is_empty()) {
+                Some(when) => write!(f, "1 Reaction, which you take when {when}"),
+                None => f.write_str("1 Reaction"),
             }
             &Self::Minute(n) => write!(f, "{n} Minute{}", if n == 1 { "" } else { "s" }),
             &Self::Hour(n) => write!(f, "{n} Hour{}", if n == 1 { "" } else { "s" }),
@@ -172,6 +219,18 @@ impl Display for CastingTime {
     }
 }
 
+/// How a spell is cast, and maybe another way to cast it, shown as `1 Action (or 8 Hours)`
+pub struct CastingTimes<'a>(pub &'a CastingTime, pub Option<&'a CastingTime>);
+
+impl Display for CastingTimes<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.1 {
+            Some(alt) => write!(f, "{} (or {alt})", self.0),
+            None => self.0.fmt(f),
+        }
+    }
+}
+
 impl<'de> Deserialize<'de> for CastingTime {
     fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
         let str = <&'de str>::deserialize(d)?;
@@ -191,7 +250,8 @@ impl<'de> Deserialize<'de> for CastingTime {
             "Reaction" => {
                 Ok(Self::Reaction(
                     str[comma..].starts_with(Self::REACTION_PHRASE)
-                        .then(|| StArc::Arc(Arc::from(&str[comma + Self::REACTION_PHRASE.len()..])))
+                        .then(|| Self::trigger(&str[comma + Self::REACTION_PHRASE.len()..]))
+                        .flatten()
                 ))
             }
             "Minute" | "Minutes" => Ok(Self::Minute(get_num()?)),
@@ -207,8 +267,8 @@ impl Serialize for CastingTime {
             Self::Special => "Special".serialize(s),
             Self::Action => "1 Action".serialize(s),
             Self::BonusAction => "1 Bonus Action".serialize(s),
-            Self::Reaction(None) => "1 Reaction".serialize(s),
-            Self::Reaction(Some(when)) => format!("1 Reaction{}{}", Self::REACTION_PHRASE, when).serialize(s),
+            Self::Reaction(Some(when)) if !when.trim().is_empty() => format!("1 Reaction{}{}", Self::REACTION_PHRASE, when).serialize(s),
+            Self::Reaction(_) => "1 Reaction".serialize(s),
             &Self::Minute(n) => if n == 1 {
                 "1 Minute".serialize(s)
             } else {
@@ -262,7 +322,7 @@ impl<'de> Deserialize<'de> for Components {
                     'S' => vsm.1 = true,
                     'M' => vsm.2 = true,
                     ' ' | ',' => {}
-                    _ => println!("Bad character {char} in {str}"),
+                    _ => log::warn!("Bad character {char} in {str}"),
                 }
             }
             vsm
@@ -322,6 +382,16 @@ impl Source {
         "Tasha's Cauldron of Everything",
         "Custom",
     ];
+
+    /// The short name players use, like `PHB`
+    pub fn abbreviation(self) -> &'static str {
+        match self {
+            Self::PlayersHandbook => "PHB",
+            Self::XanatharsGuideToEverything => "XGE",
+            Self::TashasCauldronOfEverything => "TCE",
+            Self::Custom => "Custom",
+        }
+    }
 }
 
 impl<'de> Deserialize<'de> for Source {
@@ -343,7 +413,146 @@ impl Serialize for Source {
     }
 }
 
-#[derive(Copy, Clone, Debug, Ord, PartialOrd, Eq, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum DamageType {
+    Acid,
+    Bludgeoning,
+    Cold,
+    Fire,
+    Force,
+    Lightning,
+    Necrotic,
+    Piercing,
+    Poison,
+    Psychic,
+    Radiant,
+    Slashing,
+    Thunder,
+}
+
+impl DamageType {
+    pub const ALL: [Self; 13] = [
+        Self::Acid,
+        Self::Bludgeoning,
+        Self::Cold,
+        Self::Fire,
+        Self::Force,
+        Self::Lightning,
+        Self::Necrotic,
+        Self::Piercing,
+        Self::Poison,
+        Self::Psychic,
+        Self::Radiant,
+        Self::Slashing,
+        Self::Thunder,
+    ];
+}
+
+impl Display for DamageType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Acid => "Acid",
+            Self::Bludgeoning => "Bludgeoning",
+            Self::Cold => "Cold",
+            Self::Fire => "Fire",
+            Self::Force => "Force",
+            Self::Lightning => "Lightning",
+            Self::Necrotic => "Necrotic",
+            Self::Piercing => "Piercing",
+            Self::Poison => "Poison",
+            Self::Psychic => "Psychic",
+            Self::Radiant => "Radiant",
+            Self::Slashing => "Slashing",
+            Self::Thunder => "Thunder",
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Ability {
+    Strength,
+    Dexterity,
+    Constitution,
+    Intelligence,
+    Wisdom,
+    Charisma,
+}
+
+impl Ability {
+    pub const ALL: [Self; 6] = [
+        Self::Strength,
+        Self::Dexterity,
+        Self::Constitution,
+        Self::Intelligence,
+        Self::Wisdom,
+        Self::Charisma,
+    ];
+}
+
+impl Display for Ability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Strength => "Strength",
+            Self::Dexterity => "Dexterity",
+            Self::Constitution => "Constitution",
+            Self::Intelligence => "Intelligence",
+            Self::Wisdom => "Wisdom",
+            Self::Charisma => "Charisma",
+        })
+    }
+}
+
+/// Something about a spell that's worked out from its description, to filter by
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Tag {
+    /// deals this type of damage
+    Damage(DamageType),
+    /// calls for a saving throw of this ability
+    Save(Ability),
+}
+
+impl Tag {
+    /// Finds the tags in a [folded](crate::spells::spell::fold) description, by the phrases the books
+    /// use, like "fire damage" or "wisdom saving throw", including lists like "acid, cold, or fire
+    /// damage"
+    pub fn find(desc_lower: &str) -> Vec<Self> {
+        let damage = listed_before(desc_lower, " damage", &DamageType::ALL);
+        let saves = listed_before(desc_lower, " saving throw", &Ability::ALL);
+        DamageType::ALL.into_iter()
+            .filter(|damage_type| damage.contains(damage_type))
+            .map(Self::Damage)
+            .chain(Ability::ALL.into_iter()
+                .filter(|ability| saves.contains(ability))
+                .map(Self::Save))
+            .collect()
+    }
+}
+
+/// Which of `all` are named in the list right before each `suffix` in `desc_lower`, going back
+/// through words like "acid," and "or" until one that isn't a name
+fn listed_before<T: Copy + Eq + Display>(desc_lower: &str, suffix: &str, all: &[T]) -> Vec<T> {
+    let names = all.iter()
+        .map(|t| (*t, t.to_string().to_lowercase()))
+        .collect::<Vec<_>>();
+    let mut found = Vec::new();
+    for (idx, _) in desc_lower.match_indices(suffix) {
+        let words = desc_lower[..idx].split_whitespace()
+            .rev()
+            .map(|word| word.trim_end_matches(','));
+        for word in words {
+            if word == "or" || word == "and" {
+                continue;
+            }
+            match names.iter().find(|(_, name)| name == word) {
+                Some(&(t, _)) => found.push(t),
+                None => break,
+            }
+        }
+    }
+    found
+}
+
+#[derive(Copy, Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub enum Level {
     Cantrip,
     L1,
@@ -484,4 +693,117 @@ impl<T> GetLevel<T> for [T; 9] {
     fn get_lvl_mut(&mut self, level: Level) -> Option<&mut T> {
         self.get_mut((level as usize).checked_sub(1)?)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(casting_time: &CastingTime) -> CastingTime {
+        let json = serde_json::to_string(casting_time).unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn casting_times_round_trip() {
+        for casting_time in [
+            CastingTime::Special,
+            CastingTime::Action,
+            CastingTime::BonusAction,
+            CastingTime::Reaction(None),
+            CastingTime::Reaction(CastingTime::trigger("you take fire damage")),
+            CastingTime::Reaction(CastingTime::trigger("a creature’s café burns — ½ damage")),
+            CastingTime::Reaction(CastingTime::trigger("you see a creature, within 60 feet, cast a spell")),
+            CastingTime::Minute(1),
+            CastingTime::Minute(10),
+            CastingTime::Minute(10_000),
+            CastingTime::Minute(usize::MAX),
+            CastingTime::Hour(1),
+            CastingTime::Hour(8),
+            CastingTime::Hour(8_760),
+            CastingTime::Hour(usize::MAX),
+        ] {
+            assert_eq!(round_trip(&casting_time), casting_time);
+        }
+    }
+
+    #[test]
+    fn reaction_trigger_survives_a_round_trip() {
+        let casting_time = CastingTime::Reaction(CastingTime::trigger("a creature you can see casts a spell"));
+        let json = serde_json::to_string(&casting_time).unwrap();
+        assert_eq!(json, r#""1 Reaction, which you take when a creature you can see casts a spell""#);
+        match round_trip(&casting_time) {
+            CastingTime::Reaction(Some(when)) => assert_eq!(&*when, "a creature you can see casts a spell"),
+            other => panic!("expected a reaction with a trigger, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn unicode_triggers_and_big_counts_are_saved_as_written() {
+        let trigger = CastingTime::Reaction(CastingTime::trigger("a creature’s café burns — ½ damage"));
+        assert_eq!(serde_json::to_string(&trigger).unwrap(), r#""1 Reaction, which you take when a creature’s café burns — ½ damage""#);
+        assert_eq!(serde_json::to_string(&CastingTime::Minute(10_000)).unwrap(), r#""10000 Minutes""#);
+        assert_eq!(serde_json::to_string(&CastingTime::Hour(usize::MAX)).unwrap(), format!("\"{} Hours\"", usize::MAX));
+    }
+
+    #[test]
+    fn blank_trigger_is_no_trigger() {
+        assert!(CastingTime::trigger("").is_none());
+        assert!(CastingTime::trigger("  ").is_none());
+        let blank = CastingTime::Reaction(Some(StArc::Arc(Arc::from(" "))));
+        assert_eq!(blank, CastingTime::Reaction(None));
+        assert_eq!(serde_json::to_string(&blank).unwrap(), r#""1 Reaction""#);
+        assert!(matches!(round_trip(&blank), CastingTime::Reaction(None)));
+        assert_ne!(CastingTime::Reaction(CastingTime::trigger("you fall")), CastingTime::Reaction(None));
+    }
+
+    #[test]
+    fn casting_times_keep_their_order() {
+        assert!(CastingTime::Special < CastingTime::Action);
+        assert!(CastingTime::BonusAction < CastingTime::Reaction(None));
+        assert!(CastingTime::Reaction(CastingTime::trigger("you fall")) < CastingTime::Minute(1));
+        assert!(CastingTime::Minute(10) < CastingTime::Hour(1));
+        assert!(CastingTime::Hour(1) < CastingTime::Hour(8));
+    }
+
+    #[test]
+    fn finds_tags_in_lists() {
+        assert_eq!(Tag::find("takes 8d6 fire damage"), [Tag::Damage(DamageType::Fire)]);
+        assert_eq!(
+            Tag::find("deals your choice of acid, cold, or fire damage"),
+            [Tag::Damage(DamageType::Acid), Tag::Damage(DamageType::Cold), Tag::Damage(DamageType::Fire)],
+        );
+        assert_eq!(
+            Tag::find("4d6 bludgeoning, piercing, and slashing damage"),
+            [
+                Tag::Damage(DamageType::Bludgeoning),
+                Tag::Damage(DamageType::Piercing),
+                Tag::Damage(DamageType::Slashing),
+            ],
+        );
+        assert_eq!(
+            Tag::find("must make a strength or dexterity saving throw"),
+            [Tag::Save(Ability::Strength), Tag::Save(Ability::Dexterity)],
+        );
+        // only the words right before the phrase
+        assert_eq!(Tag::find("a cold wind, then 2d6 damage"), []);
+        assert_eq!(Tag::find("the fire spreads. damage"), []);
+    }
+
+    #[test]
+    fn finds_tags_of_bundled_spells() {
+        use crate::spells::spell::find_spell;
+
+        let fireball = find_spell("Fireball", &[]).unwrap();
+        assert_eq!(fireball.tags(), [Tag::Damage(DamageType::Fire), Tag::Save(Ability::Dexterity)]);
+        let hold_person = find_spell("Hold Person", &[]).unwrap();
+        assert_eq!(hold_person.tags(), [Tag::Save(Ability::Wisdom)]);
+    }
+
+    #[test]
+    fn alternate_casting_times_are_shown_in_brackets() {
+        assert_eq!(CastingTimes(&CastingTime::Action, Some(&CastingTime::Hour(8))).to_string(), "1 Action (or 8 Hours)");
+        assert_eq!(CastingTimes(&CastingTime::BonusAction, Some(&CastingTime::Minute(1))).to_string(), "1 Bonus Action (or 1 Minute)");
+        assert_eq!(CastingTimes(&CastingTime::Action, None).to_string(), "1 Action");
+    }
+}
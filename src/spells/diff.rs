@@ -0,0 +1,178 @@
+//! What a custom spell changed from the official spell it's based on.
+
+use std::fmt::Display;
+
+use itertools::Itertools;
+
+use crate::spells::spell::{CustomSpell, StaticSpell};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Change {
+    Same,
+    Added,
+    Removed,
+}
+
+/// The words of `old` and `new` in order, joined into runs that are in both, only in `new`, or only
+/// in `old`. Finds the longest common subsequence of words, so it's quadratic in the number of
+/// words, which is fine for spell descriptions.
+pub fn words(old: &str, new: &str) -> Vec<(Change, String)> {
+    let old = old.split_whitespace().collect_vec();
+    let new = new.split_whitespace().collect_vec();
+
+    // lcs[i][j] is the length of the longest common subsequence of old[i..] and new[j..]
+    let mut lcs = vec![vec![0_u32; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = Vec::with_capacity(old.len().max(new.len()));
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            changes.push((Change::Same, old[i]));
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            changes.push((Change::Added, new[j]));
+            j += 1;
+        } else {
+            changes.push((Change::Removed, old[i]));
+            i += 1;
+        }
+    }
+
+    changes.into_iter()
+        .group_by(|&(change, _)| change)
+        .into_iter()
+        .map(|(change, words)| (change, words.map(|(_, word)| word).join(" ")))
+        .collect()
+}
+
+/// Everything other than the description that `spell` changed from `base`, like
+/// `"Level: 3rd → 2nd"` or `"Concentration removed"`
+pub fn fields(base: &StaticSpell, spell: &CustomSpell) -> Vec<String> {
+    fn changed<T: PartialEq + Display>(changes: &mut Vec<String>, label: &str, old: T, new: T) {
+        if old != new {
+            changes.push(format!("{label}: {old} → {new}"));
+        }
+    }
+    fn toggled(changes: &mut Vec<String>, label: &str, old: bool, new: bool) {
+        match (old, new) {
+            (false, true) => changes.push(format!("{label} added")),
+            (true, false) => changes.push(format!("{label} removed")),
+            _ => {}
+        }
+    }
+
+    let mut changes = Vec::new();
+    changed(&mut changes, "Level", base.level, spell.level);
+    changed(&mut changes, "School", base.school, spell.school);
+    changed(&mut changes, "Casting time", &base.casting_time, &spell.casting_time);
+    if let Some(alt) = &spell.alt_casting_time {
+        changes.push(format!("Can also be cast in {alt}"));
+    }
+    changed(&mut changes, "Range", base.range, spell.range.as_deref().unwrap_or(""));
+    changed(
+        &mut changes,
+        "Components",
+        base.components.to_string(),
+        spell.components.as_ref().map_or_else(String::new, ToString::to_string),
+    );
+    changed(&mut changes, "Duration", base.duration, spell.duration.as_deref().unwrap_or(""));
+    toggled(&mut changes, "Ritual", base.ritual, spell.ritual);
+    toggled(&mut changes, "Concentration", base.conc, spell.conc);
+    if base.higher_levels != spell.higher_levels.as_deref() {
+        changes.push("At higher levels changed".to_string());
+    }
+    let added = spell.classes.iter().filter(|class| !base.classes.contains(class)).join(", ");
+    let removed = base.classes.iter().filter(|class| !spell.classes.contains(class)).join(", ");
+    if !added.is_empty() {
+        changes.push(format!("Classes added: {added}"));
+    }
+    if !removed.is_empty() {
+        changes.push(format!("Classes removed: {removed}"));
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::spells::data::{CastingTime, Class, Level};
+    use crate::spells::spell::{find_spell, Spell};
+
+    use super::*;
+
+    /// the runs, with `+` before added words and `-` before removed ones
+    fn runs(old: &str, new: &str) -> Vec<String> {
+        words(old, new).into_iter()
+            .map(|(change, words)| match change {
+                Change::Same => words,
+                Change::Added => format!("+{words}"),
+                Change::Removed => format!("-{words}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn unchanged_text_is_one_run() {
+        assert_eq!(runs("a b  c", "a\nb c"), ["a b c"]);
+        assert_eq!(runs("", ""), Vec::<String>::new());
+    }
+
+    #[test]
+    fn added_and_removed_words() {
+        assert_eq!(runs("takes 8d6 fire damage", "takes 6d6 cold damage"), ["takes", "+6d6 cold", "-8d6 fire", "damage"]);
+        assert_eq!(runs("a b c d", "a d"), ["a", "-b c", "d"]);
+        assert_eq!(runs("a d", "a b c d"), ["a", "+b c", "d"]);
+        assert_eq!(runs("", "new text"), ["+new text"]);
+        assert_eq!(runs("old text", ""), ["-old text"]);
+    }
+
+    #[test]
+    fn runs_rebuild_both_texts() {
+        let old = "A bright streak flashes from your pointing finger to a point you choose";
+        let new = "A dim streak flashes from your finger to any point you can see";
+        let diff = words(old, new);
+        let rebuild = |skip| diff.iter()
+            .filter(|(change, _)| *change != skip)
+            .map(|(_, words)| words.as_str())
+            .join(" ");
+        assert_eq!(rebuild(Change::Removed), new);
+        assert_eq!(rebuild(Change::Added), old);
+    }
+
+    #[test]
+    fn lists_changed_fields() {
+        let Spell::Static(fireball) = find_spell("Fireball", &[]).unwrap() else { panic!("Fireball is bundled") };
+        let mut spell = CustomSpell::new("Frostball".to_string());
+        spell.level = fireball.level;
+        spell.school = fireball.school;
+        spell.casting_time = fireball.casting_time.clone();
+        spell.range = Some(fireball.range.to_string());
+        spell.components = Some(fireball.components.clone());
+        spell.duration = Some(fireball.duration.to_string());
+        spell.higher_levels = fireball.higher_levels.map(str::to_string);
+        spell.classes = fireball.classes.to_vec();
+        assert_eq!(fields(fireball, &spell), Vec::<String>::new());
+
+        spell.level = Level::L2;
+        spell.casting_time = CastingTime::BonusAction;
+        spell.conc = true;
+        spell.classes.push(Class::Druid);
+        spell.classes.retain(|&class| class != Class::Sorcerer);
+        assert_eq!(fields(fireball, &spell), [
+            "Level: 3rd → 2nd",
+            "Casting time: 1 Action → 1 Bonus Action",
+            "Concentration added",
+            "Classes added: Druid",
+            "Classes removed: Sorcerer",
+        ]);
+    }
+}
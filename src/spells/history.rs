@@ -0,0 +1,187 @@
+//! Earlier versions of each custom spell, so that homebrew wording can be rolled back.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error;
+use crate::spells::spell::CustomSpell;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Revision {
+    /// seconds since the unix epoch
+    pub saved_at: u64,
+    pub spell: CustomSpell,
+}
+
+impl Revision {
+    /// How long ago this was saved, like "5 minutes ago"
+    pub fn age(&self) -> String {
+        let secs = now().saturating_sub(self.saved_at);
+        let (n, unit) = match secs {
+            0..=59 => return "just now".into(),
+            60..=3599 => (secs / 60, "minute"),
+            3600..=86_399 => (secs / 3600, "hour"),
+            _ => (secs / 86_400, "day"),
+        };
+        format!("{n} {unit}{} ago", if n == 1 { "" } else { "s" })
+    }
+}
+
+/// Revisions of each custom spell by name, oldest first
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SpellHistory(BTreeMap<String, Vec<Revision>>);
+
+impl SpellHistory {
+    /// revisions kept per spell
+    pub const MAX: usize = 10;
+
+    pub fn read(file: &Path) -> error::Result<Self> {
+        let json = fs::read_to_string(file)?;
+        if json.trim().is_empty() {
+            Ok(Self::default())
+        } else {
+            Ok(serde_json::from_str(&json)?)
+        }
+    }
+
+    pub fn save(&self, file: &Path) -> error::Result<()> {
+        let json = serde_json::to_string(self)?;
+        fs::write(file, json)?;
+        Ok(())
+    }
+
+    pub fn revisions(&self, name: &str) -> &[Revision] {
+        self.0.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    /// Moves `old`'s revisions to `new`, renaming the spell in each of them
+    pub fn rename(&mut self, old: &str, new: &CustomSpell) {
+        if let Some(mut revisions) = self.0.remove(old) {
+            for revision in &mut revisions {
+                revision.spell.name = Arc::clone(&new.name);
+                revision.spell.name_lower.clone_from(&new.name_lower);
+            }
+            self.0.insert(new.name.to_string(), revisions);
+        }
+    }
+
+    /// Adds `spell` as its newest revision, dropping the oldest past [`Self::MAX`]. Returns `false`
+    /// without recording anything if `spell` hasn't changed since the newest revision.
+    pub fn record(&mut self, spell: &CustomSpell) -> bool {
+        let revisions = self.0.entry(spell.name.to_string()).or_default();
+        // `CustomSpell`'s `PartialEq` only compares names
+        let unchanged = revisions.last()
+            .map(|last| serde_json::to_value(&last.spell).ok())
+            .map_or(false, |last| last == serde_json::to_value(spell).ok());
+        if unchanged {
+            return false;
+        }
+        revisions.push(Revision { saved_at: now(), spell: spell.clone() });
+        if revisions.len() > Self::MAX {
+            revisions.drain(..revisions.len() - Self::MAX);
+        }
+        true
+    }
+
+    /// Replaces `spell` with its revision at `index`, recording the current version first in case
+    /// restoring was a mistake, and then the restored one. Returns `false` if there's no such
+    /// revision.
+    pub fn restore(&mut self, spell: &mut CustomSpell, index: usize) -> bool {
+        let Some(revision) = self.revisions(&spell.name).get(index).cloned() else {
+            return false;
+        };
+        self.record(spell);
+        *spell = revision.spell;
+        self.record(spell);
+        true
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(description: &str) -> CustomSpell {
+        let mut spell = CustomSpell::new("Homebrew Bolt".to_string());
+        spell.description = description.to_string();
+        spell.refold();
+        spell
+    }
+
+    fn descriptions(history: &SpellHistory, name: &str) -> Vec<String> {
+        history.revisions(name).iter()
+            .map(|revision| revision.spell.description.clone())
+            .collect()
+    }
+
+    #[test]
+    fn keeps_the_newest_revisions() {
+        let mut history = SpellHistory::default();
+        for i in 0..15 {
+            assert!(history.record(&version(&i.to_string())));
+        }
+        let expected = (5..15).map(|i| i.to_string()).collect::<Vec<_>>();
+        assert_eq!(descriptions(&history, "Homebrew Bolt"), expected);
+        assert!(history.revisions("Other Spell").is_empty());
+    }
+
+    #[test]
+    fn unchanged_spells_arent_recorded() {
+        let mut history = SpellHistory::default();
+        assert!(history.record(&version("a")));
+        assert!(!history.record(&version("a")));
+        assert!(history.record(&version("b")));
+        assert_eq!(descriptions(&history, "Homebrew Bolt"), ["a", "b"]);
+    }
+
+    #[test]
+    fn restoring_is_recorded_too() {
+        let mut history = SpellHistory::default();
+        history.record(&version("first"));
+        history.record(&version("second"));
+        let mut spell = version("third, unsaved");
+        assert!(history.restore(&mut spell, 0));
+        assert_eq!(spell.description, "first");
+        assert_eq!(descriptions(&history, "Homebrew Bolt"), ["first", "second", "third, unsaved", "first"]);
+        // and the unsaved version can be restored in turn
+        assert!(history.restore(&mut spell, 2));
+        assert_eq!(spell.description, "third, unsaved");
+        assert!(!history.restore(&mut spell, 10));
+    }
+
+    #[test]
+    fn renaming_moves_the_revisions() {
+        let mut history = SpellHistory::default();
+        history.record(&version("a"));
+        let renamed = CustomSpell::new("Homebrew Blast".to_string());
+        history.rename("Homebrew Bolt", &renamed);
+        assert!(history.revisions("Homebrew Bolt").is_empty());
+        assert_eq!(&*history.revisions("Homebrew Blast")[0].spell.name, "Homebrew Blast");
+    }
+
+    #[test]
+    fn saves_and_reads_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("custom-spells-history.json");
+        let mut history = SpellHistory::default();
+        history.record(&version("a"));
+        history.record(&version("b"));
+        history.save(&file).unwrap();
+        let read = SpellHistory::read(&file).unwrap();
+        assert_eq!(descriptions(&read, "Homebrew Bolt"), ["a", "b"]);
+
+        fs::write(&file, "").unwrap();
+        assert!(SpellHistory::read(&file).unwrap().revisions("Homebrew Bolt").is_empty());
+    }
+}
@@ -1,3 +1,6 @@
 pub mod spell;
 pub mod static_arc;
-pub mod data;
\ No newline at end of file
+pub mod data;
+pub mod history;
+pub mod overrides;
+pub mod diff;
@@ -0,0 +1,164 @@
+//! Spells from `spells-override.json` in the save directory, which replace bundled spells with
+//! the same name or add new ones, for playtesting changes without rebuilding.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::ops::Deref;
+use std::path::Path;
+use std::sync::{Mutex, PoisonError, RwLock};
+
+use once_cell::sync::Lazy;
+
+use crate::spells::spell::StaticSpell;
+
+/// The spells every search reads, swapped out by [`reload`]. Derefs to the current list.
+pub struct Spells(Lazy<RwLock<&'static [StaticSpell]>>);
+
+impl Spells {
+    pub const fn new(init: fn() -> RwLock<&'static [StaticSpell]>) -> Self {
+        Self(Lazy::new(init))
+    }
+}
+
+impl Deref for Spells {
+    type Target = [StaticSpell];
+
+    fn deref(&self) -> &Self::Target {
+        *self.0.read().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// The spells made from each version of `spells-override.json` that has been loaded, by its
+/// contents. Characters hold `&'static` spells, so a version can't be freed once it's loaded, but
+/// loading it again reuses its spells instead of leaking another copy.
+static LOADED: Lazy<Mutex<HashMap<String, (&'static [StaticSpell], Vec<String>)>>> = Lazy::new(Default::default);
+
+/// Merges the overrides in `file` over `bundled` into `spells`. Returns why each entry that
+/// couldn't be used was skipped; if `file` doesn't exist, `spells` is exactly `bundled`.
+///
+/// Spells that were replaced are still valid, but [`Spell::reloaded`](crate::spells::spell::Spell::reloaded)
+/// should be used to get their current version.
+pub fn reload(spells: &Spells, bundled: &'static [StaticSpell], file: &Path) -> Vec<String> {
+    let (merged, errors) = match fs::read_to_string(file) {
+        Ok(json) if !json.trim().is_empty() => {
+            let mut loaded = LOADED.lock().unwrap_or_else(PoisonError::into_inner);
+            loaded.entry(json)
+                .or_insert_with_key(|json| merge(bundled, json))
+                .clone()
+        }
+        Ok(_) => (bundled, Vec::new()),
+        Err(e) if e.kind() == ErrorKind::NotFound => (bundled, Vec::new()),
+        Err(e) => (bundled, vec![format!("Couldn't read {}: {e}", file.display())]),
+    };
+    *spells.0.write().unwrap_or_else(PoisonError::into_inner) = merged;
+    errors
+}
+
+/// `bundled` with the spells in `json` replacing the ones with the same name, or added
+fn merge(bundled: &'static [StaticSpell], json: &str) -> (&'static [StaticSpell], Vec<String>) {
+    let (overrides, errors) = parse(json);
+    if overrides.is_empty() {
+        return (bundled, errors);
+    }
+    let mut merged = bundled.to_vec();
+    for spell in overrides {
+        match merged.iter_mut().find(|s| s.name == spell.name) {
+            Some(bundled) => *bundled = spell,
+            None => merged.push(spell),
+        }
+    }
+    (merged.leak(), errors)
+}
+
+/// Parses each entry on its own, so one bad spell doesn't hide the rest
+fn parse(json: &str) -> (Vec<StaticSpell>, Vec<String>) {
+    let entries = match serde_json::from_str::<Vec<serde_json::Value>>(json) {
+        Ok(entries) => entries,
+        Err(e) => return (Vec::new(), vec![format!("spells-override.json isn't a list of spells: {e}")]),
+    };
+    let mut spells = Vec::new();
+    let mut errors = Vec::new();
+    for (idx, entry) in entries.into_iter().enumerate() {
+        let name = entry.get("name")
+            .and_then(serde_json::Value::as_str)
+            .unwrap_or("unnamed")
+            .to_string();
+        match serde_json::from_value(entry) {
+            Ok(spell) => spells.push(spell),
+            Err(e) => errors.push(format!("Entry {} ({name}): {e}", idx + 1)),
+        }
+    }
+    (spells, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ptr;
+
+    use super::*;
+
+    const BUNDLED: &str = r#"[{"name": "Fireball", "level": 3, "casting_time": "1 Action", "range": "150 feet", "duration": "Instantaneous", "components": "V, S, M (a tiny ball of bat guano and sulfur)", "school": "Evocation", "ritual": false, "conc": false, "description": "A bright streak flashes from your pointing finger.", "higher_levels": null, "classes": ["Sorcerer", "Wizard"], "source": "Player's Handbook", "page": 241}]"#;
+
+    fn bundled() -> &'static [StaticSpell] {
+        static BUNDLED_SPELLS: Lazy<Vec<StaticSpell>> = Lazy::new(|| StaticSpell::parse_static(BUNDLED).unwrap());
+        &BUNDLED_SPELLS
+    }
+
+    fn spell(name: &str, range: &str) -> String {
+        format!(r#"{{"name": "{name}", "level": 3, "casting_time": "1 Action", "range": "{range}", "duration": "Instantaneous", "components": "V, S", "school": "Evocation", "ritual": false, "conc": false, "description": "Boom.", "higher_levels": null, "classes": ["Wizard"], "source": "Player's Handbook", "page": 1}}"#)
+    }
+
+    fn override_file(json: &str) -> (tempfile::TempDir, std::path::PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("spells-override.json");
+        fs::write(&file, json).unwrap();
+        (dir, file)
+    }
+
+    #[test]
+    fn missing_file_is_the_bundled_spells() {
+        let spells = Spells::new(|| RwLock::new(&[]));
+        let dir = tempfile::tempdir().unwrap();
+        let errors = reload(&spells, bundled(), &dir.path().join("spells-override.json"));
+        assert!(errors.is_empty());
+        assert!(ptr::eq(&*spells, bundled()));
+    }
+
+    #[test]
+    fn overrides_replace_and_add_spells() {
+        let spells = Spells::new(|| RwLock::new(&[]));
+        let (_dir, file) = override_file(&format!("[{}, {}]", spell("Fireball", "300 feet"), spell("Firebolt Barrage", "60 feet")));
+        let errors = reload(&spells, bundled(), &file);
+        assert!(errors.is_empty());
+        assert_eq!(spells.iter().map(|spell| (spell.name, spell.range)).collect::<Vec<_>>(), [
+            ("Fireball", "300 feet"),
+            ("Firebolt Barrage", "60 feet"),
+        ]);
+    }
+
+    #[test]
+    fn reloading_the_same_file_reuses_its_spells() {
+        let spells = Spells::new(|| RwLock::new(&[]));
+        let (_dir, file) = override_file(&format!("[{}]", spell("Fireball", "120 feet")));
+        reload(&spells, bundled(), &file);
+        let first: *const [StaticSpell] = &*spells;
+        reload(&spells, bundled(), &file);
+        assert!(ptr::eq(first, &*spells));
+
+        fs::write(&file, format!("[{}]", spell("Fireball", "90 feet"))).unwrap();
+        reload(&spells, bundled(), &file);
+        assert!(!ptr::eq(first, &*spells));
+        assert_eq!(spells[0].range, "90 feet");
+    }
+
+    #[test]
+    fn bad_entries_are_skipped() {
+        let spells = Spells::new(|| RwLock::new(&[]));
+        let (_dir, file) = override_file(&format!(r#"[{}, {{"name": "Broken", "level": 12}}]"#, spell("Fireball", "10 feet")));
+        let errors = reload(&spells, bundled(), &file);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("Entry 2 (Broken)"), "{}", errors[0]);
+        assert_eq!(spells[0].range, "10 feet");
+    }
+}
@@ -1,16 +1,124 @@
+use std::borrow::Cow;
+use std::fmt::{self, Display};
+use std::iter;
+use std::ops::Range;
 use std::sync::Arc;
 
 use iced::{Alignment, Length, widget};
-use iced::widget::{container, horizontal_rule, text, text_input};
-use serde::{Deserialize, Serialize};
-
-use crate::{Container, DeserializeSpell, ListGrammaticallyExt, SpellButtons, SPELLS};
-use crate::spells::data::{CastingTime, Class, Components, Level, School, Source};
+use iced::widget::{button, container, horizontal_rule, text, text_input};
+use itertools::Itertools;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde::de::Error as _;
+
+use crate::{Column, Container, DeserializeSpell, Element, Location, Row, SpellButtons, SPELLS};
+use crate::export::model::SpellText;
+use crate::preferences::{Preferences, search_url, source_url};
+use crate::search::TextMatch;
+use crate::spells::data::{CastingTime, CastingTimes, Class, Components, Level, School, Source, Tag};
 use crate::spells::static_arc::StArc;
-use crate::utils::{SpacingExt, Tap};
+use crate::utils::{SpacingExt, Tap, TooltipExt};
+use crate::widgets::wrap::Wrap;
+
+/// Lowercases `str` for searching, with curly quotes made straight and accents dropped, so that
+/// `"tasha's"` finds "Tasha’s" and `"mele"` finds "Mêlée". Spells keep their text folded like
+/// this in their `_lower` fields, so searches only have to fold what was typed.
+pub fn fold(str: &str) -> String {
+    let mut folded = String::with_capacity(str.len());
+    for c in str.chars().flat_map(char::to_lowercase) {
+        match c {
+            '’' | '‘' | 'ʼ' | '′' => folded.push('\''),
+            '“' | '”' | '″' => folded.push('"'),
+            'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' => folded.push('a'),
+            'ç' | 'ć' | 'č' => folded.push('c'),
+            'è' | 'é' | 'ê' | 'ë' | 'ē' => folded.push('e'),
+            'ì' | 'í' | 'î' | 'ï' | 'ī' => folded.push('i'),
+            'ñ' | 'ń' => folded.push('n'),
+            'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' => folded.push('o'),
+            'ù' | 'ú' | 'û' | 'ü' | 'ū' => folded.push('u'),
+            'ý' | 'ÿ' => folded.push('y'),
+            'æ' => folded.push_str("ae"),
+            'œ' => folded.push_str("oe"),
+            'ß' => folded.push_str("ss"),
+            // combining accents, from text that was typed or pasted decomposed
+            '\u{300}'..='\u{36f}' => {}
+            c => folded.push(c),
+        }
+    }
+    folded
+}
+
+/// Where in `text` each of `terms` is, ignoring case and accents like [`fold`] does. `terms` must
+/// already be folded. The ranges are byte ranges of `text`, in order and not overlapping.
+pub fn match_ranges(text: &str, terms: &[String]) -> Vec<Range<usize>> {
+    // folding can change a character's length, so keep track of where each folded byte came from
+    let mut folded = String::with_capacity(text.len());
+    let mut origins = Vec::with_capacity(text.len() + 1);
+    let mut buf = [0; 4];
+    for (idx, c) in text.char_indices() {
+        let c = fold(c.encode_utf8(&mut buf));
+        folded.push_str(&c);
+        origins.extend(iter::repeat(idx).take(c.len()));
+    }
+    origins.push(text.len());
+
+    let mut ranges = terms.iter()
+        .filter(|term| !term.is_empty())
+        .flat_map(|term| folded.match_indices(&**term)
+            .map(|(start, term)| origins[start]..origins[start + term.len()])
+            .collect_vec())
+        .filter(|range| !range.is_empty())
+        .sorted_unstable_by_key(|range| range.start)
+        .collect_vec();
+    ranges.dedup_by(|next, range| {
+        let overlaps = next.start <= range.end;
+        if overlaps {
+            range.end = range.end.max(next.end);
+        }
+        overlaps
+    });
+    ranges
+}
 
-#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone)]
-#[serde(try_from = "DeserializeSpell")]
+/// `paragraph` with the parts in `ranges` (from [`match_ranges`]) highlighted. Only the words with a
+/// match are split up, so that the rest still lay out like text.
+fn highlighted<'c>(paragraph: &str, ranges: &[Range<usize>]) -> Element<'c> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (idx, c) in paragraph.char_indices().chain(iter::once((paragraph.len(), ' '))) {
+        match (start, c.is_whitespace()) {
+            (None, false) => start = Some(idx),
+            (Some(word), true) => {
+                words.push(word..idx);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    let words: Vec<Element<'c>> = words.into_iter()
+        .map(|word| {
+            let mut pieces = Row::new();
+            let mut at = word.start;
+            for range in ranges.iter().filter(|range| range.start < word.end && range.end > word.start) {
+                let (start, end) = (range.start.max(word.start), range.end.min(word.end));
+                if at < start {
+                    pieces = pieces.push(text(&paragraph[at..start]).size(16));
+                }
+                pieces = pieces.push(text(&paragraph[start..end]).size(16).style(Location::Match));
+                at = end;
+            }
+            if at < word.end {
+                pieces = pieces.push(text(&paragraph[at..word.end]).size(16));
+            }
+            pieces.into()
+        })
+        .collect();
+    // about the width of a space at this size
+    Wrap::new(words)
+        .spacing(4.0)
+        .into()
+}
+
+#[derive(Debug, Serialize, Eq, PartialEq, Clone)]
 pub struct StaticSpell {
     pub name: &'static str,
     #[serde(skip_serializing)]
@@ -29,32 +137,62 @@ pub struct StaticSpell {
     pub higher_levels: Option<&'static str>,
     #[serde(skip_serializing)]
     pub higher_levels_lower: Option<&'static str>,
+    #[serde(skip_serializing)]
+    pub tags: &'static [Tag],
     pub classes: &'static [Class],
     pub source: Source,
     pub page: u32,
 }
 
-impl TryFrom<DeserializeSpell> for StaticSpell {
+impl StaticSpell {
+    /// Parses a list of spells, borrowing strings from `json` instead of leaking copies of them
+    /// where possible.
+    pub fn parse_static(json: &'static str) -> Result<Vec<Self>, String> {
+        serde_json::from_str::<Vec<DeserializeSpell<'static>>>(json)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .map(Self::try_from)
+            .collect()
+    }
+}
+
+/// Parses spells from any json, leaking their strings
+impl<'de> Deserialize<'de> for StaticSpell {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        DeserializeSpell::deserialize(d)?
+            .into_owned()
+            .try_into()
+            .map_err(D::Error::custom)
+    }
+}
+
+impl TryFrom<DeserializeSpell<'static>> for StaticSpell {
     type Error = String;
 
-    fn try_from(value: DeserializeSpell) -> Result<Self, Self::Error> {
+    fn try_from(value: DeserializeSpell<'static>) -> Result<Self, Self::Error> {
         // we leak stuff since it will be around for the entire time the gui is open
         fn static_str(string: String) -> &'static str {
             Box::leak(string.into_boxed_str())
         }
-        let name_lower = static_str(value.name.to_lowercase());
-        let desc_lower = static_str(value.description.to_lowercase());
+        fn static_cow(cow: Cow<'static, str>) -> &'static str {
+            match cow {
+                Cow::Borrowed(str) => str,
+                Cow::Owned(string) => static_str(string),
+            }
+        }
+        let name_lower = static_str(fold(&value.name));
+        let desc_lower = static_str(fold(&value.description));
         let higher_levels_lower = value.higher_levels
-            .as_ref()
-            .map(|s| s.to_lowercase())
+            .as_deref()
+            .map(fold)
             .map(static_str);
         Ok(Self {
-            name: value.name,
+            name: static_cow(value.name),
             name_lower,
             level: value.level,
-            casting_time: CastingTime::from_static(value.casting_time)?,
-            range: value.range,
-            duration: value.duration,
+            casting_time: CastingTime::from_static(static_cow(value.casting_time))?,
+            range: static_cow(value.range),
+            duration: static_cow(value.duration),
             components: value.components,
             school: value.school,
             ritual: value.ritual,
@@ -63,6 +201,7 @@ impl TryFrom<DeserializeSpell> for StaticSpell {
             desc_lower,
             higher_levels: value.higher_levels.map(static_str),
             higher_levels_lower,
+            tags: Tag::find(desc_lower).leak(),
             classes: value.classes.leak(),
             source: value.source,
             page: value.page,
@@ -76,6 +215,9 @@ pub struct CustomSpell {
     pub name_lower: String,
     pub level: Level,
     pub casting_time: CastingTime,
+    /// another way to cast it, like the minute in "1 Action or 1 Minute"
+    #[serde(default)]
+    pub alt_casting_time: Option<CastingTime>,
     #[serde(skip, default = "text_input::Id::unique")]
     pub casting_time_id: text_input::Id,
     pub range: Option<String>,
@@ -102,10 +244,15 @@ pub struct CustomSpell {
     #[serde(skip, default = "text_input::Id::unique")]
     pub higher_levels_id: text_input::Id,
     pub higher_levels_lower: Option<String>,
+    #[serde(skip)]
+    pub tags: Vec<Tag>,
     pub classes: Vec<Class>,
     pub page: Option<u32>,
     #[serde(skip, default = "text_input::Id::unique")]
     pub page_id: text_input::Id,
+    /// the name of the official spell this is a version of
+    #[serde(default)]
+    pub based_on: Option<String>,
 }
 
 impl PartialEq for CustomSpell {
@@ -117,13 +264,14 @@ impl PartialEq for CustomSpell {
 impl CustomSpell {
     #[must_use]
     pub fn new(name: String) -> Self {
-        let name_lower = name.to_lowercase();
+        let name_lower = fold(&name);
         Self {
             name: Arc::from(name),
             name_lower,
             // name_state: Default::default(),
             level: Level::Cantrip,
             casting_time: CastingTime::Action,
+            alt_casting_time: None,
             casting_time_id: text_input::Id::unique(),
             range: None,
             duration: None,
@@ -138,15 +286,26 @@ impl CustomSpell {
             higher_levels: None,
             higher_levels_id: text_input::Id::unique(),
             higher_levels_lower: None,
+            tags: Vec::new(),
             classes: Vec::new(),
             page: None,
             range_id: text_input::Id::unique(),
             components_id: text_input::Id::unique(),
             duration_id: text_input::Id::unique(),
             page_id: text_input::Id::unique(),
+            based_on: None,
         }
     }
 
+    /// Re-folds the `_lower` fields and finds the [`Tag`]s after the name or text changes, or after
+    /// loading a spell saved before they were folded
+    pub fn refold(&mut self) {
+        self.name_lower = fold(&self.name);
+        self.desc_lower = fold(&self.description);
+        self.higher_levels_lower = self.higher_levels.as_deref().map(fold);
+        self.tags = Tag::find(&self.desc_lower);
+    }
+
     #[must_use]
     pub fn id(&self) -> SpellId {
         SpellId {
@@ -166,12 +325,11 @@ impl StaticSpell {
     }
 }
 
-// todo consider boxing custom spell
+/// Custom spells are shared, so that searching and cloning a character don't copy them
 #[derive(PartialEq, Clone, Debug)]
 pub enum Spell {
     Static(&'static StaticSpell),
-    // todo could
-    Custom(CustomSpell),
+    Custom(Arc<CustomSpell>),
 }
 
 macro_rules! delegate {
@@ -196,6 +354,20 @@ macro_rules! delegate {
 }
 
 impl Spell {
+    /// The current version of this spell, since reloading `spells-override.json` replaces official
+    /// spells. Custom spells are returned as they are.
+    #[must_use]
+    pub fn reloaded(self) -> Self {
+        match self {
+            Self::Static(spell) => Self::Static(
+                SPELLS.iter()
+                    .find(|current| current.name == spell.name)
+                    .unwrap_or(spell)
+            ),
+            custom @ Self::Custom(_) => custom,
+        }
+    }
+
     #[must_use]
     pub fn id(&self) -> SpellId {
         delegate!(self, id())
@@ -274,6 +446,18 @@ impl Spell {
         delegate!(self, ref ref casting_time)
     }
 
+    pub fn alt_casting_time(&self) -> Option<&CastingTime> {
+        match self {
+            Self::Static(_) => None,
+            Self::Custom(spell) => spell.alt_casting_time.as_ref(),
+        }
+    }
+
+    /// Displays both casting times, if there's an alternative
+    pub fn casting_times(&self) -> CastingTimes<'_> {
+        CastingTimes(self.casting_time(), self.alt_casting_time())
+    }
+
     pub fn range(&self) -> Option<&str> {
         match self {
             Self::Static(spell) => Some(spell.range),
@@ -288,6 +472,56 @@ impl Spell {
         }
     }
 
+    #[must_use]
+    pub fn tags(&self) -> &[Tag] {
+        delegate!(self, ref tags)
+    }
+
+    pub fn material(&self) -> Option<&str> {
+        self.components()
+            .and_then(|components| components.m.as_deref())
+    }
+
+    pub fn reaction_trigger(&self) -> Option<&str> {
+        match self.casting_time() {
+            CastingTime::Reaction(Some(when)) => Some(&**when),
+            _ => None,
+        }
+    }
+
+    /// For cantrips whose damage scales with character level, the dice at `character_level`, like
+    /// `2d10`. `None` if it doesn't scale in a way we recognize, or hasn't scaled yet.
+    pub fn cantrip_scaling(&self, character_level: u8) -> Option<&str> {
+        if self.level() != Level::Cantrip {
+            return None;
+        }
+        self.higher_levels()
+            .and_then(|higher| scaled_dice(higher, character_level))
+            .or_else(|| scaled_dice(self.description(), character_level))
+    }
+
+    /// For spells whose damage goes up with the slot they're cast with, in the usual phrasing like
+    /// "the damage increases by 1d6 for each slot level above 3rd", the dice when cast with a `slot`
+    /// level slot, like `10d6`. The base damage is the first dice of that size in the description.
+    pub fn upcast_dice(&self, slot: Level) -> Option<String> {
+        let (_, increase) = self.higher_levels()?.split_once("increases by ")?;
+        let (per_level, rest) = increase.split_once(' ')?;
+        let (per_level, sides) = parse_dice(per_level)?;
+        let above = rest.strip_prefix("for each slot level above ")?;
+        let digits = above.find(|c: char| !c.is_ascii_digit()).unwrap_or(above.len());
+        let above = Level::from_u8(above[..digits].parse().ok()?)?;
+        let (base, _) = self.description()
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter_map(parse_dice)
+            .find(|&(_, s)| s == sides)?;
+        let extra = (slot as u32).saturating_sub(above as u32);
+        Some(format!("{}d{sides}", base + per_level * extra))
+    }
+
+    pub fn scales(&self) -> bool {
+        self.cantrip_scaling(u8::MAX).is_some()
+    }
+
     pub fn duration(&self) -> Option<&str> {
         match self {
             Self::Static(spell) => Some(spell.duration),
@@ -313,14 +547,66 @@ impl Spell {
         }
     }
 
+    /// `fields` of this spell, like "3rd level · Evocation · Concentration"
+    pub fn summary(&self, fields: &[SummaryField]) -> String {
+        fields.iter()
+            .filter_map(|field| match field {
+                SummaryField::Level => Some(match self.level() {
+                    Level::Cantrip => "Cantrip".to_string(),
+                    level => format!("{level} level"),
+                }),
+                SummaryField::School => Some(self.school().to_string()),
+                SummaryField::CastingTime => Some(self.casting_times().to_string()),
+                SummaryField::Concentration => self.concentration().then(|| "Concentration".to_string()),
+                SummaryField::Ritual => self.ritual().then(|| "Ritual".to_string()),
+                SummaryField::Source => Some(self.source().to_string()),
+                SummaryField::Components => self.components().map(|Components { v, s, m }| [
+                    (*v, "V"),
+                    (*s, "S"),
+                    (m.is_some(), "M"),
+                ].into_iter()
+                    .filter_map(|(has, label)| has.then_some(label))
+                    .join(", ")),
+            })
+            .join(" · ")
+    }
+
     pub fn view<'s, 'c: 's, B: SpellButtons>(
         &'s self,
         button: B,
         data: B::Data,
         collapse: bool,
+        preferences: &Preferences,
     ) -> Container<'c> {
         let text = |label: String| row!(text(label).size(16).width(Length::FillPortion(18)));
 
+        let scaling = button.character_level()
+            .and_then(|level| self.cantrip_scaling(level));
+        let summary = (collapse && !button.summary_fields().is_empty())
+            .then(|| self.summary(button.summary_fields()));
+        // long descriptions are split into a widget per paragraph, and maybe only the first few
+        let full_text = button.full_text(self.id());
+        let shorten = preferences.shorten_long_descriptions
+            && full_text.as_ref().map_or(false, |(full, _)| !full);
+        let (paragraphs, shortened) = paragraphs(self.description(), shorten);
+        let long = preferences.shorten_long_descriptions && self.description().len() > LONG_DESCRIPTION;
+        let full_text_button = full_text
+            .filter(|&(full, _)| if full { long } else { shortened })
+            .map(|(full, message)| row!(
+                widget::button(widget::text(if full { "Show less" } else { "Show full text" }).size(14))
+                    .style(Location::Transparent)
+                    .on_press(message)
+            ));
+        // the part the text search matched goes first, so it isn't lost at the bottom
+        let matched_in = button.matched_in();
+        let first = |field| matched_in == Some(field);
+        let highlight = button.highlight().to_vec();
+        // the picker is only shown if the text can be scaled
+        let upcast = button.upcast(&self.id())
+            .and_then(|upcast| {
+                let dice = self.upcast_dice(upcast.selected)?;
+                Some((upcast, dice))
+            });
         let (buttons, title) = button.view(self.id(), data);
         let title = row!(title);
 
@@ -328,44 +614,86 @@ impl Spell {
 
         let mut column = col![
             title, buttons
-        ].align_items(Alignment::Center);
+        ].align_items(Alignment::Center)
+            .tap_if_some(summary, |col, summary| col.push(
+                widget::text(summary)
+                    .size(13)
+                    .style(Location::Muted)
+            ));
 
         if !collapse {
-            let classes = self.classes().iter().list_grammatically();
-            let an_grammar = classes.chars().next()
-                .filter(|c| *c == 'A')
-                .map_or('\0', |_| 'n');
-            let page = match self.page() {
-                Some(page) => format!(" page {page}"),
-                None => String::new(),
-            };
-            let about = text(format!("A{an_grammar} {classes} spell, from {}{page}", self.source()));
+            let strings = SpellText::from(self);
+            let about = match source_url(&preferences.source_links, self.source(), &self.name(), self.page()) {
+                Some(url) => row!(
+                    widget::button(widget::text(strings.about).size(16))
+                        .style(Location::Transparent)
+                        .padding(0)
+                        .on_press(crate::Message::OpenUrl(url.clone()))
+                        .tooltip(url)
+                ),
+                None => text(strings.about),
+            }.tap_if_some(search_url(&preferences.search_url, &self.name()), |row, url| row.push(
+                widget::button(widget::text("Search online").size(12))
+                    .on_press(crate::Message::OpenUrl(url.clone()))
+                    .tooltip(url)
+            ));
+
+            let casting_time = format!("Casting time: {}", strings.casting_time);
+            let components = strings.components.map(|comp| format!("Components: {comp}"));
+            let higher_levels = |col: Column<'c>| col.tap_if_some(self.higher_levels(), |col, higher| col
+                .push(horizontal_rule(8))
+                .push(row!(crate::text("At higher levels").size(20).width(Length::FillPortion(18))))
+                .tap_if_some(upcast.as_ref(), |col, (upcast, dice)| col.push(upcast.levels.iter()
+                    .fold(row![].spacing(2).align_items(Alignment::Center), |row, (level, message)| row.push(
+                        widget::button(widget::text(level).size(12))
+                            .style(Location::TriState((*level == upcast.selected).then_some(true)))
+                            .padding([2, 6])
+                            .on_press(message.clone())
+                    ))
+                    .push_space(8)
+                    .push(widget::text(format!("At level {}: {dice}", upcast.selected as u8)).size(16))))
+                .push_space(3)
+                .push(text(higher.to_string())));
 
             column = column
+                .tap_if_some(matched_in.filter(|&field| field != TextMatch::Name), |col, field| col.push(
+                    widget::text(format!("Matched in: {field}"))
+                        .size(13)
+                        .style(Location::Muted)
+                ))
+                .tap_if(first(TextMatch::HigherLevels), higher_levels)
+                .tap_if(first(TextMatch::Trigger), |col| col.push(text(casting_time.clone())))
+                .tap_if(first(TextMatch::Material), |col| col.tap_if_some(components.clone(), |col, comp| col.push(text(comp))))
                 .push(horizontal_rule(8))
-                .push(text(self.school().to_string()))
+                .push(text(strings.school))
                 .push_space(4)
-                .push(text(format!("Level: {}", self.level())))
-                .push(text(format!("Casting time: {}", self.casting_time())))
-                .tap_if_some(self.range(), |col, range|
+                .push(text(format!("Level: {}", strings.level)))
+                .tap_if_some(scaling, |col, dice|
+                    col.push(text(format!("Damage: {dice} at your level"))))
+                .tap_if(!first(TextMatch::Trigger), |col| col.push(text(casting_time)))
+                .tap_if_some(strings.range, |col, range|
                     col.push(text(format!("Range: {}", range))))
-                .tap_if_some(self.components(), |col, comp|
-                    col.push(text(format!("Components: {}", comp))))
-                .tap_if_some(self.duration(), |col, duration|
+                .tap_if_some(components.filter(|_| !first(TextMatch::Material)), |col, comp|
+                    col.push(text(comp)))
+                .tap_if_some(strings.duration, |col, duration|
                     col.push(text(format!("Duration: {}", duration))))
-                .push(text(format!("Ritual: {}", if self.ritual() { "Yes" } else { "No" })))
+                .push(text(format!("Ritual: {}", if strings.ritual { "Yes" } else { "No" })))
                 .push(horizontal_rule(10))
-                .push(row!(widget::text(self.description())
-                        .size(16)
-                        // todo maybe change font to be monospace? have to find a better font
-                        // .font(CONSOLAS)
-                        .width(Length::FillPortion(18))
-                    ))
-                .tap_if_some(self.higher_levels(), |col, higher| col
-                    .push(horizontal_rule(8))
-                    .push(row!(crate::text("At higher levels").size(20).width(Length::FillPortion(18))))
-                    .push_space(3)
-                    .push(text(higher.to_string())))
+                .push(paragraphs.into_iter()
+                    .fold(col![].spacing(6), |col, paragraph| {
+                        let ranges = match_ranges(paragraph, &highlight);
+                        col.push(if ranges.is_empty() {
+                            row!(widget::text(paragraph)
+                                .size(16)
+                                // todo maybe change font to be monospace? have to find a better font
+                                // .font(CONSOLAS)
+                                .width(Length::FillPortion(18)))
+                        } else {
+                            row!(highlighted(paragraph, &ranges))
+                        })
+                    }))
+                .tap_if_some(full_text_button, |col, button| col.push(button))
+                .tap_if(!first(TextMatch::HigherLevels), higher_levels)
                 .push(horizontal_rule(8))
                 .push(about);
         }
@@ -379,14 +707,110 @@ impl Spell {
     }
 }
 
-#[derive(Clone, Eq, PartialEq, Debug)]
+/// Descriptions longer than this many bytes can be shortened, see [`paragraphs`]
+pub const LONG_DESCRIPTION: usize = 1500;
+
+/// The non-empty paragraphs of `description`. If `shorten`, only as many as fit in
+/// [`LONG_DESCRIPTION`] bytes are kept, but always at least one. Also returns if any were left out.
+pub fn paragraphs(description: &str, shorten: bool) -> (Vec<&str>, bool) {
+    let mut paragraphs = description.lines()
+        .map(str::trim)
+        .filter(|paragraph| !paragraph.is_empty())
+        .collect_vec();
+    let total = paragraphs.len();
+    if shorten && description.len() > LONG_DESCRIPTION {
+        let mut len = 0;
+        let keep = paragraphs.iter()
+            .take_while(|paragraph| {
+                len += paragraph.len();
+                len <= LONG_DESCRIPTION
+            })
+            .count()
+            .max(1);
+        paragraphs.truncate(keep);
+    }
+    let shortened = paragraphs.len() < total;
+    (paragraphs, shortened)
+}
+
+/// A detail that can be shown on collapsed spells
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum SummaryField {
+    Level,
+    School,
+    CastingTime,
+    Concentration,
+    Ritual,
+    Source,
+    Components,
+}
+
+impl SummaryField {
+    pub const ALL: [Self; 7] = [
+        Self::Level,
+        Self::School,
+        Self::CastingTime,
+        Self::Concentration,
+        Self::Ritual,
+        Self::Source,
+        Self::Components,
+    ];
+
+    /// how many can be shown at once
+    pub const MAX: usize = 3;
+}
+
+impl Display for SummaryField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Level => "Level",
+            Self::School => "School",
+            Self::CastingTime => "Casting time",
+            Self::Concentration => "Concentration",
+            Self::Ritual => "Ritual",
+            Self::Source => "Source",
+            Self::Components => "Components",
+        })
+    }
+}
+
+/// `(count, sides)` of dice like `8d6`
+fn parse_dice(dice: &str) -> Option<(u32, u32)> {
+    let (count, sides) = dice.split_once('d')?;
+    Some((count.parse().ok()?, sides.parse().ok()?))
+}
+
+/// A slot level picker to see a spell's damage when it's upcast, see [`SpellButtons::upcast`]
+pub struct Upcast {
+    /// the spell's level and each higher level with slots, and the message that picks it
+    pub levels: Vec<(Level, crate::Message)>,
+    pub selected: Level,
+}
+
+/// Finds the last tier in phrases like "increases by 1d10 when you reach 5th level (2d10), 11th
+/// level (3d10), and 17th level (4d10)" that `character_level` has reached.
+fn scaled_dice(text: &str, character_level: u8) -> Option<&str> {
+    text.match_indices(" level (")
+        .filter_map(|(idx, pat)| {
+            let ordinal = text[..idx].trim_end_matches(|c: char| c.is_ascii_alphabetic());
+            let digits = ordinal.rfind(|c: char| !c.is_ascii_digit()).map_or(0, |i| i + 1);
+            let level = ordinal[digits..].parse::<u8>().ok()?;
+            let rest = &text[idx + pat.len()..];
+            Some((level, &rest[..rest.find(')')?]))
+        })
+        .filter(|&(level, _)| level <= character_level)
+        .last()
+        .map(|(_, dice)| dice)
+}
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
 pub struct SpellId {
     pub name: StArc<str>,
     pub level: Level,
 }
 
 #[must_use]
-pub fn find_spell(spell_name: &str, custom: &[CustomSpell]) -> Option<Spell> {
+pub fn find_spell(spell_name: &str, custom: &[Arc<CustomSpell>]) -> Option<Spell> {
     // TODO remove this after its been enough time that everyone probably updated it
     fn fix_name_changes(spell_name: &str, spell: &StaticSpell) -> bool {
         match spell_name {
@@ -403,4 +827,155 @@ pub fn find_spell(spell_name: &str, custom: &[CustomSpell]) -> Option<Spell> {
             .find(|s| &*s.name == spell_name)
             .cloned()
             .map(Spell::Custom))
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cantrip(name: &str) -> Spell {
+        find_spell(name, &[]).unwrap()
+    }
+
+    #[test]
+    fn cantrip_damage_tiers() {
+        let fire_bolt = cantrip("Fire Bolt");
+        assert_eq!(fire_bolt.cantrip_scaling(1), None);
+        assert_eq!(fire_bolt.cantrip_scaling(5), Some("2d10"));
+        assert_eq!(fire_bolt.cantrip_scaling(10), Some("2d10"));
+        assert_eq!(fire_bolt.cantrip_scaling(11), Some("3d10"));
+        assert_eq!(fire_bolt.cantrip_scaling(20), Some("4d10"));
+        assert_eq!(cantrip("Sacred Flame").cantrip_scaling(17), Some("4d8"));
+    }
+
+    #[test]
+    fn cantrip_tiers_can_be_in_the_description() {
+        assert_eq!(cantrip("Toll the Dead").cantrip_scaling(12), Some("3d8 or 3d12"));
+    }
+
+    #[test]
+    fn unrecognized_scaling_shows_nothing() {
+        // more beams, not more dice
+        assert_eq!(cantrip("Eldritch Blast").cantrip_scaling(20), None);
+        assert_eq!(cantrip("Mage Hand").cantrip_scaling(20), None);
+        // only cantrips scale with character level
+        assert_eq!(cantrip("Fireball").cantrip_scaling(20), None);
+        assert!(cantrip("Ray of Frost").scales());
+        assert!(!cantrip("Eldritch Blast").scales());
+    }
+
+    const JSON: &str = include_str!("../../resources/spells.json");
+
+    #[test]
+    fn bundled_and_owned_json_parse_the_same() {
+        let embedded = StaticSpell::parse_static(JSON).unwrap();
+        let from_disk = serde_json::from_str::<Vec<StaticSpell>>(&JSON.to_string()).unwrap();
+        assert!(!embedded.is_empty());
+        assert_eq!(embedded, from_disk);
+    }
+
+    #[test]
+    fn escaped_strings_are_unescaped() {
+        let spells = serde_json::from_str::<serde_json::Value>(JSON).unwrap();
+        let mut fireball = spells.as_array().unwrap()
+            .iter()
+            .find(|spell| spell["name"] == "Fireball")
+            .unwrap()
+            .clone();
+        fireball["description"] = "Two\nlines with \"quotes\"".into();
+        let json: &'static str = Box::leak(format!("[{fireball}]").into_boxed_str());
+        let parsed = StaticSpell::parse_static(json).unwrap();
+        assert_eq!(parsed[0].description, "Two\nlines with \"quotes\"");
+        assert_eq!(parsed[0].name, "Fireball");
+        assert_eq!(serde_json::from_str::<Vec<StaticSpell>>(json).unwrap(), parsed);
+    }
+
+    #[test]
+    fn folding_straightens_quotes_and_drops_accents() {
+        assert_eq!(fold("Tasha’s Mind Whip"), "tasha's mind whip");
+        assert_eq!(fold("MÊLÉE"), "melee");
+        assert_eq!(fold("“Quoted” ‘text’"), "\"quoted\" 'text'");
+        // decomposed accents too
+        assert_eq!(fold("Cafe\u{301}"), "cafe");
+        assert_eq!(fold("Straße"), "strasse");
+    }
+
+    #[test]
+    fn custom_spells_are_folded_on_edit() {
+        let mut spell = CustomSpell::new("Hunter’s Mark".to_string());
+        spell.description = "Mark a Café as your quarry".to_string();
+        spell.refold();
+        assert_eq!(spell.name_lower, "hunter's mark");
+        assert_eq!(spell.desc_lower, "mark a cafe as your quarry");
+    }
+
+    #[test]
+    fn matches_highlight_the_unfolded_text() {
+        let text = "Tasha’s Mêlée at the Cafe\u{301}";
+        let slices = |terms: &[&str]| {
+            let terms = terms.iter().map(|term| fold(term)).collect_vec();
+            match_ranges(text, &terms).into_iter()
+                .map(|range| &text[range])
+                .collect_vec()
+        };
+        assert_eq!(slices(&["tasha's"]), ["Tasha’s"]);
+        assert_eq!(slices(&["MELEE", "TASHA"]), ["Tasha", "Mêlée"]);
+        // along with the accent that folded away
+        assert_eq!(slices(&["cafe"]), ["Cafe\u{301}"]);
+        // overlapping matches are merged
+        assert_eq!(slices(&["sha’s m", "mêl"]), ["sha’s Mêl"]);
+        assert_eq!(slices(&["", "nothing"]), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn alternate_casting_times_round_trip() {
+        let mut spell = CustomSpell::new("Homebrew Ward".to_string());
+        spell.alt_casting_time = Some(CastingTime::Minute(10));
+        let json = serde_json::to_value(&spell).unwrap();
+        let loaded = serde_json::from_value::<CustomSpell>(json.clone()).unwrap();
+        assert_eq!(loaded.casting_time, CastingTime::Action);
+        assert_eq!(loaded.alt_casting_time, Some(CastingTime::Minute(10)));
+
+        // spells saved before there were alternatives have none
+        let mut old = json;
+        old.as_object_mut().unwrap().remove("alt_casting_time");
+        let loaded = serde_json::from_value::<CustomSpell>(old).unwrap();
+        assert_eq!(loaded.alt_casting_time, None);
+    }
+
+    #[test]
+    fn paragraphs_are_trimmed_lines() {
+        let description = "  First paragraph.\n\n   \nSecond one.  \n";
+        assert_eq!(paragraphs(description, true), (vec!["First paragraph.", "Second one."], false));
+        assert_eq!(paragraphs("", true), (vec![], false));
+    }
+
+    #[test]
+    fn long_descriptions_keep_the_paragraphs_that_fit() {
+        let paragraph = "x".repeat(600);
+        let description = [&*paragraph; 4].join("\n");
+        let (shortened, left_out) = paragraphs(&description, true);
+        assert_eq!(shortened.len(), 2);
+        assert!(left_out);
+        // unless the preference is off
+        assert_eq!(paragraphs(&description, false), (vec![&*paragraph; 4], false));
+    }
+
+    #[test]
+    fn long_descriptions_keep_at_least_one_paragraph() {
+        let long = "x".repeat(2 * LONG_DESCRIPTION);
+        assert_eq!(paragraphs(&long, true), (vec![&*long], false));
+        let description = format!("{long}\nMore.");
+        assert_eq!(paragraphs(&description, true), (vec![&*long], true));
+    }
+
+    #[test]
+    fn wish_is_shortened() {
+        let wish = cantrip("Wish");
+        let (shortened, left_out) = paragraphs(wish.description(), true);
+        let (full, _) = paragraphs(wish.description(), false);
+        assert!(left_out);
+        assert!(!shortened.is_empty() && shortened.len() < full.len());
+        assert!(shortened.iter().map(|paragraph| paragraph.len()).sum::<usize>() <= LONG_DESCRIPTION);
+    }
+}
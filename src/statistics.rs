@@ -0,0 +1,197 @@
+//! Totals over every spell, shown on the settings page. Mostly for fun, but also a quick check that
+//! the spell data loaded correctly.
+
+use std::sync::Arc;
+
+use iced::{Alignment, Length};
+use iced::widget::{progress_bar, text};
+use itertools::Itertools;
+
+use crate::{Column, Location, SPELLS};
+use crate::spells::data::{CastingTime, Class, Level, School, Source};
+use crate::spells::spell::{CustomSpell, Spell};
+use crate::utils::SpacingExt;
+
+pub struct Statistics {
+    total: usize,
+    by_level: Vec<(String, usize)>,
+    by_school: Vec<(String, usize)>,
+    by_class: Vec<(String, usize)>,
+    by_source: Vec<(String, usize)>,
+    by_casting_time: Vec<(String, usize)>,
+    concentration: usize,
+    ritual: usize,
+    /// (name, length of description)
+    longest: Option<(String, usize)>,
+    shortest: Option<(String, usize)>,
+}
+
+impl Statistics {
+    pub fn new(custom: &[Arc<CustomSpell>]) -> Self {
+        let spells = SPELLS.iter()
+            .map(Spell::Static)
+            .chain(custom.iter().cloned().map(Spell::Custom))
+            .collect_vec();
+        Self::of(&spells)
+    }
+
+    fn of(spells: &[Spell]) -> Self {
+        let by_casting_time = spells.iter()
+            // group all reactions together, regardless of their trigger
+            .map(|spell| match spell.casting_time() {
+                CastingTime::Reaction(_) => CastingTime::Reaction(None),
+                time => time.clone(),
+            })
+            .counts()
+            .into_iter()
+            .sorted()
+            .map(|(time, count)| (time.to_string(), count))
+            .collect();
+        let description_len = |spell: &&Spell| spell.description().len();
+        let name_and_len = |spell: &Spell| (spell.name().to_string(), spell.description().len());
+
+        Self {
+            total: spells.len(),
+            by_level: count_by(spells, Level::ALL, |spell, level| spell.level() == level),
+            by_school: count_by(spells, School::ALL, |spell, school| spell.school() == school),
+            by_class: count_by(spells, Class::ALL, |spell, class| spell.classes().contains(&class)),
+            by_source: count_by(spells, Source::ALL, |spell, source| spell.source() == source),
+            by_casting_time,
+            concentration: spells.iter().filter(|spell| spell.concentration()).count(),
+            ritual: spells.iter().filter(|spell| spell.ritual()).count(),
+            longest: spells.iter().max_by_key(description_len).map(name_and_len),
+            shortest: spells.iter().min_by_key(description_len).map(name_and_len),
+        }
+    }
+
+    pub fn view<'s, 'c: 's>(&'s self) -> Column<'c> {
+        let bars = |title: &str, counts: &[(String, usize)]| {
+            let max = counts.iter().map(|&(_, count)| count).max().unwrap_or(0);
+            counts.iter().fold(
+                col![text(title).size(20)],
+                |col, (label, count)| {
+                    #[allow(clippy::cast_precision_loss)]
+                    let bar = progress_bar(0.0..=max.max(1) as f32, *count as f32)
+                        .style(Location::Default)
+                        .height(Length::Fixed(10.0));
+                    col.push(row![
+                        text(label).size(14).width(Length::FillPortion(2)),
+                        text(count).size(14).width(Length::Fixed(36.0)),
+                        bar.width(Length::FillPortion(3)),
+                    ].spacing(6)
+                        .align_items(Alignment::Center))
+                },
+            )
+        };
+        let describe = |which: &str, spell: &Option<(String, usize)>| text(match spell {
+            Some((name, len)) => format!("{which} description: {name} ({len} characters)"),
+            None => format!("{which} description: none"),
+        }).size(14);
+
+        col![
+            text(format!("{} spells", self.total)).size(16),
+            text(format!("{} concentration, {} ritual", self.concentration, self.ritual)).size(14),
+            describe("Longest", &self.longest),
+            describe("Shortest", &self.shortest),
+        ].spacing(2)
+            .push_space(6)
+            .push(bars("By level", &self.by_level))
+            .push_space(6)
+            .push(bars("By school", &self.by_school))
+            .push_space(6)
+            .push(bars("By class", &self.by_class))
+            .push_space(6)
+            .push(bars("By source", &self.by_source))
+            .push_space(6)
+            .push(bars("By casting time", &self.by_casting_time))
+    }
+}
+
+/// How many of `spells` match each of `keys`
+fn count_by<K: Copy + ToString, const N: usize>(
+    spells: &[Spell],
+    keys: [K; N],
+    matches: impl Fn(&Spell, K) -> bool,
+) -> Vec<(String, usize)> {
+    keys.into_iter()
+        .map(|key| (key.to_string(), spells.iter().filter(|spell| matches(spell, key)).count()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spell(name: &str, level: Level, casting_time: CastingTime, description: &str) -> Spell {
+        let mut spell = CustomSpell::new(name.to_string());
+        spell.level = level;
+        spell.casting_time = casting_time;
+        spell.description = description.to_string();
+        spell.classes = vec![Class::Wizard];
+        spell.refold();
+        Spell::Custom(Arc::new(spell))
+    }
+
+    fn count(counts: &[(String, usize)], label: &str) -> usize {
+        counts.iter().find(|(l, _)| l == label).map_or(0, |&(_, count)| count)
+    }
+
+    #[test]
+    fn counts_spells() {
+        let mut concentration = CustomSpell::new("Hold".to_string());
+        concentration.conc = true;
+        concentration.ritual = true;
+        let spells = [
+            spell("Spark", Level::Cantrip, CastingTime::Action, "A spark."),
+            spell("Ward", Level::L1, CastingTime::Reaction(CastingTime::trigger("you are hit")), "A longer description."),
+            spell("Parry", Level::L1, CastingTime::Reaction(None), "Parry."),
+            Spell::Custom(Arc::new(concentration)),
+        ];
+        let statistics = Statistics::of(&spells);
+        assert_eq!(statistics.total, 4);
+        assert_eq!(count(&statistics.by_level, "Cantrip"), 2);
+        assert_eq!(count(&statistics.by_level, "1st"), 2);
+        assert_eq!(count(&statistics.by_level, "2nd"), 0);
+        assert_eq!(statistics.by_level.len(), 10);
+        assert_eq!(count(&statistics.by_class, "Wizard"), 3);
+        assert_eq!(count(&statistics.by_school, "Abjuration"), 4);
+        assert_eq!(statistics.concentration, 1);
+        assert_eq!(statistics.ritual, 1);
+    }
+
+    #[test]
+    fn groups_reactions_and_sorts_casting_times() {
+        let spells = [
+            spell("Long", Level::L1, CastingTime::Hour(1), ""),
+            spell("Ward", Level::L1, CastingTime::Reaction(CastingTime::trigger("you are hit")), ""),
+            spell("Parry", Level::L1, CastingTime::Reaction(None), ""),
+            spell("Spark", Level::Cantrip, CastingTime::Action, ""),
+        ];
+        let statistics = Statistics::of(&spells);
+        assert_eq!(statistics.by_casting_time, [
+            ("1 Action".to_string(), 1),
+            ("1 Reaction".to_string(), 2),
+            ("1 Hour".to_string(), 1),
+        ]);
+    }
+
+    #[test]
+    fn finds_longest_and_shortest_descriptions() {
+        let spells = [
+            spell("Medium", Level::L1, CastingTime::Action, "abcdef"),
+            spell("Long", Level::L1, CastingTime::Action, "abcdefghij"),
+            spell("Short", Level::L1, CastingTime::Action, "ab"),
+        ];
+        let statistics = Statistics::of(&spells);
+        assert_eq!(statistics.longest, Some(("Long".to_string(), 10)));
+        assert_eq!(statistics.shortest, Some(("Short".to_string(), 2)));
+        let empty = Statistics::of(&[]);
+        assert_eq!((empty.total, empty.longest, empty.shortest), (0, None, None));
+    }
+
+    #[test]
+    fn includes_custom_spells() {
+        let custom = CustomSpell::new("Homebrew".to_string());
+        assert_eq!(Statistics::new(&[Arc::new(custom)]).total, SPELLS.len() + 1);
+    }
+}
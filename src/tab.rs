@@ -1,16 +1,259 @@
+use std::sync::Arc;
+
+use crate::{Level, SpellId};
+use crate::character::CharacterPage;
+use crate::hotkey::Move;
+
+/// How tall the tab bar is: iced_aw's tab labels are 16pt text with 5pt of padding above and
+/// below, and the bar doesn't change their size
+pub const BAR_HEIGHT: u16 = 16 + 2 * 5;
+
+/// The tabs are Search, then each character, then Party if it's turned on, then Settings
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum Tab {
     Search,
     Character { index: usize },
+    Party,
     Settings,
 }
 
 impl Tab {
-    pub fn index(self, num_characters: usize) -> usize {
+    pub fn index(self, num_characters: usize, party: bool) -> usize {
         match self {
             Tab::Search => 0,
             Tab::Character { index } => index + 1,
-            Tab::Settings => num_characters + 1,
+            Tab::Party => num_characters + 1,
+            Tab::Settings => num_characters + 1 + usize::from(party),
+        }
+    }
+
+    /// The tab at `index`, the opposite of [`Tab::index`]. Past the end is Settings.
+    pub fn from_index(index: usize, num_characters: usize, party: bool) -> Self {
+        match index {
+            0 => Tab::Search,
+            index if index <= num_characters => Tab::Character { index: index - 1 },
+            index if party && index == num_characters + 1 => Tab::Party,
+            _ => Tab::Settings,
+        }
+    }
+
+    /// The next tab to the left or right, wrapping around at the ends
+    pub fn step(self, dir: Move, num_characters: usize, party: bool) -> Self {
+        let last = Tab::Settings.index(num_characters, party);
+        let index = self.index(num_characters, party);
+        let index = match dir {
+            Move::Left => index.checked_sub(1).unwrap_or(last),
+            Move::Right => if index >= last { 0 } else { index + 1 },
+        };
+        Self::from_index(index, num_characters, party)
+    }
+
+    /// If this tab can be closed from the tab bar, which only characters can
+    pub fn closable(index: usize, num_characters: usize) -> bool {
+        (1..=num_characters).contains(&index)
+    }
+
+    /// The index of the tab `x` pixels along a tab bar `width` wide. Every tab is the same width,
+    /// since iced_aw's tab bar gives each tab an equal share of its width unless it's told otherwise.
+    pub fn index_at(x: f32, width: f32, num_characters: usize, party: bool) -> usize {
+        let num_tabs = Tab::Settings.index(num_characters, party) + 1;
+        let index = (x / (width / num_tabs as f32)).max(0.0) as usize;
+        index.min(num_tabs - 1)
+    }
+}
+
+/// What was on screen when an undo state was saved, so that undo can go back to it too.
+/// Characters are found by name, since undoing can change their indices.
+#[derive(Debug, Clone)]
+pub struct TabContext {
+    tab: NamedTab,
+    /// each open character's name, spell tab, and viewed spell
+    characters: Vec<(Arc<str>, Option<Level>, Option<SpellId>)>,
+}
+
+#[derive(Debug, Clone)]
+enum NamedTab {
+    Search,
+    Character(Arc<str>),
+    Party,
+    Settings,
+}
+
+impl TabContext {
+    pub fn new(tab: Tab, characters: &[CharacterPage]) -> Self {
+        let tab = match tab {
+            Tab::Search => NamedTab::Search,
+            Tab::Character { index } => characters.get(index)
+                .map_or(NamedTab::Search, |page| NamedTab::Character(Arc::clone(&page.character.name))),
+            Tab::Party => NamedTab::Party,
+            Tab::Settings => NamedTab::Settings,
+        };
+        let characters = characters.iter()
+            .map(|page| (Arc::clone(&page.character.name), page.tab, page.view_spell.clone()))
+            .collect();
+        Self { tab, characters }
+    }
+
+    /// Restores each character's spell tab and viewed spell, as long as it still has them, and
+    /// returns the tab to show. Falls back to `current` if the saved character isn't open.
+    pub fn restore(&self, current: Tab, characters: &mut [CharacterPage]) -> Tab {
+        for page in characters.iter_mut() {
+            let saved = self.characters.iter()
+                .find(|(name, _, _)| *name == page.character.name);
+            let Some((_, tab, view_spell)) = saved else { continue };
+            let spells = &page.character.spells;
+            page.tab = tab.filter(|&level| !spells[level].is_empty());
+            let view_spell = view_spell.clone()
+                .filter(|id| spells[id.level].iter().any(|(spell, _)| spell.name() == id.name));
+            if view_spell.is_some() {
+                page.view_spell = view_spell;
+            }
+        }
+        let tab = match &self.tab {
+            NamedTab::Search => Some(Tab::Search),
+            NamedTab::Character(name) => characters.iter()
+                .position(|page| page.character.name == *name)
+                .map(|index| Tab::Character { index }),
+            NamedTab::Party => Some(Tab::Party),
+            NamedTab::Settings => Some(Tab::Settings),
+        };
+        tab.unwrap_or(match current {
+            Tab::Character { index } if index >= characters.len() => Tab::Search,
+            tab => tab,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::character::Character;
+    use crate::spells::spell::find_spell;
+
+    use super::*;
+
+    fn page(name: &str, spells: &[&str]) -> CharacterPage {
+        let mut character = Character::new(Arc::from(name));
+        for spell in spells {
+            let spell = find_spell(spell, &[]).unwrap();
+            character.spells[spell.level()].push((spell, true));
         }
+        CharacterPage::from(character)
+    }
+
+    /// the characters as they'd be rebuilt from an undo state
+    fn reloaded(characters: &[CharacterPage]) -> Vec<CharacterPage> {
+        characters.iter()
+            .map(|page| Character::from_serialized(&page.character.serialize(), &[]))
+            .map(CharacterPage::from)
+            .collect()
+    }
+
+    #[test]
+    fn undoing_a_close_goes_back_to_the_character() {
+        let mut characters = vec![page("Merric", &["Fire Bolt"]), page("Lia", &["Shield", "Sleep"])];
+        characters[1].tab = Some(Level::L1);
+        characters[1].view_spell = Some(characters[1].character.spells[Level::L1][1].0.id());
+        let context = TabContext::new(Tab::Character { index: 1 }, &characters);
+        let saved = reloaded(&characters);
+
+        // close Lia, then undo
+        characters.remove(1);
+        let mut characters = saved;
+        let tab = context.restore(Tab::Character { index: 0 }, &mut characters);
+        assert_eq!(tab, Tab::Character { index: 1 });
+        assert_eq!(characters[1].tab, Some(Level::L1));
+        assert_eq!(characters[1].view_spell.as_ref().map(|id| &*id.name), Some("Sleep"));
+        assert_eq!(characters[0].tab, None);
+    }
+
+    #[test]
+    fn characters_are_found_by_name() {
+        let characters = [page("Merric", &[]), page("Lia", &[])];
+        let context = TabContext::new(Tab::Character { index: 0 }, &characters);
+        let mut swapped = [page("Lia", &[]), page("Merric", &[])];
+        assert_eq!(context.restore(Tab::Search, &mut swapped), Tab::Character { index: 1 });
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn missing_characters_keep_the_current_tab() {
+        let context = TabContext::new(Tab::Character { index: 0 }, &[page("Merric", &[])]);
+        let mut others = [page("Lia", &[])];
+        assert_eq!(context.restore(Tab::Settings, &mut others), Tab::Settings);
+        assert_eq!(context.restore(Tab::Character { index: 0 }, &mut others), Tab::Character { index: 0 });
+        // unless it's past the last character
+        assert_eq!(context.restore(Tab::Character { index: 3 }, &mut others), Tab::Search);
+    }
+
+    #[test]
+    fn spells_that_are_gone_arent_restored() {
+        let mut characters = [page("Lia", &["Shield"])];
+        characters[0].tab = Some(Level::L1);
+        characters[0].view_spell = Some(characters[0].character.spells[Level::L1][0].0.id());
+        let context = TabContext::new(Tab::Search, &characters);
+
+        let mut emptied = [page("Lia", &["Fire Bolt"])];
+        assert_eq!(context.restore(Tab::Search, &mut emptied), Tab::Search);
+        assert_eq!(emptied[0].tab, None);
+        // it keeps viewing what it was
+        assert_eq!(emptied[0].view_spell.as_ref().map(|id| &*id.name), Some("Fire Bolt"));
+    }
+
+    const ALL: [Tab; 5] = [
+        Tab::Search,
+        Tab::Character { index: 0 },
+        Tab::Character { index: 1 },
+        Tab::Party,
+        Tab::Settings,
+    ];
+
+    #[test]
+    fn indices_round_trip() {
+        for (index, tab) in ALL.into_iter().enumerate() {
+            assert_eq!(tab.index(2, true), index);
+            assert_eq!(Tab::from_index(index, 2, true), tab);
+        }
+        let without_party = ALL.into_iter().filter(|&tab| tab != Tab::Party);
+        for (index, tab) in without_party.enumerate() {
+            assert_eq!(tab.index(2, false), index);
+            assert_eq!(Tab::from_index(index, 2, false), tab);
+        }
+        // past the end is Settings
+        assert_eq!(Tab::from_index(9, 2, true), Tab::Settings);
+        assert_eq!(Tab::from_index(1, 0, false), Tab::Settings);
+        assert_eq!(Tab::from_index(1, 0, true), Tab::Party);
+    }
+
+    #[test]
+    fn stepping_wraps_around() {
+        assert_eq!(Tab::Search.step(Move::Right, 2, true), Tab::Character { index: 0 });
+        assert_eq!(Tab::Character { index: 1 }.step(Move::Right, 2, true), Tab::Party);
+        assert_eq!(Tab::Character { index: 1 }.step(Move::Right, 2, false), Tab::Settings);
+        assert_eq!(Tab::Settings.step(Move::Left, 2, true), Tab::Party);
+        assert_eq!(Tab::Settings.step(Move::Right, 2, true), Tab::Search);
+        assert_eq!(Tab::Search.step(Move::Left, 2, true), Tab::Settings);
+        assert_eq!(Tab::Search.step(Move::Left, 0, false), Tab::Settings);
+        assert_eq!(Tab::Settings.step(Move::Right, 0, false), Tab::Search);
+    }
+
+    #[test]
+    fn only_characters_are_closable() {
+        let closable = (0..5).filter(|&index| Tab::closable(index, 2)).collect::<Vec<_>>();
+        assert_eq!(closable, [1, 2]);
+        assert!(!Tab::closable(1, 0));
+    }
+
+    #[test]
+    fn clicks_find_equal_width_tabs() {
+        // Search, 2 characters, Party, Settings: 100 wide each
+        assert_eq!(Tab::index_at(0.0, 500.0, 2, true), 0);
+        assert_eq!(Tab::index_at(99.0, 500.0, 2, true), 0);
+        assert_eq!(Tab::index_at(100.0, 500.0, 2, true), 1);
+        assert_eq!(Tab::index_at(350.0, 500.0, 2, true), 3);
+        assert_eq!(Tab::index_at(499.0, 500.0, 2, true), 4);
+        // without Party, 125 wide each
+        assert_eq!(Tab::index_at(350.0, 500.0, 2, false), 2);
+        // off either end
+        assert_eq!(Tab::index_at(-10.0, 500.0, 2, true), 0);
+        assert_eq!(Tab::index_at(600.0, 500.0, 2, true), 4);
+    }
+}
@@ -140,13 +140,51 @@ pub enum Location {
     SettingsBar,
     Alternating { idx: usize, highlight: bool },
     AdvancedSearch { enabled: bool },
+    /// off, required, or excluded
+    TriState(Option<bool>),
     Tooltip,
+    /// a border that pulses on and off to point something out
+    Highlight { on: bool },
+    /// text that's less important than what's around it
+    Muted,
+    /// text that's a warning, but not an error
+    Warning,
+    Error,
+    Success,
+    /// where a text search matched
+    Match,
+}
+
+#[derive(Default, Copy, Clone)]
+pub enum TextStyle {
+    /// the color of whatever the text is in
+    #[default]
+    Inherit,
+    Color(Color),
+    Location(Location),
+}
+
+impl From<Color> for TextStyle {
+    fn from(value: Color) -> Self {
+        Self::Color(value)
+    }
+}
+
+impl From<Location> for TextStyle {
+    fn from(value: Location) -> Self {
+        Self::Location(value)
+    }
 }
 
 impl text::StyleSheet for Theme {
-    type Style = Option<Color>;
+    type Style = TextStyle;
 
-    fn appearance(&self, color: Self::Style) -> text::Appearance {
+    fn appearance(&self, style: Self::Style) -> text::Appearance {
+        let color = match style {
+            TextStyle::Inherit => None,
+            TextStyle::Color(color) => Some(color),
+            TextStyle::Location(style) => Some(self.palette2(style).text),
+        };
         text::Appearance { color }
     }
 }
@@ -171,7 +209,8 @@ impl container::StyleSheet for Theme {
         container::Appearance {
             text_color: palette.text.into(),
             background: palette.background.into(),
-            border_color: Color::TRANSPARENT,
+            border_color: if let Location::Highlight { .. } = style { palette.outline } else { Color::TRANSPARENT },
+            border_width: if let Location::Highlight { .. } = style { 2.0 } else { 0.0 },
             border_radius: if *style == Location::Tooltip { 8.0 } else { 0.0 },
             ..Default::default()
         }
@@ -478,7 +517,7 @@ impl progress_bar::StyleSheet for Theme {
     fn appearance(&self, style: &Self::Style) -> progress_bar::Appearance {
         let palette = self.palette(style);
         progress_bar::Appearance {
-            background: palette.active.into(),
+            background: palette.surface.into(),
             bar: palette.active.into(),
             border_radius: 5.0,
         }
@@ -559,7 +598,41 @@ mod dark {
                 text: DEFAULT2.text.a(if enabled { 1.0 } else { 0.5 }),
                 ..TRANSPARENT2
             },
+            Location::TriState(state) => Palette2 {
+                text: DEFAULT2.text.a(if state.is_some() { 1.0 } else { 0.5 }),
+                button: match state {
+                    None => Color::TRANSPARENT,
+                    Some(true) => color!(0x3ba55c),
+                    Some(false) => color!(0xd83c3e),
+                },
+                ..TRANSPARENT2
+            },
             Location::Alternating { idx, highlight } => alternating2(idx, highlight),
+            Location::Highlight { on } => Palette2 {
+                text: DEFAULT2.text,
+                outline: if on { DEFAULT2.button } else { Color::TRANSPARENT },
+                ..TRANSPARENT2
+            },
+            Location::Muted => Palette2 {
+                text: DEFAULT2.text.a(0.5),
+                ..TRANSPARENT2
+            },
+            Location::Warning => Palette2 {
+                text: color!(0xe6b333),
+                ..TRANSPARENT2
+            },
+            Location::Error => Palette2 {
+                text: color!(0xd83c3e),
+                ..TRANSPARENT2
+            },
+            Location::Success => Palette2 {
+                text: color!(0x3ba55c),
+                ..TRANSPARENT2
+            },
+            Location::Match => Palette2 {
+                text: color!(0xf2a633),
+                ..TRANSPARENT2
+            },
         }
     }
 
@@ -619,10 +692,22 @@ mod dark {
                 text: DEFAULT.text.a(if enabled { 1.0 } else { 0.5 }),
                 ..Palette::TRANSPARENT
             },
+            Location::TriState(state) => Palette {
+                text: DEFAULT.text.a(if state.is_some() { 1.0 } else { 0.5 }),
+                ..Palette::TRANSPARENT
+            },
             Location::Tooltip => Palette {
                 background: DEFAULT.background.a(0.8),
                 ..DEFAULT
-            }
+            },
+            Location::Highlight { .. } => Palette {
+                text: DEFAULT.text,
+                ..Palette::TRANSPARENT
+            },
+            Location::Muted | Location::Warning | Location::Error | Location::Success | Location::Match => Palette {
+                text: palette2(*style).text,
+                ..Palette::TRANSPARENT
+            },
         }
     }
 
@@ -720,7 +805,41 @@ mod light {
                 text: DEFAULT2.text.a(if enabled { 1.0 } else { 0.5 }),
                 ..TRANSPARENT2
             },
+            Location::TriState(state) => Palette2 {
+                text: DEFAULT2.text.a(if state.is_some() { 1.0 } else { 0.5 }),
+                button: match state {
+                    None => Color::TRANSPARENT,
+                    Some(true) => color!(0x7fd49b),
+                    Some(false) => color!(0xf08a8b),
+                },
+                ..TRANSPARENT2
+            },
             Location::Alternating { idx, highlight } => alternating2(idx, highlight),
+            Location::Highlight { on } => Palette2 {
+                text: DEFAULT2.text,
+                outline: if on { DEFAULT2.button } else { Color::TRANSPARENT },
+                ..TRANSPARENT2
+            },
+            Location::Muted => Palette2 {
+                text: DEFAULT2.text.a(0.5),
+                ..TRANSPARENT2
+            },
+            Location::Warning => Palette2 {
+                text: color!(0xa87a00),
+                ..TRANSPARENT2
+            },
+            Location::Error => Palette2 {
+                text: color!(0xc62828),
+                ..TRANSPARENT2
+            },
+            Location::Success => Palette2 {
+                text: color!(0x2e8b4a),
+                ..TRANSPARENT2
+            },
+            Location::Match => Palette2 {
+                text: color!(0xc05e00),
+                ..TRANSPARENT2
+            },
         }
     }
 
@@ -781,10 +900,22 @@ mod light {
                 text: DEFAULT.text.a(if enabled { 1.0 } else { 0.5 }),
                 ..Palette::TRANSPARENT
             },
+            Location::TriState(state) => Palette {
+                text: DEFAULT.text.a(if state.is_some() { 1.0 } else { 0.5 }),
+                ..Palette::TRANSPARENT
+            },
             Location::Tooltip => Palette {
                 background: DEFAULT.background.a(0.8),
                 ..DEFAULT
-            }
+            },
+            Location::Highlight { .. } => Palette {
+                text: DEFAULT.text,
+                ..Palette::TRANSPARENT
+            },
+            Location::Muted | Location::Warning | Location::Error | Location::Success | Location::Match => Palette {
+                text: palette2(*style).text,
+                ..Palette::TRANSPARENT
+            },
         }
     }
 
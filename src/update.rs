@@ -3,12 +3,15 @@ use std::env::consts::EXE_SUFFIX;
 use std::fs::DirEntry;
 use std::hash::{Hash, Hasher};
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use iced_native::subscription::Recipe;
 use reqwest::header::{self, HeaderValue};
+use reqwest::StatusCode;
 use self_update::{cargo_crate_version, Move};
 use semver::Version;
+use tokio::io::AsyncWriteExt;
 
 use crate::{DndSpells, error, Tap, UpdateState};
 use crate::error::UpdateError;
@@ -17,6 +20,8 @@ use crate::error::UpdateError;
 pub enum Message {
     CheckForUpdate,
     Progress(Progress),
+    /// stop downloading, which drops the download subscription
+    Cancel,
 }
 
 #[derive(Clone, Debug)]
@@ -27,15 +32,21 @@ pub enum Progress {
     Errored(String),
 }
 
+/// how long to wait for the next part of the download before giving up
+const TIMEOUT: Duration = Duration::from_secs(60);
+
 pub struct Download {
     pub url: String,
+    /// the version being downloaded, which names its [`Cache`]
+    pub version: String,
 }
 
 pub enum State {
-    Ready(String),
+    Ready(String, Cache),
     Downloading {
         response: reqwest::Response,
-        buf: Vec<u8>,
+        cache: Cache,
+        file: tokio::fs::File,
         total: u64,
         downloaded: u64,
     },
@@ -43,6 +54,98 @@ pub enum State {
     Finished,
 }
 
+/// Where downloads are saved as they arrive, so that one that was interrupted can pick up where
+/// it left off
+pub fn cache_dir() -> PathBuf {
+    crate::SAVE_DIR.join("update-cache")
+}
+
+/// A partly downloaded version: `<version>.partial` has the bytes so far, and `<version>.size` the
+/// size of the whole file, to check that a resumed download is still the same file.
+pub struct Cache {
+    partial: PathBuf,
+    size: PathBuf,
+}
+
+impl Cache {
+    fn new(version: &str) -> Self {
+        Self::in_dir(&cache_dir(), version)
+    }
+
+    fn in_dir(dir: &Path, version: &str) -> Self {
+        Self {
+            partial: dir.join(format!("{version}.partial")),
+            size: dir.join(format!("{version}.size")),
+        }
+    }
+
+    /// how many bytes have been downloaded already
+    async fn cached(&self) -> u64 {
+        tokio::fs::metadata(&self.partial).await
+            .map_or(0, |metadata| metadata.len())
+    }
+
+    async fn total(&self) -> Option<u64> {
+        tokio::fs::read_to_string(&self.size).await.ok()?
+            .trim()
+            .parse()
+            .ok()
+    }
+
+    /// Opens the partial file to write to, from the start, or after what's already there
+    async fn open(&self, offset: u64, total: u64) -> io::Result<tokio::fs::File> {
+        if let Some(dir) = self.partial.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        tokio::fs::write(&self.size, total.to_string()).await?;
+        tokio::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(offset != 0)
+            .truncate(offset == 0)
+            .open(&self.partial)
+            .await
+    }
+
+    async fn clear(&self) {
+        // a missing file is already cleared
+        let _res = tokio::fs::remove_file(&self.partial).await;
+        let _res = tokio::fs::remove_file(&self.size).await;
+    }
+}
+
+/// The start and total size from a `Content-Range` header like `bytes 200-999/1000`. The total is
+/// `None` if it's `*`, meaning the server doesn't know it.
+fn content_range(value: &str) -> Option<(u64, Option<u64>)> {
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let (start, _end) = range.split_once('-')?;
+    let total = if total == "*" { None } else { Some(total.parse().ok()?) };
+    Some((start.parse().ok()?, total))
+}
+
+/// Where to keep writing a download that had `cached` bytes already, from the response to asking
+/// for the rest. `0` means starting over: nothing was cached, the server sent the whole file, or
+/// what it's sending doesn't line up with the cache. `remote_total` is the size from
+/// `Content-Range`, and `cached_total` is the size the cached download was going to be.
+fn resume_offset(
+    cached: u64,
+    cached_total: Option<u64>,
+    status: StatusCode,
+    range: Option<(u64, Option<u64>)>,
+) -> u64 {
+    match (status, range) {
+        (StatusCode::PARTIAL_CONTENT, Some((start, remote_total)))
+        if cached != 0 && start == cached && remote_total == cached_total => cached,
+        _ => 0,
+    }
+}
+
+/// If a partial response `length` bytes long with `range` from `Content-Range` is the whole file
+/// anyway, which some servers send even when the request didn't ask for a range
+fn whole_file(range: Option<(u64, Option<u64>)>, length: u64) -> bool {
+    range == Some((0, Some(length)))
+}
+
 impl<H: Hasher, E> Recipe<H, E> for Download {
     type Output = Progress;
 
@@ -55,51 +158,114 @@ impl<H: Hasher, E> Recipe<H, E> for Download {
         self: Box<Self>,
         _input: futures::stream::BoxStream<'_, E>,
     ) -> futures::stream::BoxStream<'_, Self::Output> {
+        let cache = Cache::new(&self.version);
         Box::pin(futures::stream::unfold(
-            State::Ready(self.url),
+            State::Ready(self.url, cache),
             |state| async move {
                 match state {
-                    State::Ready(url) => {
+                    State::Ready(url, cache) => {
+                        let cached = cache.cached().await;
                         let client = reqwest::Client::new();
-                        let response = client.get(url)
+                        let response = client.get(&url)
                             .header(header::USER_AGENT, HeaderValue::from_str("rust-reqwest/update").unwrap())
                             .header(header::ACCEPT, HeaderValue::from_str("application/octet-stream").unwrap())
-                            .send().await;
-                        match response {
-                            Ok(resp) => {
-                                match resp.content_length() {
-                                    Some(total) => Some((Progress::Started, State::Downloading {
-                                        response: resp,
-                                        buf: vec![],
-                                        total,
-                                        downloaded: 0,
-                                    })),
-                                    None => Some((Progress::Finished(Some(resp.bytes().await.unwrap().to_vec())), State::Finished)),
-                                }
+                            .tap_if(cached != 0, |request| request.header(header::RANGE, format!("bytes={cached}-")))
+                            .send();
+                        let Ok(response) = tokio::time::timeout(TIMEOUT, response).await else {
+                            return Some((Progress::Errored("timed out".into()), State::Finished));
+                        };
+                        let resp = match response {
+                            Ok(resp) => resp,
+                            Err(e) => return Some((Progress::Errored(e.to_string()), State::Finished)),
+                        };
+                        if !resp.status().is_success() {
+                            if cached != 0 {
+                                // the server wouldn't send the rest, so start over without the cache
+                                log::info!("couldn't resume update download ({}), starting over", resp.status());
+                                cache.clear().await;
+                                return Some((Progress::Started, State::Ready(url, cache)));
                             }
+                            return Some((Progress::Errored(resp.status().to_string()), State::Finished));
+                        }
+                        let Some(length) = resp.content_length() else {
+                            cache.clear().await;
+                            return match resp.bytes().await {
+                                Ok(bytes) => Some((Progress::Finished(Some(bytes.to_vec())), State::Finished)),
+                                Err(e) => Some((Progress::Errored(e.to_string()), State::Finished)),
+                            };
+                        };
+                        let range = resp.headers()
+                            .get(header::CONTENT_RANGE)
+                            .and_then(|value| value.to_str().ok())
+                            .and_then(content_range);
+                        let offset = resume_offset(cached, cache.total().await, resp.status(), range);
+                        if offset == 0 && resp.status() == StatusCode::PARTIAL_CONTENT && !whole_file(range, length) {
+                            if cached == 0 {
+                                // it wasn't asked for only part, so asking again would get the same
+                                return Some((Progress::Errored("the server only sent part of the update".into()), State::Finished));
+                            }
+                            // only part of a file that isn't the cached one, so ask for all of it
+                            log::info!("cached update download doesn't match, starting over");
+                            cache.clear().await;
+                            return Some((Progress::Started, State::Ready(url, cache)));
+                        }
+                        if offset != 0 {
+                            log::info!("resuming update download from {offset} bytes");
+                        }
+                        let total = offset + length;
+                        match cache.open(offset, total).await {
+                            Ok(file) => Some((Progress::Started, State::Downloading {
+                                response: resp,
+                                cache,
+                                file,
+                                total,
+                                downloaded: offset,
+                            })),
                             Err(e) => Some((Progress::Errored(e.to_string()), State::Finished)),
                         }
                     }
                     State::Downloading {
                         mut response,
-                        mut buf,
+                        cache,
+                        mut file,
                         total,
                         mut downloaded,
                     } => {
-                        match response.chunk().await {
+                        let Ok(chunk) = tokio::time::timeout(TIMEOUT, response.chunk()).await else {
+                            return Some((Progress::Errored("timed out".into()), State::Finished));
+                        };
+                        match chunk {
                             Ok(Some(bytes)) => {
+                                if let Err(e) = file.write_all(&bytes).await {
+                                    return Some((Progress::Errored(e.to_string()), State::Finished));
+                                }
                                 downloaded += bytes.len() as u64;
                                 #[allow(clippy::cast_precision_loss)]
                                     let percent = downloaded as f32 / total as f32 * 100.0;
-                                buf.extend_from_slice(&bytes);
                                 Some((Progress::Advanced(percent), State::Downloading {
                                     response,
-                                    buf,
+                                    cache,
+                                    file,
                                     total,
                                     downloaded,
                                 }))
                             }
-                            Ok(None) => Some((Progress::Finished(Some(buf)), State::Finished)),
+                            Ok(None) => {
+                                if let Err(e) = file.flush().await {
+                                    return Some((Progress::Errored(e.to_string()), State::Finished));
+                                }
+                                drop(file);
+                                if downloaded != total {
+                                    // the connection closed early, so keep what there is to resume
+                                    return Some((Progress::Errored(format!("only downloaded {downloaded} of {total} bytes")), State::Finished));
+                                }
+                                let progress = match tokio::fs::read(&cache.partial).await {
+                                    Ok(bytes) => Progress::Finished(Some(bytes)),
+                                    Err(e) => Progress::Errored(e.to_string()),
+                                };
+                                cache.clear().await;
+                                Some((progress, State::Finished))
+                            }
                             Err(e) => Some((Progress::Errored(e.to_string()), State::Finished)),
                         }
                     }
@@ -144,6 +310,7 @@ pub fn handle(app: &mut DndSpells, message: Message) -> error::Result<(), Update
                 if latest_version > this_version {
                     if let Some(asset) = latest_release.asset_for(self_update::get_target(), None) {
                         app.update_url = asset.download_url;
+                        app.update_version = latest_release.version;
                         UpdateState::Ready
                     } else {
                         UpdateState::UpToDate
@@ -154,25 +321,64 @@ pub fn handle(app: &mut DndSpells, message: Message) -> error::Result<(), Update
             } else {
                 UpdateState::UpToDate
             };
+            match &app.update_state {
+                UpdateState::Ready => log::info!("version {} is available", app.update_version),
+                _ => log::info!("up to date"),
+            }
+            let keep = matches!(app.update_state, UpdateState::Ready).then_some(app.update_version.as_str());
+            if let Err(e) = clear_stale_downloads(&cache_dir(), keep) {
+                log::warn!("failed to clear old update downloads: {e}");
+            }
+            Ok(())
+        }
+        Message::Cancel => {
+            log::info!("update download cancelled");
+            app.update_state = UpdateState::Cancelled;
             Ok(())
         }
         Message::Progress(progress) => {
-            app.update_state = match progress {
-                Progress::Started => UpdateState::Downloading(0.0),
-                Progress::Advanced(pct) => UpdateState::Downloading(pct),
-                Progress::Errored(e) => UpdateState::Errored(e),
-                Progress::Finished(None) => UpdateState::UpToDate,
-                Progress::Finished(Some(bytes)) => {
-                    update_extended(&bytes)?;
-                    UpdateState::Downloaded
-                }
-            };
-
+            if let Some(bytes) = progressed(&mut app.update_state, progress) {
+                update_extended(&bytes)?;
+                app.update_state = UpdateState::Downloaded;
+            }
             Ok(())
         }
     }
 }
 
+/// Moves `state` along with the download's `progress`. Returns the downloaded executable once it's
+/// finished, which is [`UpdateState::Downloaded`] after it's installed.
+fn progressed(state: &mut UpdateState, progress: Progress) -> Option<Vec<u8>> {
+    // progress that was already on its way when the download was cancelled
+    if matches!(state, UpdateState::Cancelled) {
+        return None;
+    }
+    *state = match progress {
+        Progress::Started => UpdateState::Downloading(0.0),
+        Progress::Advanced(pct) => UpdateState::Downloading(pct),
+        Progress::Errored(e) => UpdateState::Errored(e),
+        Progress::Finished(None) => UpdateState::UpToDate,
+        Progress::Finished(Some(bytes)) => return Some(bytes),
+    };
+    None
+}
+
+/// Deletes partial downloads in `dir` of every version but `keep`, since a newer version replaced
+/// them or this one is up to date
+fn clear_stale_downloads(dir: &Path, keep: Option<&str>) -> io::Result<()> {
+    if !dir.exists() {
+        return Ok(());
+    }
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let version = path.file_stem().and_then(|stem| stem.to_str());
+        if version != keep {
+            fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
 /// taken from `self_update`, but modified so that it uses the downloaded file
 fn update_extended(bytes: &[u8]) -> error::Result<(), UpdateError> {
     let current_exe = std::env::current_exe()?;
@@ -292,3 +498,146 @@ fn cleanup_backup_temp_directories(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+
+    use super::*;
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn parses_content_range() {
+        assert_eq!(content_range("bytes 200-999/1000"), Some((200, Some(1000))));
+        assert_eq!(content_range("bytes 0-99/*"), Some((0, None)));
+        assert_eq!(content_range("bytes */1000"), None);
+        assert_eq!(content_range("200-999/1000"), None);
+        assert_eq!(content_range("bytes abc-999/1000"), None);
+    }
+
+    #[test]
+    fn resumes_from_the_cached_length() {
+        let range = content_range("bytes 200-999/1000");
+        assert_eq!(resume_offset(200, Some(1000), StatusCode::PARTIAL_CONTENT, range), 200);
+    }
+
+    #[test]
+    fn starts_over_when_the_download_doesnt_line_up() {
+        let partial = StatusCode::PARTIAL_CONTENT;
+        // nothing cached
+        assert_eq!(resume_offset(0, None, partial, content_range("bytes 0-999/1000")), 0);
+        // the server sent the whole file
+        assert_eq!(resume_offset(200, Some(1000), StatusCode::OK, None), 0);
+        // from somewhere else in the file
+        assert_eq!(resume_offset(200, Some(1000), partial, content_range("bytes 100-999/1000")), 0);
+        // the remote file is a different size than the cached one
+        assert_eq!(resume_offset(200, Some(1000), partial, content_range("bytes 200-1199/1200")), 0);
+        assert_eq!(resume_offset(200, Some(1000), partial, content_range("bytes 200-999/*")), 0);
+        assert_eq!(resume_offset(200, None, partial, content_range("bytes 200-999/1000")), 0);
+        assert_eq!(resume_offset(200, Some(1000), partial, None), 0);
+    }
+
+    #[test]
+    fn cache_remembers_and_clears_a_partial_download() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Cache::in_dir(&dir.path().join("update-cache"), "1.2.0");
+        block_on(async {
+            assert_eq!(cache.cached().await, 0);
+            assert_eq!(cache.total().await, None);
+
+            let mut file = cache.open(0, 1000).await.unwrap();
+            file.write_all(&[1; 200]).await.unwrap();
+            file.flush().await.unwrap();
+            drop(file);
+            assert_eq!(cache.cached().await, 200);
+            assert_eq!(cache.total().await, Some(1000));
+
+            // resuming adds to the end
+            let mut file = cache.open(200, 1000).await.unwrap();
+            file.write_all(&[2; 100]).await.unwrap();
+            file.flush().await.unwrap();
+            drop(file);
+            assert_eq!(cache.cached().await, 300);
+
+            // starting over truncates it
+            let file = cache.open(0, 1200).await.unwrap();
+            drop(file);
+            assert_eq!(cache.cached().await, 0);
+            assert_eq!(cache.total().await, Some(1200));
+
+            cache.clear().await;
+            assert_eq!(cache.cached().await, 0);
+            assert_eq!(cache.total().await, None);
+        });
+    }
+
+    #[test]
+    fn clears_downloads_of_other_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        for file in ["1.1.0.partial", "1.1.0.size", "1.2.0.partial", "1.2.0.size"] {
+            fs::write(dir.path().join(file), "").unwrap();
+        }
+        clear_stale_downloads(dir.path(), Some("1.2.0")).unwrap();
+        let mut left = fs::read_dir(dir.path()).unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        left.sort();
+        assert_eq!(left, ["1.2.0.partial", "1.2.0.size"]);
+
+        clear_stale_downloads(dir.path(), None).unwrap();
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+        // no cache yet is fine
+        clear_stale_downloads(&dir.path().join("missing"), None).unwrap();
+    }
+
+    #[test]
+    fn progress_moves_the_download_along() {
+        let mut state = UpdateState::Ready;
+        assert!(state.downloads());
+        assert_eq!(progressed(&mut state, Progress::Started), None);
+        assert!(matches!(state, UpdateState::Downloading(pct) if pct.abs() < 0.01));
+        assert_eq!(progressed(&mut state, Progress::Advanced(42.0)), None);
+        assert!(matches!(state, UpdateState::Downloading(pct) if (pct - 42.0).abs() < 0.01));
+        assert!(state.downloads());
+        // installing it is what makes it downloaded
+        assert_eq!(progressed(&mut state, Progress::Finished(Some(vec![1, 2, 3]))), Some(vec![1, 2, 3]));
+        assert!(matches!(state, UpdateState::Downloading(_)));
+    }
+
+    #[test]
+    fn download_can_fail_or_find_nothing() {
+        let mut state = UpdateState::Downloading(10.0);
+        progressed(&mut state, Progress::Errored("timed out".into()));
+        assert!(matches!(&state, UpdateState::Errored(e) if e == "timed out"));
+        assert!(!state.downloads());
+
+        let mut state = UpdateState::Downloading(10.0);
+        progressed(&mut state, Progress::Finished(None));
+        assert!(matches!(state, UpdateState::UpToDate));
+    }
+
+    #[test]
+    fn cancelling_ignores_the_rest_of_the_download() {
+        let mut state = UpdateState::Cancelled;
+        assert!(!state.downloads());
+        assert_eq!(progressed(&mut state, Progress::Advanced(50.0)), None);
+        assert_eq!(progressed(&mut state, Progress::Finished(Some(vec![1]))), None);
+        progressed(&mut state, Progress::Errored("timed out".into()));
+        assert!(matches!(state, UpdateState::Cancelled));
+    }
+
+    #[test]
+    fn partial_responses_can_be_the_whole_file() {
+        assert!(whole_file(content_range("bytes 0-999/1000"), 1000));
+        assert!(!whole_file(content_range("bytes 0-499/1000"), 500));
+        assert!(!whole_file(content_range("bytes 200-999/1000"), 800));
+        assert!(!whole_file(content_range("bytes 0-999/*"), 1000));
+        assert!(!whole_file(None, 1000));
+    }
+}
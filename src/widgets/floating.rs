@@ -0,0 +1,264 @@
+//! Draws cards floating over some base content, at positions decided by the application.
+
+use iced::overlay;
+use iced_core::Size;
+use iced_native::{Clipboard, Element, Event, event, Layout, layout, Length, mouse, Point, Rectangle, renderer, Shell, Widget};
+use iced_native::overlay::Group;
+use iced_native::widget::{Operation, Tree};
+
+impl<'a, Message, Renderer> From<Floating<'a, Message, Renderer>> for Element<'a, Message, Renderer>
+    where
+        Message: Clone + 'a,
+        Renderer: iced_native::Renderer + 'a,
+{
+    fn from(value: Floating<'a, Message, Renderer>) -> Self {
+        Element::new(value)
+    }
+}
+
+pub struct Floating<'a, Message, Renderer> {
+    /// the base content first, then each card
+    children: Vec<Element<'a, Message, Renderer>>,
+    /// where each card is, and the message to send when it's grabbed
+    cards: Vec<(Point, Message)>,
+}
+
+impl<'a, Message, Renderer> Floating<'a, Message, Renderer> {
+    /// Creates a new [`Floating`] with no cards over `base`.
+    pub fn new<B: Into<Element<'a, Message, Renderer>>>(base: B) -> Self {
+        Self {
+            children: vec![base.into()],
+            cards: Vec::new(),
+        }
+    }
+
+    /// Adds a card with its top left corner at `position`. `on_grab` is sent when the left mouse
+    /// button is pressed on part of the card that doesn't handle clicks itself.
+    pub fn push<C: Into<Element<'a, Message, Renderer>>>(mut self, position: Point, card: C, on_grab: Message) -> Self {
+        self.children.push(card.into());
+        self.cards.push((position, on_grab));
+        self
+    }
+}
+
+impl<'a, Message, Renderer> Widget<Message, Renderer> for Floating<'a, Message, Renderer>
+    where
+        Message: 'a + Clone,
+        Renderer: 'a + iced_native::Renderer,
+{
+    fn width(&self) -> Length {
+        self.children[0].as_widget().width()
+    }
+
+    fn height(&self) -> Length {
+        self.children[0].as_widget().height()
+    }
+
+    fn layout(
+        &self,
+        renderer: &Renderer,
+        limits: &layout::Limits,
+    ) -> layout::Node {
+        self.children[0].as_widget().layout(renderer, limits)
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut Renderer,
+        theme: &Renderer::Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        viewport: &Rectangle,
+    ) {
+        self.children[0].as_widget().draw(
+            &tree.children[0],
+            renderer,
+            theme,
+            style,
+            layout,
+            cursor_position,
+            viewport,
+        );
+    }
+
+    fn children(&self) -> Vec<Tree> {
+        self.children.iter().map(Tree::new).collect()
+    }
+
+    fn diff(&self, tree: &mut Tree) {
+        tree.diff_children(&self.children);
+    }
+
+    fn operate(
+        &self,
+        tree: &mut Tree,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+        operation: &mut dyn Operation<Message>,
+    ) {
+        self.children[0].as_widget().operate(&mut tree.children[0], layout, renderer, operation);
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        renderer: &Renderer,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+    ) -> event::Status {
+        self.children[0].as_widget_mut().on_event(
+            &mut tree.children[0],
+            event,
+            layout,
+            cursor_position,
+            renderer,
+            clipboard,
+            shell,
+        )
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        viewport: &Rectangle,
+        renderer: &Renderer,
+    ) -> mouse::Interaction {
+        self.children[0].as_widget().mouse_interaction(
+            &tree.children[0],
+            layout,
+            cursor_position,
+            viewport,
+            renderer,
+        )
+    }
+
+    fn overlay<'b>(
+        &'b mut self,
+        tree: &'b mut Tree,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+    ) -> Option<overlay::Element<'b, Message, Renderer>> {
+        let (base, cards) = self.children.split_first_mut()?;
+        let (base_tree, card_trees) = tree.children.split_first_mut()?;
+        let mut overlays = cards.iter_mut()
+            .zip(card_trees)
+            .zip(&self.cards)
+            .map(|((content, tree), (position, on_grab))| overlay::Element::new(
+                *position,
+                Box::new(Card { content, tree, on_grab: on_grab.clone() }),
+            ))
+            .collect::<Vec<_>>();
+        // last so that the base's tooltips are drawn over the cards
+        overlays.extend(base.as_widget_mut().overlay(base_tree, layout, renderer));
+        (!overlays.is_empty()).then(|| Group::with_children(overlays).overlay())
+    }
+}
+
+struct Card<'a, 'b, Message, Renderer> {
+    content: &'b mut Element<'a, Message, Renderer>,
+    tree: &'b mut Tree,
+    on_grab: Message,
+}
+
+impl<'a, 'b, Message, Renderer> overlay::Overlay<Message, Renderer> for Card<'a, 'b, Message, Renderer>
+    where
+        Message: Clone,
+        Renderer: iced_native::Renderer,
+{
+    fn layout(&self, renderer: &Renderer, bounds: Size, position: Point) -> layout::Node {
+        let limits = layout::Limits::new(Size::ZERO, bounds);
+        let mut node = self.content.as_widget().layout(renderer, &limits);
+        // keep the card on screen
+        let size = node.size();
+        node.move_to(Point::new(
+            position.x.clamp(0.0, (bounds.width - size.width).max(0.0)),
+            position.y.clamp(0.0, (bounds.height - size.height).max(0.0)),
+        ));
+        node
+    }
+
+    fn draw(
+        &self,
+        renderer: &mut Renderer,
+        theme: &Renderer::Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor_position: Point,
+    ) {
+        self.content.as_widget().draw(
+            self.tree,
+            renderer,
+            theme,
+            style,
+            layout,
+            cursor_position,
+            &layout.bounds(),
+        );
+    }
+
+    fn operate(
+        &mut self,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+        operation: &mut dyn Operation<Message>,
+    ) {
+        self.content.as_widget().operate(self.tree, layout, renderer, operation);
+    }
+
+    fn on_event(
+        &mut self,
+        event: Event,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        renderer: &Renderer,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+    ) -> event::Status {
+        let status = self.content.as_widget_mut().on_event(
+            self.tree,
+            event.clone(),
+            layout,
+            cursor_position,
+            renderer,
+            clipboard,
+            shell,
+        );
+        let is_over = layout.bounds().contains(cursor_position);
+        match (status, event) {
+            (event::Status::Ignored, Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left))) if is_over => {
+                shell.publish(self.on_grab.clone());
+                event::Status::Captured
+            }
+            // the page under the card only gets clicks and scrolls outside of the card
+            (event::Status::Ignored, Event::Mouse(mouse::Event::ButtonPressed(_) | mouse::Event::WheelScrolled { .. })) if is_over => {
+                event::Status::Captured
+            }
+            (status, _) => status,
+        }
+    }
+
+    fn mouse_interaction(
+        &self,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        viewport: &Rectangle,
+        renderer: &Renderer,
+    ) -> mouse::Interaction {
+        self.content.as_widget().mouse_interaction(self.tree, layout, cursor_position, viewport, renderer)
+    }
+
+    fn overlay<'c>(
+        &'c mut self,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+    ) -> Option<overlay::Element<'c, Message, Renderer>> {
+        self.content.as_widget_mut().overlay(self.tree, layout, renderer)
+    }
+}
@@ -0,0 +1,255 @@
+//! Shows a preview over the page once the cursor has rested on some content for a moment. Unlike
+//! [`iced::widget::tooltip`], the preview can be any element, not just text.
+
+use std::time::{Duration, Instant};
+
+use iced::overlay;
+use iced_core::{Size, Vector};
+use iced_native::{Clipboard, Element, Event, event, Layout, layout, Length, mouse, Point, Rectangle, renderer, Shell, Widget, window};
+use iced_native::widget::{Operation, Tree, tree};
+
+/// how long the cursor has to stay still over the content
+const DELAY: Duration = Duration::from_millis(400);
+/// how far the cursor can drift, in pixels, and still count as still
+const STILL: f32 = 3.0;
+/// from the cursor to the preview's top left corner
+const OFFSET: Vector = Vector::new(16.0, 16.0);
+
+impl<'a, Message, Renderer> From<HoverPreview<'a, Message, Renderer>> for Element<'a, Message, Renderer>
+    where
+        Message: 'a,
+        Renderer: iced_native::Renderer + 'a,
+{
+    fn from(value: HoverPreview<'a, Message, Renderer>) -> Self {
+        Element::new(value)
+    }
+}
+
+pub struct HoverPreview<'a, Message, Renderer> {
+    content: Element<'a, Message, Renderer>,
+    preview: Element<'a, Message, Renderer>,
+}
+
+impl<'a, Message, Renderer> HoverPreview<'a, Message, Renderer> {
+    /// Creates a new [`HoverPreview`] that shows `preview` by the cursor when it rests on `content`.
+    pub fn new<C, P>(content: C, preview: P) -> Self
+        where
+            C: Into<Element<'a, Message, Renderer>>,
+            P: Into<Element<'a, Message, Renderer>>,
+    {
+        Self {
+            content: content.into(),
+            preview: preview.into(),
+        }
+    }
+}
+
+/// Where the cursor came to rest over the content, and when. Scrolling or moving more than
+/// [`STILL`] starts the wait over, so flicking through a list doesn't pop up previews.
+#[derive(Debug, Default, Copy, Clone)]
+struct State {
+    resting: Option<(Point, Instant)>,
+}
+
+impl State {
+    fn showing(&self) -> Option<Point> {
+        self.resting
+            .filter(|(_, since)| since.elapsed() >= DELAY)
+            .map(|(point, _)| point)
+    }
+}
+
+impl<'a, Message, Renderer> Widget<Message, Renderer> for HoverPreview<'a, Message, Renderer>
+    where
+        Renderer: iced_native::Renderer,
+{
+    fn width(&self) -> Length {
+        self.content.as_widget().width()
+    }
+
+    fn height(&self) -> Length {
+        self.content.as_widget().height()
+    }
+
+    fn layout(
+        &self,
+        renderer: &Renderer,
+        limits: &layout::Limits,
+    ) -> layout::Node {
+        self.content.as_widget().layout(renderer, limits)
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut Renderer,
+        theme: &Renderer::Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        viewport: &Rectangle,
+    ) {
+        self.content.as_widget().draw(
+            &tree.children[0],
+            renderer,
+            theme,
+            style,
+            layout,
+            cursor_position,
+            viewport,
+        );
+    }
+
+    fn tag(&self) -> tree::Tag {
+        tree::Tag::of::<State>()
+    }
+
+    fn state(&self) -> tree::State {
+        tree::State::new(State::default())
+    }
+
+    fn children(&self) -> Vec<Tree> {
+        vec![Tree::new(&self.content), Tree::new(&self.preview)]
+    }
+
+    fn diff(&self, tree: &mut Tree) {
+        tree.diff_children(&[self.content.as_widget(), self.preview.as_widget()]);
+    }
+
+    fn operate(
+        &self,
+        tree: &mut Tree,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+        operation: &mut dyn Operation<Message>,
+    ) {
+        self.content.as_widget().operate(&mut tree.children[0], layout, renderer, operation);
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        renderer: &Renderer,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+    ) -> event::Status {
+        let state = tree.state.downcast_mut::<State>();
+        let is_over = layout.bounds().contains(cursor_position);
+        match &event {
+            Event::Mouse(mouse::Event::CursorMoved { .. }) => {
+                let moved = state.resting.map_or(true, |(point, _)| point.distance(cursor_position) > STILL);
+                if !is_over {
+                    state.resting = None;
+                } else if moved {
+                    let now = Instant::now();
+                    state.resting = Some((cursor_position, now));
+                    shell.request_redraw(window::RedrawRequest::At(now + DELAY));
+                }
+            }
+            Event::Mouse(
+                mouse::Event::WheelScrolled { .. }
+                | mouse::Event::ButtonPressed(_)
+                | mouse::Event::CursorLeft
+            ) => state.resting = None,
+            _ => {}
+        }
+
+        self.content.as_widget_mut().on_event(
+            &mut tree.children[0],
+            event,
+            layout,
+            cursor_position,
+            renderer,
+            clipboard,
+            shell,
+        )
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        viewport: &Rectangle,
+        renderer: &Renderer,
+    ) -> mouse::Interaction {
+        self.content.as_widget().mouse_interaction(
+            &tree.children[0],
+            layout,
+            cursor_position,
+            viewport,
+            renderer,
+        )
+    }
+
+    fn overlay<'b>(
+        &'b mut self,
+        tree: &'b mut Tree,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+    ) -> Option<overlay::Element<'b, Message, Renderer>> {
+        let showing = tree.state.downcast_ref::<State>().showing();
+        let [content_tree, preview_tree] = &mut tree.children[..] else { return None };
+        match showing {
+            Some(point) => Some(overlay::Element::new(
+                point + OFFSET,
+                Box::new(Preview { content: &self.preview, tree: preview_tree }),
+            )),
+            None => self.content.as_widget_mut().overlay(content_tree, layout, renderer),
+        }
+    }
+}
+
+struct Preview<'a, 'b, Message, Renderer> {
+    content: &'b Element<'a, Message, Renderer>,
+    tree: &'b Tree,
+}
+
+impl<'a, 'b, Message, Renderer> overlay::Overlay<Message, Renderer> for Preview<'a, 'b, Message, Renderer>
+    where
+        Renderer: iced_native::Renderer,
+{
+    fn layout(&self, renderer: &Renderer, bounds: Size, position: Point) -> layout::Node {
+        let limits = layout::Limits::new(Size::ZERO, bounds);
+        let mut node = self.content.as_widget().layout(renderer, &limits);
+        // keep the preview on screen, flipping it above the cursor if it would go off the bottom
+        let size = node.size();
+        let y = if position.y + size.height > bounds.height {
+            position.y - size.height - 2.0 * OFFSET.y
+        } else {
+            position.y
+        };
+        node.move_to(Point::new(
+            position.x.clamp(0.0, (bounds.width - size.width).max(0.0)),
+            y.clamp(0.0, (bounds.height - size.height).max(0.0)),
+        ));
+        node
+    }
+
+    fn draw(
+        &self,
+        renderer: &mut Renderer,
+        theme: &Renderer::Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor_position: Point,
+    ) {
+        self.content.as_widget().draw(
+            self.tree,
+            renderer,
+            theme,
+            style,
+            layout,
+            cursor_position,
+            &layout.bounds(),
+        );
+    }
+
+    /// the preview is only to look at, so the content under it still gets every event
+    fn is_over(&self, _layout: Layout<'_>, _cursor_position: Point) -> bool {
+        false
+    }
+}
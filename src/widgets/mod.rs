@@ -1 +1,4 @@
-pub mod click_button;
\ No newline at end of file
+pub mod click_button;
+pub mod floating;
+pub mod hover_preview;
+pub mod wrap;
\ No newline at end of file
@@ -0,0 +1,161 @@
+//! Lays out its children left to right like words in a paragraph, starting a new line whenever the
+//! next one doesn't fit. iced's text can only be one color, so this is how part of a paragraph gets
+//! highlighted: a text per word.
+
+use iced_core::Size;
+use iced_native::{Clipboard, Element, Event, event, Layout, layout, Length, mouse, Point, Rectangle, renderer, Shell, Widget};
+use iced_native::widget::Tree;
+
+impl<'a, Message, Renderer> From<Wrap<'a, Message, Renderer>> for Element<'a, Message, Renderer>
+    where
+        Message: 'a,
+        Renderer: iced_native::Renderer + 'a,
+{
+    fn from(value: Wrap<'a, Message, Renderer>) -> Self {
+        Element::new(value)
+    }
+}
+
+pub struct Wrap<'a, Message, Renderer> {
+    children: Vec<Element<'a, Message, Renderer>>,
+    /// between children on the same line
+    spacing: f32,
+    /// between lines
+    line_spacing: f32,
+}
+
+impl<'a, Message, Renderer> Wrap<'a, Message, Renderer> {
+    pub fn new(children: Vec<Element<'a, Message, Renderer>>) -> Self {
+        Self {
+            children,
+            spacing: 0.0,
+            line_spacing: 0.0,
+        }
+    }
+
+    pub fn spacing(mut self, spacing: f32) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    pub fn line_spacing(mut self, line_spacing: f32) -> Self {
+        self.line_spacing = line_spacing;
+        self
+    }
+}
+
+impl<'a, Message, Renderer> Widget<Message, Renderer> for Wrap<'a, Message, Renderer>
+    where
+        Renderer: iced_native::Renderer,
+{
+    fn width(&self) -> Length {
+        Length::Fill
+    }
+
+    fn height(&self) -> Length {
+        Length::Shrink
+    }
+
+    fn layout(
+        &self,
+        renderer: &Renderer,
+        limits: &layout::Limits,
+    ) -> layout::Node {
+        let limits = limits.width(Length::Fill).height(Length::Shrink);
+        let max_width = limits.max().width;
+        let child_limits = layout::Limits::new(Size::ZERO, Size::new(max_width, f32::INFINITY));
+
+        let mut x = 0.0;
+        let mut y = 0.0;
+        let mut line_height: f32 = 0.0;
+        let mut width: f32 = 0.0;
+        let children = self.children.iter()
+            .map(|child| {
+                let mut node = child.as_widget().layout(renderer, &child_limits);
+                let size = node.size();
+                if x > 0.0 && x + size.width > max_width {
+                    x = 0.0;
+                    y += line_height + self.line_spacing;
+                    line_height = 0.0;
+                }
+                node.move_to(Point::new(x, y));
+                width = width.max(x + size.width);
+                line_height = line_height.max(size.height);
+                x += size.width + self.spacing;
+                node
+            })
+            .collect();
+
+        layout::Node::with_children(limits.resolve(Size::new(width, y + line_height)), children)
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut Renderer,
+        theme: &Renderer::Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        viewport: &Rectangle,
+    ) {
+        for ((child, state), layout) in self.children.iter().zip(&tree.children).zip(layout.children()) {
+            child.as_widget().draw(state, renderer, theme, style, layout, cursor_position, viewport);
+        }
+    }
+
+    fn children(&self) -> Vec<Tree> {
+        self.children.iter().map(Tree::new).collect()
+    }
+
+    fn diff(&self, tree: &mut Tree) {
+        tree.diff_children(&self.children);
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        renderer: &Renderer,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+    ) -> event::Status {
+        self.children.iter_mut()
+            .zip(&mut tree.children)
+            .zip(layout.children())
+            .map(|((child, state), layout)| child.as_widget_mut().on_event(
+                state,
+                event.clone(),
+                layout,
+                cursor_position,
+                renderer,
+                clipboard,
+                shell,
+            ))
+            .fold(event::Status::Ignored, event::Status::merge)
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        viewport: &Rectangle,
+        renderer: &Renderer,
+    ) -> mouse::Interaction {
+        self.children.iter()
+            .zip(&tree.children)
+            .zip(layout.children())
+            .map(|((child, state), layout)| child.as_widget().mouse_interaction(
+                state,
+                layout,
+                cursor_position,
+                viewport,
+                renderer,
+            ))
+            .max()
+            .unwrap_or_default()
+    }
+}